@@ -0,0 +1,150 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+// .gltf or .glb, buffers come from the glb bin chunk, data uris or files next to the gltf
+pub fn load_gltf(path: &Path) -> Result<(gltf::Document, Vec<gltf::buffer::Data>)> {
+    let base_path = path.parent().unwrap_or(Path::new(""));
+    let gltf::Gltf { document, blob } = gltf::Gltf::open(path)?;
+    let buffer_data = gltf::import_buffers(&document, Some(base_path), blob)?;
+    Ok((document, buffer_data))
+}
+
+// encoded image bytes, decoding is left to gen_texture_view
+pub fn load_gltf_image(
+    image: &gltf::Image,
+    base_path: &Path,
+    buffer_data: &[gltf::buffer::Data],
+) -> Result<Vec<u8>> {
+    match image.source() {
+        gltf::image::Source::View { view, .. } => {
+            let data = buffer_data.get(view.buffer().index()).ok_or_else(|| {
+                anyhow!(
+                    "image {:?} uses missing buffer {}",
+                    image.index(),
+                    view.buffer().index()
+                )
+            })?;
+            let bytes = data
+                .get(view.offset()..view.offset() + view.length())
+                .ok_or_else(|| {
+                    anyhow!(
+                        "image {:?} view {}..{} is outside its buffer of {} bytes",
+                        image.index(),
+                        view.offset(),
+                        view.offset() + view.length(),
+                        data.len()
+                    )
+                })?;
+            Ok(bytes.to_vec())
+        }
+        // image uris resolve like buffer uris, a data uri or a file relative to the gltf
+        gltf::image::Source::Uri { uri, .. } => {
+            let data =
+                gltf::buffer::Data::from_source(gltf::buffer::Source::Uri(uri), Some(base_path))?;
+            Ok(data.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    // "AAECAwQFBgc=" is the bytes 0 to 7
+    const BUFFER_DATA_URI: &str = "data:application/octet-stream;base64,AAECAwQFBgc=";
+
+    // one 8 byte buffer and an image read from its bytes 2..6
+    fn gen_json(buffer_uri: Option<&str>) -> String {
+        let uri = match buffer_uri {
+            Some(uri) => format!(r#""uri": "{}", "#, uri),
+            None => String::new(),
+        };
+        format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "buffers": [{{ {}"byteLength": 8 }}],
+                "bufferViews": [{{ "buffer": 0, "byteOffset": 2, "byteLength": 4 }}],
+                "images": [{{ "bufferView": 0, "mimeType": "image/png" }}]
+            }}"#,
+            uri
+        )
+    }
+
+    fn gen_glb(json: &str, bin: &[u8]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut bin = bin.to_vec();
+        bin.resize(bin.len().next_multiple_of(4), 0);
+
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+        let mut glb = vec![];
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(length as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin);
+        glb
+    }
+
+    // a file of its own per test, they run in parallel
+    fn write_temp(name: &str, bytes: &[u8]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("common-gltf-data-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn load_gltf_data_uri_buffer() {
+        let path = write_temp("data_uri.gltf", gen_json(Some(BUFFER_DATA_URI)).as_bytes());
+        let (_, buffer_data) = load_gltf(&path).unwrap();
+
+        assert_eq!(buffer_data.len(), 1);
+        assert_eq!(buffer_data[0].0, vec![0, 1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn load_gltf_glb_bin_chunk() {
+        let path = write_temp(
+            "bin_chunk.glb",
+            &gen_glb(&gen_json(None), &[7, 6, 5, 4, 3, 2, 1, 0]),
+        );
+        let (_, buffer_data) = load_gltf(&path).unwrap();
+
+        assert_eq!(buffer_data.len(), 1);
+        assert_eq!(buffer_data[0].0, vec![7, 6, 5, 4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn load_gltf_image_buffer_view() {
+        let path = write_temp(
+            "buffer_view.gltf",
+            gen_json(Some(BUFFER_DATA_URI)).as_bytes(),
+        );
+        let (document, buffer_data) = load_gltf(&path).unwrap();
+        let image = document.images().next().unwrap();
+
+        let bytes = load_gltf_image(&image, path.parent().unwrap(), &buffer_data).unwrap();
+        assert_eq!(bytes, vec![2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn load_gltf_image_view_out_of_range() {
+        let gltf = gltf::Gltf::from_slice(gen_json(None).as_bytes()).unwrap();
+        let image = gltf.document.images().next().unwrap();
+
+        // the view ends at byte 6, past a 4 byte buffer
+        let buffer_data = vec![gltf::buffer::Data(vec![0; 4])];
+        assert!(load_gltf_image(&image, Path::new(""), &buffer_data).is_err());
+        // no buffer 0 at all
+        assert!(load_gltf_image(&image, Path::new(""), &[]).is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use std::path::Path;
+
+use image::{Rgba, RgbaImage};
+
+// a pixel counts as changed above this perceptual delta, 0 same color, 1 black vs white
+const PIXEL_THRESHOLD: f32 = 0.01;
+// the image fails when more than this part of its pixels changed
+const CHANGED_RATIO_MAX: f32 = 0.005;
+
+// compare img against tests/golden/<name>.png of the calling crate, GOLDEN_BLESS=1 writes the reference
+#[macro_export]
+macro_rules! assert_golden {
+    ($name:expr, $img:expr) => {
+        $crate::golden::assert_golden_in(
+            &std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden"),
+            &std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden"),
+            $name,
+            $img,
+        )
+    };
+}
+
+// failing images and their diff go to out_dir
+pub fn assert_golden_in(golden_dir: &Path, out_dir: &Path, name: &str, img: &RgbaImage) {
+    let golden_path = golden_dir.join(format!("{}.png", name));
+
+    if std::env::var("GOLDEN_BLESS").is_ok() {
+        std::fs::create_dir_all(golden_dir).unwrap();
+        img.save(&golden_path).unwrap();
+        eprintln!("golden {}: wrote reference {:?}", name, golden_path);
+        return;
+    }
+    if !golden_path.exists() {
+        panic!(
+            "golden {}: missing reference {:?}, run with GOLDEN_BLESS=1 to write it",
+            name, golden_path
+        );
+    }
+
+    let golden = image::open(&golden_path).unwrap().to_rgba8();
+    std::fs::create_dir_all(out_dir).unwrap();
+    let actual_path = out_dir.join(format!("{}.actual.png", name));
+
+    if golden.dimensions() != img.dimensions() {
+        img.save(&actual_path).unwrap();
+        panic!(
+            "golden {}: size {:?} differs from reference {:?}, actual image at {:?}",
+            name,
+            img.dimensions(),
+            golden.dimensions(),
+            actual_path
+        );
+    }
+
+    let mut diff = RgbaImage::new(img.width(), img.height());
+    let mut changed_num = 0;
+    for (x, y, pixel) in img.enumerate_pixels() {
+        let pixel_golden = golden.get_pixel(x, y);
+        if color_delta(pixel, pixel_golden) > PIXEL_THRESHOLD {
+            changed_num += 1;
+            diff.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        } else {
+            // faded reference keeps the changed pixels readable
+            let gray = (255.0 - luma(pixel_golden) * 0.1 * 255.0) as u8;
+            diff.put_pixel(x, y, Rgba([gray, gray, gray, 255]));
+        }
+    }
+
+    let changed_ratio = changed_num as f32 / (img.width() * img.height()) as f32;
+    if changed_ratio > CHANGED_RATIO_MAX {
+        let diff_path = out_dir.join(format!("{}.diff.png", name));
+        img.save(&actual_path).unwrap();
+        diff.save(&diff_path).unwrap();
+        panic!(
+            "golden {}: {:.2}% pixels changed, allowed {:.2}%, actual image at {:?}, diff at {:?}",
+            name,
+            changed_ratio * 100.0,
+            CHANGED_RATIO_MAX * 100.0,
+            actual_path,
+            diff_path
+        );
+    }
+}
+
+fn luma(pixel: &Rgba<u8>) -> f32 {
+    (pixel[0] as f32 * 0.299 + pixel[1] as f32 * 0.587 + pixel[2] as f32 * 0.114) / 255.0
+}
+
+// YIQ color distance, weights the luma difference over the chroma difference like the eye does
+fn color_delta(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+    let r = (a[0] as f32 - b[0] as f32) / 255.0;
+    let g = (a[1] as f32 - b[1] as f32) / 255.0;
+    let b = (a[2] as f32 - b[2] as f32) / 255.0;
+
+    let y = r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_2;
+    let i = r * 0.595_977_99 - g * 0.274_176_1 - b * 0.321_801_9;
+    let q = r * 0.211_470_17 - g * 0.522_617_1 + b * 0.311_146_94;
+
+    // normalize so black vs white is 1.0
+    (0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q) / 0.5053
+}
@@ -0,0 +1,121 @@
+use anyhow::{anyhow, Result};
+use image::{Rgba, RgbaImage};
+use wgpu::{Adapter, Device, Instance, Queue, SurfaceConfiguration, Texture, TextureFormat};
+
+// prefer a real gpu, machine without one still gets a software adapter
+pub async fn request_adapter_headless(instance: &Instance) -> Option<Adapter> {
+    for force_fallback_adapter in [false, true] {
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptionsBase {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                force_fallback_adapter,
+                compatible_surface: None,
+            })
+            .await;
+        if adapter.is_some() {
+            return adapter;
+        }
+    }
+    None
+}
+
+// texture_to_image reads back 8 bit rgba and bgra, new_headless turns the rest away
+pub fn check_format_headless(format: TextureFormat) -> Result<()> {
+    match format {
+        TextureFormat::Rgba8Unorm
+        | TextureFormat::Rgba8UnormSrgb
+        | TextureFormat::Bgra8Unorm
+        | TextureFormat::Bgra8UnormSrgb => Ok(()),
+        format => Err(anyhow!("headless format {:?} is not supported", format)),
+    }
+}
+
+// stand in for the surface texture
+pub fn gen_texture_headless(device: &Device, surface_config: &SurfaceConfiguration) -> Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Texture Headless"),
+        size: wgpu::Extent3d {
+            width: surface_config.width,
+            height: surface_config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: surface_config.format,
+        usage: surface_config.usage,
+        view_formats: &[],
+    })
+}
+
+pub fn texture_to_image(
+    device: &Device,
+    queue: &Queue,
+    texture: &Texture,
+    surface_config: &SurfaceConfiguration,
+) -> RgbaImage {
+    // the format passed check_format_headless
+    let is_bgra = matches!(
+        surface_config.format,
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+    );
+    let (width, height) = (surface_config.width, surface_config.height);
+
+    // bytes per row of a texture copy has to be a multiple of 256
+    let bytes_per_row_unpadded = 4 * width;
+    let bytes_per_row = bytes_per_row_unpadded.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+        * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Buffer Headless Read"),
+        size: (bytes_per_row * height) as u64,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let buffer_slice = buffer.slice(..);
+    buffer_slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+    device.poll(wgpu::Maintain::Wait);
+
+    let mut img = RgbaImage::new(width, height);
+    {
+        let data = buffer_slice.get_mapped_range();
+        for (y, row) in data.chunks(bytes_per_row as usize).enumerate() {
+            for (x, pixel) in row[..bytes_per_row_unpadded as usize].chunks(4).enumerate() {
+                let rgba = if is_bgra {
+                    [pixel[2], pixel[1], pixel[0], pixel[3]]
+                } else {
+                    [pixel[0], pixel[1], pixel[2], pixel[3]]
+                };
+                img.put_pixel(x as u32, y as u32, Rgba(rgba));
+            }
+        }
+    }
+    buffer.unmap();
+
+    img
+}
@@ -0,0 +1,3 @@
+pub mod gltf_data;
+pub mod golden;
+pub mod headless;
@@ -2,6 +2,7 @@ struct VertexIn {
     @location(0) pos: vec3<f32>,
     @location(1) normal: vec3<f32>,
     @location(2) tex_coord: vec2<f32>,
+    @location(3) tangent: vec4<f32>,
 }
 
 struct VertexOut {
@@ -9,6 +10,7 @@ struct VertexOut {
     @location(0) frag_pos: vec3<f32>,
     @location(1) normal: vec3<f32>,
     @location(2) tex_coord: vec2<f32>,
+    @location(3) tangent: vec4<f32>,
 }
 
 struct Transform {
@@ -26,6 +28,14 @@ struct Light {
     specular: vec3<f32>,
 }
 
+struct Parallax {
+    // 0 none, 1 parallax, 2 steep, 3 occlusion
+    method: u32,
+    height_scale: f32,
+    min_layer_count: f32,
+    max_layer_count: f32,
+}
+
 @group(0)@binding(0)
 var<uniform> view: mat4x4<f32>;
 @group(0)@binding(1)
@@ -39,8 +49,9 @@ fn vs_main(in: VertexIn, transform: Transform) -> VertexOut {
     var out: VertexOut;
     out.clip_pos = proj * view * model * vec4<f32>(in.pos, 1.0);
     out.frag_pos = (model * vec4<f32>(in.pos, 1.0)).xyz;
-    out.normal = in.normal;
+    out.normal = (model * vec4<f32>(in.normal, 0.0)).xyz;
     out.tex_coord = in.tex_coord;
+    out.tangent = vec4<f32>((model * vec4<f32>(in.tangent.xyz, 0.0)).xyz, in.tangent.w);
 
     return out;
 }
@@ -61,20 +72,74 @@ var texture_specular: texture_2d<f32>;
 var<uniform> color: vec3<f32>;
 @group(3)@binding(4)
 var<uniform> shininess: f32;
+@group(3)@binding(5)
+var texture_height: texture_2d<f32>;
+@group(3)@binding(6)
+var<uniform> parallax: Parallax;
+
+// depth below the surface, the height map is white at the top
+fn sample_depth(tex_coord: vec2<f32>) -> f32 {
+    // explicit lod, the marching loops are not uniform control flow
+    return 1.0 - textureSampleLevel(texture_height, texture_sampler, tex_coord, 0.0).r;
+}
+
+// view_dir is in tangent space, its y points up the image while v grows down
+fn parallax_tex_coord(tex_coord: vec2<f32>, view_dir: vec3<f32>) -> vec2<f32> {
+    let view_uv = vec2<f32>(view_dir.x, -view_dir.y);
+
+    if parallax.method == 1u {
+        let depth = sample_depth(tex_coord);
+        return tex_coord - view_uv / view_dir.z * depth * parallax.height_scale;
+    }
+
+    // steep and occlusion, march the layers until the view ray is below the surface
+    let layer_count = mix(parallax.max_layer_count, parallax.min_layer_count, abs(view_dir.z));
+    let layer_depth = 1.0 / layer_count;
+    let delta_tex_coord = view_uv / view_dir.z * parallax.height_scale / layer_count;
+
+    var current_tex_coord = tex_coord;
+    var current_depth = sample_depth(current_tex_coord);
+    var current_layer_depth = 0.0;
+    for (var i = 0; i < i32(layer_count) && current_layer_depth < current_depth; i = i + 1) {
+        current_tex_coord -= delta_tex_coord;
+        current_depth = sample_depth(current_tex_coord);
+        current_layer_depth += layer_depth;
+    }
+
+    if parallax.method == 2u {
+        return current_tex_coord;
+    }
+
+    // occlusion, interpolate between the layers before and after the hit
+    let prev_tex_coord = current_tex_coord + delta_tex_coord;
+    let after_depth = current_depth - current_layer_depth;
+    let before_depth = sample_depth(prev_tex_coord) - current_layer_depth + layer_depth;
+    let weight = after_depth / (after_depth - before_depth);
+    return mix(current_tex_coord, prev_tex_coord, weight);
+}
 
 @fragment
 fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
-    let tex_diffuse = textureSample(texture_diffuse, texture_sampler, in.tex_coord).rgb;
-    let tex_specular = textureSample(texture_specular, texture_sampler, in.tex_coord).rgb;
+    let normal = normalize(in.normal);
+    let view_dir = normalize(camera_pos - in.frag_pos);
+
+    var tex_coord = in.tex_coord;
+    if parallax.method != 0u {
+        let tangent = normalize(in.tangent.xyz - dot(in.tangent.xyz, normal) * normal);
+        let bitangent = cross(normal, tangent) * in.tangent.w;
+        let tbn = transpose(mat3x3<f32>(tangent, bitangent, normal));
+        tex_coord = parallax_tex_coord(in.tex_coord, tbn * view_dir);
+    }
+
+    let tex_diffuse = textureSample(texture_diffuse, texture_sampler, tex_coord).rgb;
+    let tex_specular = textureSample(texture_specular, texture_sampler, tex_coord).rgb;
 
     let ambient = light.ambient * tex_diffuse;
 
-    let normal = normalize(in.normal);
     let light_dir = normalize(light.pos - in.frag_pos);
     let diff = max(dot(normal, light_dir), 0.0);
     let diffuse = light.diffuse * (diff * tex_diffuse);
 
-    let view_dir = normalize(camera_pos - in.frag_pos);
     let reflect_dir = reflect(-light_dir, normal);
     let spec = pow(max(dot(view_dir, reflect_dir), 0.0), shininess);
     let specular = light.specular * (spec * tex_specular);
@@ -1,22 +1,156 @@
+use wgpu::util::DeviceExt;
+
+use crate::texture::gen_texture_sampler;
+
 pub struct Material {
     pub sampler: wgpu::Sampler,
     pub diffuse: wgpu::TextureView,
     pub specular: wgpu::TextureView,
+    // linear, white is high, black is deep
+    pub height: wgpu::TextureView,
     pub shininess: f32,
+    pub parallax: Parallax,
+
+    pub parallax_buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ParallaxMethod {
+    NONE,
+    PARALLAX,
+    STEEP,
+    OCCLUSION,
+}
+
+impl ParallaxMethod {
+    pub fn next(self) -> Self {
+        match self {
+            Self::NONE => Self::PARALLAX,
+            Self::PARALLAX => Self::STEEP,
+            Self::STEEP => Self::OCCLUSION,
+            Self::OCCLUSION => Self::NONE,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Parallax {
+    method: u32,
+    pub height_scale: f32,
+    // steep and occlusion march more layers at grazing angles
+    pub min_layer_count: f32,
+    pub max_layer_count: f32,
+}
+
+impl Parallax {
+    pub fn new(
+        method: ParallaxMethod,
+        height_scale: f32,
+        min_layer_count: f32,
+        max_layer_count: f32,
+    ) -> Self {
+        Self {
+            method: method as u32,
+            height_scale,
+            min_layer_count,
+            max_layer_count,
+        }
+    }
+
+    pub fn method(&self) -> ParallaxMethod {
+        match self.method {
+            1 => ParallaxMethod::PARALLAX,
+            2 => ParallaxMethod::STEEP,
+            3 => ParallaxMethod::OCCLUSION,
+            _ => ParallaxMethod::NONE,
+        }
+    }
 }
 
 impl Material {
     pub fn new(
-        sampler: wgpu::Sampler,
         diffuse: wgpu::TextureView,
         specular: wgpu::TextureView,
+        height: wgpu::TextureView,
         shininess: f32,
+        parallax: Parallax,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
     ) -> Self {
+        let sampler = gen_texture_sampler(device);
+        let color_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Color Buffer"),
+            contents: bytemuck::cast_slice(&[1.0, 0.5, 0.31]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let shininess_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Material Buffer"),
+            contents: bytemuck::bytes_of(&shininess),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let parallax_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Parallax Buffer"),
+            contents: bytemuck::bytes_of(&parallax),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Material Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&diffuse),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&specular),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Buffer(
+                        color_buffer.as_entire_buffer_binding(),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Buffer(
+                        shininess_buffer.as_entire_buffer_binding(),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(&height),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::Buffer(
+                        parallax_buffer.as_entire_buffer_binding(),
+                    ),
+                },
+            ],
+        });
+
         Self {
             sampler,
             diffuse,
             specular,
+            height,
             shininess,
+            parallax,
+            parallax_buffer,
+            bind_group,
         }
     }
+
+    pub fn set_parallax_method(&mut self, method: ParallaxMethod, queue: &wgpu::Queue) {
+        self.parallax.method = method as u32;
+        queue.write_buffer(&self.parallax_buffer, 0, bytemuck::bytes_of(&self.parallax));
+    }
 }
@@ -10,8 +10,8 @@ use crate::{
     camera::Camera,
     input::Input,
     light::Light,
-    material::Material,
-    texture::{self, gen_texture_depth, gen_texture_sampler, gen_texture_view},
+    material::{Material, Parallax, ParallaxMethod},
+    texture::{self, gen_texture_depth, gen_texture_view, gen_texture_view_linear},
     transform::Transform,
     vertex::Vertex,
 };
@@ -178,6 +178,26 @@ pub async fn run(event_loop: EventLoop<()>, window: Window) -> Result<()> {
                     },
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
         });
 
@@ -362,54 +382,24 @@ pub async fn run(event_loop: EventLoop<()>, window: Window) -> Result<()> {
         }],
     });
 
-    let texture_sampler = gen_texture_sampler(&device);
-    let texture_diffuse_view = gen_texture_view("assets/texture/container2.png", &device, &queue)?;
-    let texture_specular_view =
-        gen_texture_view("assets/texture/container2_specular.png", &device, &queue)?;
-    let material = Material::new(
-        texture_sampler,
-        texture_diffuse_view,
-        texture_specular_view,
-        32.0,
-    );
-    let color_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some("Color Buffer"),
-        contents: bytemuck::cast_slice(&[1.0, 0.5, 0.31]),
-        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-    });
-    let shininess_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-        label: Some("Material Buffer"),
-        contents: bytemuck::bytes_of(&material.shininess),
-        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-    });
-    let material_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        label: Some("Material Bind Group"),
-        layout: &material_bind_group_layout,
-        entries: &[
-            wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::Sampler(&material.sampler),
-            },
-            wgpu::BindGroupEntry {
-                binding: 1,
-                resource: wgpu::BindingResource::TextureView(&material.diffuse),
-            },
-            wgpu::BindGroupEntry {
-                binding: 2,
-                resource: wgpu::BindingResource::TextureView(&material.specular),
-            },
-            wgpu::BindGroupEntry {
-                binding: 3,
-                resource: wgpu::BindingResource::Buffer(color_buffer.as_entire_buffer_binding()),
-            },
-            wgpu::BindGroupEntry {
-                binding: 4,
-                resource: wgpu::BindingResource::Buffer(
-                    shininess_buffer.as_entire_buffer_binding(),
-                ),
-            },
-        ],
-    });
+    // one cube per method side by side, keys 1 to 4 cycle the method of each cube
+    let mut material_arr = vec![];
+    for method in [
+        ParallaxMethod::NONE,
+        ParallaxMethod::PARALLAX,
+        ParallaxMethod::STEEP,
+        ParallaxMethod::OCCLUSION,
+    ] {
+        material_arr.push(Material::new(
+            gen_texture_view("assets/texture/container2.png", &device, &queue)?,
+            gen_texture_view("assets/texture/container2_specular.png", &device, &queue)?,
+            gen_texture_view_linear("assets/texture/container2_height.png", &device, &queue)?,
+            32.0,
+            Parallax::new(method, 0.1, 8.0, 32.0),
+            &device,
+            &material_bind_group_layout,
+        ));
+    }
 
     let transform_arr = transforms();
     let transform_mat_arr = transform_arr
@@ -511,8 +501,10 @@ pub async fn run(event_loop: EventLoop<()>, window: Window) -> Result<()> {
                 render_pass.set_bind_group(0, &view_proj_bind_group, &[]);
                 render_pass.set_bind_group(1, &light_bind_group, &[]);
                 render_pass.set_bind_group(2, &camera_pos_bind_group, &[]);
-                render_pass.set_bind_group(3, &material_bind_group, &[]);
-                render_pass.draw(0..vertices.len() as _, 0..transform_arr.len() as _);
+                for (i, material) in material_arr.iter().enumerate() {
+                    render_pass.set_bind_group(3, &material.bind_group, &[]);
+                    render_pass.draw(0..vertices.len() as _, i as u32..i as u32 + 1);
+                }
 
                 render_pass.set_pipeline(&render_pipline_2);
                 render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
@@ -562,6 +554,21 @@ pub async fn run(event_loop: EventLoop<()>, window: Window) -> Result<()> {
                     input: keyboard_input,
                     ..
                 } => {
+                    if keyboard_input.state == winit::event::ElementState::Released {
+                        let index = match keyboard_input.virtual_keycode {
+                            Some(winit::event::VirtualKeyCode::Key1) => Some(0),
+                            Some(winit::event::VirtualKeyCode::Key2) => Some(1),
+                            Some(winit::event::VirtualKeyCode::Key3) => Some(2),
+                            Some(winit::event::VirtualKeyCode::Key4) => Some(3),
+                            _ => None,
+                        };
+                        if let Some(i) = index {
+                            let material = &mut material_arr[i];
+                            let method = material.parallax.method().next();
+                            material.set_parallax_method(method, &queue);
+                            debug!("cube {} parallax {:?}", i + 1, method);
+                        }
+                    }
                     input.on_input(keyboard_input);
                 }
                 _ => {}
@@ -573,15 +580,16 @@ pub async fn run(event_loop: EventLoop<()>, window: Window) -> Result<()> {
 }
 
 fn transforms() -> Vec<Transform> {
-    let mut transform_arr = vec![];
-    let axis = Vec3::new(1.0, 0.3, 0.5).normalize();
-    let pos_arr = vec![Vec3::new(0.0, 0.0, 0.0)];
-    for (i, pos) in pos_arr.into_iter().enumerate() {
-        transform_arr.push(Transform::new(
-            pos,
-            Quat::from_axis_angle(axis, (20.0 * i as f32).to_radians()),
-            Vec3::ONE,
-        ));
-    }
-    transform_arr
+    // same turn for every cube, the methods only differ at grazing angles
+    let rotation = Quat::from_axis_angle(Vec3::Y, 35.0_f32.to_radians());
+    let pos_arr = vec![
+        Vec3::new(-1.6, 0.0, -1.0),
+        Vec3::new(-0.4, 0.0, -1.0),
+        Vec3::new(0.8, 0.0, -1.0),
+        Vec3::new(2.0, 0.0, -1.0),
+    ];
+    pos_arr
+        .into_iter()
+        .map(|pos| Transform::new(pos, rotation, Vec3::splat(0.8)))
+        .collect()
 }
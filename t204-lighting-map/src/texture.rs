@@ -18,6 +18,24 @@ pub fn gen_texture_view(
     img_path: &str,
     device: &wgpu::Device,
     queue: &wgpu::Queue,
+) -> Result<wgpu::TextureView> {
+    gen_texture_view_format(img_path, wgpu::TextureFormat::Rgba8UnormSrgb, device, queue)
+}
+
+// data textures like height maps, no srgb decode
+pub fn gen_texture_view_linear(
+    img_path: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> Result<wgpu::TextureView> {
+    gen_texture_view_format(img_path, wgpu::TextureFormat::Rgba8Unorm, device, queue)
+}
+
+fn gen_texture_view_format(
+    img_path: &str,
+    format: wgpu::TextureFormat,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
 ) -> Result<wgpu::TextureView> {
     let img_bytes = std::fs::read(img_path)?;
     let img = image::load_from_memory(&img_bytes)?;
@@ -36,7 +54,7 @@ pub fn gen_texture_view(
         mip_level_count: 1,
         sample_count: 1,
         dimension: wgpu::TextureDimension::D2,
-        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        format,
         usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
         view_formats: &[],
     });
@@ -1,9 +1,13 @@
+use glam::{Vec2, Vec3};
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
     pub pos: [f32; 3],
     pub normal: [f32; 3],
     pub tex_coord: [f32; 2],
+    // w is the handedness, bitangent = cross(normal, tangent.xyz) * w
+    pub tangent: [f32; 4],
 }
 
 impl Vertex {
@@ -12,11 +16,16 @@ impl Vertex {
             pos,
             normal,
             tex_coord,
+            tangent: [0.0; 4],
         }
     }
 
-    const ATTRS: [wgpu::VertexAttribute; 3] =
-        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x2];
+    const ATTRS: [wgpu::VertexAttribute; 4] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Float32x3,
+        2 => Float32x2,
+        3 => Float32x4
+    ];
     pub fn vertex_buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
         wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<Self>() as u64,
@@ -26,7 +35,7 @@ impl Vertex {
     }
 
     pub fn cube() -> [Self; 36] {
-        [
+        Self::with_tangents([
             // front
             Self::new([-0.5, -0.5, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0]),
             Self::new([0.5, -0.5, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0]),
@@ -69,6 +78,38 @@ impl Vertex {
             Self::new([0.5, 0.5, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0]),
             Self::new([0.5, 0.5, -1.0], [0.0, 1.0, 0.0], [1.0, 0.0]),
             Self::new([-0.5, 0.5, -1.0], [0.0, 1.0, 0.0], [0.0, 0.0]),
-        ]
+        ])
+    }
+
+    // faces are flat, the tangent of each triangle is exact
+    // the bitangent points up the image, v grows downward in wgpu
+    fn with_tangents<const N: usize>(mut vertices: [Self; N]) -> [Self; N] {
+        for triangle in vertices.chunks_exact_mut(3) {
+            let pos = triangle
+                .iter()
+                .map(|v| Vec3::from(v.pos))
+                .collect::<Vec<_>>();
+            let uv = triangle
+                .iter()
+                .map(|v| Vec2::from(v.tex_coord))
+                .collect::<Vec<_>>();
+
+            let (edge1, edge2) = (pos[1] - pos[0], pos[2] - pos[0]);
+            let (duv1, duv2) = (uv[1] - uv[0], uv[2] - uv[0]);
+            let r = 1.0 / (duv1.x * duv2.y - duv2.x * duv1.y);
+            let tangent = ((edge1 * duv2.y - edge2 * duv1.y) * r).normalize();
+            let dpos_dv = (edge2 * duv1.x - edge1 * duv2.x) * r;
+
+            for v in triangle.iter_mut() {
+                let normal = Vec3::from(v.normal);
+                let w = if normal.cross(tangent).dot(dpos_dv) < 0.0 {
+                    1.0
+                } else {
+                    -1.0
+                };
+                v.tangent = tangent.extend(w).to_array();
+            }
+        }
+        vertices
     }
 }
@@ -1,6 +1,7 @@
 pub mod camera;
 pub mod color;
 pub mod core;
+pub use common::gltf_data;
 pub mod input;
 pub mod light_direction;
 pub mod light_point;
@@ -1,8 +1,10 @@
+use anyhow::{anyhow, Result};
 use glam::{Quat, Vec3};
 use winit::{event_loop::EventLoop, window::WindowBuilder};
 
 use crate::{
     core::Core,
+    gltf_data::{load_gltf, load_gltf_image},
     material::Material,
     model::{DrawMethod, Model},
     texture::{gen_texture_sampler, gen_texture_view},
@@ -20,29 +22,17 @@ pub fn run() {
         // load_box_model(&mut core);
         // load_rect_model(&mut core);
         // load_triangle_model(&mut core);
-        load_gltf_model(&mut core);
+        load_gltf_model(&mut core).unwrap();
 
         Core::block_loop(event_loop, core);
     });
 }
 
-pub fn load_gltf_model(core: &mut Core) {
+pub fn load_gltf_model(core: &mut Core) -> Result<()> {
     let base_path = std::path::Path::new(BASE_GLTF_PATH);
 
     let gltf_path = base_path.join("box.gltf");
-    let gltf_info = gltf::Gltf::open(gltf_path).unwrap();
-
-    let mut buffer_data = Vec::new();
-    for buffer in gltf_info.buffers() {
-        match buffer.source() {
-            gltf::buffer::Source::Bin => {}
-            gltf::buffer::Source::Uri(path) => {
-                let path = base_path.join(path);
-                let data = std::fs::read(path).unwrap();
-                buffer_data.push(data);
-            }
-        }
-    }
+    let (gltf_info, buffer_data) = load_gltf(&gltf_path)?;
 
     // let material_arr = vec![];
     for material in gltf_info.materials() {
@@ -51,34 +41,17 @@ pub fn load_gltf_model(core: &mut Core) {
         let info = material
             .pbr_metallic_roughness()
             .base_color_texture()
-            .unwrap();
-        let texture = info.texture();
-        let source = texture.source().source();
-        let texture_view;
-        match source {
-            gltf::image::Source::View { view, .. } => {
-                texture_view =
-                    gen_texture_view(buffer_data[view.index()].clone(), &core.device, &core.queue)
-                        .unwrap();
-            }
-            gltf::image::Source::Uri { uri, .. } => {
-                texture_view = gen_texture_view(
-                    std::fs::read(base_path.join(url_escape::decode(uri).to_string())).unwrap(),
-                    &core.device,
-                    &core.queue,
-                )
-                .unwrap();
-            }
-        }
+            .ok_or_else(|| anyhow!("material {:?} has no base color texture", material.name()))?;
+        let img_bytes = load_gltf_image(&info.texture().source(), base_path, &buffer_data)?;
+        let texture_view = gen_texture_view(img_bytes, &core.device, &core.queue)?;
 
         let material = Material::new(texture_sampler, texture_view, 32.0, core);
         core.material_arr.push(material);
     }
 
     for mesh in gltf_info.meshes() {
-        let primitives = mesh.primitives();
-        primitives.for_each(|primitive| {
-            let reader = primitive.reader(|buffer| Some(&buffer_data[buffer.index()]));
+        for primitive in mesh.primitives() {
+            let reader = primitive.reader(|buffer| Some(&*buffer_data[buffer.index()]));
 
             let mut vertices = vec![];
             if let Some(positions) = reader.read_positions() {
@@ -124,11 +97,14 @@ pub fn load_gltf_model(core: &mut Core) {
                 )],
             );
 
-            core.material_arr[primitive.material().index().unwrap() as usize]
-                .model_arr
-                .push(model);
-        });
+            let material_idx = primitive.material().index().ok_or_else(|| {
+                anyhow!("mesh {:?} has a primitive without material", mesh.name())
+            })?;
+            core.material_arr[material_idx].model_arr.push(model);
+        }
     }
+
+    Ok(())
 }
 
 pub fn load_box_model(core: &mut Core) {
@@ -1,15 +1,20 @@
 use std::time::Instant;
 
+use anyhow::{anyhow, Result};
+use image::RgbaImage;
 use wgpu::{
-    util::DeviceExt, Adapter, Backends, BindGroup, BindGroupLayout, Buffer, Device,
-    DeviceDescriptor, Features, IndexFormat, Instance, PresentMode, Queue, RenderPipeline, Sampler,
-    Surface, SurfaceConfiguration, TextureUsages,
+    util::DeviceExt, Adapter, Backends, BindGroup, BindGroupLayout, Buffer, CompositeAlphaMode,
+    Device, DeviceDescriptor, Features, IndexFormat, Instance, PresentMode, Queue, RenderPipeline,
+    Sampler, Surface, SurfaceConfiguration, Texture, TextureFormat, TextureUsages,
 };
 use winit::{dpi::PhysicalPosition, event_loop::EventLoop, window::Window};
 
 use crate::{
     camera::Camera,
     depth::Depth,
+    headless::{
+        check_format_headless, gen_texture_headless, request_adapter_headless, texture_to_image,
+    },
     input::Input,
     light_direction::LightDirection,
     light_point::LightPoint,
@@ -23,13 +28,15 @@ use crate::{
 };
 
 pub struct Core {
-    pub window: Window,
+    pub window: Option<Window>,
     pub instance: Instance,
-    pub surface: Surface,
+    pub surface: Option<Surface>,
     pub adapter: Adapter,
     pub device: Device,
     pub queue: Queue,
     pub surface_config: SurfaceConfiguration,
+    // offscreen target when there is no surface
+    pub texture_headless: Option<Texture>,
     pub render_pipline_mesh: RenderPipeline,
     pub render_pipline_light: RenderPipeline,
     pub camera: Camera,
@@ -83,6 +90,61 @@ impl Core {
             .await
             .unwrap();
 
+        let surface_caps = surface.get_capabilities(&adapter);
+        let surface_config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            format: surface_caps.formats[0],
+            width: window_size.width,
+            height: window_size.height,
+            present_mode: PresentMode::Fifo,
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![],
+        };
+
+        Self::init(
+            instance,
+            adapter,
+            Some(window),
+            Some(surface),
+            surface_config,
+        )
+        .await
+        .unwrap()
+    }
+
+    // render into an offscreen texture of the given size and format instead of a window
+    pub async fn new_headless(width: u32, height: u32, format: TextureFormat) -> Result<Self> {
+        check_format_headless(format)?;
+
+        let instance = Instance::new(wgpu::InstanceDescriptor {
+            backends: Backends::all(),
+            dx12_shader_compiler: Default::default(),
+        });
+
+        let adapter = request_adapter_headless(&instance)
+            .await
+            .ok_or_else(|| anyhow!("no adapter for headless rendering"))?;
+
+        let surface_config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            format,
+            width,
+            height,
+            present_mode: PresentMode::Fifo,
+            alpha_mode: CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+
+        Self::init(instance, adapter, None, None, surface_config).await
+    }
+
+    async fn init(
+        instance: Instance,
+        adapter: Adapter,
+        window: Option<Window>,
+        surface: Option<Surface>,
+        surface_config: SurfaceConfiguration,
+    ) -> Result<Self> {
         let (device, queue) = adapter
             .request_device(
                 &DeviceDescriptor {
@@ -92,20 +154,15 @@ impl Core {
                 },
                 None,
             )
-            .await
-            .unwrap();
+            .await?;
 
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_config = SurfaceConfiguration {
-            usage: TextureUsages::RENDER_ATTACHMENT,
-            format: surface_caps.formats[0],
-            width: window_size.width,
-            height: window_size.height,
-            present_mode: PresentMode::Fifo,
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
+        let texture_headless = match &surface {
+            Some(surface) => {
+                surface.configure(&device, &surface_config);
+                None
+            }
+            None => Some(gen_texture_headless(&device, &surface_config)),
         };
-        surface.configure(&device, &surface_config);
 
         let mesh_shader = std::fs::read_to_string("assets/shader/mesh.wgsl").unwrap();
         let mesh_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -465,7 +522,7 @@ impl Core {
 
         let depth = Depth::new(&device, &surface_config);
 
-        Self {
+        Ok(Self {
             window,
             instance,
             surface,
@@ -473,6 +530,7 @@ impl Core {
             device,
             queue,
             surface_config,
+            texture_headless,
             render_pipline_mesh,
             render_pipline_light,
             camera,
@@ -502,13 +560,19 @@ impl Core {
 
             start_time: Instant::now(),
             last_time: Instant::now(),
-        }
+        })
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
         self.surface_config.width = width;
         self.surface_config.height = height;
-        self.surface.configure(&self.device, &self.surface_config);
+        match &self.surface {
+            Some(surface) => surface.configure(&self.device, &self.surface_config),
+            None => {
+                self.texture_headless =
+                    Some(gen_texture_headless(&self.device, &self.surface_config))
+            }
+        }
         self.camera.update_size(
             self.surface_config.width as _,
             self.surface_config.height as _,
@@ -518,13 +582,21 @@ impl Core {
     }
 
     fn render(&self) {
-        let current_texture = self
-            .surface
-            .get_current_texture()
-            .expect("get current texture fail");
-        let texture_view = current_texture
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let current_texture = self.surface.as_ref().map(|surface| {
+            surface
+                .get_current_texture()
+                .expect("get current texture fail")
+        });
+        let texture_view = match &current_texture {
+            Some(current_texture) => current_texture
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+            None => self
+                .texture_headless
+                .as_ref()
+                .unwrap()
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+        };
 
         let mut encoder = self
             .device
@@ -613,7 +685,26 @@ impl Core {
 
         self.queue.submit(std::iter::once(encoder.finish()));
 
-        current_texture.present();
+        if let Some(current_texture) = current_texture {
+            current_texture.present();
+        }
+    }
+
+    // only for a core created by new_headless
+    pub fn render_to_image(&mut self) -> RgbaImage {
+        self.render();
+        texture_to_image(
+            &self.device,
+            &self.queue,
+            self.texture_headless
+                .as_ref()
+                .expect("render to image needs headless mode"),
+            &self.surface_config,
+        )
+    }
+
+    pub fn window(&self) -> &Window {
+        self.window.as_ref().expect("no window in headless mode")
     }
 
     fn update(&mut self) {
@@ -621,6 +712,11 @@ impl Core {
         let delta_time = (Instant::now() - self.last_time).as_secs_f32();
         self.last_time = Instant::now();
 
+        self.update_delta_time(delta_time);
+    }
+
+    // a fixed delta time keeps headless frames reproducible
+    pub fn update_delta_time(&mut self, delta_time: f32) {
         self.camera.moving(&self.input, delta_time);
 
         self.queue.write_buffer(
@@ -663,12 +759,12 @@ impl Core {
 
     pub fn block_loop(event_loop: EventLoop<()>, mut core: Core) {
         event_loop.run(move |event, _, control_flow| match event {
-            winit::event::Event::RedrawRequested(window_id) if window_id == core.window.id() => {
+            winit::event::Event::RedrawRequested(window_id) if window_id == core.window().id() => {
                 core.update();
                 core.render();
             }
             winit::event::Event::WindowEvent { window_id, event }
-                if window_id == core.window.id() =>
+                if window_id == core.window().id() =>
             {
                 match event {
                     winit::event::WindowEvent::Resized(new_size) => {
@@ -705,7 +801,7 @@ impl Core {
                     _ => {}
                 }
             }
-            winit::event::Event::MainEventsCleared => core.window.request_redraw(),
+            winit::event::Event::MainEventsCleared => core.window().request_redraw(),
             _ => {}
         });
     }
@@ -2,6 +2,7 @@ pub mod camera;
 pub mod color;
 pub mod core;
 pub mod depth;
+pub use common::headless;
 pub mod input;
 pub mod light_direction;
 pub mod light_point;
@@ -0,0 +1,26 @@
+use glam::Vec3;
+use t207_gltf::{core::Core, runner::load_scene};
+use wgpu::TextureFormat;
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+const FRAME_TIME: f32 = 1.0 / 60.0;
+
+#[test]
+fn golden_scene() {
+    let mut core = pollster::block_on(Core::new_headless(
+        WIDTH,
+        HEIGHT,
+        TextureFormat::Rgba8UnormSrgb,
+    ))
+    .expect("golden tests need an adapter");
+
+    load_scene(&mut core);
+
+    let camera = &mut core.camera;
+    camera.pos = Vec3::new(2.0, 2.0, 4.0);
+    camera.front = (Vec3::new(0.0, 0.0, 0.0) - camera.pos).normalize();
+    core.update_delta_time(FRAME_TIME);
+
+    common::assert_golden!("scene", &core.render_to_image());
+}
@@ -1,4 +1,6 @@
+use anyhow::Result;
 use glam::{Quat, Vec3};
+use wgpu::TextureFormat;
 use winit::{event_loop::EventLoop, window::WindowBuilder};
 
 use crate::{
@@ -17,16 +19,30 @@ pub fn run() {
     let window = WindowBuilder::new().build(&event_loop).unwrap();
     pollster::block_on(async {
         let mut core = Core::new(window).await;
-        // load_box_model(&mut core);
-        // load_rect_model(&mut core);
-        // load_triangle_model(&mut core);
-        load_gltf_model(&mut core);
-        load_rect_blend_model(&mut core);
+        load_scene(&mut core);
 
         Core::block_loop(event_loop, core);
     });
 }
 
+pub fn run_headless(width: u32, height: u32, path: &str) -> Result<()> {
+    pollster::block_on(async {
+        let mut core = Core::new_headless(width, height, TextureFormat::Rgba8UnormSrgb).await?;
+        load_scene(&mut core);
+        core.update_delta_time(1.0 / 60.0);
+        core.render_to_image().save(path)?;
+        Ok(())
+    })
+}
+
+pub fn load_scene(core: &mut Core) {
+    // load_box_model(core);
+    // load_rect_model(core);
+    // load_triangle_model(core);
+    load_gltf_model(core);
+    load_rect_blend_model(core);
+}
+
 pub fn load_gltf_model(core: &mut Core) {
     let base_path = std::path::Path::new(BASE_GLTF_PATH);
 
@@ -198,8 +214,8 @@ fn box_material(core: &Core) -> Material {
     let (device, queue) = (&core.device, &core.queue);
     let texture_diffuse_view = gen_texture_view(
         std::fs::read("assets/texture/container2.png").unwrap(),
-        &device,
-        &queue,
+        device,
+        queue,
     )
     .unwrap();
 
@@ -210,8 +226,8 @@ fn blend_material(core: &Core) -> Material {
     let (device, queue) = (&core.device, &core.queue);
     let texture_diffuse_view = gen_texture_view(
         std::fs::read("assets/texture/blending_transparent_window.png").unwrap(),
-        &device,
-        &queue,
+        device,
+        queue,
     )
     .unwrap();
 
@@ -0,0 +1,84 @@
+// average scene luminance from a log2 histogram, adapted over time like an eye
+
+struct ToneMapping {
+    method: u32,
+    exposure: f32,
+    is_auto_exposure: u32,
+    is_encode_srgb: u32,
+    min_log_luminance: f32,
+    log_luminance_range: f32,
+    adaptation_time: f32,
+    delta_time: f32,
+}
+
+const BIN_COUNT: u32 = 256u;
+
+@group(0)@binding(0)
+var texture_hdr: texture_2d<f32>;
+@group(0)@binding(1)
+var<storage, read_write> histogram: array<atomic<u32>, 256>;
+@group(0)@binding(2)
+var<storage, read_write> luminance: f32;
+@group(0)@binding(3)
+var<uniform> tone_mapping: ToneMapping;
+
+var<workgroup> histogram_shared: array<atomic<u32>, 256>;
+var<workgroup> weight_shared: array<f32, 256>;
+
+// bin 0 is black and stays out of the average
+fn luminance_bin(color: vec3<f32>) -> u32 {
+    let l = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+    if l < 0.0001 {
+        return 0u;
+    }
+    let t = clamp((log2(l) - tone_mapping.min_log_luminance) / tone_mapping.log_luminance_range, 0.0, 1.0);
+    return u32(t * f32(BIN_COUNT - 2u)) + 1u;
+}
+
+// 16x16 pixels per workgroup, one bin per invocation
+@compute @workgroup_size(16, 16, 1)
+fn cs_histogram(@builtin(global_invocation_id) id: vec3<u32>, @builtin(local_invocation_index) index: u32) {
+    atomicStore(&histogram_shared[index], 0u);
+    workgroupBarrier();
+
+    let size = textureDimensions(texture_hdr);
+    if id.x < size.x && id.y < size.y {
+        let color = textureLoad(texture_hdr, vec2<i32>(id.xy), 0).rgb;
+        atomicAdd(&histogram_shared[luminance_bin(color)], 1u);
+    }
+    workgroupBarrier();
+
+    atomicAdd(&histogram[index], atomicLoad(&histogram_shared[index]));
+}
+
+// one workgroup, sums the weighted bins and clears the histogram for the next frame
+@compute @workgroup_size(256, 1, 1)
+fn cs_average(@builtin(local_invocation_index) index: u32) {
+    let count = atomicExchange(&histogram[index], 0u);
+    weight_shared[index] = f32(count) * f32(index);
+    workgroupBarrier();
+
+    for (var stride = BIN_COUNT / 2u; stride > 0u; stride = stride / 2u) {
+        if index < stride {
+            weight_shared[index] += weight_shared[index + stride];
+        }
+        workgroupBarrier();
+    }
+
+    // invocation 0 holds the black count
+    if index == 0u {
+        let size = textureDimensions(texture_hdr);
+        let lit_count = max(f32(size.x * size.y) - f32(count), 1.0);
+        let bin = max(weight_shared[0] / lit_count - 1.0, 0.0);
+        let log_luminance = bin / f32(BIN_COUNT - 2u) * tone_mapping.log_luminance_range + tone_mapping.min_log_luminance;
+        let target_luminance = exp2(log_luminance);
+
+        // the first frame starts adapted
+        if luminance <= 0.0 {
+            luminance = target_luminance;
+        } else {
+            let t = 1.0 - exp(-tone_mapping.delta_time / tone_mapping.adaptation_time);
+            luminance += (target_luminance - luminance) * t;
+        }
+    }
+}
@@ -0,0 +1,78 @@
+// bright pass and a mip chain blur of the hdr image
+
+struct Bloom {
+    is_enabled: u32,
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+}
+
+struct VertexOut {
+    @builtin(position) clip_pos: vec4<f32>,
+    @location(0) tex_coord: vec2<f32>,
+}
+
+@group(0)@binding(0)
+var texture_sampler: sampler;
+@group(0)@binding(1)
+var texture_src: texture_2d<f32>;
+@group(0)@binding(2)
+var<uniform> bloom: Bloom;
+
+// one triangle covering the screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOut {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOut;
+    out.clip_pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.tex_coord = uv;
+    return out;
+}
+
+// dual kawase, the center and four diagonal taps on the texel corners
+fn downsample(uv: vec2<f32>) -> vec3<f32> {
+    let half_texel = 0.5 / vec2<f32>(textureDimensions(texture_src));
+    var color = textureSample(texture_src, texture_sampler, uv).rgb * 4.0;
+    color += textureSample(texture_src, texture_sampler, uv + vec2<f32>(-half_texel.x, -half_texel.y)).rgb;
+    color += textureSample(texture_src, texture_sampler, uv + vec2<f32>(half_texel.x, -half_texel.y)).rgb;
+    color += textureSample(texture_src, texture_sampler, uv + vec2<f32>(-half_texel.x, half_texel.y)).rgb;
+    color += textureSample(texture_src, texture_sampler, uv + vec2<f32>(half_texel.x, half_texel.y)).rgb;
+    return color / 8.0;
+}
+
+// soft knee below the threshold instead of a hard cut
+fn bright_pass(color: vec3<f32>) -> vec3<f32> {
+    let brightness = max(color.r, max(color.g, color.b));
+    var soft = clamp(brightness - bloom.threshold + bloom.knee, 0.0, 2.0 * bloom.knee);
+    soft = soft * soft / (4.0 * bloom.knee + 0.0001);
+    let contribution = max(soft, brightness - bloom.threshold) / max(brightness, 0.0001);
+    return color * contribution;
+}
+
+// the first level, from the full size hdr image
+@fragment
+fn fs_prefilter(in: VertexOut) -> @location(0) vec4<f32> {
+    return vec4<f32>(bright_pass(downsample(in.tex_coord)), 1.0);
+}
+
+@fragment
+fn fs_downsample(in: VertexOut) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample(in.tex_coord), 1.0);
+}
+
+// 3x3 tent, added onto the larger level by the blend state
+@fragment
+fn fs_upsample(in: VertexOut) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(texture_src));
+    let uv = in.tex_coord;
+    var color = textureSample(texture_src, texture_sampler, uv).rgb * 4.0;
+    color += textureSample(texture_src, texture_sampler, uv + vec2<f32>(-texel.x, 0.0)).rgb * 2.0;
+    color += textureSample(texture_src, texture_sampler, uv + vec2<f32>(texel.x, 0.0)).rgb * 2.0;
+    color += textureSample(texture_src, texture_sampler, uv + vec2<f32>(0.0, -texel.y)).rgb * 2.0;
+    color += textureSample(texture_src, texture_sampler, uv + vec2<f32>(0.0, texel.y)).rgb * 2.0;
+    color += textureSample(texture_src, texture_sampler, uv + vec2<f32>(-texel.x, -texel.y)).rgb;
+    color += textureSample(texture_src, texture_sampler, uv + vec2<f32>(texel.x, -texel.y)).rgb;
+    color += textureSample(texture_src, texture_sampler, uv + vec2<f32>(-texel.x, texel.y)).rgb;
+    color += textureSample(texture_src, texture_sampler, uv + vec2<f32>(texel.x, texel.y)).rgb;
+    return vec4<f32>(color / 16.0, 1.0);
+}
@@ -0,0 +1,17 @@
+struct ChromaticAberration {
+    // uv shift of red and blue at the screen edge
+    offset: f32,
+}
+
+@group(1)@binding(0)
+var<uniform> chromatic_aberration: ChromaticAberration;
+
+// red and blue pulled apart along the direction from the center
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    let dir = (in.tex_coord - vec2<f32>(0.5)) * chromatic_aberration.offset;
+    let color = textureSample(texture_in, texture_sampler, in.tex_coord);
+    let r = textureSample(texture_in, texture_sampler, in.tex_coord + dir).r;
+    let b = textureSample(texture_in, texture_sampler, in.tex_coord - dir).b;
+    return vec4<f32>(r, color.g, b, color.a);
+}
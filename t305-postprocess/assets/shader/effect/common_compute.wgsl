@@ -0,0 +1,7 @@
+// prepended to every compute effect, group 1 is the effect's own parameters
+
+@group(0)@binding(0)
+var texture_in: texture_2d<f32>;
+@group(0)@binding(1)
+var texture_out: texture_storage_2d<rgba16float, write>;
+
@@ -0,0 +1,22 @@
+// prepended to every fragment effect, group 1 is the effect's own parameters
+
+struct VertexOut {
+    @builtin(position) clip_pos: vec4<f32>,
+    @location(0) tex_coord: vec2<f32>,
+}
+
+@group(0)@binding(0)
+var texture_sampler: sampler;
+@group(0)@binding(1)
+var texture_in: texture_2d<f32>;
+
+// one triangle covering the screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOut {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOut;
+    out.clip_pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.tex_coord = uv;
+    return out;
+}
+
@@ -0,0 +1,14 @@
+struct Grayscale {
+    weight: vec3<f32>,
+    amount: f32,
+}
+
+@group(1)@binding(0)
+var<uniform> grayscale: Grayscale;
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    let color = textureSample(texture_in, texture_sampler, in.tex_coord);
+    let average = dot(color.rgb, grayscale.weight);
+    return vec4<f32>(mix(color.rgb, vec3<f32>(average), grayscale.amount), color.a);
+}
@@ -0,0 +1,15 @@
+struct Inversion {
+    // hdr has no upper bound, invert against this white
+    white: f32,
+    amount: f32,
+}
+
+@group(1)@binding(0)
+var<uniform> inversion: Inversion;
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    let color = textureSample(texture_in, texture_sampler, in.tex_coord);
+    let inverted = max(vec3<f32>(inversion.white) - color.rgb, vec3<f32>(0.0));
+    return vec4<f32>(mix(color.rgb, inverted, inversion.amount), color.a);
+}
@@ -0,0 +1,62 @@
+struct Kernel {
+    // weights per side, odd
+    size: u32,
+    normalization: f32,
+    // pixels between taps
+    offset: i32,
+    // 0 clamp, 1 mirror, 2 wrap
+    border: u32,
+}
+
+@group(1)@binding(0)
+var<uniform> kernel: Kernel;
+// size * size weights, row 0 is the top row
+@group(1)@binding(1)
+var<storage, read> weight_arr: array<f32>;
+
+// floored modulo, always in [0, b), % on negative operands is not portable
+fn modulo(a: vec2<i32>, b: vec2<i32>) -> vec2<i32> {
+    return a - b * vec2<i32>(floor(vec2<f32>(a) / vec2<f32>(b)));
+}
+
+// keep taps past the edge inside the texture
+fn border_coord(coord: vec2<i32>, size: vec2<i32>) -> vec2<i32> {
+    switch kernel.border {
+        case 1u: {
+            // repeats the edge pixel like mirrored repeat, -1 reads 0
+            let period = size * 2;
+            let m = modulo(coord, period);
+            return select(m, period - 1 - m, m >= size);
+        }
+        case 2u: {
+            return modulo(coord, size);
+        }
+        default: {
+            return clamp(coord, vec2<i32>(0), size - 1);
+        }
+    }
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn cs_main(@builtin(global_invocation_id) idx: vec3<u32>) {
+    let texture_size = textureDimensions(texture_in);
+    if idx.x >= texture_size.x || idx.y >= texture_size.y {
+        return;
+    }
+
+    let tex_coord = vec2<i32>(i32(idx.x), i32(idx.y));
+    let size = vec2<i32>(texture_size);
+    let radius = i32(kernel.size / 2u);
+
+    var color = vec3<f32>(0.0);
+    for (var y = 0; y < i32(kernel.size); y++) {
+        for (var x = 0; x < i32(kernel.size); x++) {
+            let offset = vec2<i32>(x - radius, y - radius) * kernel.offset;
+            let sample_tex = textureLoad(texture_in, border_coord(tex_coord + offset, size), 0).xyz;
+            color += sample_tex * weight_arr[u32(y) * kernel.size + u32(x)];
+        }
+    }
+
+    textureStore(texture_out, tex_coord, vec4<f32>(color * kernel.normalization, 1.0));
+}
@@ -0,0 +1,17 @@
+struct Vignette {
+    intensity: f32,
+    // distance from the center where darkening starts, 0.5 is the edge
+    radius: f32,
+    softness: f32,
+}
+
+@group(1)@binding(0)
+var<uniform> vignette: Vignette;
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    let color = textureSample(texture_in, texture_sampler, in.tex_coord);
+    let dist = distance(in.tex_coord, vec2<f32>(0.5));
+    let shade = smoothstep(vignette.radius, vignette.radius + vignette.softness, dist);
+    return vec4<f32>(color.rgb * (1.0 - shade * vignette.intensity), color.a);
+}
@@ -11,11 +11,6 @@ struct VertexOut {
     @location(2) tex_coord: vec2<f32>,
 }
 
-struct FragmentOut {
-    @location(0) view0: vec4<f32>,
-    @location(1) view1: vec4<f32>,
-}
-
 struct TransformIT {
     @location(5) t0: vec4<f32>,
     @location(6) t1: vec4<f32>,
@@ -108,11 +103,15 @@ var texture_diffuse: texture_2d<f32>;
 var<uniform> color: vec3<f32>;
 @group(2)@binding(3)
 var<uniform> shininess: f32;
+@group(2)@binding(4)
+var texture_emission: texture_2d<f32>;
+@group(2)@binding(5)
+var<uniform> emission_strength: f32;
 
 
 
 @fragment
-fn fs_main(in: VertexOut) -> FragmentOut {
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
     var l = vec3<f32>(0.0, 0.0, 0.0);
     let normal = normalize(in.normal);
     let view_dir = normalize(camera_pos - in.frag_pos);
@@ -131,10 +130,10 @@ fn fs_main(in: VertexOut) -> FragmentOut {
         l += do_light_spot(light_spot_arr.arr[i], normal, tex_diffuse, in.frag_pos);
     }
 
-    var out: FragmentOut;
-    out.view0 = vec4<f32>(l, 1.0);
-    out.view1 = vec4<f32>(l, 1.0);
-    return out;
+    // above 1.0 it reaches the bloom threshold
+    l += textureSample(texture_emission, texture_sampler, in.tex_coord).rgb * emission_strength;
+
+    return vec4<f32>(l, 1.0);
 }
 
 fn do_light_direction(light_direction: LightDirection, normal: vec3<f32>, view_dir: vec3<f32>, tex_diffuse: vec3<f32>) -> vec3<f32> {
@@ -9,6 +9,25 @@ struct VertexOut {
     @location(0) tex_coord: vec2<f32>,
 }
 
+struct ToneMapping {
+    // 0 none, 1 reinhard, 2 aces filmic, 3 exposure
+    method: u32,
+    exposure: f32,
+    is_auto_exposure: u32,
+    is_encode_srgb: u32,
+    min_log_luminance: f32,
+    log_luminance_range: f32,
+    adaptation_time: f32,
+    delta_time: f32,
+}
+
+struct Bloom {
+    is_enabled: u32,
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+}
+
 @vertex
 fn vs_main(in: VertexIn) -> VertexOut {
     var out: VertexOut;
@@ -21,17 +40,71 @@ fn vs_main(in: VertexIn) -> VertexOut {
 var texture_post_processing_sampler: sampler;
 @group(0)@binding(1)
 var texture_post_processing: texture_2d<f32>;
+@group(0)@binding(2)
+var<uniform> tone_mapping: ToneMapping;
+@group(0)@binding(3)
+var<storage, read> luminance: f32;
+@group(0)@binding(4)
+var texture_bloom: texture_2d<f32>;
+@group(0)@binding(5)
+var<uniform> bloom: Bloom;
+
+// middle gray the average luminance is exposed to
+const KEY: f32 = 0.18;
+
+// krzysztof narkowicz's fit of the aces reference curve
+fn aces_filmic(x: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+fn tone_map(hdr: vec3<f32>) -> vec3<f32> {
+    var exposure = tone_mapping.exposure;
+    if tone_mapping.is_auto_exposure != 0u {
+        exposure *= KEY / max(luminance, 0.0001);
+    }
+    let color = hdr * exposure;
+
+    switch tone_mapping.method {
+        case 1u: {
+            return color / (color + 1.0);
+        }
+        case 2u: {
+            return aces_filmic(color);
+        }
+        case 3u: {
+            return 1.0 - exp(-color);
+        }
+        default: {
+            return clamp(color, vec3<f32>(0.0), vec3<f32>(1.0));
+        }
+    }
+}
+
+// for a surface without an srgb format
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
 
 @fragment
 fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
-    let t = textureSample(texture_post_processing, texture_post_processing_sampler, in.tex_coord);
-    //return vec4<f32>(1.0, 1.0, 0.0, 1.0);
-
-    // Inversion
-    //return vec4<f32>(1.0 - t.rgb, 1.0);
+    var hdr = textureSample(texture_post_processing, texture_post_processing_sampler, in.tex_coord).rgb;
+    // the bloom texture is half size, the sampler filters it up
+    let blur = textureSample(texture_bloom, texture_post_processing_sampler, in.tex_coord).rgb;
+    if bloom.is_enabled != 0u {
+        hdr += blur * bloom.intensity;
+    }
+    var t = vec4<f32>(tone_map(hdr), 1.0);
+    //t = vec4<f32>(1.0, 1.0, 0.0, 1.0);
 
-    // Grayscale
-    //let average = 0.2126 * t.r + 0.7152 * t.g + 0.0722 * t.b;
-    //return vec4<f32>(average, average, average, 1.0);
+    if tone_mapping.is_encode_srgb != 0u {
+        t = vec4<f32>(linear_to_srgb(t.rgb), t.a);
+    }
     return t;
 }
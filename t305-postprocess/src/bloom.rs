@@ -0,0 +1,328 @@
+use wgpu::{
+    util::DeviceExt, BindGroup, BindGroupLayout, Buffer, Device, Queue, RenderPipeline, Sampler,
+    ShaderStages, TextureView,
+};
+
+use crate::texture::{self, gen_texture_view_bloom};
+
+// levels below the half size one, fewer when the window is small
+const BLOOM_MIP_COUNT: u32 = 6;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BloomParam {
+    pub is_enabled: u32,
+    // brightness where the bright pass starts, in hdr units
+    pub threshold: f32,
+    // width of the soft transition below the threshold
+    pub knee: f32,
+    // how much of the blurred image is added back
+    pub intensity: f32,
+}
+
+impl BloomParam {
+    pub fn new(threshold: f32, knee: f32, intensity: f32) -> Self {
+        Self {
+            is_enabled: 1,
+            threshold,
+            knee,
+            intensity,
+        }
+    }
+}
+
+// the hdr image blurred down and up a mip chain, read by the tone mapping pass
+pub struct Bloom {
+    pub param: BloomParam,
+    pub buffer_param: Buffer,
+    pub sampler: Sampler,
+
+    pub render_pipline_prefilter: RenderPipeline,
+    pub render_pipline_downsample: RenderPipeline,
+    pub render_pipline_upsample: RenderPipeline,
+    pub bind_group_layout_bloom: BindGroupLayout,
+
+    // one view per level, level 0 is the result
+    pub texture_view_mip_arr: Vec<TextureView>,
+    pub bind_group_hdr: BindGroup,
+    pub bind_group_mip_arr: Vec<BindGroup>,
+}
+
+impl Bloom {
+    pub fn new(
+        device: &Device,
+        param: BloomParam,
+        texture_view_hdr: &TextureView,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let buffer_param = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Buffer Bloom Param"),
+            contents: bytemuck::bytes_of(&param),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        // the taps land between texels and rely on bilinear filtering
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Sampler Bloom"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bind_group_layout_bloom =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Bind Group Layout Bloom"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let render_pipline_layout_bloom =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipline Layout Bloom"),
+                bind_group_layouts: &[&bind_group_layout_bloom],
+                push_constant_ranges: &[],
+            });
+
+        let shader = std::fs::read_to_string("assets/shader/bloom.wgsl").unwrap();
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader Bloom"),
+            source: wgpu::ShaderSource::Wgsl(shader.into()),
+        });
+        let gen_render_pipline = |label, entry_point, blend| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&render_pipline_layout_bloom),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: texture::HDR_FORMAT,
+                        blend: Some(blend),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            })
+        };
+        let render_pipline_prefilter = gen_render_pipline(
+            "Render Pipline Bloom Prefilter",
+            "fs_prefilter",
+            wgpu::BlendState::REPLACE,
+        );
+        let render_pipline_downsample = gen_render_pipline(
+            "Render Pipline Bloom Downsample",
+            "fs_downsample",
+            wgpu::BlendState::REPLACE,
+        );
+        // each level is added onto the one above it
+        let render_pipline_upsample = gen_render_pipline(
+            "Render Pipline Bloom Upsample",
+            "fs_upsample",
+            wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::REPLACE,
+            },
+        );
+
+        let texture_view_mip_arr = gen_texture_view_bloom(device, width, height, BLOOM_MIP_COUNT);
+        let bind_group_hdr = gen_bind_group_bloom(
+            device,
+            &bind_group_layout_bloom,
+            &sampler,
+            texture_view_hdr,
+            &buffer_param,
+        );
+        let bind_group_mip_arr = texture_view_mip_arr
+            .iter()
+            .map(|texture_view| {
+                gen_bind_group_bloom(
+                    device,
+                    &bind_group_layout_bloom,
+                    &sampler,
+                    texture_view,
+                    &buffer_param,
+                )
+            })
+            .collect();
+
+        Self {
+            param,
+            buffer_param,
+            sampler,
+            render_pipline_prefilter,
+            render_pipline_downsample,
+            render_pipline_upsample,
+            bind_group_layout_bloom,
+            texture_view_mip_arr,
+            bind_group_hdr,
+            bind_group_mip_arr,
+        }
+    }
+
+    // the chain follows the size of the hdr target
+    pub fn resize(
+        &mut self,
+        device: &Device,
+        texture_view_hdr: &TextureView,
+        width: u32,
+        height: u32,
+    ) {
+        self.texture_view_mip_arr = gen_texture_view_bloom(device, width, height, BLOOM_MIP_COUNT);
+        self.bind_group_hdr = gen_bind_group_bloom(
+            device,
+            &self.bind_group_layout_bloom,
+            &self.sampler,
+            texture_view_hdr,
+            &self.buffer_param,
+        );
+        self.bind_group_mip_arr = self
+            .texture_view_mip_arr
+            .iter()
+            .map(|texture_view| {
+                gen_bind_group_bloom(
+                    device,
+                    &self.bind_group_layout_bloom,
+                    &self.sampler,
+                    texture_view,
+                    &self.buffer_param,
+                )
+            })
+            .collect();
+    }
+
+    // level 0, what gets composited
+    pub fn texture_view(&self) -> &TextureView {
+        &self.texture_view_mip_arr[0]
+    }
+
+    pub fn update(&self, queue: &Queue) {
+        queue.write_buffer(&self.buffer_param, 0, bytemuck::bytes_of(&self.param));
+    }
+
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.param.is_enabled == 0 {
+            return;
+        }
+
+        // bright pass into level 0, then each level from the one above
+        for (i, texture_view) in self.texture_view_mip_arr.iter().enumerate() {
+            let (render_pipline, bind_group) = match i {
+                0 => (&self.render_pipline_prefilter, &self.bind_group_hdr),
+                _ => (
+                    &self.render_pipline_downsample,
+                    &self.bind_group_mip_arr[i - 1],
+                ),
+            };
+            draw_level(
+                encoder,
+                "Render Pass Bloom Downsample",
+                texture_view,
+                wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                render_pipline,
+                bind_group,
+            );
+        }
+
+        // back up, level 0 ends up with the sum of every level
+        for i in (1..self.texture_view_mip_arr.len()).rev() {
+            draw_level(
+                encoder,
+                "Render Pass Bloom Upsample",
+                &self.texture_view_mip_arr[i - 1],
+                wgpu::LoadOp::Load,
+                &self.render_pipline_upsample,
+                &self.bind_group_mip_arr[i],
+            );
+        }
+    }
+}
+
+fn draw_level(
+    encoder: &mut wgpu::CommandEncoder,
+    label: &str,
+    texture_view: &TextureView,
+    load: wgpu::LoadOp<wgpu::Color>,
+    render_pipline: &RenderPipeline,
+    bind_group: &BindGroup,
+) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: texture_view,
+            resolve_target: None,
+            ops: wgpu::Operations { load, store: true },
+        })],
+        depth_stencil_attachment: None,
+    });
+    render_pass.set_pipeline(render_pipline);
+    render_pass.set_bind_group(0, bind_group, &[]);
+    render_pass.draw(0..3, 0..1);
+}
+
+fn gen_bind_group_bloom(
+    device: &Device,
+    layout: &BindGroupLayout,
+    sampler: &Sampler,
+    texture_view_src: &TextureView,
+    buffer_param: &Buffer,
+) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Bind Group Bloom"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(texture_view_src),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer(buffer_param.as_entire_buffer_binding()),
+            },
+        ],
+    })
+}
@@ -1,14 +1,22 @@
 use std::time::Instant;
 
+use anyhow::{anyhow, Result};
+use image::RgbaImage;
+use log::debug;
 use wgpu::{
-    util::DeviceExt, Adapter, Backends, BindGroup, BindGroupLayout, Buffer, ComputePipeline,
+    util::DeviceExt, Adapter, Backends, BindGroup, BindGroupLayout, Buffer, CompositeAlphaMode,
     Device, DeviceDescriptor, Features, IndexFormat, Instance, PresentMode, Queue, RenderPipeline,
-    Sampler, ShaderStages, Surface, SurfaceConfiguration, TextureUsages, TextureView,
+    Sampler, ShaderStages, Surface, SurfaceConfiguration, Texture, TextureFormat, TextureUsages,
+    TextureView,
 };
-use winit::{dpi::PhysicalPosition, event_loop::EventLoop, window::Window};
+use winit::{dpi::PhysicalPosition, event::VirtualKeyCode, event_loop::EventLoop, window::Window};
 
 use crate::{
+    bloom::{Bloom, BloomParam},
     camera::Camera,
+    headless::{
+        check_format_headless, gen_texture_headless, request_adapter_headless, texture_to_image,
+    },
     input::Input,
     light_direction::LightDirection,
     light_point::LightPoint,
@@ -16,39 +24,46 @@ use crate::{
     material::Material,
     model::DrawMethod,
     model_light::ModelLight,
-    texture::{
-        self, gen_texture_depth, gen_texture_sampler, gen_texture_view_post_processing_in,
-        gen_texture_view_post_processing_out,
+    post_process_chain::{PostProcessChain, PostProcessEffect},
+    post_process_effect::{
+        ChromaticAberrationParam, Effect, GrayscaleParam, InversionParam, VignetteParam,
     },
-    transform::TransformRawIT,
+    post_process_kernel::{BorderMode, Kernel, KernelEffect},
+    texture::{self, gen_texture_depth, gen_texture_sampler},
+    tone_mapping::{ToneMapping, ToneMappingMethod, ToneMappingParam},
+    transform::{TransformRaw, TransformRawIT},
     vertex::Vertex,
+    vertex_light::VertexLight,
 };
 
 pub struct Core {
-    pub window: Window,
+    pub window: Option<Window>,
     pub instance: Instance,
-    pub surface: Surface,
+    pub surface: Option<Surface>,
     pub adapter: Adapter,
     pub device: Device,
     pub queue: Queue,
     pub surface_config: SurfaceConfiguration,
+    // offscreen target when there is no surface
+    pub texture_headless: Option<Texture>,
     pub render_pipline_mesh: RenderPipeline,
     pub render_pipline_light: RenderPipeline,
     pub camera: Camera,
     pub input: Input,
 
     pub render_pipline_post_processing: RenderPipeline,
-    pub compute_pipline_post_processing: ComputePipeline,
     pub bind_group_layout_post_processing: BindGroupLayout,
-    pub bind_group_layout_post_processing_compute: BindGroupLayout,
     pub bind_group_post_processing: BindGroup,
-    pub bind_group_post_processing_compute: BindGroup,
-    pub texture_view_post_processing_compute_in: TextureView,
-    pub texture_view_post_processing_compute_out: TextureView,
+    pub post_process_chain: PostProcessChain,
+    // the effect number keys last toggled, arrow keys move it in the chain
+    pub effect_selected: usize,
     pub vertex_buffer_post_processing: Buffer,
     pub index_buffer_post_processing: Buffer,
     pub index_post_processing_len: u32,
 
+    pub tone_mapping: ToneMapping,
+    pub bloom: Bloom,
+
     pub texture_sampler: Sampler,
 
     pub camera_bind_group: BindGroup,
@@ -97,6 +112,68 @@ impl Core {
             .await
             .unwrap();
 
+        let surface_caps = surface.get_capabilities(&adapter);
+        // prefer srgb, the tone mapping pass encodes by hand otherwise
+        let format = surface_caps
+            .formats
+            .iter()
+            .copied()
+            .find(|format| format.is_srgb())
+            .unwrap_or(surface_caps.formats[0]);
+        let surface_config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width: window_size.width,
+            height: window_size.height,
+            present_mode: PresentMode::Fifo,
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![],
+        };
+
+        Self::init(
+            instance,
+            adapter,
+            Some(window),
+            Some(surface),
+            surface_config,
+        )
+        .await
+        .unwrap()
+    }
+
+    // render into an offscreen texture of the given size and format instead of a window
+    pub async fn new_headless(width: u32, height: u32, format: TextureFormat) -> Result<Self> {
+        check_format_headless(format)?;
+
+        let instance = Instance::new(wgpu::InstanceDescriptor {
+            backends: Backends::all(),
+            dx12_shader_compiler: Default::default(),
+        });
+
+        let adapter = request_adapter_headless(&instance)
+            .await
+            .ok_or_else(|| anyhow!("no adapter for headless rendering"))?;
+
+        let surface_config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            format,
+            width,
+            height,
+            present_mode: PresentMode::Fifo,
+            alpha_mode: CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+
+        Self::init(instance, adapter, None, None, surface_config).await
+    }
+
+    async fn init(
+        instance: Instance,
+        adapter: Adapter,
+        window: Option<Window>,
+        surface: Option<Surface>,
+        surface_config: SurfaceConfiguration,
+    ) -> Result<Self> {
         let (device, queue) = adapter
             .request_device(
                 &DeviceDescriptor {
@@ -106,20 +183,15 @@ impl Core {
                 },
                 None,
             )
-            .await
-            .unwrap();
+            .await?;
 
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_config = SurfaceConfiguration {
-            usage: TextureUsages::RENDER_ATTACHMENT,
-            format: surface_caps.formats[0],
-            width: window_size.width,
-            height: window_size.height,
-            present_mode: PresentMode::Fifo,
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
+        let texture_headless = match &surface {
+            Some(surface) => {
+                surface.configure(&device, &surface_config);
+                None
+            }
+            None => Some(gen_texture_headless(&device, &surface_config)),
         };
-        surface.configure(&device, &surface_config);
 
         let mesh_shader = std::fs::read_to_string("assets/shader/mesh.wgsl").unwrap();
         let mesh_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -247,6 +319,26 @@ impl Core {
                         },
                         count: None,
                     },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 5,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
                 ],
             });
 
@@ -270,36 +362,39 @@ impl Core {
                         },
                         count: None,
                     },
-                ],
-            });
-
-        let bind_group_layout_post_processing_compute =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Bind Group Layout Post Processing Compute"),
-                entries: &[
                     wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            multisampled: false,
+                        binding: 2,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
                         },
                         count: None,
                     },
                     wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::StorageTexture {
-                            access: wgpu::StorageTextureAccess::WriteOnly,
-                            format: wgpu::TextureFormat::Rgba8Unorm,
+                        binding: 3,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
                             view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
                         },
                         count: None,
                     },
                     wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: ShaderStages::COMPUTE,
+                        binding: 5,
+                        visibility: ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::Buffer {
                             ty: wgpu::BufferBindingType::Uniform,
                             has_dynamic_offset: false,
@@ -334,13 +429,6 @@ impl Core {
                 bind_group_layouts: &[&bind_group_layout_post_processing],
                 push_constant_ranges: &[],
             });
-        let compute_pipline_layout_post_processing =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Compute Pipline Layout Post Processing"),
-                bind_group_layouts: &[&bind_group_layout_post_processing_compute],
-                push_constant_ranges: &[],
-            });
-
         let render_pipline_mesh = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Render Pipline"),
             layout: Some(&render_pipline_layout_mesh),
@@ -380,18 +468,11 @@ impl Core {
             fragment: Some(wgpu::FragmentState {
                 module: &mesh_shader,
                 entry_point: "fs_main",
-                targets: &[
-                    Some(wgpu::ColorTargetState {
-                        format: surface_config.format,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    }),
-                    Some(wgpu::ColorTargetState {
-                        format: surface_config.format,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    }),
-                ],
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: texture::HDR_FORMAT,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
             }),
             multiview: None,
         });
@@ -403,9 +484,8 @@ impl Core {
                 module: &light_shader,
                 entry_point: "vs_main",
                 buffers: &[
-                    Vertex::vertex_buffer_layout(),
-                    TransformRawIT::vertex_buffer_layout(),
-                    crate::color::Color::vertex_buffer_layout(),
+                    VertexLight::vertex_buffer_layout(),
+                    TransformRaw::vertex_buffer_layout(),
                 ],
             },
             primitive: wgpu::PrimitiveState {
@@ -433,7 +513,7 @@ impl Core {
                 module: &light_shader,
                 entry_point: "fs_main",
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_config.format,
+                    format: texture::HDR_FORMAT,
                     blend: Some(wgpu::BlendState::REPLACE),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
@@ -483,20 +563,6 @@ impl Core {
                 multiview: None,
             });
 
-        let compute_shader =
-            std::fs::read_to_string("assets/shader/post_processing_compute.wgsl").unwrap();
-        let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Compute Pipline Post Processing"),
-            source: wgpu::ShaderSource::Wgsl(compute_shader.into()),
-        });
-
-        let compute_pipline_post_processing =
-            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-                label: Some("Compute Pipline Post Processing"),
-                layout: Some(&compute_pipline_layout_post_processing),
-                module: &compute_shader,
-                entry_point: "cp_main",
-            });
         let camera = Camera::new(surface_config.width as _, surface_config.height as _);
         let view_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("View Buffer"),
@@ -547,7 +613,7 @@ impl Core {
 
         let light_point_arr = vec![LightPoint::new(
             [-2.5, 0.0, -1.0],
-            [1.0, 0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0, 1.0],
             [0.1, 0.1, 0.1],
             [0.5, 0.5, 0.5],
             [1.0, 1.0, 1.0],
@@ -629,10 +695,92 @@ impl Core {
                 usage: wgpu::BufferUsages::INDEX,
             });
 
-        let texture_view_post_processing_compute_in =
-            gen_texture_view_post_processing_in(&device, &surface_config);
-        let texture_view_post_processing_compute_out =
-            gen_texture_view_post_processing_out(&device, &surface_config);
+        let mut post_process_chain =
+            PostProcessChain::new(&device, surface_config.width, surface_config.height);
+        // only the edge kernel runs at first, number keys toggle the others
+        let effect_arr: Vec<Box<dyn PostProcessEffect>> = vec![
+            Box::new(KernelEffect::new(
+                &device,
+                &post_process_chain,
+                "Edge",
+                Kernel::edge(),
+                BorderMode::Clamp,
+            )),
+            Box::new(
+                KernelEffect::new(
+                    &device,
+                    &post_process_chain,
+                    "Sharpen",
+                    Kernel::sharpen(),
+                    BorderMode::Clamp,
+                )
+                .with_enabled(false),
+            ),
+            Box::new(
+                KernelEffect::new(
+                    &device,
+                    &post_process_chain,
+                    "Gaussian Blur",
+                    Kernel::gaussian(2.0),
+                    BorderMode::Mirror,
+                )
+                .with_enabled(false),
+            ),
+            Box::new(
+                KernelEffect::new(
+                    &device,
+                    &post_process_chain,
+                    "Emboss",
+                    Kernel::emboss(),
+                    BorderMode::Clamp,
+                )
+                .with_enabled(false),
+            ),
+            Box::new(
+                Effect::grayscale(&device, &post_process_chain, GrayscaleParam::new(1.0))
+                    .with_enabled(false),
+            ),
+            Box::new(
+                Effect::inversion(&device, &post_process_chain, InversionParam::new(1.0, 1.0))
+                    .with_enabled(false),
+            ),
+            Box::new(
+                Effect::vignette(
+                    &device,
+                    &post_process_chain,
+                    VignetteParam::new(0.8, 0.25, 0.5),
+                )
+                .with_enabled(false),
+            ),
+            Box::new(
+                Effect::chromatic_aberration(
+                    &device,
+                    &post_process_chain,
+                    ChromaticAberrationParam::new(0.02),
+                )
+                .with_enabled(false),
+            ),
+        ];
+        for effect in effect_arr {
+            post_process_chain.push(effect);
+        }
+
+        let tone_mapping = ToneMapping::new(
+            &device,
+            ToneMappingParam::new(
+                ToneMappingMethod::AcesFilmic,
+                !surface_config.format.is_srgb(),
+            ),
+            post_process_chain.texture_view_out(),
+        );
+        let bloom = Bloom::new(
+            &device,
+            BloomParam::new(1.0, 0.5, 0.5),
+            post_process_chain.texture_view_out(),
+            surface_config.width,
+            surface_config.height,
+        );
+
         let bind_group_post_processing = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Bind Group Post Processing"),
             layout: &bind_group_layout_post_processing,
@@ -644,44 +792,35 @@ impl Core {
                 wgpu::BindGroupEntry {
                     binding: 1,
                     resource: wgpu::BindingResource::TextureView(
-                        &texture_view_post_processing_compute_out,
+                        post_process_chain.texture_view_out(),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(
+                        tone_mapping.buffer_param.as_entire_buffer_binding(),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Buffer(
+                        tone_mapping.buffer_luminance.as_entire_buffer_binding(),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(bloom.texture_view()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::Buffer(
+                        bloom.buffer_param.as_entire_buffer_binding(),
                     ),
                 },
             ],
         });
 
-        let texture_size_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Texture Size Buffer"),
-            contents: bytemuck::cast_slice(&[surface_config.width, surface_config.height]),
-            usage: wgpu::BufferUsages::UNIFORM,
-        });
-        let bind_group_post_processing_compute =
-            device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("Bind Group Post Processing Compute"),
-                layout: &bind_group_layout_post_processing_compute,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(
-                            &texture_view_post_processing_compute_in,
-                        ),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::TextureView(
-                            &texture_view_post_processing_compute_out,
-                        ),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 2,
-                        resource: wgpu::BindingResource::Buffer(
-                            texture_size_buffer.as_entire_buffer_binding(),
-                        ),
-                    },
-                ],
-            });
-
-        Self {
+        Ok(Self {
             window,
             instance,
             surface,
@@ -689,6 +828,7 @@ impl Core {
             device,
             queue,
             surface_config,
+            texture_headless,
             render_pipline_mesh,
             render_pipline_light,
             camera,
@@ -696,17 +836,17 @@ impl Core {
             texture_sampler,
 
             render_pipline_post_processing,
-            compute_pipline_post_processing,
             bind_group_layout_post_processing,
-            bind_group_layout_post_processing_compute,
             bind_group_post_processing,
-            bind_group_post_processing_compute,
-            texture_view_post_processing_compute_in,
-            texture_view_post_processing_compute_out,
+            post_process_chain,
+            effect_selected: 0,
             vertex_buffer_post_processing,
             index_buffer_post_processing,
             index_post_processing_len: indices_post_processing.len() as u32,
 
+            tone_mapping,
+            bloom,
+
             camera_bind_group,
             light_arr_bind_group,
             material_bind_group_layout,
@@ -730,13 +870,19 @@ impl Core {
 
             start_time: Instant::now(),
             last_time: Instant::now(),
-        }
+        })
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
         self.surface_config.width = width;
         self.surface_config.height = height;
-        self.surface.configure(&self.device, &self.surface_config);
+        match &self.surface {
+            Some(surface) => surface.configure(&self.device, &self.surface_config),
+            None => {
+                self.texture_headless =
+                    Some(gen_texture_headless(&self.device, &self.surface_config))
+            }
+        }
         self.camera.update_size(
             self.surface_config.width as _,
             self.surface_config.height as _,
@@ -744,10 +890,15 @@ impl Core {
 
         self.texture_depth = gen_texture_depth(&self.device, &self.surface_config);
 
-        self.texture_view_post_processing_compute_in =
-            gen_texture_view_post_processing_in(&self.device, &self.surface_config);
-        self.texture_view_post_processing_compute_out =
-            gen_texture_view_post_processing_out(&self.device, &self.surface_config);
+        self.post_process_chain.resize(&self.device, width, height);
+        self.tone_mapping
+            .resize(&self.device, self.post_process_chain.texture_view_out());
+        self.bloom.resize(
+            &self.device,
+            self.post_process_chain.texture_view_out(),
+            width,
+            height,
+        );
 
         self.bind_group_post_processing =
             self.device.create_bind_group(&wgpu::BindGroupDescriptor {
@@ -761,43 +912,31 @@ impl Core {
                     wgpu::BindGroupEntry {
                         binding: 1,
                         resource: wgpu::BindingResource::TextureView(
-                            &self.texture_view_post_processing_compute_out,
+                            self.post_process_chain.texture_view_out(),
                         ),
                     },
-                ],
-            });
-
-        let texture_size_buffer =
-            self.device
-                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Texture Size Buffer"),
-                    contents: bytemuck::cast_slice(&[
-                        self.surface_config.width,
-                        self.surface_config.height,
-                    ]),
-                    usage: wgpu::BufferUsages::UNIFORM,
-                });
-        self.bind_group_post_processing_compute =
-            self.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                label: Some("Bind Group Post Processing Compute"),
-                layout: &self.bind_group_layout_post_processing_compute,
-                entries: &[
                     wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(
-                            &self.texture_view_post_processing_compute_in,
+                        binding: 2,
+                        resource: wgpu::BindingResource::Buffer(
+                            self.tone_mapping.buffer_param.as_entire_buffer_binding(),
                         ),
                     },
                     wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::TextureView(
-                            &self.texture_view_post_processing_compute_out,
+                        binding: 3,
+                        resource: wgpu::BindingResource::Buffer(
+                            self.tone_mapping
+                                .buffer_luminance
+                                .as_entire_buffer_binding(),
                         ),
                     },
                     wgpu::BindGroupEntry {
-                        binding: 2,
+                        binding: 4,
+                        resource: wgpu::BindingResource::TextureView(self.bloom.texture_view()),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 5,
                         resource: wgpu::BindingResource::Buffer(
-                            texture_size_buffer.as_entire_buffer_binding(),
+                            self.bloom.buffer_param.as_entire_buffer_binding(),
                         ),
                     },
                 ],
@@ -805,13 +944,21 @@ impl Core {
     }
 
     fn render(&mut self) {
-        let current_texture = self
-            .surface
-            .get_current_texture()
-            .expect("get current texture fail");
-        let texture_view = current_texture
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let current_texture = self.surface.as_ref().map(|surface| {
+            surface
+                .get_current_texture()
+                .expect("get current texture fail")
+        });
+        let texture_view = match &current_texture {
+            Some(current_texture) => current_texture
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+            None => self
+                .texture_headless
+                .as_ref()
+                .unwrap()
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+        };
 
         let mut encoder = self
             .device
@@ -820,34 +967,20 @@ impl Core {
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
-                color_attachments: &[
-                    Some(wgpu::RenderPassColorAttachment {
-                        view: &texture_view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color {
-                                r: 0.1,
-                                g: 0.2,
-                                b: 0.3,
-                                a: 1.0,
-                            }),
-                            store: true,
-                        },
-                    }),
-                    Some(wgpu::RenderPassColorAttachment {
-                        view: &self.texture_view_post_processing_compute_in,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color {
-                                r: 0.1,
-                                g: 0.2,
-                                b: 0.3,
-                                a: 1.0,
-                            }),
-                            store: true,
-                        },
-                    }),
-                ],
+                // hdr, tone mapped onto the surface at the end
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: self.post_process_chain.texture_view_in(),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.1,
+                            g: 0.2,
+                            b: 0.3,
+                            a: 1.0,
+                        }),
+                        store: true,
+                    },
+                })],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
                     view: &self.texture_depth,
                     depth_ops: Some(wgpu::Operations {
@@ -880,24 +1013,31 @@ impl Core {
                     }
                 }
             }
-        }
 
-        // compute pipline
-        // compute kernel effect
-        {
-            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-                label: Some("Compute Pass Post Processing"),
-            });
-            compute_pass.set_pipeline(&self.compute_pipline_post_processing);
-            compute_pass.set_bind_group(0, &self.bind_group_post_processing_compute, &[]);
-            compute_pass.dispatch_workgroups(
-                (self.surface_config.width as f32 / 8.0).ceil() as u32,
-                (self.surface_config.height as f32 / 8.0).ceil() as u32,
-                1,
-            );
+            // light cubes, unlit
+            render_pass.set_pipeline(&self.render_pipline_light);
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            for model_light in &self.model_light_arr {
+                render_pass.set_vertex_buffer(0, model_light.vertex_buffer.slice(..));
+                render_pass.set_vertex_buffer(1, model_light.transform_buffer.slice(..));
+                render_pass.draw(0..model_light.vertices_len, 0..model_light.instance_len);
+            }
         }
 
-        // draw final texture
+        // effects in chain order, from the scene into the chain output
+        self.post_process_chain.render(&mut encoder);
+
+        // blur what is above the threshold, composited when tone mapping
+        self.bloom.render(&mut encoder);
+
+        // exposure of the hdr image about to be tone mapped
+        self.tone_mapping.compute_auto_exposure(
+            &mut encoder,
+            self.surface_config.width,
+            self.surface_config.height,
+        );
+
+        // tone map the final texture
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass Post Processing"),
@@ -930,7 +1070,26 @@ impl Core {
 
         self.queue.submit(std::iter::once(encoder.finish()));
 
-        current_texture.present();
+        if let Some(current_texture) = current_texture {
+            current_texture.present();
+        }
+    }
+
+    // only for a core created by new_headless
+    pub fn render_to_image(&mut self) -> RgbaImage {
+        self.render();
+        texture_to_image(
+            &self.device,
+            &self.queue,
+            self.texture_headless
+                .as_ref()
+                .expect("render to image needs headless mode"),
+            &self.surface_config,
+        )
+    }
+
+    pub fn window(&self) -> &Window {
+        self.window.as_ref().expect("no window in headless mode")
     }
 
     fn update(&mut self) {
@@ -938,7 +1097,15 @@ impl Core {
         let delta_time = (Instant::now() - self.last_time).as_secs_f32();
         self.last_time = Instant::now();
 
+        self.update_delta_time(delta_time);
+    }
+
+    // a fixed delta time keeps headless frames reproducible
+    pub fn update_delta_time(&mut self, delta_time: f32) {
         self.camera.moving(&self.input, delta_time);
+        self.tone_mapping.update(&self.queue, delta_time);
+        self.bloom.update(&self.queue);
+        self.post_process_chain.update(&self.queue);
 
         self.queue.write_buffer(
             &self.view_buffer,
@@ -974,18 +1141,104 @@ impl Core {
         );
     }
 
+    // t cycles the tone mapping method, e toggles auto exposure
+    // b toggles bloom, [ and ] change its intensity
+    // 1 to 9 toggle an effect of the chain, up and down move it
+    // k cycles the border mode of the selected kernel effect
+    fn on_key_released(&mut self, key: Option<VirtualKeyCode>) {
+        let param = &mut self.tone_mapping.param;
+        let bloom_param = &mut self.bloom.param;
+        match key {
+            Some(VirtualKeyCode::T) => {
+                param.set_method(param.method().next());
+                debug!("tone mapping {:?}", param.method());
+            }
+            Some(VirtualKeyCode::E) => {
+                param.is_auto_exposure = 1 - param.is_auto_exposure;
+                debug!("auto exposure {}", param.is_auto_exposure);
+            }
+            Some(VirtualKeyCode::B) => {
+                bloom_param.is_enabled = 1 - bloom_param.is_enabled;
+                debug!("bloom {}", bloom_param.is_enabled);
+            }
+            Some(VirtualKeyCode::LBracket) => {
+                bloom_param.intensity = (bloom_param.intensity - 0.05).max(0.0);
+                debug!("bloom intensity {}", bloom_param.intensity);
+            }
+            Some(VirtualKeyCode::RBracket) => {
+                bloom_param.intensity += 0.05;
+                debug!("bloom intensity {}", bloom_param.intensity);
+            }
+            Some(VirtualKeyCode::K) => {
+                if let Some(effect) = self
+                    .post_process_chain
+                    .effect_mut::<KernelEffect>(self.effect_selected)
+                {
+                    effect.border_mode = effect.border_mode.next();
+                    debug!("{} border {:?}", effect.name(), effect.border_mode);
+                }
+            }
+            Some(VirtualKeyCode::Up) if self.effect_selected > 0 => {
+                self.post_process_chain
+                    .move_effect(self.effect_selected, self.effect_selected - 1);
+                self.effect_selected -= 1;
+                self.debug_post_process_chain();
+            }
+            Some(VirtualKeyCode::Down)
+                if self.effect_selected + 1 < self.post_process_chain.effect_arr.len() =>
+            {
+                self.post_process_chain
+                    .move_effect(self.effect_selected, self.effect_selected + 1);
+                self.effect_selected += 1;
+                self.debug_post_process_chain();
+            }
+            Some(key) => {
+                let index = match key {
+                    VirtualKeyCode::Key1 => 0,
+                    VirtualKeyCode::Key2 => 1,
+                    VirtualKeyCode::Key3 => 2,
+                    VirtualKeyCode::Key4 => 3,
+                    VirtualKeyCode::Key5 => 4,
+                    VirtualKeyCode::Key6 => 5,
+                    VirtualKeyCode::Key7 => 6,
+                    VirtualKeyCode::Key8 => 7,
+                    VirtualKeyCode::Key9 => 8,
+                    _ => return,
+                };
+                if let Some(effect) = self.post_process_chain.effect_arr.get_mut(index) {
+                    effect.set_enabled(!effect.is_enabled());
+                    self.effect_selected = index;
+                    self.debug_post_process_chain();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn debug_post_process_chain(&self) {
+        for (i, effect) in self.post_process_chain.effect_arr.iter().enumerate() {
+            debug!(
+                "{}{} {} {}",
+                if i == self.effect_selected { ">" } else { " " },
+                i + 1,
+                effect.name(),
+                effect.is_enabled()
+            );
+        }
+    }
+
     pub fn add_model_light(&mut self, model_light: ModelLight) {
         self.model_light_arr.push(model_light);
     }
 
     pub fn block_loop(event_loop: EventLoop<()>, mut core: Core) {
         event_loop.run(move |event, _, control_flow| match event {
-            winit::event::Event::RedrawRequested(window_id) if window_id == core.window.id() => {
+            winit::event::Event::RedrawRequested(window_id) if window_id == core.window().id() => {
                 core.update();
                 core.render();
             }
             winit::event::Event::WindowEvent { window_id, event }
-                if window_id == core.window.id() =>
+                if window_id == core.window().id() =>
             {
                 match event {
                     winit::event::WindowEvent::Resized(new_size) => {
@@ -1017,12 +1270,15 @@ impl Core {
                         input: keyboard_input,
                         ..
                     } => {
+                        if keyboard_input.state == winit::event::ElementState::Released {
+                            core.on_key_released(keyboard_input.virtual_keycode);
+                        }
                         core.input.on_input(keyboard_input);
                     }
                     _ => {}
                 }
             }
-            winit::event::Event::MainEventsCleared => core.window.request_redraw(),
+            winit::event::Event::MainEventsCleared => core.window().request_redraw(),
             _ => {}
         });
     }
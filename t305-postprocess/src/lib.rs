@@ -1,6 +1,8 @@
+pub mod bloom;
 pub mod camera;
 pub mod color;
 pub mod core;
+pub use common::headless;
 pub mod input;
 pub mod light_direction;
 pub mod light_point;
@@ -8,8 +10,12 @@ pub mod light_spot;
 pub mod material;
 pub mod model;
 pub mod model_light;
+pub mod post_process_chain;
+pub mod post_process_effect;
+pub mod post_process_kernel;
 pub mod runner;
 pub mod texture;
+pub mod tone_mapping;
 pub mod transform;
 pub mod vertex;
 pub mod vertex_light;
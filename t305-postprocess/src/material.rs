@@ -1,9 +1,12 @@
 use wgpu::util::DeviceExt;
 
-use crate::{core::Core, model::Model};
+use crate::{core::Core, model::Model, texture::gen_texture_view_color};
 
 pub struct Material {
     pub diffuse: wgpu::TextureView,
+    // added on top of the lighting, black when the material has none
+    pub emission: wgpu::TextureView,
+    pub emission_strength: f32,
     pub shininess: f32,
 
     pub bind_group: wgpu::BindGroup,
@@ -11,8 +14,16 @@ pub struct Material {
 }
 
 impl Material {
-    pub fn new(diffuse: wgpu::TextureView, shininess: f32, core: &Core) -> Self {
+    pub fn new(
+        diffuse: wgpu::TextureView,
+        emission: Option<wgpu::TextureView>,
+        emission_strength: f32,
+        shininess: f32,
+        core: &Core,
+    ) -> Self {
         let device = &core.device;
+        let emission =
+            emission.unwrap_or_else(|| gen_texture_view_color([0, 0, 0, 255], device, &core.queue));
 
         let color_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Color Buffer"),
@@ -24,6 +35,12 @@ impl Material {
             contents: bytemuck::bytes_of(&shininess),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
+        let emission_strength_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Emission Strength Buffer"),
+                contents: bytemuck::bytes_of(&emission_strength),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Material Bind Group"),
             layout: &core.material_bind_group_layout,
@@ -48,11 +65,23 @@ impl Material {
                         shininess_buffer.as_entire_buffer_binding(),
                     ),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&emission),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::Buffer(
+                        emission_strength_buffer.as_entire_buffer_binding(),
+                    ),
+                },
             ],
         });
 
         Self {
             diffuse,
+            emission,
+            emission_strength,
             shininess,
             bind_group,
             model_arr: vec![],
@@ -0,0 +1,314 @@
+use std::any::Any;
+
+use wgpu::{
+    BindGroup, BindGroupLayout, CommandEncoder, ComputePipeline, Device, Queue, RenderPipeline,
+    Sampler, ShaderStages, Texture, TextureView,
+};
+
+use crate::texture::{self, gen_texture_post_processing};
+
+// the scene, then the two textures the effects ping-pong between
+const TEXTURE_IN: usize = 0;
+const TEXTURE_PING: usize = 1;
+const TEXTURE_PONG: usize = 2;
+
+pub enum EffectStage {
+    // reads texture_in with textureLoad, writes the storage texture_out
+    Compute(ComputePipeline),
+    // samples texture_in, draws one triangle over the target
+    Fragment(RenderPipeline),
+}
+
+pub trait PostProcessEffect {
+    fn name(&self) -> &str;
+    fn is_enabled(&self) -> bool;
+    fn set_enabled(&mut self, is_enabled: bool);
+    fn stage(&self) -> &EffectStage;
+    // group 1, the effect's own parameters
+    fn bind_group(&self) -> &BindGroup;
+    fn update(&self, queue: &Queue);
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+// effects applied in order to the hdr scene, the result always ends up in texture_view_out
+pub struct PostProcessChain {
+    pub effect_arr: Vec<Box<dyn PostProcessEffect>>,
+
+    pub sampler: Sampler,
+    // group 0 of every effect pipeline
+    pub bind_group_layout_compute: BindGroupLayout,
+    pub bind_group_layout_fragment: BindGroupLayout,
+
+    width: u32,
+    height: u32,
+    texture_arr: [Texture; 3],
+    texture_view_arr: [TextureView; 3],
+    // by source and target texture, None where the pair is never used
+    bind_group_compute_arr: [[Option<BindGroup>; 3]; 3],
+    bind_group_fragment_arr: [BindGroup; 3],
+}
+
+impl PostProcessChain {
+    pub fn new(device: &Device, width: u32, height: u32) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Sampler Post Process"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bind_group_layout_compute =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Bind Group Layout Post Process Compute"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: texture::HDR_FORMAT,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let bind_group_layout_fragment =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Bind Group Layout Post Process Fragment"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let texture_arr = [0, 1, 2].map(|_| gen_texture_post_processing(device, width, height));
+        let texture_view_arr = texture_arr
+            .each_ref()
+            .map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()));
+        let bind_group_compute_arr =
+            gen_bind_group_compute_arr(device, &bind_group_layout_compute, &texture_view_arr);
+        let bind_group_fragment_arr = gen_bind_group_fragment_arr(
+            device,
+            &bind_group_layout_fragment,
+            &sampler,
+            &texture_view_arr,
+        );
+
+        Self {
+            effect_arr: vec![],
+            sampler,
+            bind_group_layout_compute,
+            bind_group_layout_fragment,
+            width,
+            height,
+            texture_arr,
+            texture_view_arr,
+            bind_group_compute_arr,
+            bind_group_fragment_arr,
+        }
+    }
+
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.texture_arr = [0, 1, 2].map(|_| gen_texture_post_processing(device, width, height));
+        self.texture_view_arr = self
+            .texture_arr
+            .each_ref()
+            .map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()));
+        self.bind_group_compute_arr = gen_bind_group_compute_arr(
+            device,
+            &self.bind_group_layout_compute,
+            &self.texture_view_arr,
+        );
+        self.bind_group_fragment_arr = gen_bind_group_fragment_arr(
+            device,
+            &self.bind_group_layout_fragment,
+            &self.sampler,
+            &self.texture_view_arr,
+        );
+    }
+
+    // the scene renders into this
+    pub fn texture_view_in(&self) -> &TextureView {
+        &self.texture_view_arr[TEXTURE_IN]
+    }
+
+    pub fn texture_view_out(&self) -> &TextureView {
+        &self.texture_view_arr[TEXTURE_PONG]
+    }
+
+    pub fn push(&mut self, effect: Box<dyn PostProcessEffect>) {
+        self.effect_arr.push(effect);
+    }
+
+    // the concrete effect, to change its typed parameters
+    pub fn effect_mut<E: PostProcessEffect + 'static>(&mut self, index: usize) -> Option<&mut E> {
+        self.effect_arr.get_mut(index)?.as_any_mut().downcast_mut()
+    }
+
+    // move the effect at from so it runs at position to
+    pub fn move_effect(&mut self, from: usize, to: usize) {
+        if from >= self.effect_arr.len() || to >= self.effect_arr.len() {
+            return;
+        }
+        let effect = self.effect_arr.remove(from);
+        self.effect_arr.insert(to, effect);
+    }
+
+    pub fn update(&self, queue: &Queue) {
+        for effect in &self.effect_arr {
+            effect.update(queue);
+        }
+    }
+
+    pub fn render(&self, encoder: &mut CommandEncoder) {
+        let effect_arr = self
+            .effect_arr
+            .iter()
+            .filter(|effect| effect.is_enabled())
+            .collect::<Vec<_>>();
+
+        if effect_arr.is_empty() {
+            encoder.copy_texture_to_texture(
+                self.texture_arr[TEXTURE_IN].as_image_copy(),
+                self.texture_arr[TEXTURE_PONG].as_image_copy(),
+                self.texture_arr[TEXTURE_IN].size(),
+            );
+            return;
+        }
+
+        // alternate so the last effect lands in pong
+        let mut src = TEXTURE_IN;
+        for (i, effect) in effect_arr.iter().enumerate() {
+            let dst = if (effect_arr.len() - 1 - i) % 2 == 0 {
+                TEXTURE_PONG
+            } else {
+                TEXTURE_PING
+            };
+
+            match effect.stage() {
+                EffectStage::Compute(compute_pipline) => {
+                    let mut compute_pass =
+                        encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                            label: Some(effect.name()),
+                        });
+                    compute_pass.set_pipeline(compute_pipline);
+                    compute_pass.set_bind_group(
+                        0,
+                        self.bind_group_compute_arr[src][dst].as_ref().unwrap(),
+                        &[],
+                    );
+                    compute_pass.set_bind_group(1, effect.bind_group(), &[]);
+                    compute_pass.dispatch_workgroups(
+                        self.width.div_ceil(8),
+                        self.height.div_ceil(8),
+                        1,
+                    );
+                }
+                EffectStage::Fragment(render_pipline) => {
+                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some(effect.name()),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                            view: &self.texture_view_arr[dst],
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                                store: true,
+                            },
+                        })],
+                        depth_stencil_attachment: None,
+                    });
+                    render_pass.set_pipeline(render_pipline);
+                    render_pass.set_bind_group(0, &self.bind_group_fragment_arr[src], &[]);
+                    render_pass.set_bind_group(1, effect.bind_group(), &[]);
+                    render_pass.draw(0..3, 0..1);
+                }
+            }
+
+            src = dst;
+        }
+    }
+}
+
+fn gen_bind_group_compute_arr(
+    device: &Device,
+    layout: &BindGroupLayout,
+    texture_view_arr: &[TextureView; 3],
+) -> [[Option<BindGroup>; 3]; 3] {
+    [TEXTURE_IN, TEXTURE_PING, TEXTURE_PONG].map(|src| {
+        [TEXTURE_IN, TEXTURE_PING, TEXTURE_PONG].map(|dst| {
+            // the scene is never written, a texture is never read and written at once
+            if dst == TEXTURE_IN || dst == src {
+                return None;
+            }
+            Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Bind Group Post Process Compute"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&texture_view_arr[src]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&texture_view_arr[dst]),
+                    },
+                ],
+            }))
+        })
+    })
+}
+
+fn gen_bind_group_fragment_arr(
+    device: &Device,
+    layout: &BindGroupLayout,
+    sampler: &Sampler,
+    texture_view_arr: &[TextureView; 3],
+) -> [BindGroup; 3] {
+    texture_view_arr.each_ref().map(|texture_view| {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Bind Group Post Process Fragment"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(texture_view),
+                },
+            ],
+        })
+    })
+}
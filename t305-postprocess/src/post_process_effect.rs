@@ -0,0 +1,293 @@
+use std::any::Any;
+
+use bytemuck::Pod;
+use wgpu::{util::DeviceExt, BindGroup, Buffer, Device, Queue, ShaderStages};
+
+use crate::{
+    post_process_chain::{EffectStage, PostProcessChain, PostProcessEffect},
+    texture,
+};
+
+const BASE_EFFECT_SHADER_PATH: &str = "assets/shader/effect/";
+
+// one pass of the chain, P is the uniform at group 1 binding 0
+pub struct Effect<P: Pod> {
+    pub param: P,
+    name: &'static str,
+    is_enabled: bool,
+    stage: EffectStage,
+    buffer_param: Buffer,
+    bind_group: BindGroup,
+}
+
+impl<P: Pod> Effect<P> {
+    // shader_name is under assets/shader/effect, common_compute.wgsl is put in front of it
+    pub fn new_compute(
+        device: &Device,
+        chain: &PostProcessChain,
+        name: &'static str,
+        shader_name: &str,
+        param: P,
+    ) -> Self {
+        let shader = gen_effect_shader(device, name, "common_compute.wgsl", shader_name);
+        let (buffer_param, bind_group_layout, bind_group) =
+            gen_param(device, name, ShaderStages::COMPUTE, &param);
+
+        let compute_pipline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(name),
+                bind_group_layouts: &[&chain.bind_group_layout_compute, &bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let compute_pipline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(name),
+            layout: Some(&compute_pipline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        Self {
+            param,
+            name,
+            is_enabled: true,
+            stage: EffectStage::Compute(compute_pipline),
+            buffer_param,
+            bind_group,
+        }
+    }
+
+    // shader_name is under assets/shader/effect, common_fragment.wgsl is put in front of it
+    pub fn new_fragment(
+        device: &Device,
+        chain: &PostProcessChain,
+        name: &'static str,
+        shader_name: &str,
+        param: P,
+    ) -> Self {
+        let shader = gen_effect_shader(device, name, "common_fragment.wgsl", shader_name);
+        let (buffer_param, bind_group_layout, bind_group) =
+            gen_param(device, name, ShaderStages::FRAGMENT, &param);
+
+        let render_pipline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(name),
+                bind_group_layouts: &[&chain.bind_group_layout_fragment, &bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let render_pipline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(name),
+            layout: Some(&render_pipline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: texture::HDR_FORMAT,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        Self {
+            param,
+            name,
+            is_enabled: true,
+            stage: EffectStage::Fragment(render_pipline),
+            buffer_param,
+            bind_group,
+        }
+    }
+
+    pub fn with_enabled(mut self, is_enabled: bool) -> Self {
+        self.is_enabled = is_enabled;
+        self
+    }
+}
+
+impl<P: Pod> PostProcessEffect for Effect<P> {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.is_enabled
+    }
+
+    fn set_enabled(&mut self, is_enabled: bool) {
+        self.is_enabled = is_enabled;
+    }
+
+    fn stage(&self) -> &EffectStage {
+        &self.stage
+    }
+
+    fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    fn update(&self, queue: &Queue) {
+        queue.write_buffer(&self.buffer_param, 0, bytemuck::bytes_of(&self.param));
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// common_name is put in front of shader_name, both under assets/shader/effect
+pub fn gen_effect_shader(
+    device: &Device,
+    name: &str,
+    common_name: &str,
+    shader_name: &str,
+) -> wgpu::ShaderModule {
+    let base_path = std::path::Path::new(BASE_EFFECT_SHADER_PATH);
+    let shader = std::fs::read_to_string(base_path.join(common_name)).unwrap()
+        + &std::fs::read_to_string(base_path.join(shader_name)).unwrap();
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(name),
+        source: wgpu::ShaderSource::Wgsl(shader.into()),
+    })
+}
+
+fn gen_param<P: Pod>(
+    device: &Device,
+    name: &str,
+    visibility: ShaderStages,
+    param: &P,
+) -> (Buffer, wgpu::BindGroupLayout, BindGroup) {
+    let buffer_param = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(name),
+        contents: bytemuck::bytes_of(param),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    });
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some(name),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some(name),
+        layout: &bind_group_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Buffer(buffer_param.as_entire_buffer_binding()),
+        }],
+    });
+    (buffer_param, bind_group_layout, bind_group)
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GrayscaleParam {
+    // luminance of each channel
+    pub weight: [f32; 3],
+    // 0 keeps the color, 1 is fully gray
+    pub amount: f32,
+}
+
+impl GrayscaleParam {
+    pub fn new(amount: f32) -> Self {
+        Self {
+            weight: [0.2126, 0.7152, 0.0722],
+            amount,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct InversionParam {
+    // hdr has no upper bound, colors are inverted against this
+    pub white: f32,
+    pub amount: f32,
+}
+
+impl InversionParam {
+    pub fn new(white: f32, amount: f32) -> Self {
+        Self { white, amount }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct VignetteParam {
+    pub intensity: f32,
+    // uv distance from the center, 0.5 reaches the edge
+    pub radius: f32,
+    pub softness: f32,
+}
+
+impl VignetteParam {
+    pub fn new(intensity: f32, radius: f32, softness: f32) -> Self {
+        Self {
+            intensity,
+            radius,
+            softness,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ChromaticAberrationParam {
+    // uv shift of red and blue, scaled by the distance from the center
+    pub offset: f32,
+}
+
+impl ChromaticAberrationParam {
+    pub fn new(offset: f32) -> Self {
+        Self { offset }
+    }
+}
+
+impl Effect<GrayscaleParam> {
+    pub fn grayscale(device: &Device, chain: &PostProcessChain, param: GrayscaleParam) -> Self {
+        Self::new_fragment(device, chain, "Grayscale", "grayscale.wgsl", param)
+    }
+}
+
+impl Effect<InversionParam> {
+    pub fn inversion(device: &Device, chain: &PostProcessChain, param: InversionParam) -> Self {
+        Self::new_fragment(device, chain, "Inversion", "inversion.wgsl", param)
+    }
+}
+
+impl Effect<VignetteParam> {
+    pub fn vignette(device: &Device, chain: &PostProcessChain, param: VignetteParam) -> Self {
+        Self::new_fragment(device, chain, "Vignette", "vignette.wgsl", param)
+    }
+}
+
+impl Effect<ChromaticAberrationParam> {
+    pub fn chromatic_aberration(
+        device: &Device,
+        chain: &PostProcessChain,
+        param: ChromaticAberrationParam,
+    ) -> Self {
+        Self::new_fragment(
+            device,
+            chain,
+            "Chromatic Aberration",
+            "chromatic_aberration.wgsl",
+            param,
+        )
+    }
+}
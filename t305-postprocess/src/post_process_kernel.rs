@@ -0,0 +1,320 @@
+use std::any::Any;
+
+use anyhow::{anyhow, Result};
+use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, Buffer, Device, Queue, ShaderStages};
+
+use crate::{
+    post_process_chain::{EffectStage, PostProcessChain, PostProcessEffect},
+    post_process_effect::gen_effect_shader,
+};
+
+// where taps past the edge of the texture read from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BorderMode {
+    Clamp,
+    // -1 reads 0, -2 reads 1
+    Mirror,
+    Wrap,
+}
+
+impl BorderMode {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Clamp => Self::Mirror,
+            Self::Mirror => Self::Wrap,
+            Self::Wrap => Self::Clamp,
+        }
+    }
+}
+
+// size * size weights, row 0 is the top row
+#[derive(Debug, Clone)]
+pub struct Kernel {
+    size: u32,
+    weight_arr: Vec<f32>,
+    // every weight is multiplied by it
+    pub normalization: f32,
+}
+
+impl Kernel {
+    pub fn new(size: u32, weight_arr: Vec<f32>, normalization: f32) -> Result<Self> {
+        if size.is_multiple_of(2) {
+            return Err(anyhow!("kernel size {} is not odd", size));
+        }
+        if weight_arr.len() != (size * size) as usize {
+            return Err(anyhow!(
+                "kernel of size {} needs {} weights, got {}",
+                size,
+                size * size,
+                weight_arr.len()
+            ));
+        }
+        Ok(Self {
+            size,
+            weight_arr,
+            normalization,
+        })
+    }
+
+    // normalized by the sum of the weights, so a blur keeps the brightness
+    pub fn new_normalized(size: u32, weight_arr: Vec<f32>) -> Result<Self> {
+        let sum = weight_arr.iter().sum::<f32>();
+        let normalization = if sum.abs() > f32::EPSILON {
+            1.0 / sum
+        } else {
+            1.0
+        };
+        Self::new(size, weight_arr, normalization)
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn weight_arr(&self) -> &[f32] {
+        &self.weight_arr
+    }
+
+    // box blur over size * size pixels
+    pub fn blur(size: u32) -> Self {
+        let size = size | 1;
+        Self::new_normalized(size, vec![1.0; (size * size) as usize]).unwrap()
+    }
+
+    pub fn sharpen() -> Self {
+        #[rustfmt::skip]
+        let weight_arr = vec![
+            0.0, -1.0, 0.0,
+            -1.0, 5.0, -1.0,
+            0.0, -1.0, 0.0,
+        ];
+        Self::new(3, weight_arr, 1.0).unwrap()
+    }
+
+    // lit from the top left
+    pub fn emboss() -> Self {
+        #[rustfmt::skip]
+        let weight_arr = vec![
+            -2.0, -1.0, 0.0,
+            -1.0, 1.0, 1.0,
+            0.0, 1.0, 2.0,
+        ];
+        Self::new(3, weight_arr, 1.0).unwrap()
+    }
+
+    pub fn edge() -> Self {
+        #[rustfmt::skip]
+        let weight_arr = vec![
+            1.0, 1.0, 1.0,
+            1.0, -8.0, 1.0,
+            1.0, 1.0, 1.0,
+        ];
+        Self::new(3, weight_arr, 1.0).unwrap()
+    }
+
+    // wide enough to cover three sigma on each side
+    pub fn gaussian(sigma: f32) -> Self {
+        let sigma = sigma.max(0.1);
+        let radius = (sigma * 3.0).ceil() as i32;
+        let size = (radius * 2 + 1) as u32;
+        let weight_arr = (-radius..=radius)
+            .flat_map(|y| {
+                (-radius..=radius)
+                    .map(move |x| (-((x * x + y * y) as f32) / (2.0 * sigma * sigma)).exp())
+            })
+            .collect();
+        Self::new_normalized(size, weight_arr).unwrap()
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct KernelParam {
+    size: u32,
+    normalization: f32,
+    offset: i32,
+    border: u32,
+}
+
+// convolution with a kernel from a storage buffer, a compute stage of the chain
+pub struct KernelEffect {
+    // pixels between taps
+    pub offset: i32,
+    pub border_mode: BorderMode,
+
+    name: &'static str,
+    is_enabled: bool,
+    kernel: Kernel,
+    stage: EffectStage,
+    buffer_param: Buffer,
+    buffer_weight: Buffer,
+    bind_group_layout: BindGroupLayout,
+    bind_group: BindGroup,
+}
+
+impl KernelEffect {
+    pub fn new(
+        device: &Device,
+        chain: &PostProcessChain,
+        name: &'static str,
+        kernel: Kernel,
+        border_mode: BorderMode,
+    ) -> Self {
+        let shader = gen_effect_shader(device, name, "common_compute.wgsl", "kernel.wgsl");
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(name),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let compute_pipline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(name),
+                bind_group_layouts: &[&chain.bind_group_layout_compute, &bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let compute_pipline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(name),
+            layout: Some(&compute_pipline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        let buffer_param = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(name),
+            size: std::mem::size_of::<KernelParam>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let buffer_weight = gen_buffer_weight(device, name, &kernel);
+        let bind_group = gen_bind_group(
+            device,
+            name,
+            &bind_group_layout,
+            &buffer_param,
+            &buffer_weight,
+        );
+
+        Self {
+            offset: 1,
+            border_mode,
+            name,
+            is_enabled: true,
+            kernel,
+            stage: EffectStage::Compute(compute_pipline),
+            buffer_param,
+            buffer_weight,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    pub fn with_enabled(mut self, is_enabled: bool) -> Self {
+        self.is_enabled = is_enabled;
+        self
+    }
+
+    pub fn kernel(&self) -> &Kernel {
+        &self.kernel
+    }
+
+    // the weights may change size, so the buffer is made again
+    pub fn set_kernel(&mut self, device: &Device, kernel: Kernel) {
+        self.buffer_weight = gen_buffer_weight(device, self.name, &kernel);
+        self.bind_group = gen_bind_group(
+            device,
+            self.name,
+            &self.bind_group_layout,
+            &self.buffer_param,
+            &self.buffer_weight,
+        );
+        self.kernel = kernel;
+    }
+}
+
+impl PostProcessEffect for KernelEffect {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.is_enabled
+    }
+
+    fn set_enabled(&mut self, is_enabled: bool) {
+        self.is_enabled = is_enabled;
+    }
+
+    fn stage(&self) -> &EffectStage {
+        &self.stage
+    }
+
+    fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    fn update(&self, queue: &Queue) {
+        let param = KernelParam {
+            size: self.kernel.size,
+            normalization: self.kernel.normalization,
+            offset: self.offset,
+            border: self.border_mode as u32,
+        };
+        queue.write_buffer(&self.buffer_param, 0, bytemuck::bytes_of(&param));
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+fn gen_buffer_weight(device: &Device, name: &str, kernel: &Kernel) -> Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(name),
+        contents: bytemuck::cast_slice(&kernel.weight_arr),
+        usage: wgpu::BufferUsages::STORAGE,
+    })
+}
+
+fn gen_bind_group(
+    device: &Device,
+    name: &str,
+    layout: &BindGroupLayout,
+    buffer_param: &Buffer,
+    buffer_weight: &Buffer,
+) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some(name),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(buffer_param.as_entire_buffer_binding()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Buffer(buffer_weight.as_entire_buffer_binding()),
+            },
+        ],
+    })
+}
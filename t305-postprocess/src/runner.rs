@@ -1,13 +1,17 @@
+use anyhow::Result;
 use glam::{Quat, Vec3};
+use wgpu::TextureFormat;
 use winit::{event_loop::EventLoop, window::WindowBuilder};
 
 use crate::{
     core::Core,
     material::Material,
     model::{DrawMethod, Model},
+    model_light::ModelLight,
     texture::gen_texture_view,
     transform::Transform,
     vertex::Vertex,
+    vertex_light::VertexLight,
 };
 
 const BASE_GLTF_PATH: &str = "assets/gltf/";
@@ -17,15 +21,31 @@ pub fn run() {
     let window = WindowBuilder::new().build(&event_loop).unwrap();
     pollster::block_on(async {
         let mut core = Core::new(window).await;
-        load_box_model(&mut core);
-        // load_rect_model(&mut core);
-        // load_triangle_model(&mut core);
-        // load_gltf_model(&mut core);
+        load_scene(&mut core);
 
         Core::block_loop(event_loop, core);
     });
 }
 
+pub fn run_headless(width: u32, height: u32, path: &str) -> Result<()> {
+    pollster::block_on(async {
+        let mut core = Core::new_headless(width, height, TextureFormat::Rgba8UnormSrgb).await?;
+        load_scene(&mut core);
+        core.update_delta_time(1.0 / 60.0);
+        core.render_to_image().save(path)?;
+        Ok(())
+    })
+}
+
+pub fn load_scene(core: &mut Core) {
+    load_box_model(core);
+    load_emission_box_model(core);
+    load_light_model(core);
+    // load_rect_model(core);
+    // load_triangle_model(core);
+    // load_gltf_model(core);
+}
+
 pub fn load_gltf_model(core: &mut Core) {
     let base_path = std::path::Path::new(BASE_GLTF_PATH);
 
@@ -69,7 +89,7 @@ pub fn load_gltf_model(core: &mut Core) {
             }
         }
 
-        let material = Material::new(texture_view, 32.0, core);
+        let material = Material::new(texture_view, None, 0.0, 32.0, core);
         core.material_arr.push(material);
     }
 
@@ -144,6 +164,68 @@ pub fn load_box_model(core: &mut Core) {
     core.material_arr.push(material);
 }
 
+// glowing through bloom, like the emission map of t204-lighting-map-e04
+pub fn load_emission_box_model(core: &mut Core) {
+    let (device, queue) = (&core.device, &core.queue);
+    let texture_diffuse_view = gen_texture_view(
+        std::fs::read("assets/texture/container2.png").unwrap(),
+        device,
+        queue,
+    )
+    .unwrap();
+    let texture_emission_view = gen_texture_view(
+        std::fs::read("assets/texture/matrix.jpg").unwrap(),
+        device,
+        queue,
+    )
+    .unwrap();
+    let mut material = Material::new(
+        texture_diffuse_view,
+        Some(texture_emission_view),
+        4.0,
+        32.0,
+        core,
+    );
+
+    let model = Model::new(
+        &core.device,
+        DrawMethod::Vertex,
+        Vertex::cube().into(),
+        vec![],
+        vec![Transform::new(
+            Vec3::new(0.45, -0.45, 1.6),
+            Quat::from_rotation_y(30_f32.to_radians()),
+            Vec3::splat(0.3),
+        )],
+    );
+    material.model_arr.push(model);
+    core.material_arr.push(material);
+}
+
+// a small cube on every point light, in its color well above 1.0
+pub fn load_light_model(core: &mut Core) {
+    let light_intensity = 8.0;
+    let model_light_arr = core
+        .light_point_arr
+        .iter()
+        .map(|light_point| {
+            let color = Vec3::from_slice(&light_point.color[..3]) * light_intensity;
+            ModelLight::new(
+                &core.device,
+                VertexLight::cube(color.into()).into(),
+                vec![Transform::new(
+                    light_point.pos.into(),
+                    Quat::IDENTITY,
+                    Vec3::splat(0.2),
+                )],
+            )
+        })
+        .collect::<Vec<_>>();
+    for model_light in model_light_arr {
+        core.add_model_light(model_light);
+    }
+}
+
 pub fn load_rect_model(core: &mut Core) {
     let mut material = box_material(core);
 
@@ -184,7 +266,7 @@ fn box_material(core: &Core) -> Material {
     )
     .unwrap();
 
-    Material::new(texture_diffuse_view, 32.0, core)
+    Material::new(texture_diffuse_view, None, 0.0, 32.0, core)
 }
 
 fn transforms() -> Vec<Transform> {
@@ -0,0 +1,275 @@
+use wgpu::{
+    util::DeviceExt, BindGroup, BindGroupLayout, Buffer, ComputePipeline, Device, Queue,
+    ShaderStages, TextureView,
+};
+
+// auto_exposure.wgsl BIN_COUNT, one bin per invocation of a 16x16 workgroup
+const HISTOGRAM_BIN_COUNT: u64 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMappingMethod {
+    // clamp to 1.0
+    None,
+    Reinhard,
+    AcesFilmic,
+    Exposure,
+}
+
+impl ToneMappingMethod {
+    pub fn next(self) -> Self {
+        match self {
+            Self::None => Self::Reinhard,
+            Self::Reinhard => Self::AcesFilmic,
+            Self::AcesFilmic => Self::Exposure,
+            Self::Exposure => Self::None,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ToneMappingParam {
+    method: u32,
+    // manual exposure, a compensation on top of auto exposure
+    pub exposure: f32,
+    pub is_auto_exposure: u32,
+    // the surface format is not srgb
+    pub is_encode_srgb: u32,
+    // log2 luminance range of the histogram
+    pub min_log_luminance: f32,
+    pub log_luminance_range: f32,
+    // seconds for the exposure to mostly catch up
+    pub adaptation_time: f32,
+    pub delta_time: f32,
+}
+
+impl ToneMappingParam {
+    pub fn new(method: ToneMappingMethod, is_encode_srgb: bool) -> Self {
+        Self {
+            method: method as u32,
+            exposure: 1.0,
+            is_auto_exposure: 1,
+            is_encode_srgb: is_encode_srgb as u32,
+            min_log_luminance: -8.0,
+            log_luminance_range: 12.0,
+            adaptation_time: 1.0,
+            delta_time: 0.0,
+        }
+    }
+
+    pub fn method(&self) -> ToneMappingMethod {
+        match self.method {
+            1 => ToneMappingMethod::Reinhard,
+            2 => ToneMappingMethod::AcesFilmic,
+            3 => ToneMappingMethod::Exposure,
+            _ => ToneMappingMethod::None,
+        }
+    }
+
+    pub fn set_method(&mut self, method: ToneMappingMethod) {
+        self.method = method as u32;
+    }
+}
+
+// hdr to the surface, exposure from a luminance histogram of the hdr image
+pub struct ToneMapping {
+    pub param: ToneMappingParam,
+    pub buffer_param: Buffer,
+    // adapted average luminance, read by the tone mapping pass
+    pub buffer_luminance: Buffer,
+    pub buffer_histogram: Buffer,
+
+    pub compute_pipline_histogram: ComputePipeline,
+    pub compute_pipline_average: ComputePipeline,
+    pub bind_group_layout_auto_exposure: BindGroupLayout,
+    pub bind_group_auto_exposure: BindGroup,
+}
+
+impl ToneMapping {
+    pub fn new(device: &Device, param: ToneMappingParam, texture_view_hdr: &TextureView) -> Self {
+        let buffer_param = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Buffer Tone Mapping Param"),
+            contents: bytemuck::bytes_of(&param),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        // 0 until the first frame is measured
+        let buffer_luminance = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Buffer Luminance"),
+            contents: bytemuck::bytes_of(&0.0_f32),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let buffer_histogram = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Buffer Histogram"),
+            size: HISTOGRAM_BIN_COUNT * std::mem::size_of::<u32>() as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        // both entry points share the layout and the bind group
+        let bind_group_layout_auto_exposure =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Bind Group Layout Auto Exposure"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let compute_pipline_layout_auto_exposure =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Compute Pipline Layout Auto Exposure"),
+                bind_group_layouts: &[&bind_group_layout_auto_exposure],
+                push_constant_ranges: &[],
+            });
+
+        let shader = std::fs::read_to_string("assets/shader/auto_exposure.wgsl").unwrap();
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader Auto Exposure"),
+            source: wgpu::ShaderSource::Wgsl(shader.into()),
+        });
+        let compute_pipline_histogram =
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Compute Pipline Histogram"),
+                layout: Some(&compute_pipline_layout_auto_exposure),
+                module: &shader,
+                entry_point: "cs_histogram",
+            });
+        let compute_pipline_average =
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Compute Pipline Average"),
+                layout: Some(&compute_pipline_layout_auto_exposure),
+                module: &shader,
+                entry_point: "cs_average",
+            });
+
+        let bind_group_auto_exposure = gen_bind_group_auto_exposure(
+            device,
+            &bind_group_layout_auto_exposure,
+            texture_view_hdr,
+            &buffer_histogram,
+            &buffer_luminance,
+            &buffer_param,
+        );
+
+        Self {
+            param,
+            buffer_param,
+            buffer_luminance,
+            buffer_histogram,
+            compute_pipline_histogram,
+            compute_pipline_average,
+            bind_group_layout_auto_exposure,
+            bind_group_auto_exposure,
+        }
+    }
+
+    // the hdr target is recreated on resize
+    pub fn resize(&mut self, device: &Device, texture_view_hdr: &TextureView) {
+        self.bind_group_auto_exposure = gen_bind_group_auto_exposure(
+            device,
+            &self.bind_group_layout_auto_exposure,
+            texture_view_hdr,
+            &self.buffer_histogram,
+            &self.buffer_luminance,
+            &self.buffer_param,
+        );
+    }
+
+    pub fn update(&mut self, queue: &Queue, delta_time: f32) {
+        self.param.delta_time = delta_time;
+        queue.write_buffer(&self.buffer_param, 0, bytemuck::bytes_of(&self.param));
+    }
+
+    pub fn compute_auto_exposure(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        width: u32,
+        height: u32,
+    ) {
+        if self.param.is_auto_exposure == 0 {
+            return;
+        }
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Compute Pass Auto Exposure"),
+        });
+        compute_pass.set_bind_group(0, &self.bind_group_auto_exposure, &[]);
+        compute_pass.set_pipeline(&self.compute_pipline_histogram);
+        compute_pass.dispatch_workgroups(width.div_ceil(16), height.div_ceil(16), 1);
+        compute_pass.set_pipeline(&self.compute_pipline_average);
+        compute_pass.dispatch_workgroups(1, 1, 1);
+    }
+}
+
+fn gen_bind_group_auto_exposure(
+    device: &Device,
+    layout: &BindGroupLayout,
+    texture_view_hdr: &TextureView,
+    buffer_histogram: &Buffer,
+    buffer_luminance: &Buffer,
+    buffer_param: &Buffer,
+) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Bind Group Auto Exposure"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(texture_view_hdr),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Buffer(
+                    buffer_histogram.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer(
+                    buffer_luminance.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Buffer(buffer_param.as_entire_buffer_binding()),
+            },
+        ],
+    })
+}
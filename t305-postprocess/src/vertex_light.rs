@@ -1,5 +1,7 @@
 use wgpu::{VertexAttribute, VertexBufferLayout, VertexStepMode};
 
+use crate::vertex::Vertex;
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct VertexLight {
@@ -8,6 +10,15 @@ pub struct VertexLight {
 }
 
 impl VertexLight {
+    pub fn new(pos: [f32; 3], color: [f32; 3]) -> Self {
+        Self { pos, color }
+    }
+
+    // color may go above 1.0 so the cube blooms
+    pub fn cube(color: [f32; 3]) -> [Self; 36] {
+        Vertex::cube().map(|vertex| Self::new(vertex.pos, color))
+    }
+
     const ATTRS: [VertexAttribute; 2] = wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
     pub fn vertex_buffer_layout<'a>() -> VertexBufferLayout<'a> {
         VertexBufferLayout {
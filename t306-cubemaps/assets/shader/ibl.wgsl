@@ -0,0 +1,243 @@
+// image based lighting, everything is computed once from the equirectangular hdr
+
+const PI: f32 = 3.14159265359;
+
+// face 0..5 is +x -x +y -y +z -z, st in [-1, 1], t points down the face
+fn cube_dir(face: u32, st: vec2<f32>) -> vec3<f32> {
+    switch face {
+    case 0u: {
+            return normalize(vec3<f32>(1.0, -st.y, -st.x));
+        }
+    case 1u: {
+            return normalize(vec3<f32>(-1.0, -st.y, st.x));
+        }
+    case 2u: {
+            return normalize(vec3<f32>(st.x, 1.0, st.y));
+        }
+    case 3u: {
+            return normalize(vec3<f32>(st.x, -1.0, -st.y));
+        }
+    case 4u: {
+            return normalize(vec3<f32>(st.x, -st.y, 1.0));
+        }
+    default: {
+            return normalize(vec3<f32>(-st.x, -st.y, -1.0));
+        }
+  }
+}
+
+fn texel_dir(id: vec3<u32>, size: vec2<u32>) -> vec3<f32> {
+    let st = (vec2<f32>(id.xy) + 0.5) / vec2<f32>(size) * 2.0 - 1.0;
+    return cube_dir(id.z, st);
+}
+
+// equirect to cube
+
+@group(0)@binding(0)
+var texture_equirect: texture_2d<f32>;
+@group(0)@binding(1)
+var texture_env_out: texture_storage_2d_array<rgba16float, write>;
+
+// rgba32float is not filterable, bilinear by hand
+fn load_equirect(uv: vec2<f32>) -> vec4<f32> {
+    let size = vec2<i32>(textureDimensions(texture_equirect));
+    let p = uv * vec2<f32>(size) - 0.5;
+    let p0 = floor(p);
+    let w = p - p0;
+
+    // u wraps around, v clamps at the poles
+    let x0 = (i32(p0.x) % size.x + size.x) % size.x;
+    let x1 = (x0 + 1) % size.x;
+    let y0 = clamp(i32(p0.y), 0, size.y - 1);
+    let y1 = clamp(i32(p0.y) + 1, 0, size.y - 1);
+
+    let top = mix(textureLoad(texture_equirect, vec2<i32>(x0, y0), 0), textureLoad(texture_equirect, vec2<i32>(x1, y0), 0), w.x);
+    let bottom = mix(textureLoad(texture_equirect, vec2<i32>(x0, y1), 0), textureLoad(texture_equirect, vec2<i32>(x1, y1), 0), w.x);
+    return mix(top, bottom, w.y);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn cs_equirect(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(texture_env_out);
+    if id.x >= size.x || id.y >= size.y {
+        return;
+    }
+
+    let dir = texel_dir(id, size);
+    let uv = vec2<f32>(atan2(dir.z, dir.x) / (2.0 * PI) + 0.5, acos(clamp(dir.y, -1.0, 1.0)) / PI);
+    textureStore(texture_env_out, id.xy, id.z, vec4<f32>(load_equirect(uv).rgb, 1.0));
+}
+
+// diffuse irradiance
+
+@group(0)@binding(0)
+var sampler_env: sampler;
+@group(0)@binding(1)
+var texture_env: texture_cube<f32>;
+@group(0)@binding(2)
+var texture_irradiance_out: texture_storage_2d_array<rgba16float, write>;
+
+@compute @workgroup_size(8, 8, 1)
+fn cs_irradiance(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(texture_irradiance_out);
+    if id.x >= size.x || id.y >= size.y {
+        return;
+    }
+
+    let normal = texel_dir(id, size);
+    var up = vec3<f32>(0.0, 1.0, 0.0);
+    if abs(normal.y) > 0.999 {
+        up = vec3<f32>(1.0, 0.0, 0.0);
+    }
+    let right = normalize(cross(up, normal));
+    up = cross(normal, right);
+
+    // riemann sum over the hemisphere, a blurry env mip hides the coarse steps
+    let phi_count = 64u;
+    let theta_count = 16u;
+    let lod = 4.0; // 32x32 mip of the 512 env
+    var irradiance = vec3<f32>(0.0, 0.0, 0.0);
+    for (var i: u32 = 0u; i < phi_count; i = i + 1u) {
+        let phi = (f32(i) + 0.5) / f32(phi_count) * 2.0 * PI;
+        for (var j: u32 = 0u; j < theta_count; j = j + 1u) {
+            let theta = (f32(j) + 0.5) / f32(theta_count) * 0.5 * PI;
+            let tangent = vec3<f32>(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            let dir = tangent.x * right + tangent.y * up + tangent.z * normal;
+            irradiance += textureSampleLevel(texture_env, sampler_env, dir, lod).rgb * cos(theta) * sin(theta);
+        }
+    }
+    irradiance = PI * irradiance / f32(phi_count * theta_count);
+
+    textureStore(texture_irradiance_out, id.xy, id.z, vec4<f32>(irradiance, 1.0));
+}
+
+// env mip chain, bilinear at the texel center is a box filter of the mip above
+
+@group(0)@binding(2)
+var texture_mip_out: texture_storage_2d_array<rgba16float, write>;
+
+@compute @workgroup_size(8, 8, 1)
+fn cs_downsample(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(texture_mip_out);
+    if id.x >= size.x || id.y >= size.y {
+        return;
+    }
+
+    // texture_env is a view of the mip above only
+    let dir = texel_dir(id, size);
+    textureStore(texture_mip_out, id.xy, id.z, textureSampleLevel(texture_env, sampler_env, dir, 0.0));
+}
+
+// specular prefilter, one dispatch per mip
+
+@group(0)@binding(2)
+var texture_prefilter_out: texture_storage_2d_array<rgba16float, write>;
+@group(0)@binding(3)
+var<uniform> roughness: f32;
+
+fn hammersley(i: u32, count: u32) -> vec2<f32> {
+    return vec2<f32>(f32(i) / f32(count), f32(reverseBits(i)) * 2.3283064365386963e-10);
+}
+
+fn importance_sample_ggx(xi: vec2<f32>, normal: vec3<f32>, roughness: f32) -> vec3<f32> {
+    let a = roughness * roughness;
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    let h = vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+
+    var up = vec3<f32>(0.0, 0.0, 1.0);
+    if abs(normal.z) > 0.999 {
+        up = vec3<f32>(1.0, 0.0, 0.0);
+    }
+    let tangent = normalize(cross(up, normal));
+    let bitangent = cross(normal, tangent);
+    return normalize(tangent * h.x + bitangent * h.y + normal * h.z);
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denom * denom);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn cs_prefilter(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(texture_prefilter_out);
+    if id.x >= size.x || id.y >= size.y {
+        return;
+    }
+
+    // view and reflection are the normal
+    let normal = texel_dir(id, size);
+    let env_size = f32(textureDimensions(texture_env).x);
+    let sa_texel = 4.0 * PI / (6.0 * env_size * env_size);
+
+    let sample_count = 128u;
+    var color = vec3<f32>(0.0, 0.0, 0.0);
+    var weight = 0.0;
+    for (var i: u32 = 0u; i < sample_count; i = i + 1u) {
+        let h = importance_sample_ggx(hammersley(i, sample_count), normal, roughness);
+        let l = normalize(2.0 * dot(normal, h) * h - normal);
+        let n_dot_l = dot(normal, l);
+        if n_dot_l > 0.0 {
+            // a sample covering many texels reads a blurrier mip, no bright dots
+            let n_dot_h = max(dot(normal, h), 0.0);
+            let pdf = distribution_ggx(n_dot_h, roughness) * 0.25 + 0.0001;
+            let sa_sample = 1.0 / (f32(sample_count) * pdf + 0.0001);
+            var lod = 0.0;
+            if roughness > 0.0 {
+                lod = 0.5 * log2(sa_sample / sa_texel);
+            }
+            color += textureSampleLevel(texture_env, sampler_env, l, lod).rgb * n_dot_l;
+            weight += n_dot_l;
+        }
+    }
+
+    textureStore(texture_prefilter_out, id.xy, id.z, vec4<f32>(color / weight, 1.0));
+}
+
+// split sum brdf, x is n_dot_v, y is roughness
+
+@group(0)@binding(0)
+var texture_brdf_lut_out: texture_storage_2d<rgba16float, write>;
+
+fn geometry_schlick_ggx(n_dot_v: f32, roughness: f32) -> f32 {
+    // k of ibl, not of direct light
+    let k = roughness * roughness / 2.0;
+    return n_dot_v / (n_dot_v * (1.0 - k) + k);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn cs_brdf_lut(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(texture_brdf_lut_out);
+    if id.x >= size.x || id.y >= size.y {
+        return;
+    }
+
+    let n_dot_v = (f32(id.x) + 0.5) / f32(size.x);
+    let roughness = (f32(id.y) + 0.5) / f32(size.y);
+    let v = vec3<f32>(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+    let normal = vec3<f32>(0.0, 0.0, 1.0);
+
+    let sample_count = 256u;
+    var a = 0.0;
+    var b = 0.0;
+    for (var i: u32 = 0u; i < sample_count; i = i + 1u) {
+        let h = importance_sample_ggx(hammersley(i, sample_count), normal, roughness);
+        let l = normalize(2.0 * dot(v, h) * h - v);
+        let n_dot_l = max(l.z, 0.0);
+        let n_dot_h = max(h.z, 0.0);
+        let v_dot_h = max(dot(v, h), 0.0);
+        if n_dot_l > 0.0 {
+            let g = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+            let g_vis = g * v_dot_h / (n_dot_h * n_dot_v);
+            let fc = pow(1.0 - v_dot_h, 5.0);
+            a += (1.0 - fc) * g_vis;
+            b += fc * g_vis;
+        }
+    }
+
+    textureStore(texture_brdf_lut_out, vec2<i32>(id.xy), vec4<f32>(a, b, 0.0, 1.0) / vec4<f32>(f32(sample_count), f32(sample_count), 1.0, 1.0));
+}
@@ -108,6 +108,14 @@ var<uniform> render_method: u32; // 0: normal, 1: reflact, 2: refract
 var sampler_skybox: sampler;
 @group(3) @binding(1)
 var texture_skybox: texture_cube<f32>;
+@group(3) @binding(2)
+var texture_irradiance: texture_cube<f32>;
+@group(3) @binding(3)
+var texture_prefilter: texture_cube<f32>;
+@group(3) @binding(4)
+var texture_brdf_lut: texture_2d<f32>;
+
+const PREFILTER_MIP_MAX: f32 = 4.0; // ibl::PREFILTER_MIP_LEVEL_COUNT - 1
 
 @fragment
 fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
@@ -127,8 +135,8 @@ fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
 }
 
 fn render_normal(normal: vec3<f32>, view_dir: vec3<f32>, tex_coord: vec2<f32>, frag_pos: vec3<f32>) -> vec4<f32> {
-    var l = vec3<f32>(0.0, 0.0, 0.0);
     let tex_diffuse = textureSample(texture_diffuse, texture_sampler, tex_coord).rgb;
+    var l = do_light_ibl(normal, view_dir, tex_diffuse);
 
     for (var i: u32 = 0u; i < arrayLength(&light_direction_arr.arr); i = i + 1u) {
         l += do_light_direction(light_direction_arr.arr[i], normal, view_dir, tex_diffuse);
@@ -158,6 +166,25 @@ fn render_refract(normal: vec3<f32>, view_dir: vec3<f32>, frag_pos: vec3<f32>) -
     return vec4<f32>(textureSample(texture_skybox, sampler_skybox, r).rgb, 1.0);
 }
 
+// ambient of the environment, dielectric with a roughness matching the shininess
+fn do_light_ibl(normal: vec3<f32>, view_dir: vec3<f32>, tex_diffuse: vec3<f32>) -> vec3<f32> {
+    let roughness = sqrt(2.0 / (shininess + 2.0));
+    let n_dot_v = max(dot(normal, view_dir), 0.0);
+
+    let f0 = vec3<f32>(0.04, 0.04, 0.04);
+    let fresnel = f0 + (max(vec3<f32>(1.0 - roughness), f0) - f0) * pow(1.0 - n_dot_v, 5.0);
+
+    let irradiance = textureSample(texture_irradiance, sampler_skybox, normal).rgb;
+    let diffuse = (1.0 - fresnel) * irradiance * tex_diffuse;
+
+    let reflect_dir = reflect(-view_dir, normal);
+    let prefiltered = textureSampleLevel(texture_prefilter, sampler_skybox, reflect_dir, roughness * PREFILTER_MIP_MAX).rgb;
+    let brdf = textureSampleLevel(texture_brdf_lut, sampler_skybox, vec2<f32>(n_dot_v, roughness), 0.0).rg;
+    let specular = prefiltered * (fresnel * brdf.x + brdf.y);
+
+    return diffuse + specular;
+}
+
 fn do_light_direction(light_direction: LightDirection, normal: vec3<f32>, view_dir: vec3<f32>, tex_diffuse: vec3<f32>) -> vec3<f32> {
     if light_direction.color.a == 0.0 {
         return vec3<f32>(0.0, 0.0, 0.0);
@@ -165,8 +192,6 @@ fn do_light_direction(light_direction: LightDirection, normal: vec3<f32>, view_d
 
     let light_color = light_direction.color.rgb;
 
-    let ambient = light_color * light_direction.ambient * tex_diffuse;
-
     let light_dir = normalize(-light_direction.dir);
     let diff = max(dot(normal, light_dir), 0.0);
     let diffuse = light_color * light_direction.diffuse * (diff * tex_diffuse);
@@ -174,7 +199,7 @@ fn do_light_direction(light_direction: LightDirection, normal: vec3<f32>, view_d
     let reflect_dir = reflect(-light_dir, normal);
     let spec = pow(max(dot(view_dir, reflect_dir), 0.0), shininess);
 
-    return ambient + diffuse;
+    return diffuse;
 }
 
 fn do_light_point(light_point: LightPoint, normal: vec3<f32>, view_dir: vec3<f32>, tex_diffuse: vec3<f32>, frag_pos: vec3<f32>) -> vec3<f32> {
@@ -184,8 +209,6 @@ fn do_light_point(light_point: LightPoint, normal: vec3<f32>, view_dir: vec3<f32
 
     let light_color = light_point.color.rgb;
 
-    var ambient = light_color * light_point.ambient * tex_diffuse;
-
     let light_dir = normalize(light_point.pos - frag_pos);
     let diff = max(dot(normal, light_dir), 0.0);
     var diffuse = light_color * light_point.diffuse * (diff * tex_diffuse);
@@ -197,10 +220,9 @@ fn do_light_point(light_point: LightPoint, normal: vec3<f32>, view_dir: vec3<f32
     let len = length(light_point.pos - frag_pos);
     let attenuation = 1.0 / (light_point.constant + light_point.linear * len + light_point.quadratic * (len * len));
 
-    ambient *= attenuation;
     diffuse *= attenuation;
 
-    return ambient + diffuse;
+    return diffuse;
 }
 
 fn do_light_spot(light_spot: LightSpot, normal: vec3<f32>, tex_diffuse: vec3<f32>, frag_pos: vec3<f32>) -> vec3<f32> {
@@ -215,8 +237,6 @@ fn do_light_spot(light_spot: LightSpot, normal: vec3<f32>, tex_diffuse: vec3<f32
     let epsilon = light_spot.in_cutoff - light_spot.out_cutoff;
     let intensity = clamp((theta - light_spot.out_cutoff) / epsilon, 0.0, 1.0);
 
-    let ambient = light_color * light_spot.ambient * tex_diffuse;
-
     let diff = max(dot(normal, light_dir), 0.0);
     var diffuse = light_color * light_spot.diffuse * (diff * tex_diffuse);
 
@@ -226,5 +246,5 @@ fn do_light_spot(light_spot: LightSpot, normal: vec3<f32>, tex_diffuse: vec3<f32
 
     diffuse *= intensity;
 
-    return ambient + diffuse;
+    return diffuse;
 }
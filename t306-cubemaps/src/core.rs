@@ -1,14 +1,20 @@
-use std::time::Instant;
+use std::{path::Path, time::Instant};
 
+use anyhow::{anyhow, Result};
+use image::RgbaImage;
 use wgpu::{
-    util::DeviceExt, Adapter, Backends, BindGroup, BindGroupLayout, Buffer, Device,
-    DeviceDescriptor, Features, IndexFormat, Instance, PresentMode, Queue, RenderPipeline, Sampler,
-    Surface, SurfaceConfiguration, TextureUsages,
+    util::DeviceExt, Adapter, Backends, BindGroup, BindGroupLayout, Buffer, CompositeAlphaMode,
+    Device, DeviceDescriptor, Features, IndexFormat, Instance, PresentMode, Queue, RenderPipeline,
+    Sampler, Surface, SurfaceConfiguration, Texture, TextureFormat, TextureUsages,
 };
 use winit::{dpi::PhysicalPosition, event_loop::EventLoop, window::Window};
 
 use crate::{
     camera::Camera,
+    headless::{
+        check_format_headless, gen_texture_headless, request_adapter_headless, texture_to_image,
+    },
+    ibl::Ibl,
     input::Input,
     light_direction::LightDirection,
     light_point::LightPoint,
@@ -16,22 +22,21 @@ use crate::{
     material::Material,
     model::DrawMethod,
     model_light::ModelLight,
-    texture::{
-        self, gen_texture_depth, gen_texture_sampler, gen_texture_sampler_skybox,
-        gen_texture_skybox,
-    },
+    texture::{self, gen_texture_depth, gen_texture_sampler, gen_texture_sampler_skybox},
     transform::TransformRawIT,
     vertex::Vertex,
 };
 
 pub struct Core {
-    pub window: Window,
+    pub window: Option<Window>,
     pub instance: Instance,
-    pub surface: Surface,
+    pub surface: Option<Surface>,
     pub adapter: Adapter,
     pub device: Device,
     pub queue: Queue,
     pub surface_config: SurfaceConfiguration,
+    // offscreen target when there is no surface
+    pub texture_headless: Option<Texture>,
     pub render_pipline_mesh: RenderPipeline,
     pub camera: Camera,
     pub input: Input,
@@ -41,6 +46,9 @@ pub struct Core {
     pub bind_group_skybox: BindGroup,
     pub vertex_skybox_len: u32,
 
+    pub ibl: Ibl,
+    pub bind_group_ibl: BindGroup,
+
     pub texture_sampler: Sampler,
 
     pub camera_bind_group: BindGroup,
@@ -91,6 +99,61 @@ impl Core {
             .await
             .unwrap();
 
+        let surface_caps = surface.get_capabilities(&adapter);
+        let surface_config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            format: surface_caps.formats[0],
+            width: window_size.width,
+            height: window_size.height,
+            present_mode: PresentMode::Fifo,
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![],
+        };
+
+        Self::init(
+            instance,
+            adapter,
+            Some(window),
+            Some(surface),
+            surface_config,
+        )
+        .await
+        .unwrap()
+    }
+
+    // render into an offscreen texture of the given size and format instead of a window
+    pub async fn new_headless(width: u32, height: u32, format: TextureFormat) -> Result<Self> {
+        check_format_headless(format)?;
+
+        let instance = Instance::new(wgpu::InstanceDescriptor {
+            backends: Backends::all(),
+            dx12_shader_compiler: Default::default(),
+        });
+
+        let adapter = request_adapter_headless(&instance)
+            .await
+            .ok_or_else(|| anyhow!("no adapter for headless rendering"))?;
+
+        let surface_config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            format,
+            width,
+            height,
+            present_mode: PresentMode::Fifo,
+            alpha_mode: CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+
+        Self::init(instance, adapter, None, None, surface_config).await
+    }
+
+    async fn init(
+        instance: Instance,
+        adapter: Adapter,
+        window: Option<Window>,
+        surface: Option<Surface>,
+        surface_config: SurfaceConfiguration,
+    ) -> Result<Self> {
         let (device, queue) = adapter
             .request_device(
                 &DeviceDescriptor {
@@ -100,20 +163,15 @@ impl Core {
                 },
                 None,
             )
-            .await
-            .unwrap();
+            .await?;
 
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_config = SurfaceConfiguration {
-            usage: TextureUsages::RENDER_ATTACHMENT,
-            format: surface_caps.formats[0],
-            width: window_size.width,
-            height: window_size.height,
-            present_mode: PresentMode::Fifo,
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
+        let texture_headless = match &surface {
+            Some(surface) => {
+                surface.configure(&device, &surface_config);
+                None
+            }
+            None => Some(gen_texture_headless(&device, &surface_config)),
         };
-        surface.configure(&device, &surface_config);
 
         let mesh_shader = std::fs::read_to_string("assets/shader/mesh.wgsl").unwrap();
         let mesh_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -271,6 +329,59 @@ impl Core {
                 ],
             });
 
+        let bind_group_layout_ibl =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Bind Group Layout IBL"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::Cube,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::Cube,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::Cube,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
         let render_pipline_layout_mesh =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipline Layout"),
@@ -278,7 +389,7 @@ impl Core {
                     &camera_bind_group_layout,
                     &light_arr_bind_group_layout,
                     &material_bind_group_layout,
-                    &bind_group_layout_skybox,
+                    &bind_group_layout_ibl,
                 ],
                 push_constant_ranges: &[],
             });
@@ -534,7 +645,12 @@ impl Core {
         let texture_depth = gen_texture_depth(&device, &surface_config);
 
         let texture_sampler_skybox = gen_texture_sampler_skybox(&device);
-        let texture_view_skybox = gen_texture_skybox(&device, &queue);
+        // the skybox is the hdr environment the ambient light comes from
+        let ibl = Ibl::new(
+            &device,
+            &queue,
+            Path::new("assets/texture/cubemap/bridge.hdr"),
+        )?;
 
         let bind_group_skybox = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Bind Group Skybox"),
@@ -546,7 +662,34 @@ impl Core {
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
-                    resource: wgpu::BindingResource::TextureView(&texture_view_skybox),
+                    resource: wgpu::BindingResource::TextureView(&ibl.texture_view_env),
+                },
+            ],
+        });
+
+        let bind_group_ibl = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Bind Group IBL"),
+            layout: &bind_group_layout_ibl,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&texture_sampler_skybox),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&ibl.texture_view_env),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&ibl.texture_view_irradiance),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&ibl.texture_view_prefilter),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&ibl.texture_view_brdf_lut),
                 },
             ],
         });
@@ -558,7 +701,7 @@ impl Core {
             usage: wgpu::BufferUsages::VERTEX,
         });
 
-        Self {
+        Ok(Self {
             window,
             instance,
             surface,
@@ -566,6 +709,7 @@ impl Core {
             device,
             queue,
             surface_config,
+            texture_headless,
             render_pipline_mesh,
             camera,
             input,
@@ -576,6 +720,9 @@ impl Core {
             vertex_buffer_skybox,
             vertex_skybox_len: vertices_skybox.len() as _,
 
+            ibl,
+            bind_group_ibl,
+
             camera_bind_group,
             camera_bind_group_skybox,
             light_arr_bind_group,
@@ -601,13 +748,19 @@ impl Core {
 
             start_time: Instant::now(),
             last_time: Instant::now(),
-        }
+        })
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
         self.surface_config.width = width;
         self.surface_config.height = height;
-        self.surface.configure(&self.device, &self.surface_config);
+        match &self.surface {
+            Some(surface) => surface.configure(&self.device, &self.surface_config),
+            None => {
+                self.texture_headless =
+                    Some(gen_texture_headless(&self.device, &self.surface_config))
+            }
+        }
         self.camera.update_size(
             self.surface_config.width as _,
             self.surface_config.height as _,
@@ -617,13 +770,21 @@ impl Core {
     }
 
     fn render(&self) {
-        let current_texture = self
-            .surface
-            .get_current_texture()
-            .expect("get current texture fail");
-        let texture_view = current_texture
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let current_texture = self.surface.as_ref().map(|surface| {
+            surface
+                .get_current_texture()
+                .expect("get current texture fail")
+        });
+        let texture_view = match &current_texture {
+            Some(current_texture) => current_texture
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+            None => self
+                .texture_headless
+                .as_ref()
+                .unwrap()
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+        };
 
         let mut encoder = self
             .device
@@ -658,7 +819,7 @@ impl Core {
             render_pass.set_pipeline(&self.render_pipline_mesh);
             render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
             render_pass.set_bind_group(1, &self.light_arr_bind_group, &[]);
-            render_pass.set_bind_group(3, &self.bind_group_skybox, &[]);
+            render_pass.set_bind_group(3, &self.bind_group_ibl, &[]);
 
             for material in &self.material_arr {
                 render_pass.set_bind_group(2, &material.bind_group, &[]);
@@ -711,7 +872,26 @@ impl Core {
 
         self.queue.submit(std::iter::once(encoder.finish()));
 
-        current_texture.present();
+        if let Some(current_texture) = current_texture {
+            current_texture.present();
+        }
+    }
+
+    // only for a core created by new_headless
+    pub fn render_to_image(&mut self) -> RgbaImage {
+        self.render();
+        texture_to_image(
+            &self.device,
+            &self.queue,
+            self.texture_headless
+                .as_ref()
+                .expect("render to image needs headless mode"),
+            &self.surface_config,
+        )
+    }
+
+    pub fn window(&self) -> &Window {
+        self.window.as_ref().expect("no window in headless mode")
     }
 
     fn update(&mut self) {
@@ -719,6 +899,11 @@ impl Core {
         let delta_time = (Instant::now() - self.last_time).as_secs_f32();
         self.last_time = Instant::now();
 
+        self.update_delta_time(delta_time);
+    }
+
+    // a fixed delta time keeps headless frames reproducible
+    pub fn update_delta_time(&mut self, delta_time: f32) {
         self.camera.moving(&self.input, delta_time);
 
         self.queue.write_buffer(
@@ -767,12 +952,12 @@ impl Core {
 
     pub fn block_loop(event_loop: EventLoop<()>, mut core: Core) {
         event_loop.run(move |event, _, control_flow| match event {
-            winit::event::Event::RedrawRequested(window_id) if window_id == core.window.id() => {
+            winit::event::Event::RedrawRequested(window_id) if window_id == core.window().id() => {
                 core.update();
                 core.render();
             }
             winit::event::Event::WindowEvent { window_id, event }
-                if window_id == core.window.id() =>
+                if window_id == core.window().id() =>
             {
                 match event {
                     winit::event::WindowEvent::Resized(new_size) => {
@@ -809,7 +994,7 @@ impl Core {
                     _ => {}
                 }
             }
-            winit::event::Event::MainEventsCleared => core.window.request_redraw(),
+            winit::event::Event::MainEventsCleared => core.window().request_redraw(),
             _ => {}
         });
     }
@@ -0,0 +1,346 @@
+use std::path::Path;
+
+use anyhow::Result;
+use wgpu::{
+    util::DeviceExt, BindGroup, ComputePipeline, Device, PipelineLayout, Queue, ShaderModule,
+    Texture, TextureView,
+};
+
+use crate::texture::gen_texture_sampler_skybox;
+
+const IBL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const ENV_SIZE: u32 = 512;
+const IRRADIANCE_SIZE: u32 = 32;
+const PREFILTER_SIZE: u32 = 128;
+// mesh.wgsl PREFILTER_MIP_MAX is this minus one
+pub const PREFILTER_MIP_LEVEL_COUNT: u32 = 5;
+const BRDF_LUT_SIZE: u32 = 256;
+
+// ambient light of an hdr environment, split sum of the cook torrance specular
+pub struct Ibl {
+    // radiance cube with a full mip chain, for the skybox and reflections
+    pub texture_view_env: TextureView,
+    // cosine weighted hemisphere of every normal
+    pub texture_view_irradiance: TextureView,
+    // ggx filtered radiance, roughness 0 to 1 across the mips
+    pub texture_view_prefilter: TextureView,
+    // r scale and g bias of f0, indexed by n_dot_v and roughness
+    pub texture_view_brdf_lut: TextureView,
+}
+
+impl Ibl {
+    // equirectangular .hdr, converted and filtered on the gpu
+    pub fn new(device: &Device, queue: &Queue, path: &Path) -> Result<Self> {
+        let img = image::open(path)?.to_rgba32f();
+        let texture_equirect = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("Texture Equirect"),
+                size: wgpu::Extent3d {
+                    width: img.width(),
+                    height: img.height(),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba32Float,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            bytemuck::cast_slice(img.as_raw()),
+        );
+
+        let shader = std::fs::read_to_string("assets/shader/ibl.wgsl")?;
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader IBL"),
+            source: wgpu::ShaderSource::Wgsl(shader.into()),
+        });
+        let sampler = gen_texture_sampler_skybox(device);
+
+        let env_mip_level_count = ENV_SIZE.ilog2() + 1;
+        let texture_env = gen_texture_ibl(device, "Texture Env", ENV_SIZE, 6, env_mip_level_count);
+        let texture_irradiance =
+            gen_texture_ibl(device, "Texture Irradiance", IRRADIANCE_SIZE, 6, 1);
+        let texture_prefilter = gen_texture_ibl(
+            device,
+            "Texture Prefilter",
+            PREFILTER_SIZE,
+            6,
+            PREFILTER_MIP_LEVEL_COUNT,
+        );
+        let texture_brdf_lut = gen_texture_ibl(device, "Texture BRDF LUT", BRDF_LUT_SIZE, 1, 1);
+
+        let texture_view_env = texture_env.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Texture View Env"),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Encoder IBL"),
+        });
+
+        // equirect to the first env mip, rgba32float is unfilterable so the layout is explicit
+        let bind_group_layout_equirect =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Bind Group Layout Equirect"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: IBL_FORMAT,
+                            view_dimension: wgpu::TextureViewDimension::D2Array,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let pipeline_layout_equirect =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Pipeline Layout Equirect"),
+                bind_group_layouts: &[&bind_group_layout_equirect],
+                push_constant_ranges: &[],
+            });
+        let pipeline = gen_compute_pipeline(
+            device,
+            Some(&pipeline_layout_equirect),
+            &shader,
+            "cs_equirect",
+        );
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Bind Group Equirect"),
+            layout: &bind_group_layout_equirect,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &texture_equirect.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&gen_texture_view_mip(
+                        &texture_env,
+                        0,
+                    )),
+                },
+            ],
+        });
+        dispatch(&mut encoder, &pipeline, &bind_group, ENV_SIZE, 6);
+
+        // env mips, prefilter reads blurrier mips for wide lobes
+        let pipeline = gen_compute_pipeline(device, None, &shader, "cs_downsample");
+        for mip in 1..env_mip_level_count {
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Bind Group Downsample"),
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&texture_env.create_view(
+                            &wgpu::TextureViewDescriptor {
+                                label: Some("Texture View Env Mip"),
+                                dimension: Some(wgpu::TextureViewDimension::Cube),
+                                base_mip_level: mip - 1,
+                                mip_level_count: Some(1),
+                                ..Default::default()
+                            },
+                        )),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(&gen_texture_view_mip(
+                            &texture_env,
+                            mip,
+                        )),
+                    },
+                ],
+            });
+            dispatch(&mut encoder, &pipeline, &bind_group, ENV_SIZE >> mip, 6);
+        }
+
+        let pipeline = gen_compute_pipeline(device, None, &shader, "cs_irradiance");
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Bind Group Irradiance"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&texture_view_env),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&gen_texture_view_mip(
+                        &texture_irradiance,
+                        0,
+                    )),
+                },
+            ],
+        });
+        dispatch(&mut encoder, &pipeline, &bind_group, IRRADIANCE_SIZE, 6);
+
+        // roughness grows linearly with the mip
+        let pipeline = gen_compute_pipeline(device, None, &shader, "cs_prefilter");
+        for mip in 0..PREFILTER_MIP_LEVEL_COUNT {
+            let roughness = mip as f32 / (PREFILTER_MIP_LEVEL_COUNT - 1) as f32;
+            let buffer_roughness = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Buffer Roughness"),
+                contents: bytemuck::bytes_of(&roughness),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Bind Group Prefilter"),
+                layout: &pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&texture_view_env),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(&gen_texture_view_mip(
+                            &texture_prefilter,
+                            mip,
+                        )),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::Buffer(
+                            buffer_roughness.as_entire_buffer_binding(),
+                        ),
+                    },
+                ],
+            });
+            dispatch(
+                &mut encoder,
+                &pipeline,
+                &bind_group,
+                PREFILTER_SIZE >> mip,
+                6,
+            );
+        }
+
+        let pipeline = gen_compute_pipeline(device, None, &shader, "cs_brdf_lut");
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Bind Group BRDF LUT"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(
+                    &texture_brdf_lut.create_view(&wgpu::TextureViewDescriptor::default()),
+                ),
+            }],
+        });
+        dispatch(&mut encoder, &pipeline, &bind_group, BRDF_LUT_SIZE, 1);
+
+        queue.submit(std::iter::once(encoder.finish()));
+
+        Ok(Self {
+            texture_view_env,
+            texture_view_irradiance: texture_irradiance.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("Texture View Irradiance"),
+                dimension: Some(wgpu::TextureViewDimension::Cube),
+                ..Default::default()
+            }),
+            texture_view_prefilter: texture_prefilter.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("Texture View Prefilter"),
+                dimension: Some(wgpu::TextureViewDimension::Cube),
+                ..Default::default()
+            }),
+            texture_view_brdf_lut: texture_brdf_lut
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+        })
+    }
+}
+
+// written by compute, sampled by the mesh shader
+fn gen_texture_ibl(
+    device: &Device,
+    label: &str,
+    size: u32,
+    layer_count: u32,
+    mip_level_count: u32,
+) -> Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: layer_count,
+        },
+        mip_level_count,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: IBL_FORMAT,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
+        view_formats: &[],
+    })
+}
+
+// one mip of a cube as a 2d array, storage textures can not be cubes
+fn gen_texture_view_mip(texture: &Texture, mip: u32) -> TextureView {
+    texture.create_view(&wgpu::TextureViewDescriptor {
+        label: Some("Texture View Mip"),
+        dimension: Some(wgpu::TextureViewDimension::D2Array),
+        base_mip_level: mip,
+        mip_level_count: Some(1),
+        ..Default::default()
+    })
+}
+
+// without a layout it is derived from the shader
+fn gen_compute_pipeline(
+    device: &Device,
+    layout: Option<&PipelineLayout>,
+    shader: &ShaderModule,
+    entry_point: &str,
+) -> ComputePipeline {
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some(entry_point),
+        layout,
+        module: shader,
+        entry_point,
+    })
+}
+
+// 8x8 workgroups over every layer
+fn dispatch(
+    encoder: &mut wgpu::CommandEncoder,
+    pipeline: &ComputePipeline,
+    bind_group: &BindGroup,
+    size: u32,
+    layer_count: u32,
+) {
+    let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+        label: Some("Compute Pass IBL"),
+    });
+    compute_pass.set_pipeline(pipeline);
+    compute_pass.set_bind_group(0, bind_group, &[]);
+    let group_count = size.div_ceil(8);
+    compute_pass.dispatch_workgroups(group_count, group_count, layer_count);
+}
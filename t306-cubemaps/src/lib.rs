@@ -1,6 +1,8 @@
 pub mod camera;
 pub mod color;
 pub mod core;
+pub use common::headless;
+pub mod ibl;
 pub mod input;
 pub mod light_direction;
 pub mod light_point;
@@ -7,6 +7,7 @@ pub struct LightDirection {
 
     pub color: [f32; 4],
 
+    // unused, replaced by the ibl ambient
     pub ambient: [f32; 3],
     // 16 bytes padding
     _padding2: u32,
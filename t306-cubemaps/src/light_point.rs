@@ -7,6 +7,7 @@ pub struct LightPoint {
 
     pub color: [f32; 4],
 
+    // mesh.wgsl takes the ambient from the ibl environment instead
     pub ambient: [f32; 3],
     // 16 bytes padding
     pub constant: f32,
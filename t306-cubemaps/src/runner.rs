@@ -1,4 +1,6 @@
+use anyhow::Result;
 use glam::{Quat, Vec3};
+use wgpu::TextureFormat;
 use winit::{event_loop::EventLoop, window::WindowBuilder};
 
 use crate::{
@@ -17,43 +19,57 @@ pub fn run() {
     let window = WindowBuilder::new().build(&event_loop).unwrap();
     pollster::block_on(async {
         let mut core = Core::new(window).await;
-        // load_box_model(&mut core);
-        // load_rect_model(&mut core);
-        // load_triangle_model(&mut core);
-        load_gltf_model(
-            &mut core,
-            &vec![Transform::new(
-                Vec3::new(0.0, 0.0, 0.0),
-                Quat::IDENTITY,
-                Vec3::ONE,
-            )],
-            RenderMethod::NORMAL,
-        );
-
-        load_gltf_model(
-            &mut core,
-            &vec![Transform::new(
-                Vec3::new(3.0, 0.0, 0.0),
-                Quat::IDENTITY,
-                Vec3::ONE,
-            )],
-            RenderMethod::REFLACT,
-        );
-
-        load_gltf_model(
-            &mut core,
-            &vec![Transform::new(
-                Vec3::new(6.0, 0.0, 0.0),
-                Quat::IDENTITY,
-                Vec3::ONE,
-            )],
-            RenderMethod::REFRACT,
-        );
+        load_scene(&mut core);
 
         Core::block_loop(event_loop, core);
     });
 }
 
+pub fn run_headless(width: u32, height: u32, path: &str) -> Result<()> {
+    pollster::block_on(async {
+        let mut core = Core::new_headless(width, height, TextureFormat::Rgba8UnormSrgb).await?;
+        load_scene(&mut core);
+        core.update_delta_time(1.0 / 60.0);
+        core.render_to_image().save(path)?;
+        Ok(())
+    })
+}
+
+pub fn load_scene(core: &mut Core) {
+    // load_box_model(core);
+    // load_rect_model(core);
+    // load_triangle_model(core);
+    load_gltf_model(
+        core,
+        &vec![Transform::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            Quat::IDENTITY,
+            Vec3::ONE,
+        )],
+        RenderMethod::NORMAL,
+    );
+
+    load_gltf_model(
+        core,
+        &vec![Transform::new(
+            Vec3::new(3.0, 0.0, 0.0),
+            Quat::IDENTITY,
+            Vec3::ONE,
+        )],
+        RenderMethod::REFLACT,
+    );
+
+    load_gltf_model(
+        core,
+        &vec![Transform::new(
+            Vec3::new(6.0, 0.0, 0.0),
+            Quat::IDENTITY,
+            Vec3::ONE,
+        )],
+        RenderMethod::REFRACT,
+    );
+}
+
 pub fn load_gltf_model(
     core: &mut Core,
     transform_arr: &Vec<Transform>,
@@ -1,9 +1,11 @@
+use anyhow::{anyhow, Result};
 use glam::{Quat, Vec3};
 use rand::Rng;
 use winit::{event_loop::EventLoop, window::WindowBuilder};
 
 use crate::{
     core::Core,
+    gltf_data::{load_gltf, load_gltf_image},
     material::Material,
     model::{DrawMethod, Model},
     texture::gen_texture_view,
@@ -27,7 +29,8 @@ pub fn run() {
                 Vec3::splat(8.0),
             )],
             &mut core,
-        );
+        )
+        .unwrap();
 
         // rocks
         let amount = 100000;
@@ -54,7 +57,7 @@ pub fn run() {
             let transform = Transform::new(pos, rotation, scale);
             transform_arr.push(transform);
         }
-        load_gltf_model("rock.gltf", &transform_arr, &mut core);
+        load_gltf_model("rock.gltf", &transform_arr, &mut core).unwrap();
 
         Core::block_loop(event_loop, core);
     });
@@ -65,58 +68,28 @@ fn rand() -> i32 {
     (rng.gen::<f32>() * 2000.0) as i32
 }
 
-pub fn load_gltf_model(path: &str, transform_arr: &Vec<Transform>, core: &mut Core) {
+pub fn load_gltf_model(path: &str, transform_arr: &Vec<Transform>, core: &mut Core) -> Result<()> {
     let base_path = std::path::Path::new(BASE_GLTF_PATH);
-    println!("path: {:?}", path);
 
     let gltf_path = base_path.join(path);
-    let gltf_info = gltf::Gltf::open(gltf_path).unwrap();
-
-    let mut buffer_data = Vec::new();
-    for buffer in gltf_info.buffers() {
-        match buffer.source() {
-            gltf::buffer::Source::Bin => {}
-            gltf::buffer::Source::Uri(path) => {
-                let path = base_path.join(path);
-                let data = std::fs::read(path).unwrap();
-                buffer_data.push(data);
-            }
-        }
-    }
+    let (gltf_info, buffer_data) = load_gltf(&gltf_path)?;
 
     // let material_arr = vec![];
     for material in gltf_info.materials() {
         let info = material
             .pbr_metallic_roughness()
             .base_color_texture()
-            .unwrap();
-        let texture = info.texture();
-        let source = texture.source().source();
-        let texture_view;
-        match source {
-            gltf::image::Source::View { view, .. } => {
-                texture_view =
-                    gen_texture_view(buffer_data[view.index()].clone(), &core.device, &core.queue)
-                        .unwrap();
-            }
-            gltf::image::Source::Uri { uri, .. } => {
-                texture_view = gen_texture_view(
-                    std::fs::read(base_path.join(url_escape::decode(uri).to_string())).unwrap(),
-                    &core.device,
-                    &core.queue,
-                )
-                .unwrap();
-            }
-        }
+            .ok_or_else(|| anyhow!("material {:?} has no base color texture", material.name()))?;
+        let img_bytes = load_gltf_image(&info.texture().source(), base_path, &buffer_data)?;
+        let texture_view = gen_texture_view(img_bytes, &core.device, &core.queue)?;
 
         let material = Material::new(texture_view, 32.0, core);
         core.material_arr.push(material);
     }
 
     for mesh in gltf_info.meshes() {
-        let primitives = mesh.primitives();
-        primitives.for_each(|primitive| {
-            let reader = primitive.reader(|buffer| Some(&buffer_data[buffer.index()]));
+        for primitive in mesh.primitives() {
+            let reader = primitive.reader(|buffer| Some(&*buffer_data[buffer.index()]));
 
             let mut vertices = vec![];
             if let Some(positions) = reader.read_positions() {
@@ -158,11 +131,14 @@ pub fn load_gltf_model(path: &str, transform_arr: &Vec<Transform>, core: &mut Co
                 transform_arr,
             );
 
-            core.material_arr[primitive.material().index().unwrap() as usize]
-                .model_arr
-                .push(model);
-        });
+            let material_idx = primitive.material().index().ok_or_else(|| {
+                anyhow!("mesh {:?} has a primitive without material", mesh.name())
+            })?;
+            core.material_arr[material_idx].model_arr.push(model);
+        }
     }
+
+    Ok(())
 }
 
 pub fn load_box_model(core: &mut Core) {
@@ -0,0 +1,158 @@
+// fxaa 3.11, the pc quality version with the preset 39 search steps
+
+struct VertexOut {
+    @builtin(position) clip_pos: vec4<f32>,
+    @location(0) tex_coord: vec2<f32>,
+}
+
+@group(0)@binding(0)
+var texture_sampler: sampler;
+@group(0)@binding(1)
+var texture_in: texture_2d<f32>;
+
+// amount of sub-pixel aliasing removal, 0 off, 1 softer
+const SUBPIX: f32 = 0.75;
+// minimum local contrast to be treated as an edge
+const EDGE_THRESHOLD: f32 = 0.166;
+// darks below this are skipped
+const EDGE_THRESHOLD_MIN: f32 = 0.0833;
+// pixels walked along the edge at each search step
+const STEP_NUM: i32 = 12;
+var<private> step_arr: array<f32, 12> = array<f32, 12>(1.0, 1.0, 1.0, 1.0, 1.0, 1.5, 2.0, 2.0, 2.0, 2.0, 4.0, 8.0);
+
+// one triangle covering the screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOut {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOut;
+    out.clip_pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.tex_coord = uv;
+    return out;
+}
+
+// fxaa expects perceptual luma, the srgb texture is read back as linear
+fn luma(color: vec3<f32>) -> f32 {
+    return dot(sqrt(color), vec3<f32>(0.299, 0.587, 0.114));
+}
+
+fn luma_at(uv: vec2<f32>) -> f32 {
+    return luma(textureSampleLevel(texture_in, texture_sampler, uv, 0.0).rgb);
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    let rcp_frame = 1.0 / vec2<f32>(textureDimensions(texture_in));
+    var pos_m = in.tex_coord;
+    let color_m = textureSampleLevel(texture_in, texture_sampler, pos_m, 0.0);
+
+    let luma_m = luma(color_m.rgb);
+    var luma_s = luma_at(pos_m + vec2<f32>(0.0, 1.0) * rcp_frame);
+    let luma_e = luma_at(pos_m + vec2<f32>(1.0, 0.0) * rcp_frame);
+    var luma_n = luma_at(pos_m + vec2<f32>(0.0, -1.0) * rcp_frame);
+    let luma_w = luma_at(pos_m + vec2<f32>(-1.0, 0.0) * rcp_frame);
+
+    let range_max = max(max(luma_n, luma_w), max(luma_e, max(luma_s, luma_m)));
+    let range_min = min(min(luma_n, luma_w), min(luma_e, min(luma_s, luma_m)));
+    let range = range_max - range_min;
+    if range < max(EDGE_THRESHOLD_MIN, range_max * EDGE_THRESHOLD) {
+        return color_m;
+    }
+
+    let luma_nw = luma_at(pos_m + vec2<f32>(-1.0, -1.0) * rcp_frame);
+    let luma_se = luma_at(pos_m + vec2<f32>(1.0, 1.0) * rcp_frame);
+    let luma_ne = luma_at(pos_m + vec2<f32>(1.0, -1.0) * rcp_frame);
+    let luma_sw = luma_at(pos_m + vec2<f32>(-1.0, 1.0) * rcp_frame);
+
+    // is the edge horizontal or vertical
+    let luma_ns = luma_n + luma_s;
+    let luma_we = luma_w + luma_e;
+    let luma_nese = luma_ne + luma_se;
+    let luma_nwne = luma_nw + luma_ne;
+    let luma_nwsw = luma_nw + luma_sw;
+    let luma_swse = luma_sw + luma_se;
+    let edge_horz = abs(-2.0 * luma_w + luma_nwsw) + abs(-2.0 * luma_m + luma_ns) * 2.0
+        + abs(-2.0 * luma_e + luma_nese);
+    let edge_vert = abs(-2.0 * luma_s + luma_swse) + abs(-2.0 * luma_m + luma_we) * 2.0
+        + abs(-2.0 * luma_n + luma_nwne);
+    let is_horz = edge_horz >= edge_vert;
+
+    // sub-pixel blend from the average of the neighbours
+    let subpix_a = (luma_ns + luma_we) * 2.0 + luma_nwsw + luma_nese;
+    let subpix_b = subpix_a / 12.0 - luma_m;
+    let subpix_c = saturate(abs(subpix_b) / range);
+
+    // the side of the pixel the edge is on
+    if !is_horz {
+        luma_n = luma_w;
+        luma_s = luma_e;
+    }
+    var length_sign = select(rcp_frame.x, rcp_frame.y, is_horz);
+    let gradient_n = luma_n - luma_m;
+    let gradient_s = luma_s - luma_m;
+    let is_pair_n = abs(gradient_n) >= abs(gradient_s);
+    let gradient = max(abs(gradient_n), abs(gradient_s));
+    if is_pair_n {
+        length_sign = -length_sign;
+    }
+    let luma_nn = select(luma_s + luma_m, luma_n + luma_m, is_pair_n);
+
+    // walk along the edge both ways until the luma changes
+    var pos_b = pos_m;
+    if is_horz {
+        pos_b.y += length_sign * 0.5;
+    } else {
+        pos_b.x += length_sign * 0.5;
+    }
+    let off_np = select(vec2<f32>(0.0, rcp_frame.y), vec2<f32>(rcp_frame.x, 0.0), is_horz);
+    let gradient_scaled = gradient / 4.0;
+    let is_luma_m_neg = luma_m - luma_nn * 0.5 < 0.0;
+
+    var pos_n = pos_b - off_np * step_arr[0];
+    var pos_p = pos_b + off_np * step_arr[0];
+    var luma_end_n = luma_at(pos_n) - luma_nn * 0.5;
+    var luma_end_p = luma_at(pos_p) - luma_nn * 0.5;
+    var is_done_n = abs(luma_end_n) >= gradient_scaled;
+    var is_done_p = abs(luma_end_p) >= gradient_scaled;
+    if !is_done_n {
+        pos_n -= off_np * step_arr[1];
+    }
+    if !is_done_p {
+        pos_p += off_np * step_arr[1];
+    }
+    for (var i = 2; i < STEP_NUM; i++) {
+        if is_done_n && is_done_p {
+            break;
+        }
+        if !is_done_n {
+            luma_end_n = luma_at(pos_n) - luma_nn * 0.5;
+            is_done_n = abs(luma_end_n) >= gradient_scaled;
+            if !is_done_n {
+                pos_n -= off_np * step_arr[i];
+            }
+        }
+        if !is_done_p {
+            luma_end_p = luma_at(pos_p) - luma_nn * 0.5;
+            is_done_p = abs(luma_end_p) >= gradient_scaled;
+            if !is_done_p {
+                pos_p += off_np * step_arr[i];
+            }
+        }
+    }
+
+    // shift toward the nearer end, only when the luma there crosses the edge
+    let dst_n = select(pos_m.y - pos_n.y, pos_m.x - pos_n.x, is_horz);
+    let dst_p = select(pos_p.y - pos_m.y, pos_p.x - pos_m.x, is_horz);
+    let is_direction_n = dst_n < dst_p;
+    let dst = min(dst_n, dst_p);
+    let is_good_span = select((luma_end_p < 0.0) != is_luma_m_neg, (luma_end_n < 0.0) != is_luma_m_neg, is_direction_n);
+    let pixel_offset = select(0.0, 0.5 - dst / (dst_n + dst_p), is_good_span);
+
+    let subpix_f = (-2.0 * subpix_c + 3.0) * subpix_c * subpix_c;
+    let pixel_offset_subpix = max(pixel_offset, subpix_f * subpix_f * SUBPIX);
+    if is_horz {
+        pos_m.y += pixel_offset_subpix * length_sign;
+    } else {
+        pos_m.x += pixel_offset_subpix * length_sign;
+    }
+    return vec4<f32>(textureSampleLevel(texture_in, texture_sampler, pos_m, 0.0).rgb, color_m.a);
+}
@@ -9,6 +9,14 @@ struct VertexOut {
     @location(0) frag_pos: vec3<f32>,
     @location(1) normal: vec3<f32>,
     @location(2) tex_coord: vec2<f32>,
+    // unjittered, for the velocity
+    @location(3) clip_pos_cur: vec4<f32>,
+    @location(4) clip_pos_prev: vec4<f32>,
+}
+
+struct FragmentOutTaa {
+    @location(0) color: vec4<f32>,
+    @location(1) velocity: vec2<f32>,
 }
 
 struct TransformIT {
@@ -22,6 +30,19 @@ struct TransformIT {
     @location(12) t7: vec4<f32>,
 }
 
+// rows of the previous model matrix
+struct TransformPrev {
+    @location(13) r0: vec4<f32>,
+    @location(14) r1: vec4<f32>,
+    @location(15) r2: vec4<f32>,
+}
+
+struct TaaParam {
+    view_proj: mat4x4<f32>,
+    view_proj_prev: mat4x4<f32>,
+    history_weight: f32,
+}
+
 struct LightDirection {
     dir: vec3<f32>,
     color: vec4<f32>,
@@ -70,9 +91,11 @@ var<uniform> view: mat4x4<f32>;
 var<uniform> proj: mat4x4<f32>;
 @group(0)@binding(2)
 var<uniform> camera_pos: vec3<f32>;
+@group(0)@binding(3)
+var<uniform> taa: TaaParam;
 
 @vertex
-fn vs_main(in: VertexIn, transform: TransformIT) -> VertexOut {
+fn vs_main(in: VertexIn, transform: TransformIT, transform_prev: TransformPrev) -> VertexOut {
     let model = mat4x4<f32>(transform.t0, transform.t1, transform.t2, transform.t3);
     let it_model = mat3x3<f32>(transform.t4.xyz, transform.t5.xyz, transform.t6.xyz); // inverse transpose model
 
@@ -83,6 +106,11 @@ fn vs_main(in: VertexIn, transform: TransformIT) -> VertexOut {
     out.normal = it_model * in.normal;
     out.tex_coord = in.tex_coord;
 
+    let pos = vec4<f32>(in.pos, 1.0);
+    let pos_prev = vec4<f32>(dot(transform_prev.r0, pos), dot(transform_prev.r1, pos), dot(transform_prev.r2, pos), 1.0);
+    out.clip_pos_cur = taa.view_proj * model * pos;
+    out.clip_pos_prev = taa.view_proj_prev * pos_prev;
+
     return out;
 }
 
@@ -106,6 +134,22 @@ var<uniform> shininess: f32;
 
 @fragment
 fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    return shade(in);
+}
+
+// also writes how far the fragment moved on screen, in texture coordinates
+@fragment
+fn fs_taa(in: VertexOut) -> FragmentOutTaa {
+    let ndc_cur = in.clip_pos_cur.xy / in.clip_pos_cur.w;
+    let ndc_prev = in.clip_pos_prev.xy / in.clip_pos_prev.w;
+
+    var out: FragmentOutTaa;
+    out.color = shade(in);
+    out.velocity = (ndc_cur - ndc_prev) * vec2<f32>(0.5, -0.5);
+    return out;
+}
+
+fn shade(in: VertexOut) -> vec4<f32> {
     var l = vec3<f32>(0.0, 0.0, 0.0);
     let normal = normalize(in.normal);
     let view_dir = normalize(camera_pos - in.frag_pos);
@@ -0,0 +1,242 @@
+// smaa 1x, luma edge detection, blending weight calculation and neighborhood blending
+// orthogonal patterns only, the diagonal search is left out
+
+struct VertexOut {
+    @builtin(position) clip_pos: vec4<f32>,
+    @location(0) tex_coord: vec2<f32>,
+}
+
+// every pass binds only the textures it reads
+@group(0)@binding(0)
+var texture_sampler: sampler;
+@group(0)@binding(1)
+var texture_color: texture_2d<f32>;
+@group(0)@binding(2)
+var texture_edge: texture_2d<f32>;
+@group(0)@binding(3)
+var texture_blend: texture_2d<f32>;
+@group(0)@binding(4)
+var texture_area: texture_2d<f32>;
+@group(0)@binding(5)
+var texture_search: texture_2d<f32>;
+
+// luma difference that counts as an edge
+const THRESHOLD: f32 = 0.1;
+// an edge is dropped when a neighbour edge is this many times stronger
+const LOCAL_CONTRAST_ADAPTATION_FACTOR: f32 = 2.0;
+// each step covers two pixels
+const MAX_SEARCH_STEP: f32 = 16.0;
+// percent of the corner kept sharp
+const CORNER_ROUNDING: f32 = 25.0;
+// texels of one pattern along each side of the area texture
+const AREA_MAX_DISTANCE: f32 = 16.0;
+// one half of the search texture, left then right
+const SEARCH_SIZE: i32 = 33;
+
+// one triangle covering the screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOut {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOut;
+    out.clip_pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.tex_coord = uv;
+    return out;
+}
+
+// the edges are found in gamma space, the srgb texture is read back as linear
+fn luma_at(coord: vec2<i32>) -> f32 {
+    let size = vec2<i32>(textureDimensions(texture_color));
+    let color = textureLoad(texture_color, clamp(coord, vec2<i32>(0), size - 1), 0).rgb;
+    return dot(sqrt(color), vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// r is an edge on the left of the pixel, g on the top
+@fragment
+fn fs_edge(in: VertexOut) -> @location(0) vec4<f32> {
+    let coord = vec2<i32>(in.clip_pos.xy);
+    let l = luma_at(coord);
+    let l_left = luma_at(coord + vec2<i32>(-1, 0));
+    let l_top = luma_at(coord + vec2<i32>(0, -1));
+
+    let delta_left_top = abs(l - vec2<f32>(l_left, l_top));
+    var edge = step(vec2<f32>(THRESHOLD), delta_left_top);
+    if dot(edge, vec2<f32>(1.0)) == 0.0 {
+        return vec4<f32>(0.0);
+    }
+
+    // local contrast adaptation against the strongest neighbour edge
+    let l_right = luma_at(coord + vec2<i32>(1, 0));
+    let l_bottom = luma_at(coord + vec2<i32>(0, 1));
+    let delta_right_bottom = abs(l - vec2<f32>(l_right, l_bottom));
+    let l_left_left = luma_at(coord + vec2<i32>(-2, 0));
+    let l_top_top = luma_at(coord + vec2<i32>(0, -2));
+    let delta_far = abs(vec2<f32>(l_left, l_top) - vec2<f32>(l_left_left, l_top_top));
+    let delta_max = max(max(delta_left_top, delta_right_bottom), delta_far);
+    let delta_final = max(delta_max.x, delta_max.y);
+    edge *= step(vec2<f32>(delta_final), LOCAL_CONTRAST_ADAPTATION_FACTOR * delta_left_top);
+
+    return vec4<f32>(edge, 0.0, 0.0);
+}
+
+fn edge_at(uv: vec2<f32>) -> vec2<f32> {
+    return textureSampleLevel(texture_edge, texture_sampler, uv, 0.0).rg;
+}
+
+// the bilinear edge fetch names the edge combination around the end of a line,
+// the search texture turns it into the pixels to step back
+fn search_length(e: vec2<f32>, half_offset: i32) -> f32 {
+    let coord = vec2<i32>(round(e * 32.0)) + vec2<i32>(half_offset * SEARCH_SIZE, 0);
+    return textureLoad(texture_search, coord, 0).r;
+}
+
+fn search_x_left(uv_start: vec2<f32>, end: f32, rt: vec2<f32>) -> f32 {
+    var uv = uv_start;
+    var e = vec2<f32>(0.0, 1.0);
+    while uv.x > end && e.g > 0.8281 && e.r == 0.0 {
+        e = edge_at(uv);
+        uv -= vec2<f32>(2.0, 0.0) * rt;
+    }
+    let offset = -(255.0 / 127.0) * search_length(e, 0) + 3.25;
+    return rt.x * offset + uv.x;
+}
+
+fn search_x_right(uv_start: vec2<f32>, end: f32, rt: vec2<f32>) -> f32 {
+    var uv = uv_start;
+    var e = vec2<f32>(0.0, 1.0);
+    while uv.x < end && e.g > 0.8281 && e.r == 0.0 {
+        e = edge_at(uv);
+        uv += vec2<f32>(2.0, 0.0) * rt;
+    }
+    let offset = -(255.0 / 127.0) * search_length(e, 1) + 3.25;
+    return -rt.x * offset + uv.x;
+}
+
+fn search_y_up(uv_start: vec2<f32>, end: f32, rt: vec2<f32>) -> f32 {
+    var uv = uv_start;
+    var e = vec2<f32>(1.0, 0.0);
+    while uv.y > end && e.r > 0.8281 && e.g == 0.0 {
+        e = edge_at(uv);
+        uv -= vec2<f32>(0.0, 2.0) * rt;
+    }
+    let offset = -(255.0 / 127.0) * search_length(e.gr, 0) + 3.25;
+    return rt.y * offset + uv.y;
+}
+
+fn search_y_down(uv_start: vec2<f32>, end: f32, rt: vec2<f32>) -> f32 {
+    var uv = uv_start;
+    var e = vec2<f32>(1.0, 0.0);
+    while uv.y < end && e.r > 0.8281 && e.g == 0.0 {
+        e = edge_at(uv);
+        uv += vec2<f32>(0.0, 2.0) * rt;
+    }
+    let offset = -(255.0 / 127.0) * search_length(e.gr, 1) + 3.25;
+    return -rt.y * offset + uv.y;
+}
+
+// coverage of the pixel on both sides of the line, by the distance to
+// each end and the crossing edges found there
+fn area(dist: vec2<f32>, e1: f32, e2: f32) -> vec2<f32> {
+    let size = vec2<f32>(textureDimensions(texture_area));
+    let coord = AREA_MAX_DISTANCE * round(4.0 * vec2<f32>(e1, e2)) + dist;
+    return textureSampleLevel(texture_area, texture_sampler, (coord + 0.5) / size, 0.0).rg;
+}
+
+// keep some of the corner where the line ends at a crossing edge
+fn corner_horizontal(weight: vec2<f32>, uv: vec4<f32>, d: vec2<f32>, rt: vec2<f32>) -> vec2<f32> {
+    let left_right = step(d.xy, d.yx);
+    let rounding = (1.0 - CORNER_ROUNDING / 100.0) * left_right / (left_right.x + left_right.y);
+    var factor = vec2<f32>(1.0);
+    factor.x -= rounding.x * edge_at(uv.xy + vec2<f32>(0.0, 1.0) * rt).r;
+    factor.x -= rounding.y * edge_at(uv.zw + vec2<f32>(1.0, 1.0) * rt).r;
+    factor.y -= rounding.x * edge_at(uv.xy + vec2<f32>(0.0, -2.0) * rt).r;
+    factor.y -= rounding.y * edge_at(uv.zw + vec2<f32>(1.0, -2.0) * rt).r;
+    return weight * saturate(factor);
+}
+
+fn corner_vertical(weight: vec2<f32>, uv: vec4<f32>, d: vec2<f32>, rt: vec2<f32>) -> vec2<f32> {
+    let left_right = step(d.xy, d.yx);
+    let rounding = (1.0 - CORNER_ROUNDING / 100.0) * left_right / (left_right.x + left_right.y);
+    var factor = vec2<f32>(1.0);
+    factor.x -= rounding.x * edge_at(uv.xy + vec2<f32>(1.0, 0.0) * rt).g;
+    factor.x -= rounding.y * edge_at(uv.zw + vec2<f32>(1.0, 1.0) * rt).g;
+    factor.y -= rounding.x * edge_at(uv.xy + vec2<f32>(-2.0, 0.0) * rt).g;
+    factor.y -= rounding.y * edge_at(uv.zw + vec2<f32>(-2.0, 1.0) * rt).g;
+    return weight * saturate(factor);
+}
+
+// rg blend with the pixel above and below for a top edge, ba left and right for a left edge
+@fragment
+fn fs_weight(in: VertexOut) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(texture_edge));
+    let rt = 1.0 / size;
+    let uv = in.tex_coord;
+    let pixel = uv * size;
+
+    let offset_0 = uv.xyxy + rt.xyxy * vec4<f32>(-0.25, -0.125, 1.25, -0.125);
+    let offset_1 = uv.xyxy + rt.xyxy * vec4<f32>(-0.125, -0.25, -0.125, 1.25);
+    let offset_2 = vec4<f32>(offset_0.xz, offset_1.yw) + rt.xxyy * vec4<f32>(-2.0, 2.0, -2.0, 2.0) * MAX_SEARCH_STEP;
+
+    var weight = vec4<f32>(0.0);
+    let e = textureLoad(texture_edge, vec2<i32>(in.clip_pos.xy), 0).rg;
+
+    // edge at the top
+    if e.g > 0.0 {
+        var coord = vec3<f32>(0.0);
+        coord.x = search_x_left(offset_0.xy, offset_2.x, rt);
+        coord.y = offset_1.y;
+        coord.z = search_x_right(offset_0.zw, offset_2.y, rt);
+        let d = abs(round(size.x * coord.xz - pixel.x));
+
+        let e1 = edge_at(coord.xy).r;
+        let e2 = edge_at(coord.zy + vec2<f32>(rt.x, 0.0)).r;
+        let weight_rg = area(sqrt(d), e1, e2);
+        let corner = corner_horizontal(weight_rg, vec4<f32>(coord.x, uv.y, coord.z, uv.y), d, rt);
+        weight = vec4<f32>(corner, weight.ba);
+    }
+
+    // edge on the left
+    if e.r > 0.0 {
+        var coord = vec3<f32>(0.0);
+        coord.y = search_y_up(offset_1.xy, offset_2.z, rt);
+        coord.x = offset_0.x;
+        coord.z = search_y_down(offset_1.zw, offset_2.w, rt);
+        let d = abs(round(size.y * coord.yz - pixel.y));
+
+        let e1 = edge_at(coord.xy).g;
+        let e2 = edge_at(coord.xz + vec2<f32>(0.0, rt.y)).g;
+        let weight_ba = area(sqrt(d), e1, e2);
+        let corner = corner_vertical(weight_ba, vec4<f32>(uv.x, coord.y, uv.x, coord.z), d, rt);
+        weight = vec4<f32>(weight.rg, corner);
+    }
+
+    return weight;
+}
+
+// mix each pixel with the neighbour its weights point to
+@fragment
+fn fs_blend(in: VertexOut) -> @location(0) vec4<f32> {
+    let rt = 1.0 / vec2<f32>(textureDimensions(texture_color));
+    let uv = in.tex_coord;
+
+    var a: vec4<f32>;
+    a.x = textureSampleLevel(texture_blend, texture_sampler, uv + vec2<f32>(rt.x, 0.0), 0.0).a;
+    a.y = textureSampleLevel(texture_blend, texture_sampler, uv + vec2<f32>(0.0, rt.y), 0.0).g;
+    let own = textureSampleLevel(texture_blend, texture_sampler, uv, 0.0);
+    a.w = own.r;
+    a.z = own.b;
+
+    if dot(a, vec4<f32>(1.0)) < 1e-5 {
+        return textureSampleLevel(texture_color, texture_sampler, uv, 0.0);
+    }
+
+    // blend along the stronger direction only
+    let is_horizontal = max(a.x, a.z) > max(a.y, a.w);
+    let blend_offset = select(vec4<f32>(0.0, a.y, 0.0, a.w), vec4<f32>(a.x, 0.0, a.z, 0.0), is_horizontal);
+    var blend_weight = select(a.yw, a.xz, is_horizontal);
+    blend_weight /= dot(blend_weight, vec2<f32>(1.0));
+
+    let blend_coord = uv.xyxy + blend_offset * vec4<f32>(rt, -rt);
+    var color = blend_weight.x * textureSampleLevel(texture_color, texture_sampler, blend_coord.xy, 0.0);
+    color += blend_weight.y * textureSampleLevel(texture_color, texture_sampler, blend_coord.zw, 0.0);
+    return color;
+}
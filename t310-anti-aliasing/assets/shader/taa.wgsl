@@ -0,0 +1,71 @@
+// taa resolve, the jittered frame is blended into the history reprojected with the velocity,
+// the history is clamped to the colors around the pixel so stale colors do not ghost
+
+struct VertexOut {
+    @builtin(position) clip_pos: vec4<f32>,
+    @location(0) tex_coord: vec2<f32>,
+}
+
+struct TaaParam {
+    view_proj: mat4x4<f32>,
+    view_proj_prev: mat4x4<f32>,
+    history_weight: f32,
+}
+
+struct FragmentOut {
+    // read back by the next frame
+    @location(0) history: vec4<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@group(0)@binding(0)
+var texture_sampler: sampler;
+@group(0)@binding(1)
+var texture_color: texture_2d<f32>;
+@group(0)@binding(2)
+var texture_velocity: texture_2d<f32>;
+@group(0)@binding(3)
+var texture_history: texture_2d<f32>;
+@group(0)@binding(4)
+var<uniform> param: TaaParam;
+
+// one triangle covering the screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOut {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOut;
+    out.clip_pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.tex_coord = uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> FragmentOut {
+    let size = vec2<i32>(textureDimensions(texture_color));
+    let pixel = vec2<i32>(in.clip_pos.xy);
+    let color = textureLoad(texture_color, pixel, 0).rgb;
+
+    var color_min = color;
+    var color_max = color;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let neighbour = textureLoad(texture_color, clamp(pixel + vec2<i32>(x, y), vec2<i32>(0), size - 1), 0).rgb;
+            color_min = min(color_min, neighbour);
+            color_max = max(color_max, neighbour);
+        }
+    }
+
+    // where the pixel was on screen last frame, nothing to reuse when it came from outside
+    let tex_coord_prev = in.tex_coord - textureLoad(texture_velocity, pixel, 0).xy;
+    var history_weight = param.history_weight;
+    if any(tex_coord_prev < vec2<f32>(0.0)) || any(tex_coord_prev > vec2<f32>(1.0)) {
+        history_weight = 0.0;
+    }
+    let history = textureSampleLevel(texture_history, texture_sampler, tex_coord_prev, 0.0).rgb;
+
+    let result = vec4<f32>(mix(color, clamp(history, color_min, color_max), history_weight), 1.0);
+    var out: FragmentOut;
+    out.history = result;
+    out.color = result;
+    return out;
+}
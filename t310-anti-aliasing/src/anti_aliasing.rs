@@ -0,0 +1,43 @@
+use wgpu::{Adapter, Device, TextureFormat};
+
+use crate::texture::supported_sample_count;
+
+// how edges are smoothed, msaa in the scene pass, a post pass over the final image
+// or taa blending jittered frames over time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AntiAliasing {
+    None,
+    // samples per pixel
+    Msaa(u32),
+    Fxaa,
+    Smaa,
+    Taa,
+}
+
+impl AntiAliasing {
+    // of the scene pass, 1 unless msaa
+    pub fn sample_count(self) -> u32 {
+        match self {
+            Self::Msaa(sample_count) => sample_count,
+            _ => 1,
+        }
+    }
+
+    // msaa lowered to a sample count the adapter can render format with, none when it can't
+    pub fn supported(self, adapter: &Adapter, device: &Device, format: TextureFormat) -> Self {
+        match self {
+            Self::Msaa(sample_count) => {
+                match supported_sample_count(adapter, device, format, sample_count) {
+                    1 => Self::None,
+                    sample_count => Self::Msaa(sample_count),
+                }
+            }
+            _ => self,
+        }
+    }
+
+    // runs after the post processing pass
+    pub fn is_post_pass(self) -> bool {
+        matches!(self, Self::Fxaa | Self::Smaa)
+    }
+}
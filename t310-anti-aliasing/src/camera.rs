@@ -1,4 +1,4 @@
-use glam::{Mat4, Vec3};
+use glam::{Mat4, Vec2, Vec3};
 use winit::event::VirtualKeyCode;
 
 use crate::input::Input;
@@ -129,4 +129,9 @@ impl Camera {
     pub fn proj(&self) -> Mat4 {
         Mat4::perspective_rh(self.fov.to_radians(), self.ratio, self.z_near, self.z_far)
     }
+
+    // jitter is in ndc, shifts the whole image without changing the depth
+    pub fn proj_jitter(&self, jitter: Vec2) -> Mat4 {
+        Mat4::from_translation(jitter.extend(0.0)) * self.proj()
+    }
 }
@@ -1,14 +1,23 @@
 use std::time::Instant;
 
+use anyhow::{anyhow, Result};
+use image::RgbaImage;
+use log::{debug, warn};
 use wgpu::{
-    util::DeviceExt, Adapter, Backends, BindGroup, BindGroupLayout, Buffer, Device,
-    DeviceDescriptor, Features, IndexFormat, Instance, PresentMode, Queue, RenderPipeline, Sampler,
-    Surface, SurfaceConfiguration, TextureUsages, TextureView,
+    util::DeviceExt, Adapter, Backends, BindGroup, BindGroupLayout, Buffer, CompositeAlphaMode,
+    Device, DeviceDescriptor, Features, IndexFormat, Instance, PipelineLayout, PresentMode, Queue,
+    RenderPipeline, Sampler, ShaderModule, Surface, SurfaceConfiguration, Texture, TextureFormat,
+    TextureUsages, TextureView,
 };
-use winit::{dpi::PhysicalPosition, event_loop::EventLoop, window::Window};
+use winit::{dpi::PhysicalPosition, event::VirtualKeyCode, event_loop::EventLoop, window::Window};
 
 use crate::{
+    anti_aliasing::AntiAliasing,
     camera::Camera,
+    fxaa::Fxaa,
+    headless::{
+        check_format_headless, gen_texture_headless, request_adapter_headless, texture_to_image,
+    },
     input::Input,
     light_direction::LightDirection,
     light_point::LightPoint,
@@ -16,23 +25,29 @@ use crate::{
     material::Material,
     model::DrawMethod,
     model_light::ModelLight,
+    smaa::Smaa,
+    taa::{Taa, VELOCITY_FORMAT},
     texture::{
-        self, gen_texture_depth, gen_texture_msaa, gen_texture_post_processing, gen_texture_sampler,
+        self, gen_texture_depth, gen_texture_msaa, gen_texture_post_processing,
+        gen_texture_sampler, supported_sample_count_arr,
     },
-    transform::TransformRawIT,
+    transform::{TransformRawIT, TransformRawPrev},
     vertex::Vertex,
 };
 
-const SAMPLE_COUNT: u32 = 4;
-
 pub struct Core {
-    pub window: Window,
+    pub window: Option<Window>,
     pub instance: Instance,
-    pub surface: Surface,
+    pub surface: Option<Surface>,
     pub adapter: Adapter,
     pub device: Device,
     pub queue: Queue,
     pub surface_config: SurfaceConfiguration,
+    // offscreen target when there is no surface
+    pub texture_headless: Option<Texture>,
+    pub shader_mesh: ShaderModule,
+    pub render_pipline_layout_mesh: PipelineLayout,
+    // rebuilt when the msaa sample count changes
     pub render_pipline_mesh: RenderPipeline,
     pub render_pipline_post_processing: RenderPipeline,
     pub camera: Camera,
@@ -62,8 +77,15 @@ pub struct Core {
     pub bind_group_post_processing: BindGroup,
     pub texture_post_processing: TextureView,
 
+    pub anti_aliasing: AntiAliasing,
     pub texture_depth: TextureView,
-    pub texture_msaa: TextureView,
+    // only with msaa, resolved into texture_post_processing
+    pub texture_msaa: Option<TextureView>,
+    // the post processing pass writes here when a post pass smooths the image
+    pub texture_anti_aliasing: TextureView,
+    pub fxaa: Fxaa,
+    pub smaa: Smaa,
+    pub taa: Taa,
 
     pub light_direction_buffer: Buffer,
     pub light_point_buffer: Buffer,
@@ -95,18 +117,6 @@ impl Core {
             .await
             .unwrap();
 
-        let (device, queue) = adapter
-            .request_device(
-                &DeviceDescriptor {
-                    label: Some("Device"),
-                    features: Features::empty(),
-                    limits: Default::default(),
-                },
-                None,
-            )
-            .await
-            .unwrap();
-
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_config = SurfaceConfiguration {
             usage: TextureUsages::RENDER_ATTACHMENT,
@@ -117,12 +127,76 @@ impl Core {
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
         };
-        surface.configure(&device, &surface_config);
 
-        let mesh_shader = std::fs::read_to_string("assets/shader/mesh.wgsl").unwrap();
-        let mesh_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        Self::init(
+            instance,
+            adapter,
+            Some(window),
+            Some(surface),
+            surface_config,
+        )
+        .await
+        .unwrap()
+    }
+
+    // render into an offscreen texture of the given size and format instead of a window
+    pub async fn new_headless(width: u32, height: u32, format: TextureFormat) -> Result<Self> {
+        check_format_headless(format)?;
+
+        let instance = Instance::new(wgpu::InstanceDescriptor {
+            backends: Backends::all(),
+            dx12_shader_compiler: Default::default(),
+        });
+
+        let adapter = request_adapter_headless(&instance)
+            .await
+            .ok_or_else(|| anyhow!("no adapter for headless rendering"))?;
+
+        let surface_config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            format,
+            width,
+            height,
+            present_mode: PresentMode::Fifo,
+            alpha_mode: CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+
+        Self::init(instance, adapter, None, None, surface_config).await
+    }
+
+    async fn init(
+        instance: Instance,
+        adapter: Adapter,
+        window: Option<Window>,
+        surface: Option<Surface>,
+        surface_config: SurfaceConfiguration,
+    ) -> Result<Self> {
+        let (device, queue) = adapter
+            .request_device(
+                &DeviceDescriptor {
+                    label: Some("Device"),
+                    // lets the msaa sample count go beyond what webgpu guarantees
+                    features: adapter.features()
+                        & Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+                    limits: Default::default(),
+                },
+                None,
+            )
+            .await?;
+
+        let texture_headless = match &surface {
+            Some(surface) => {
+                surface.configure(&device, &surface_config);
+                None
+            }
+            None => Some(gen_texture_headless(&device, &surface_config)),
+        };
+
+        let shader_mesh = std::fs::read_to_string("assets/shader/mesh.wgsl").unwrap();
+        let shader_mesh = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader 1"),
-            source: wgpu::ShaderSource::Wgsl(mesh_shader.into()),
+            source: wgpu::ShaderSource::Wgsl(shader_mesh.into()),
         });
 
         let camera_bind_group_layout =
@@ -159,6 +233,17 @@ impl Core {
                         },
                         count: None,
                     },
+                    // taa matrices for the velocity
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
                 ],
             });
 
@@ -253,60 +338,15 @@ impl Core {
                 push_constant_ranges: &[],
             });
 
-        let render_pipline_mesh = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipline Mesh"),
-            layout: Some(&render_pipline_layout_mesh),
-            vertex: wgpu::VertexState {
-                module: &mesh_shader,
-                entry_point: "vs_main",
-                buffers: &[
-                    Vertex::vertex_buffer_layout(),
-                    TransformRawIT::vertex_buffer_layout(),
-                ],
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: texture::DEPTH_FORMAT,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState {
-                    constant: 2, // Corresponds to bilinear filtering
-                    slope_scale: 2.0,
-                    clamp: 0.0,
-                },
-            }),
-            multisample: wgpu::MultisampleState {
-                count: SAMPLE_COUNT,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &mesh_shader,
-                entry_point: "fs_main",
-                targets: &[
-                    Some(wgpu::ColorTargetState {
-                        format: surface_config.format,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    }),
-                    Some(wgpu::ColorTargetState {
-                        format: surface_config.format,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    }),
-                ],
-            }),
-            multiview: None,
-        });
+        let anti_aliasing =
+            AntiAliasing::Msaa(4).supported(&adapter, &device, surface_config.format);
+        let render_pipline_mesh = gen_render_pipline_mesh(
+            &device,
+            &render_pipline_layout_mesh,
+            &shader_mesh,
+            surface_config.format,
+            anti_aliasing,
+        );
 
         let bind_group_layout_post_processing =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -400,6 +440,8 @@ impl Core {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
+        let taa = Taa::new(&device, &surface_config);
+
         let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("View Bind Group"),
             layout: &camera_bind_group_layout,
@@ -418,6 +460,12 @@ impl Core {
                         camera_pos_buffer.as_entire_buffer_binding(),
                     ),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Buffer(
+                        taa.buffer_param.as_entire_buffer_binding(),
+                    ),
+                },
             ],
         });
 
@@ -497,11 +545,27 @@ impl Core {
         let texture_sampler = gen_texture_sampler(&device);
         let input = Input::new();
 
-        let texture_depth = gen_texture_depth(&device, &surface_config, SAMPLE_COUNT);
-
-        let texture_msaa = gen_texture_msaa(&device, &surface_config, SAMPLE_COUNT);
+        let backend = adapter.get_info().backend;
+        let texture_depth = gen_texture_depth(
+            &device,
+            &surface_config,
+            anti_aliasing.sample_count(),
+            backend,
+        );
+        let texture_msaa = (anti_aliasing.sample_count() > 1).then(|| {
+            gen_texture_msaa(
+                &device,
+                &surface_config,
+                anti_aliasing.sample_count(),
+                backend,
+            )
+        });
         let texture_post_processing = gen_texture_post_processing(&device, &surface_config);
 
+        let texture_anti_aliasing = gen_texture_post_processing(&device, &surface_config);
+        let fxaa = Fxaa::new(&device, surface_config.format, &texture_anti_aliasing);
+        let smaa = Smaa::new(&device, &queue, &surface_config, &texture_anti_aliasing);
+
         let bind_group_post_processing = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Bind Group Post Processing"),
             layout: &bind_group_layout_post_processing,
@@ -531,7 +595,7 @@ impl Core {
                 usage: wgpu::BufferUsages::INDEX,
             });
 
-        Self {
+        Ok(Self {
             window,
             instance,
             surface,
@@ -539,6 +603,9 @@ impl Core {
             device,
             queue,
             surface_config,
+            texture_headless,
+            shader_mesh,
+            render_pipline_layout_mesh,
             render_pipline_mesh,
             render_pipline_post_processing,
             camera,
@@ -568,8 +635,13 @@ impl Core {
             bind_group_post_processing,
             texture_post_processing,
 
+            anti_aliasing,
             texture_depth,
             texture_msaa,
+            texture_anti_aliasing,
+            fxaa,
+            smaa,
+            taa,
 
             light_direction_buffer,
             light_point_buffer,
@@ -577,20 +649,48 @@ impl Core {
 
             start_time: Instant::now(),
             last_time: Instant::now(),
-        }
+        })
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
         self.surface_config.width = width;
         self.surface_config.height = height;
-        self.surface.configure(&self.device, &self.surface_config);
+        match &self.surface {
+            Some(surface) => surface.configure(&self.device, &self.surface_config),
+            None => {
+                self.texture_headless =
+                    Some(gen_texture_headless(&self.device, &self.surface_config))
+            }
+        }
         self.camera.update_size(
             self.surface_config.width as _,
             self.surface_config.height as _,
         );
 
-        self.texture_depth = gen_texture_depth(&self.device, &self.surface_config, SAMPLE_COUNT);
-        self.texture_msaa = gen_texture_msaa(&self.device, &self.surface_config, SAMPLE_COUNT);
+        let backend = self.adapter.get_info().backend;
+        self.texture_depth = gen_texture_depth(
+            &self.device,
+            &self.surface_config,
+            self.anti_aliasing.sample_count(),
+            backend,
+        );
+        self.texture_msaa = (self.anti_aliasing.sample_count() > 1).then(|| {
+            gen_texture_msaa(
+                &self.device,
+                &self.surface_config,
+                self.anti_aliasing.sample_count(),
+                backend,
+            )
+        });
+        self.texture_anti_aliasing =
+            gen_texture_post_processing(&self.device, &self.surface_config);
+        self.fxaa.resize(&self.device, &self.texture_anti_aliasing);
+        self.smaa.resize(
+            &self.device,
+            &self.surface_config,
+            &self.texture_anti_aliasing,
+        );
+        self.taa.resize(&self.device, &self.surface_config);
         self.texture_post_processing =
             gen_texture_post_processing(&self.device, &self.surface_config);
         self.bind_group_post_processing =
@@ -611,49 +711,60 @@ impl Core {
     }
 
     fn render(&self) {
-        let current_texture = self
-            .surface
-            .get_current_texture()
-            .expect("get current texture fail");
-        let texture_view = current_texture
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let current_texture = self.surface.as_ref().map(|surface| {
+            surface
+                .get_current_texture()
+                .expect("get current texture fail")
+        });
+        let texture_view = match &current_texture {
+            Some(current_texture) => current_texture
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+            None => self
+                .texture_headless
+                .as_ref()
+                .unwrap()
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+        };
 
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
 
+        // taa renders into its own targets and resolves into texture_post_processing
+        let (view, resolve_target) = match (self.anti_aliasing, &self.texture_msaa) {
+            (AntiAliasing::Taa, _) => (&self.taa.texture_view_color, None),
+            (_, Some(texture_msaa)) => (texture_msaa, Some(&self.texture_post_processing)),
+            (_, None) => (&self.texture_post_processing, None),
+        };
+        let mut color_attachment_arr = vec![Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color {
+                    r: 0.1,
+                    g: 0.2,
+                    b: 0.3,
+                    a: 1.0,
+                }),
+                store: true,
+            },
+        })];
+        if self.anti_aliasing == AntiAliasing::Taa {
+            color_attachment_arr.push(Some(wgpu::RenderPassColorAttachment {
+                view: &self.taa.texture_view_velocity,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            }));
+        }
+
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
-                color_attachments: &[
-                    Some(wgpu::RenderPassColorAttachment {
-                        view: &self.texture_msaa,
-                        resolve_target: Some(&texture_view),
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color {
-                                r: 0.1,
-                                g: 0.2,
-                                b: 0.3,
-                                a: 1.0,
-                            }),
-                            store: true,
-                        },
-                    }),
-                    Some(wgpu::RenderPassColorAttachment {
-                        view: &self.texture_msaa,
-                        resolve_target: Some(&self.texture_post_processing),
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color {
-                                r: 0.1,
-                                g: 0.2,
-                                b: 0.3,
-                                a: 1.0,
-                            }),
-                            store: true,
-                        },
-                    }),
-                ],
+                color_attachments: &color_attachment_arr,
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
                     view: &self.texture_depth,
                     depth_ops: Some(wgpu::Operations {
@@ -676,10 +787,12 @@ impl Core {
                     if model.draw_method == DrawMethod::Vertex {
                         render_pass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
                         render_pass.set_vertex_buffer(1, model.transform_buffer.slice(..));
+                        render_pass.set_vertex_buffer(2, model.transform_buffer_prev.slice(..));
                         render_pass.draw(0..model.vertices_len, 0..model.instance_num);
                     } else {
                         render_pass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
                         render_pass.set_vertex_buffer(1, model.transform_buffer.slice(..));
+                        render_pass.set_vertex_buffer(2, model.transform_buffer_prev.slice(..));
                         render_pass
                             .set_index_buffer(model.index_buffer.slice(..), IndexFormat::Uint32);
                         render_pass.draw_indexed(0..model.indices_len, 0, 0..model.instance_num);
@@ -688,11 +801,19 @@ impl Core {
             }
         }
 
+        if self.anti_aliasing == AntiAliasing::Taa {
+            self.taa.render(&mut encoder, &self.texture_post_processing);
+        }
+
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass Post Processing"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &texture_view,
+                    view: if self.anti_aliasing.is_post_pass() {
+                        &self.texture_anti_aliasing
+                    } else {
+                        &texture_view
+                    },
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color {
@@ -717,9 +838,101 @@ impl Core {
             render_pass.draw_indexed(0..self.index_num_post_processing, 0, 0..1);
         }
 
+        match self.anti_aliasing {
+            AntiAliasing::Fxaa => self.fxaa.render(&mut encoder, &texture_view),
+            AntiAliasing::Smaa => self.smaa.render(&mut encoder, &texture_view),
+            AntiAliasing::None | AntiAliasing::Msaa(_) | AntiAliasing::Taa => {}
+        }
+
         self.queue.submit(std::iter::once(encoder.finish()));
 
-        current_texture.present();
+        if let Some(current_texture) = current_texture {
+            current_texture.present();
+        }
+    }
+
+    // only for a core created by new_headless
+    pub fn render_to_image(&mut self) -> RgbaImage {
+        self.render();
+        texture_to_image(
+            &self.device,
+            &self.queue,
+            self.texture_headless
+                .as_ref()
+                .expect("render to image needs headless mode"),
+            &self.surface_config,
+        )
+    }
+
+    // only the msaa attachments and the mesh pipeline depend on it,
+    // the post pass and taa targets are always there
+    pub fn set_anti_aliasing(&mut self, anti_aliasing: AntiAliasing) {
+        let anti_aliasing_requested = anti_aliasing;
+        let anti_aliasing =
+            anti_aliasing.supported(&self.adapter, &self.device, self.surface_config.format);
+        if anti_aliasing != anti_aliasing_requested {
+            warn!(
+                "{:?} is not supported, falling back to {:?}",
+                anti_aliasing_requested, anti_aliasing
+            );
+        }
+
+        let sample_count = anti_aliasing.sample_count();
+        let is_taa_changed =
+            (anti_aliasing == AntiAliasing::Taa) != (self.anti_aliasing == AntiAliasing::Taa);
+        if is_taa_changed {
+            self.taa.reset();
+        }
+        if sample_count != self.anti_aliasing.sample_count() || is_taa_changed {
+            self.render_pipline_mesh = gen_render_pipline_mesh(
+                &self.device,
+                &self.render_pipline_layout_mesh,
+                &self.shader_mesh,
+                self.surface_config.format,
+                anti_aliasing,
+            );
+            let backend = self.adapter.get_info().backend;
+            self.texture_depth =
+                gen_texture_depth(&self.device, &self.surface_config, sample_count, backend);
+            self.texture_msaa = (sample_count > 1).then(|| {
+                gen_texture_msaa(&self.device, &self.surface_config, sample_count, backend)
+            });
+        }
+        self.anti_aliasing = anti_aliasing;
+        debug!("anti aliasing {:?}", self.anti_aliasing);
+    }
+
+    // 1 none, 2 msaa, 3 fxaa, 4 smaa, 5 taa
+    fn on_key_released(&mut self, key: Option<VirtualKeyCode>) {
+        let anti_aliasing = match key {
+            Some(VirtualKeyCode::Key1) => AntiAliasing::None,
+            Some(VirtualKeyCode::Key2) => self.next_msaa(),
+            Some(VirtualKeyCode::Key3) => AntiAliasing::Fxaa,
+            Some(VirtualKeyCode::Key4) => AntiAliasing::Smaa,
+            Some(VirtualKeyCode::Key5) => AntiAliasing::Taa,
+            _ => return,
+        };
+        self.set_anti_aliasing(anti_aliasing);
+    }
+
+    // 4x when msaa is off, otherwise the next supported sample count, wrapping around
+    fn next_msaa(&self) -> AntiAliasing {
+        let AntiAliasing::Msaa(sample_count) = self.anti_aliasing else {
+            return AntiAliasing::Msaa(4);
+        };
+        let sample_count_arr =
+            supported_sample_count_arr(&self.adapter, &self.device, self.surface_config.format);
+        let sample_count = sample_count_arr
+            .iter()
+            .find(|&&supported| supported > sample_count)
+            .or_else(|| sample_count_arr.iter().find(|&&supported| supported > 1))
+            .copied()
+            .unwrap_or(1);
+        AntiAliasing::Msaa(sample_count)
+    }
+
+    pub fn window(&self) -> &Window {
+        self.window.as_ref().expect("no window in headless mode")
     }
 
     fn update(&mut self) {
@@ -727,6 +940,17 @@ impl Core {
         let delta_time = (Instant::now() - self.last_time).as_secs_f32();
         self.last_time = Instant::now();
 
+        self.update_delta_time(delta_time);
+    }
+
+    // a fixed delta time keeps headless frames reproducible
+    pub fn update_delta_time(&mut self, delta_time: f32) {
+        for material in &self.material_arr {
+            for model in &material.model_arr {
+                model.update_transform_prev(&self.queue);
+            }
+        }
+
         self.camera.moving(&self.input, delta_time);
 
         self.queue.write_buffer(
@@ -734,10 +958,18 @@ impl Core {
             0,
             bytemuck::cast_slice(&self.camera.view().to_cols_array_2d()),
         );
+        // a different sub-pixel offset every frame gives taa new samples to blend
+        let proj = if self.anti_aliasing == AntiAliasing::Taa {
+            self.taa.update(&self.queue, &self.camera);
+            self.camera
+                .proj_jitter(self.taa.jitter(&self.surface_config))
+        } else {
+            self.camera.proj()
+        };
         self.queue.write_buffer(
             &self.proj_buffer,
             0,
-            bytemuck::cast_slice(&self.camera.proj().to_cols_array_2d()),
+            bytemuck::cast_slice(&proj.to_cols_array_2d()),
         );
 
         self.queue.write_buffer(
@@ -769,12 +1001,12 @@ impl Core {
 
     pub fn block_loop(event_loop: EventLoop<()>, mut core: Core) {
         event_loop.run(move |event, _, control_flow| match event {
-            winit::event::Event::RedrawRequested(window_id) if window_id == core.window.id() => {
+            winit::event::Event::RedrawRequested(window_id) if window_id == core.window().id() => {
                 core.update();
                 core.render();
             }
             winit::event::Event::WindowEvent { window_id, event }
-                if window_id == core.window.id() =>
+                if window_id == core.window().id() =>
             {
                 match event {
                     winit::event::WindowEvent::Resized(new_size) => {
@@ -806,13 +1038,86 @@ impl Core {
                         input: keyboard_input,
                         ..
                     } => {
+                        if keyboard_input.state == winit::event::ElementState::Released {
+                            core.on_key_released(keyboard_input.virtual_keycode);
+                        }
                         core.input.on_input(keyboard_input);
                     }
                     _ => {}
                 }
             }
-            winit::event::Event::MainEventsCleared => core.window.request_redraw(),
+            winit::event::Event::MainEventsCleared => core.window().request_redraw(),
             _ => {}
         });
     }
 }
+
+fn gen_render_pipline_mesh(
+    device: &Device,
+    render_pipline_layout: &PipelineLayout,
+    shader: &ShaderModule,
+    format: TextureFormat,
+    anti_aliasing: AntiAliasing,
+) -> RenderPipeline {
+    // taa also writes the velocity
+    let (entry_point, target_num) = match anti_aliasing {
+        AntiAliasing::Taa => ("fs_taa", 2),
+        _ => ("fs_main", 1),
+    };
+    let target_arr = [
+        Some(wgpu::ColorTargetState {
+            format,
+            blend: Some(wgpu::BlendState::REPLACE),
+            write_mask: wgpu::ColorWrites::ALL,
+        }),
+        Some(wgpu::ColorTargetState {
+            format: VELOCITY_FORMAT,
+            blend: None,
+            write_mask: wgpu::ColorWrites::ALL,
+        }),
+    ];
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipline Mesh"),
+        layout: Some(render_pipline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[
+                Vertex::vertex_buffer_layout(),
+                TransformRawIT::vertex_buffer_layout(),
+                TransformRawPrev::vertex_buffer_layout(),
+            ],
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState {
+                constant: 2, // Corresponds to bilinear filtering
+                slope_scale: 2.0,
+                clamp: 0.0,
+            },
+        }),
+        multisample: wgpu::MultisampleState {
+            count: anti_aliasing.sample_count(),
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point,
+            targets: &target_arr[..target_num],
+        }),
+        multiview: None,
+    })
+}
@@ -1,6 +1,9 @@
+pub mod anti_aliasing;
 pub mod camera;
 pub mod color;
 pub mod core;
+pub mod fxaa;
+pub use common::headless;
 pub mod input;
 pub mod light_direction;
 pub mod light_point;
@@ -9,6 +12,8 @@ pub mod material;
 pub mod model;
 pub mod model_light;
 pub mod runner;
+pub mod smaa;
+pub mod taa;
 pub mod texture;
 pub mod transform;
 pub mod vertex;
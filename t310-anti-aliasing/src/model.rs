@@ -1,6 +1,9 @@
-use wgpu::{util::DeviceExt, Buffer, Device};
+use wgpu::{util::DeviceExt, Buffer, Device, Queue};
 
-use crate::{transform::Transform, vertex::Vertex};
+use crate::{
+    transform::{Transform, TransformRawPrev},
+    vertex::Vertex,
+};
 
 #[derive(Debug)]
 pub struct Model {
@@ -11,6 +14,10 @@ pub struct Model {
     pub vertex_buffer: Buffer,
     pub index_buffer: Buffer,
     pub transform_buffer: Buffer,
+    // model matrices of the frame before, for the velocity buffer
+    pub transform_buffer_prev: Buffer,
+    // the current transforms, copied into transform_buffer_prev at the next frame
+    pub transform_prev_arr: Vec<TransformRawPrev>,
     pub vertices_len: u32,
     pub indices_len: u32,
     pub instance_num: u32,
@@ -43,7 +50,18 @@ impl Model {
         let transform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Transform Buffer"),
             contents: bytemuck::cast_slice(&transform_mat_arr),
-            usage: wgpu::BufferUsages::VERTEX,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // nothing moved before the first frame
+        let transform_prev_arr = transform_arr
+            .iter()
+            .map(|t| t.to_raw_prev())
+            .collect::<Vec<_>>();
+        let transform_buffer_prev = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Transform Buffer Prev"),
+            contents: bytemuck::cast_slice(&transform_prev_arr),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         });
 
         Self {
@@ -51,11 +69,36 @@ impl Model {
             vertex_buffer,
             index_buffer,
             transform_buffer,
+            transform_buffer_prev,
+            transform_prev_arr,
             vertices_len: vertices.len() as u32,
             indices_len: indices.len() as u32,
             instance_num: transform_arr.len() as u32,
         }
     }
+
+    // the instance count stays the same
+    pub fn set_transform_arr(&mut self, queue: &Queue, transform_arr: &[Transform]) {
+        let transform_mat_arr = transform_arr
+            .iter()
+            .map(|t| t.to_raw_it())
+            .collect::<Vec<_>>();
+        queue.write_buffer(
+            &self.transform_buffer,
+            0,
+            bytemuck::cast_slice(&transform_mat_arr),
+        );
+        self.transform_prev_arr = transform_arr.iter().map(|t| t.to_raw_prev()).collect();
+    }
+
+    // call once per frame before the transforms of the frame are set
+    pub fn update_transform_prev(&self, queue: &Queue) {
+        queue.write_buffer(
+            &self.transform_buffer_prev,
+            0,
+            bytemuck::cast_slice(&self.transform_prev_arr),
+        );
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, PartialOrd, Ord)]
@@ -0,0 +1,489 @@
+use std::collections::HashMap;
+
+use wgpu::{
+    BindGroup, BindGroupLayout, CommandEncoder, Device, Queue, RenderPipeline, Sampler,
+    ShaderStages, SurfaceConfiguration, TextureFormat, TextureView,
+};
+
+use crate::texture::{gen_texture_view_lookup, gen_texture_view_target};
+
+// texels along each side of one pattern in the area texture
+const AREA_MAX_DISTANCE: u32 = 16;
+// 5 x 5 slots, edge values round(4 * e) are 0, 1, 3 or 4
+const AREA_SIZE: u32 = AREA_MAX_DISTANCE * 5;
+// longer lines get the plain trapezoid areas of patterns 3 and 12
+const AREA_SMOOTH_MAX_DISTANCE: f32 = 32.0;
+// slot of each orthogonal pattern, bit 0 crossing edge down at the left end,
+// bit 1 down at the right end, bit 2 up at the left end, bit 3 up at the right end
+const AREA_PATTERN_SLOT_ARR: [(u32, u32); 16] = [
+    (0, 0),
+    (3, 0),
+    (0, 3),
+    (3, 3),
+    (1, 0),
+    (4, 0),
+    (1, 3),
+    (4, 3),
+    (0, 1),
+    (3, 1),
+    (0, 4),
+    (3, 4),
+    (1, 1),
+    (4, 1),
+    (1, 4),
+    (4, 4),
+];
+
+// bilinear edge values are multiples of 1 / 32, left half then right half
+const SEARCH_HALF_SIZE: u32 = 33;
+
+const EDGE_FORMAT: TextureFormat = TextureFormat::Rg8Unorm;
+const BLEND_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+// smaa 1x over the finished image, edges, blending weights, then the blend into the target
+pub struct Smaa {
+    pub sampler: Sampler,
+
+    pub render_pipline_edge: RenderPipeline,
+    pub render_pipline_weight: RenderPipeline,
+    pub render_pipline_blend: RenderPipeline,
+    pub bind_group_layout_edge: BindGroupLayout,
+    pub bind_group_layout_weight: BindGroupLayout,
+    pub bind_group_layout_blend: BindGroupLayout,
+
+    // precomputed once, independent of the window size
+    pub texture_view_area: TextureView,
+    pub texture_view_search: TextureView,
+
+    pub texture_view_edge: TextureView,
+    pub texture_view_blend: TextureView,
+    pub bind_group_edge: BindGroup,
+    pub bind_group_weight: BindGroup,
+    pub bind_group_blend: BindGroup,
+}
+
+impl Smaa {
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        surface_config: &SurfaceConfiguration,
+        texture_view_in: &TextureView,
+    ) -> Self {
+        // the searches and the area lookup land between texels
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Sampler Smaa"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        // bindings as declared in smaa.wgsl
+        let bind_group_layout_edge =
+            gen_bind_group_layout_smaa(device, "Bind Group Layout Smaa Edge", &[1]);
+        let bind_group_layout_weight =
+            gen_bind_group_layout_smaa(device, "Bind Group Layout Smaa Weight", &[2, 4, 5]);
+        let bind_group_layout_blend =
+            gen_bind_group_layout_smaa(device, "Bind Group Layout Smaa Blend", &[1, 3]);
+
+        let shader = std::fs::read_to_string("assets/shader/smaa.wgsl").unwrap();
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader Smaa"),
+            source: wgpu::ShaderSource::Wgsl(shader.into()),
+        });
+        let gen_render_pipline = |label, bind_group_layout, entry_point, format| {
+            let render_pipline_layout =
+                device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some(label),
+                    bind_group_layouts: &[bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&render_pipline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            })
+        };
+        let render_pipline_edge = gen_render_pipline(
+            "Render Pipline Smaa Edge",
+            &bind_group_layout_edge,
+            "fs_edge",
+            EDGE_FORMAT,
+        );
+        let render_pipline_weight = gen_render_pipline(
+            "Render Pipline Smaa Weight",
+            &bind_group_layout_weight,
+            "fs_weight",
+            BLEND_FORMAT,
+        );
+        let render_pipline_blend = gen_render_pipline(
+            "Render Pipline Smaa Blend",
+            &bind_group_layout_blend,
+            "fs_blend",
+            surface_config.format,
+        );
+
+        let texture_view_area = gen_texture_view_lookup(
+            &gen_area_data(),
+            AREA_SIZE,
+            AREA_SIZE,
+            TextureFormat::Rg8Unorm,
+            device,
+            queue,
+        );
+        let texture_view_search = gen_texture_view_lookup(
+            &gen_search_data(),
+            SEARCH_HALF_SIZE * 2,
+            SEARCH_HALF_SIZE,
+            TextureFormat::R8Unorm,
+            device,
+            queue,
+        );
+
+        let texture_view_edge = gen_texture_view_target(device, surface_config, EDGE_FORMAT);
+        let texture_view_blend = gen_texture_view_target(device, surface_config, BLEND_FORMAT);
+        let bind_group_edge = gen_bind_group_smaa(
+            device,
+            &bind_group_layout_edge,
+            &sampler,
+            &[(1, texture_view_in)],
+        );
+        let bind_group_weight = gen_bind_group_smaa(
+            device,
+            &bind_group_layout_weight,
+            &sampler,
+            &[
+                (2, &texture_view_edge),
+                (4, &texture_view_area),
+                (5, &texture_view_search),
+            ],
+        );
+        let bind_group_blend = gen_bind_group_smaa(
+            device,
+            &bind_group_layout_blend,
+            &sampler,
+            &[(1, texture_view_in), (3, &texture_view_blend)],
+        );
+
+        Self {
+            sampler,
+            render_pipline_edge,
+            render_pipline_weight,
+            render_pipline_blend,
+            bind_group_layout_edge,
+            bind_group_layout_weight,
+            bind_group_layout_blend,
+            texture_view_area,
+            texture_view_search,
+            texture_view_edge,
+            texture_view_blend,
+            bind_group_edge,
+            bind_group_weight,
+            bind_group_blend,
+        }
+    }
+
+    pub fn resize(
+        &mut self,
+        device: &Device,
+        surface_config: &SurfaceConfiguration,
+        texture_view_in: &TextureView,
+    ) {
+        self.texture_view_edge = gen_texture_view_target(device, surface_config, EDGE_FORMAT);
+        self.texture_view_blend = gen_texture_view_target(device, surface_config, BLEND_FORMAT);
+        self.bind_group_edge = gen_bind_group_smaa(
+            device,
+            &self.bind_group_layout_edge,
+            &self.sampler,
+            &[(1, texture_view_in)],
+        );
+        self.bind_group_weight = gen_bind_group_smaa(
+            device,
+            &self.bind_group_layout_weight,
+            &self.sampler,
+            &[
+                (2, &self.texture_view_edge),
+                (4, &self.texture_view_area),
+                (5, &self.texture_view_search),
+            ],
+        );
+        self.bind_group_blend = gen_bind_group_smaa(
+            device,
+            &self.bind_group_layout_blend,
+            &self.sampler,
+            &[(1, texture_view_in), (3, &self.texture_view_blend)],
+        );
+    }
+
+    pub fn render(&self, encoder: &mut CommandEncoder, texture_view_out: &TextureView) {
+        for (label, render_pipline, bind_group, texture_view) in [
+            (
+                "Render Pass Smaa Edge",
+                &self.render_pipline_edge,
+                &self.bind_group_edge,
+                &self.texture_view_edge,
+            ),
+            (
+                "Render Pass Smaa Weight",
+                &self.render_pipline_weight,
+                &self.bind_group_weight,
+                &self.texture_view_blend,
+            ),
+            (
+                "Render Pass Smaa Blend",
+                &self.render_pipline_blend,
+                &self.bind_group_blend,
+                texture_view_out,
+            ),
+        ] {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(label),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: texture_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(render_pipline);
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+}
+
+// binding 0 is the sampler, the others are textures
+fn gen_bind_group_layout_smaa(
+    device: &Device,
+    label: &str,
+    texture_binding_arr: &[u32],
+) -> BindGroupLayout {
+    let mut entries = vec![wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    }];
+    for &binding in texture_binding_arr {
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        });
+    }
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some(label),
+        entries: &entries,
+    })
+}
+
+fn gen_bind_group_smaa(
+    device: &Device,
+    layout: &BindGroupLayout,
+    sampler: &Sampler,
+    texture_view_arr: &[(u32, &TextureView)],
+) -> BindGroup {
+    let mut entries = vec![wgpu::BindGroupEntry {
+        binding: 0,
+        resource: wgpu::BindingResource::Sampler(sampler),
+    }];
+    for &(binding, texture_view) in texture_view_arr {
+        entries.push(wgpu::BindGroupEntry {
+            binding,
+            resource: wgpu::BindingResource::TextureView(texture_view),
+        });
+    }
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Bind Group Smaa"),
+        layout,
+        entries: &entries,
+    })
+}
+
+// rg coverage for the orthogonal patterns at subsample offset 0, the texel at
+// (left, right) inside a slot holds the pattern for the distances left^2 and right^2
+fn gen_area_data() -> Vec<u8> {
+    let mut data = vec![0; (AREA_SIZE * AREA_SIZE * 2) as usize];
+    for (pattern, &(e1, e2)) in AREA_PATTERN_SLOT_ARR.iter().enumerate() {
+        for left in 0..AREA_MAX_DISTANCE {
+            for right in 0..AREA_MAX_DISTANCE {
+                let (a1, a2) = area_ortho(pattern, (left * left) as f32, (right * right) as f32);
+                let x = e1 * AREA_MAX_DISTANCE + left;
+                let y = e2 * AREA_MAX_DISTANCE + right;
+                let index = ((y * AREA_SIZE + x) * 2) as usize;
+                data[index] = (a1 * 255.0).round() as u8;
+                data[index + 1] = (a2 * 255.0).round() as u8;
+            }
+        }
+    }
+    data
+}
+
+// area under the line p1 to p2 over the pixel x to x + 1, below the edge then above it
+fn area(p1: (f32, f32), p2: (f32, f32), x: f32) -> (f32, f32) {
+    let d = (p2.0 - p1.0, p2.1 - p1.1);
+    let x1 = x;
+    let x2 = x + 1.0;
+    let y1 = p1.1 + d.1 * (x1 - p1.0) / d.0;
+    let y2 = p1.1 + d.1 * (x2 - p1.0) / d.0;
+
+    let is_inside = (x1 >= p1.0 && x1 < p2.0) || (x2 > p1.0 && x2 <= p2.0);
+    if !is_inside {
+        return (0.0, 0.0);
+    }
+
+    let is_trapezoid = y1.signum() == y2.signum() || y1.abs() < 1e-4 || y2.abs() < 1e-4;
+    if is_trapezoid {
+        let a = (y1 + y2) / 2.0;
+        return if a < 0.0 { (a.abs(), 0.0) } else { (0.0, a) };
+    }
+
+    // the line crosses the edge inside the pixel, a triangle on each side
+    let x = -p1.1 * d.0 / d.1 + p1.0;
+    let a1 = if x > p1.0 { y1 * x.fract() / 2.0 } else { 0.0 };
+    let a2 = if x < p2.0 {
+        y2 * (1.0 - x.fract()) / 2.0
+    } else {
+        0.0
+    };
+    let a = if a1.abs() > a2.abs() { a1 } else { -a2 };
+    if a < 0.0 {
+        (a1.abs(), a2.abs())
+    } else {
+        (a2.abs(), a1.abs())
+    }
+}
+
+// short u and z shapes are rounded, fading into the plain area as they get longer
+fn area_smooth(d: f32, a1: (f32, f32), a2: (f32, f32)) -> (f32, f32) {
+    let b1 = ((a1.0 * 2.0).sqrt() * 0.5, (a1.1 * 2.0).sqrt() * 0.5);
+    let b2 = ((a2.0 * 2.0).sqrt() * 0.5, (a2.1 * 2.0).sqrt() * 0.5);
+    let p = (d / AREA_SMOOTH_MAX_DISTANCE).clamp(0.0, 1.0);
+    let lerp = |a: f32, b: f32| a + (b - a) * p;
+    (
+        lerp(b1.0, a1.0) + lerp(b2.0, a2.0),
+        lerp(b1.1, a1.1) + lerp(b2.1, a2.1),
+    )
+}
+
+// the line runs from the pixel edge at one end of the pattern to the other,
+// left and right are the distances to the ends
+fn area_ortho(pattern: usize, left: f32, right: f32) -> (f32, f32) {
+    let d = left + right + 1.0;
+    // heights where the line leaves a crossing edge above or below
+    let o1 = 0.5;
+    let o2 = -0.5;
+
+    match pattern {
+        // crossing down at the left
+        1 if left <= right => area((0.0, o2), (d / 2.0, 0.0), left),
+        // crossing down at the right
+        2 if left >= right => area((d / 2.0, 0.0), (d, o2), left),
+        // u shape below
+        3 => area_smooth(
+            d,
+            area((0.0, o2), (d / 2.0, 0.0), left),
+            area((d / 2.0, 0.0), (d, o2), left),
+        ),
+        // crossing up at the left
+        4 if left <= right => area((0.0, o1), (d / 2.0, 0.0), left),
+        // z shapes, up at the left and down at the right
+        6 | 7 | 14 => area((0.0, o1), (d, o2), left),
+        // crossing up at the right
+        8 if left >= right => area((d / 2.0, 0.0), (d, o1), left),
+        // z shapes, down at the left and up at the right
+        9 | 11 | 13 => area((0.0, o2), (d, o1), left),
+        // u shape above
+        12 => area_smooth(
+            d,
+            area((0.0, o1), (d / 2.0, 0.0), left),
+            area((d / 2.0, 0.0), (d, o1), left),
+        ),
+        // no crossing edge, or crossings on both sides of one end
+        _ => (0.0, 0.0),
+    }
+}
+
+// pixels to step back for each left and top edge combination the search stopped at,
+// stored as 127 per pixel, left search in the first half and right search in the second
+fn gen_search_data() -> Vec<u8> {
+    // the four edges around the fetch, top left, top right, bottom left, bottom right
+    let bilinear = |e: [u32; 4]| {
+        let a = e[0] as f32 * 0.25 + e[1] as f32 * 0.75;
+        let b = e[2] as f32 * 0.25 + e[3] as f32 * 0.75;
+        a * 0.125 + b * 0.875
+    };
+    let edge_map = (0..16)
+        .map(|i| {
+            let e = [i & 1, (i >> 1) & 1, (i >> 2) & 1, (i >> 3) & 1];
+            ((bilinear(e) * 32.0).round() as u32, e)
+        })
+        .collect::<HashMap<_, _>>();
+
+    let width = SEARCH_HALF_SIZE * 2;
+    let mut data = vec![0; (width * SEARCH_HALF_SIZE) as usize];
+    for y in 0..SEARCH_HALF_SIZE {
+        for x in 0..SEARCH_HALF_SIZE {
+            if let (Some(left), Some(top)) = (edge_map.get(&x), edge_map.get(&y)) {
+                let index = (y * width + x) as usize;
+                data[index] = 127 * search_delta_left(left, top);
+                data[index + SEARCH_HALF_SIZE as usize] = 127 * search_delta_right(left, top);
+            }
+        }
+    }
+    data
+}
+
+fn search_delta_left(left: &[u32; 4], top: &[u32; 4]) -> u8 {
+    let mut d = 0;
+    // the edge goes on
+    if top[3] == 1 {
+        d += 1;
+    }
+    // and on past the next pixel with no crossing edge in between
+    if d == 1 && top[2] == 1 && left[1] != 1 && left[3] != 1 {
+        d += 1;
+    }
+    d
+}
+
+fn search_delta_right(left: &[u32; 4], top: &[u32; 4]) -> u8 {
+    let mut d = 0;
+    // the edge goes on with no crossing edge
+    if top[3] == 1 && left[1] != 1 && left[3] != 1 {
+        d += 1;
+    }
+    // and on past the next pixel with no crossing edge in between
+    if d == 1 && top[2] == 1 && left[0] != 1 && left[2] != 1 {
+        d += 1;
+    }
+    d
+}
@@ -0,0 +1,325 @@
+use glam::{Mat4, Vec2};
+use wgpu::{
+    util::DeviceExt, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device, Queue,
+    RenderPipeline, Sampler, ShaderStages, SurfaceConfiguration, TextureFormat, TextureView,
+};
+
+use crate::{camera::Camera, texture::gen_texture_view_target};
+
+// jitter positions before the sequence repeats
+const JITTER_NUM: u32 = 8;
+// share of the reprojected history in the resolved color
+const HISTORY_WEIGHT: f32 = 0.9;
+// screen space motion of each pixel since the last frame
+pub const VELOCITY_FORMAT: TextureFormat = TextureFormat::Rg16Float;
+// keeps the precision over many blended frames
+const HISTORY_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TaaParam {
+    // both without jitter, so the velocity is only the motion
+    view_proj: [[f32; 4]; 4],
+    view_proj_prev: [[f32; 4]; 4],
+    // 0 when the history is not usable
+    history_weight: f32,
+    // 16 bytes padding
+    _padding0: [f32; 3],
+}
+
+// temporal anti aliasing, the mesh pass renders with a sub-pixel jitter that changes every
+// frame and the resolve pass blends it into the history of the frames before
+pub struct Taa {
+    pub frame_index: u32,
+    pub view_proj_prev: Mat4,
+    pub is_history_valid: bool,
+    // also bound to the mesh pass for the velocity
+    pub buffer_param: Buffer,
+
+    pub sampler: Sampler,
+    pub render_pipline: RenderPipeline,
+    pub bind_group_layout: BindGroupLayout,
+
+    // targets of the mesh pass
+    pub texture_view_color: TextureView,
+    pub texture_view_velocity: TextureView,
+    // one is read while the other is written, swapped every frame
+    pub texture_view_history_arr: [TextureView; 2],
+    // bind_group_arr[i] reads the history that frame i does not write
+    pub bind_group_arr: [BindGroup; 2],
+}
+
+impl Taa {
+    pub fn new(device: &Device, surface_config: &SurfaceConfiguration) -> Self {
+        let param = TaaParam {
+            view_proj: Mat4::IDENTITY.to_cols_array_2d(),
+            view_proj_prev: Mat4::IDENTITY.to_cols_array_2d(),
+            history_weight: 0.0,
+            _padding0: [0.0; 3],
+        };
+        let buffer_param = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Buffer Taa Param"),
+            contents: bytemuck::bytes_of(&param),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // the history is read between texels after reprojection
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Sampler Taa"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Bind Group Layout Taa"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                texture_entry(1),
+                texture_entry(2),
+                texture_entry(3),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let render_pipline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipline Layout Taa"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let shader = std::fs::read_to_string("assets/shader/taa.wgsl").unwrap();
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader Taa"),
+            source: wgpu::ShaderSource::Wgsl(shader.into()),
+        });
+        let render_pipline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipline Taa"),
+            layout: Some(&render_pipline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[
+                    Some(wgpu::ColorTargetState {
+                        format: HISTORY_FORMAT,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                    Some(wgpu::ColorTargetState {
+                        format: surface_config.format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                ],
+            }),
+            multiview: None,
+        });
+
+        let texture_view_color =
+            gen_texture_view_target(device, surface_config, surface_config.format);
+        let texture_view_velocity =
+            gen_texture_view_target(device, surface_config, VELOCITY_FORMAT);
+        let texture_view_history_arr = [
+            gen_texture_view_target(device, surface_config, HISTORY_FORMAT),
+            gen_texture_view_target(device, surface_config, HISTORY_FORMAT),
+        ];
+        let bind_group_arr = gen_bind_group_arr_taa(
+            device,
+            &bind_group_layout,
+            &sampler,
+            &texture_view_color,
+            &texture_view_velocity,
+            &texture_view_history_arr,
+            &buffer_param,
+        );
+
+        Self {
+            frame_index: 0,
+            view_proj_prev: Mat4::IDENTITY,
+            is_history_valid: false,
+            buffer_param,
+            sampler,
+            render_pipline,
+            bind_group_layout,
+            texture_view_color,
+            texture_view_velocity,
+            texture_view_history_arr,
+            bind_group_arr,
+        }
+    }
+
+    pub fn resize(&mut self, device: &Device, surface_config: &SurfaceConfiguration) {
+        self.texture_view_color =
+            gen_texture_view_target(device, surface_config, surface_config.format);
+        self.texture_view_velocity =
+            gen_texture_view_target(device, surface_config, VELOCITY_FORMAT);
+        self.texture_view_history_arr = [
+            gen_texture_view_target(device, surface_config, HISTORY_FORMAT),
+            gen_texture_view_target(device, surface_config, HISTORY_FORMAT),
+        ];
+        self.bind_group_arr = gen_bind_group_arr_taa(
+            device,
+            &self.bind_group_layout,
+            &self.sampler,
+            &self.texture_view_color,
+            &self.texture_view_velocity,
+            &self.texture_view_history_arr,
+            &self.buffer_param,
+        );
+        self.reset();
+    }
+
+    // the next frame starts a new history, after a resize or when taa is switched on
+    pub fn reset(&mut self) {
+        self.is_history_valid = false;
+    }
+
+    // halton 2, 3 point of the current frame, in ndc so it is below one pixel
+    pub fn jitter(&self, surface_config: &SurfaceConfiguration) -> Vec2 {
+        let index = self.frame_index % JITTER_NUM + 1;
+        let point = Vec2::new(halton(index, 2), halton(index, 3)) - 0.5;
+        point * 2.0 / Vec2::new(surface_config.width as _, surface_config.height as _)
+    }
+
+    // once per frame, before the jittered projection is taken
+    pub fn update(&mut self, queue: &Queue, camera: &Camera) {
+        self.frame_index = self.frame_index.wrapping_add(1);
+
+        let view_proj = camera.proj() * camera.view();
+        if !self.is_history_valid {
+            self.view_proj_prev = view_proj;
+        }
+        let param = TaaParam {
+            view_proj: view_proj.to_cols_array_2d(),
+            view_proj_prev: self.view_proj_prev.to_cols_array_2d(),
+            history_weight: if self.is_history_valid {
+                HISTORY_WEIGHT
+            } else {
+                0.0
+            },
+            _padding0: [0.0; 3],
+        };
+        queue.write_buffer(&self.buffer_param, 0, bytemuck::bytes_of(&param));
+
+        self.view_proj_prev = view_proj;
+        self.is_history_valid = true;
+    }
+
+    pub fn render(&self, encoder: &mut CommandEncoder, texture_view_out: &TextureView) {
+        let index = (self.frame_index % 2) as usize;
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass Taa"),
+            color_attachments: &[
+                Some(wgpu::RenderPassColorAttachment {
+                    view: &self.texture_view_history_arr[index],
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                }),
+                Some(wgpu::RenderPassColorAttachment {
+                    view: texture_view_out,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                }),
+            ],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.render_pipline);
+        render_pass.set_bind_group(0, &self.bind_group_arr[index], &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+// radical inverse of index in base, well spread points in 0..1
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+fn gen_bind_group_arr_taa(
+    device: &Device,
+    layout: &BindGroupLayout,
+    sampler: &Sampler,
+    texture_view_color: &TextureView,
+    texture_view_velocity: &TextureView,
+    texture_view_history_arr: &[TextureView; 2],
+    buffer_param: &Buffer,
+) -> [BindGroup; 2] {
+    [0, 1].map(|i| {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Bind Group Taa"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(texture_view_color),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(texture_view_velocity),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&texture_view_history_arr[1 - i]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Buffer(
+                        buffer_param.as_entire_buffer_binding(),
+                    ),
+                },
+            ],
+        })
+    })
+}
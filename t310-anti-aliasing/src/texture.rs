@@ -64,6 +64,7 @@ pub fn gen_texture_depth(
     device: &wgpu::Device,
     surface_config: &wgpu::SurfaceConfiguration,
     sample_count: u32,
+    backend: wgpu::Backend,
 ) -> wgpu::TextureView {
     device
         .create_texture(&wgpu::TextureDescriptor {
@@ -77,7 +78,7 @@ pub fn gen_texture_depth(
             sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: DEPTH_FORMAT,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage: gen_usage_attachment(sample_count, backend),
             view_formats: &[],
         })
         .create_view(&wgpu::TextureViewDescriptor::default())
@@ -87,6 +88,7 @@ pub fn gen_texture_msaa(
     device: &wgpu::Device,
     surface_config: &wgpu::SurfaceConfiguration,
     sample_count: u32,
+    backend: wgpu::Backend,
 ) -> wgpu::TextureView {
     device
         .create_texture(&wgpu::TextureDescriptor {
@@ -100,7 +102,7 @@ pub fn gen_texture_msaa(
             sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: surface_config.format,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage: gen_usage_attachment(sample_count, backend),
             view_formats: &[],
         })
         .create_view(&wgpu::TextureViewDescriptor::default())
@@ -127,3 +129,128 @@ pub fn gen_texture_post_processing(
         })
         .create_view(&wgpu::TextureViewDescriptor::default())
 }
+
+// render target of the post passes, sized like the surface
+pub fn gen_texture_view_target(
+    device: &wgpu::Device,
+    surface_config: &wgpu::SurfaceConfiguration,
+    format: wgpu::TextureFormat,
+) -> wgpu::TextureView {
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some("Texture Target"),
+            size: wgpu::Extent3d {
+                width: surface_config.width,
+                height: surface_config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+// table computed on the cpu, data is height rows of tightly packed texels
+pub fn gen_texture_view_lookup(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> wgpu::TextureView {
+    let texture_size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Texture Lookup"),
+        size: texture_size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+
+    queue.write_texture(
+        wgpu::ImageCopyTextureBase {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        data,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(data.len() as u32 / height),
+            rows_per_image: Some(height),
+        },
+        texture_size,
+    );
+
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+// msaa sample counts the color format and DEPTH_FORMAT can both be rendered and resolved with,
+// ascending and always starting with 1
+pub fn supported_sample_count_arr(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+) -> Vec<u32> {
+    // the device only trusts the adapter flags with this feature or on a downlevel backend,
+    // otherwise it validates against the counts webgpu guarantees
+    let is_adapter_flags = device
+        .features()
+        .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+        || !adapter.get_downlevel_capabilities().is_webgpu_compliant();
+    let flags = |format: wgpu::TextureFormat| {
+        if is_adapter_flags {
+            adapter.get_texture_format_features(format).flags
+        } else {
+            format.guaranteed_format_features(device.features()).flags
+        }
+    };
+    let (flags_color, flags_depth) = (flags(format), flags(DEPTH_FORMAT));
+
+    [1, 2, 4, 8, 16]
+        .into_iter()
+        .filter(|&sample_count| {
+            sample_count == 1
+                || (flags_color.sample_count_supported(sample_count)
+                    && flags_color.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE)
+                    && flags_depth.sample_count_supported(sample_count))
+        })
+        .collect()
+}
+
+// sample_count when supported, otherwise the highest supported count below it
+pub fn supported_sample_count(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> u32 {
+    supported_sample_count_arr(adapter, device, format)
+        .into_iter()
+        .rev()
+        .find(|&supported| supported <= sample_count)
+        .unwrap_or(1)
+}
+
+// wgpu's GL backend makes a render only multisampled texture a renderbuffer, a bindable one gets
+// glTexStorage2DMultisample on GL_TEXTURE_2D, GL rejects that target and the attachment stays empty
+fn gen_usage_attachment(sample_count: u32, backend: wgpu::Backend) -> wgpu::TextureUsages {
+    if sample_count > 1 && backend == wgpu::Backend::Gl {
+        wgpu::TextureUsages::RENDER_ATTACHMENT
+    } else {
+        wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT
+    }
+}
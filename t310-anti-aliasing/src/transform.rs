@@ -36,6 +36,15 @@ impl Transform {
 
         TransformRawIT { model: combine }
     }
+    pub fn to_raw_prev(&self) -> TransformRawPrev {
+        let mat =
+            Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation);
+        let mat = mat.transpose();
+
+        TransformRawPrev {
+            model_row: [mat.x_axis.into(), mat.y_axis.into(), mat.z_axis.into()],
+        }
+    }
 }
 
 #[repr(C)]
@@ -72,3 +81,23 @@ impl TransformRawIT {
         }
     }
 }
+
+// first three rows of the model matrix of the previous frame, the last row of an affine
+// transform is always 0 0 0 1 so it fits the free locations after TransformRawIT
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TransformRawPrev {
+    model_row: [[f32; 4]; 3],
+}
+
+impl TransformRawPrev {
+    const ATTRS: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![13 => Float32x4, 14 => Float32x4, 15 => Float32x4];
+    pub fn vertex_buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: 4 * 4 * 3,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRS,
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use glam::Vec3;
+use image::RgbaImage;
+use t207_gltf::{anti_aliasing::AntiAliasing, core::Core, runner::load_scene};
+use wgpu::TextureFormat;
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+const FRAME_TIME: f32 = 1.0 / 60.0;
+// taa needs a few jittered frames before the history settles
+const TAA_FRAME_NUM: u32 = 8;
+
+#[test]
+fn golden_none() {
+    common::assert_golden!("none", &render(AntiAliasing::None, 1));
+}
+
+#[test]
+fn golden_msaa() {
+    common::assert_golden!("msaa", &render(AntiAliasing::Msaa(4), 1));
+}
+
+#[test]
+fn golden_fxaa() {
+    common::assert_golden!("fxaa", &render(AntiAliasing::Fxaa, 1));
+}
+
+#[test]
+fn golden_smaa() {
+    common::assert_golden!("smaa", &render(AntiAliasing::Smaa, 1));
+}
+
+#[test]
+fn golden_taa() {
+    common::assert_golden!("taa", &render(AntiAliasing::Taa, TAA_FRAME_NUM));
+}
+
+// the last of frame_num frames of the scene
+fn render(anti_aliasing: AntiAliasing, frame_num: u32) -> RgbaImage {
+    let mut core = pollster::block_on(Core::new_headless(
+        WIDTH,
+        HEIGHT,
+        TextureFormat::Rgba8UnormSrgb,
+    ))
+    .expect("golden tests need an adapter");
+
+    load_scene(&mut core);
+    core.set_anti_aliasing(anti_aliasing);
+    // a fallback would compare another mode against the reference
+    assert_eq!(core.anti_aliasing, anti_aliasing);
+
+    let camera = &mut core.camera;
+    camera.pos = Vec3::new(2.0, 2.0, 4.0);
+    camera.front = (Vec3::new(0.0, 0.0, 0.0) - camera.pos).normalize();
+
+    let mut img = RgbaImage::new(WIDTH, HEIGHT);
+    for _ in 0..frame_num {
+        core.update_delta_time(FRAME_TIME);
+        img = core.render_to_image();
+    }
+    img
+}
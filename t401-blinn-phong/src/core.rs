@@ -1,11 +1,13 @@
 use std::time::Instant;
 
+use log::{debug, warn};
 use wgpu::{
-    util::DeviceExt, Adapter, Backends, BindGroup, BindGroupLayout, Buffer, Device,
-    DeviceDescriptor, Features, IndexFormat, Instance, PresentMode, Queue, RenderPipeline, Sampler,
-    Surface, SurfaceConfiguration, TextureUsages, TextureView,
+    util::DeviceExt, Adapter, Backend, Backends, BindGroup, BindGroupLayout, Buffer, Device,
+    DeviceDescriptor, Features, IndexFormat, Instance, PipelineLayout, PresentMode, Queue,
+    RenderPipeline, Sampler, ShaderModule, Surface, SurfaceConfiguration, TextureFormat,
+    TextureUsages, TextureView,
 };
-use winit::{dpi::PhysicalPosition, event_loop::EventLoop, window::Window};
+use winit::{dpi::PhysicalPosition, event::VirtualKeyCode, event_loop::EventLoop, window::Window};
 
 use crate::{
     camera::Camera,
@@ -18,13 +20,12 @@ use crate::{
     model_light::ModelLight,
     texture::{
         self, gen_texture_depth, gen_texture_msaa, gen_texture_sampler, gen_texture_sampler_repeat,
+        supported_sample_count, supported_sample_count_arr,
     },
     transform::TransformRawIT,
     vertex::Vertex,
 };
 
-const SAMPLE_COUNT: u32 = 4;
-
 pub struct Core {
     pub window: Window,
     pub instance: Instance,
@@ -33,7 +34,11 @@ pub struct Core {
     pub device: Device,
     pub queue: Queue,
     pub surface_config: SurfaceConfiguration,
+    pub shader_mesh: ShaderModule,
+    pub render_pipline_layout_mesh: PipelineLayout,
     pub render_pipline_mesh: RenderPipeline,
+    // msaa, see set_sample_count
+    pub sample_count: u32,
     pub camera: Camera,
     pub input: Input,
 
@@ -55,7 +60,8 @@ pub struct Core {
     pub camera_pos_buffer: Buffer,
 
     pub texture_depth: TextureView,
-    pub texture_msaa: TextureView,
+    // none without msaa
+    pub texture_msaa: Option<TextureView>,
 
     pub light_direction_buffer: Buffer,
     pub light_point_buffer: Buffer,
@@ -91,7 +97,9 @@ impl Core {
             .request_device(
                 &DeviceDescriptor {
                     label: Some("Device"),
-                    features: Features::empty(),
+                    // lets the msaa sample count go beyond what webgpu guarantees
+                    features: adapter.features()
+                        & Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
                     limits: Default::default(),
                 },
                 None,
@@ -245,53 +253,15 @@ impl Core {
                 push_constant_ranges: &[],
             });
 
-        let render_pipline_mesh = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipline Mesh"),
-            layout: Some(&render_pipline_layout_mesh),
-            vertex: wgpu::VertexState {
-                module: &mesh_shader,
-                entry_point: "vs_main",
-                buffers: &[
-                    Vertex::vertex_buffer_layout(),
-                    TransformRawIT::vertex_buffer_layout(),
-                ],
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: texture::DEPTH_FORMAT,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState {
-                    constant: 2, // Corresponds to bilinear filtering
-                    slope_scale: 2.0,
-                    clamp: 0.0,
-                },
-            }),
-            multisample: wgpu::MultisampleState {
-                count: SAMPLE_COUNT,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &mesh_shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
-        });
+        // 4x to start with, lowered when the adapter can't do it
+        let sample_count = supported_sample_count(&adapter, &device, surface_config.format, 4);
+        let render_pipline_mesh = gen_render_pipline_mesh(
+            &device,
+            &render_pipline_layout_mesh,
+            &mesh_shader,
+            surface_config.format,
+            sample_count,
+        );
 
         let camera = Camera::new(surface_config.width as _, surface_config.height as _);
         let view_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -410,8 +380,12 @@ impl Core {
         let texture_sampler_repeat = gen_texture_sampler_repeat(&device);
         let input = Input::new();
 
-        let texture_depth = gen_texture_depth(&device, &surface_config, SAMPLE_COUNT);
-        let texture_msaa = gen_texture_msaa(&device, &surface_config, SAMPLE_COUNT);
+        let (texture_depth, texture_msaa) = gen_texture_attachment(
+            &device,
+            &surface_config,
+            sample_count,
+            adapter.get_info().backend,
+        );
 
         Self {
             window,
@@ -421,7 +395,10 @@ impl Core {
             device,
             queue,
             surface_config,
+            shader_mesh: mesh_shader,
+            render_pipline_layout_mesh,
             render_pipline_mesh,
+            sample_count,
             camera,
             input,
             texture_sampler,
@@ -464,8 +441,59 @@ impl Core {
             self.surface_config.height as _,
         );
 
-        self.texture_depth = gen_texture_depth(&self.device, &self.surface_config, SAMPLE_COUNT);
-        self.texture_msaa = gen_texture_msaa(&self.device, &self.surface_config, SAMPLE_COUNT);
+        (self.texture_depth, self.texture_msaa) = gen_texture_attachment(
+            &self.device,
+            &self.surface_config,
+            self.sample_count,
+            self.adapter.get_info().backend,
+        );
+    }
+
+    // falls back to the highest supported count below sample_count
+    pub fn set_sample_count(&mut self, sample_count: u32) {
+        let sample_count_supported = supported_sample_count(
+            &self.adapter,
+            &self.device,
+            self.surface_config.format,
+            sample_count,
+        );
+        if sample_count_supported != sample_count {
+            warn!(
+                "msaa sample count {} is not supported, falling back to {}",
+                sample_count, sample_count_supported
+            );
+        }
+        if sample_count_supported != self.sample_count {
+            self.sample_count = sample_count_supported;
+            self.render_pipline_mesh = gen_render_pipline_mesh(
+                &self.device,
+                &self.render_pipline_layout_mesh,
+                &self.shader_mesh,
+                self.surface_config.format,
+                self.sample_count,
+            );
+            (self.texture_depth, self.texture_msaa) = gen_texture_attachment(
+                &self.device,
+                &self.surface_config,
+                self.sample_count,
+                self.adapter.get_info().backend,
+            );
+        }
+        debug!("msaa sample count {}", self.sample_count);
+    }
+
+    // m steps through the supported msaa sample counts, 1 is off
+    fn on_key_released(&mut self, key: Option<VirtualKeyCode>) {
+        if key == Some(VirtualKeyCode::M) {
+            let sample_count_arr =
+                supported_sample_count_arr(&self.adapter, &self.device, self.surface_config.format);
+            let sample_count = sample_count_arr
+                .iter()
+                .find(|&&sample_count| sample_count > self.sample_count)
+                .copied()
+                .unwrap_or(1);
+            self.set_sample_count(sample_count);
+        }
     }
 
     fn render(&self) {
@@ -485,8 +513,8 @@ impl Core {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &self.texture_msaa,
-                    resolve_target: Some(&texture_view),
+                    view: self.texture_msaa.as_ref().unwrap_or(&texture_view),
+                    resolve_target: self.texture_msaa.as_ref().map(|_| &texture_view),
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color {
                             r: 0.1,
@@ -612,6 +640,9 @@ impl Core {
                         input: keyboard_input,
                         ..
                     } => {
+                        if keyboard_input.state == winit::event::ElementState::Released {
+                            core.on_key_released(keyboard_input.virtual_keycode);
+                        }
                         core.input.on_input(keyboard_input);
                     }
                     _ => {}
@@ -622,3 +653,72 @@ impl Core {
         });
     }
 }
+
+fn gen_render_pipline_mesh(
+    device: &Device,
+    render_pipline_layout: &PipelineLayout,
+    shader: &ShaderModule,
+    format: TextureFormat,
+    sample_count: u32,
+) -> RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipline Mesh"),
+        layout: Some(render_pipline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[
+                Vertex::vertex_buffer_layout(),
+                TransformRawIT::vertex_buffer_layout(),
+            ],
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState {
+                constant: 2, // Corresponds to bilinear filtering
+                slope_scale: 2.0,
+                clamp: 0.0,
+            },
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    })
+}
+
+// depth and, with msaa, the multisampled color target
+fn gen_texture_attachment(
+    device: &Device,
+    surface_config: &SurfaceConfiguration,
+    sample_count: u32,
+    backend: Backend,
+) -> (TextureView, Option<TextureView>) {
+    let texture_depth = gen_texture_depth(device, surface_config, sample_count, backend);
+    let texture_msaa =
+        (sample_count > 1).then(|| gen_texture_msaa(device, surface_config, sample_count, backend));
+    (texture_depth, texture_msaa)
+}
@@ -0,0 +1,8 @@
+
+// appended to depth.wgsl, depth_read_gl.wgsl replaces it on GL
+@group(0)@binding(0)
+var texture_depth: texture_depth_2d;
+
+fn get_depth(texel: vec2<u32>) -> f32 {
+    return textureLoad(texture_depth, texel, 0);
+}
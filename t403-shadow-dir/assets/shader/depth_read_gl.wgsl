@@ -0,0 +1,9 @@
+
+// appended instead of depth_read.wgsl on GL, naga's GLSL output has no textureLoad from a depth
+// texture, as plain floats it reads the same values
+@group(0)@binding(0)
+var texture_depth: texture_2d<f32>;
+
+fn get_depth(texel: vec2<u32>) -> f32 {
+    return textureLoad(texture_depth, texel, 0).r;
+}
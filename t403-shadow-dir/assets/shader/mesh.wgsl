@@ -9,7 +9,7 @@ struct VertexOut {
     @location(0) frag_pos: vec3<f32>,
     @location(1) normal: vec3<f32>,
     @location(2) tex_coord: vec2<f32>,
-    @location(3) frag_pos_light_space: vec4<f32>,
+    @location(3) view_depth: f32,
 }
 
 struct TransformIT {
@@ -25,6 +25,7 @@ struct TransformIT {
 
 struct LightDirection {
     dir: vec3<f32>,
+    shadow_sample_num: u32,
     color: vec4<f32>,
     ambient: vec3<f32>,
     diffuse: vec3<f32>,
@@ -37,6 +38,7 @@ struct LightDirectionArray {
 
 struct LightPoint {
     pos: vec3<f32>,
+    shadow_idx: i32,
     color: vec4<f32>,
     ambient: vec3<f32>,
     constant: f32,
@@ -52,6 +54,7 @@ struct LightPointArray {
 
 struct LightSpot {
     pos: vec3<f32>,
+    shadow_idx: i32,
     front: vec3<f32>,
     color: vec4<f32>,
     ambient: vec3<f32>,
@@ -65,16 +68,78 @@ struct LightSpotArray {
     arr: array<LightSpot>,
 }
 
+struct Cascade {
+    view_proj: mat4x4<f32>,
+    split_far: f32,
+    world_size: f32,
+    depth_range: f32,
+}
+
+struct CascadeArray {
+    arr: array<Cascade>,
+}
+
+struct ShadowTile {
+    view_proj: mat4x4<f32>,
+    rect: vec4<f32>,
+    tan_half_fov: f32,
+}
+
+struct ShadowTileArray {
+    arr: array<ShadowTile>,
+}
+
+struct CascadeSetting {
+    shadow_map_size: vec2<u32>,
+    cascade_num: u32,
+    is_debug: u32,
+    blend_range: f32,
+}
+
 @group(0)@binding(0)
 var<uniform> view_proj: mat4x4<f32>;
 @group(0)@binding(1)
 var<uniform> camera_pos: vec3<f32>;
 @group(0)@binding(2)
-var<uniform> view_proj_light: mat4x4<f32>;
+var<storage> cascade_arr: CascadeArray;
 @group(0)@binding(3)
-var<uniform> shadow_map_size: vec2<u32>;
+var<uniform> cascade_setting: CascadeSetting;
 @group(0)@binding(4)
-var texture_shadow_map: texture_depth_multisampled_2d;
+var texture_shadow_map: texture_depth_2d;
+@group(0)@binding(5)
+var sampler_shadow_map: sampler;
+@group(0)@binding(6)
+var sampler_shadow_map_compare: sampler_comparison;
+// the cascades first, then the spot and point lights at their shadow_idx
+@group(0)@binding(7)
+var<storage> shadow_tile_arr: ShadowTileArray;
+
+// tan of the half angle the sun covers, wider softens the shadows further from their casters
+const LIGHT_SIZE: f32 = 0.02;
+// in shadow map texels, caps the blocker search and the filter
+const PENUMBRA_MAX: f32 = 24.0;
+// the fixed filter of the spot and point lights, in texels of their tiles
+const LOCAL_SAMPLE_NUM: u32 = 8u;
+const LOCAL_FILTER_RADIUS: f32 = 1.5;
+
+// the first 8 and 16 points are spread as evenly as all 32
+var<private> poisson_disk: array<vec2<f32>, 32> = array<vec2<f32>, 32>(
+    vec2<f32>(-0.3523, -0.6983), vec2<f32>(0.5679, 0.7941), vec2<f32>(-0.6625, 0.5697), vec2<f32>(0.7603, -0.3429),
+    vec2<f32>(0.0266, 0.0610), vec2<f32>(-0.8812, -0.2843), vec2<f32>(0.3789, -0.9243), vec2<f32>(-0.1118, 0.9157),
+    vec2<f32>(0.9286, 0.2866), vec2<f32>(0.2081, -0.4226), vec2<f32>(0.4607, 0.2975), vec2<f32>(-0.4603, -0.0256),
+    vec2<f32>(-0.2136, 0.5106), vec2<f32>(0.1900, 0.6294), vec2<f32>(-0.9132, 0.2248), vec2<f32>(-0.0117, -0.9878),
+    vec2<f32>(0.4534, -0.1024), vec2<f32>(-0.1689, -0.2995), vec2<f32>(-0.7197, -0.6678), vec2<f32>(0.5193, -0.6059),
+    vec2<f32>(-0.4316, 0.8239), vec2<f32>(0.2324, 0.9711), vec2<f32>(-0.5459, -0.3946), vec2<f32>(0.0033, -0.6559),
+    vec2<f32>(0.9230, -0.0443), vec2<f32>(0.1172, 0.3327), vec2<f32>(-0.4973, 0.3008), vec2<f32>(0.7432, 0.4915),
+    vec2<f32>(-0.7507, -0.0154), vec2<f32>(0.1728, -0.1671), vec2<f32>(0.6924, 0.1873), vec2<f32>(0.6913, -0.1062),
+);
+// rotates the disk per pixel, set by the fragment entry point, noise instead of banding
+var<private> noise_angle: f32 = 0.0;
+
+// interleaved gradient noise
+fn get_noise_angle(pixel: vec2<f32>) -> f32 {
+    return 6.2831853 * fract(52.9829189 * fract(dot(pixel, vec2<f32>(0.06711056, 0.00583715))));
+}
 
 @vertex
 fn vs_main(in: VertexIn, transform: TransformIT) -> VertexOut {
@@ -87,7 +152,7 @@ fn vs_main(in: VertexIn, transform: TransformIT) -> VertexOut {
     out.frag_pos = (model * vec4<f32>(in.pos, 1.0)).xyz;
     out.normal = it_model * in.normal;
     out.tex_coord = in.tex_coord;
-    out.frag_pos_light_space = view_proj_light * model * vec4<f32>(in.pos, 1.0);
+    out.view_depth = out.clip_pos.w;
 
     return out;
 }
@@ -117,14 +182,11 @@ fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
     let normal = normalize(in.normal);
     let view_dir = normalize(camera_pos - in.frag_pos);
 
-    // 0.5 for x, -0.5 for y, because texture is y down
-    let frag_pos_light_space_xy = (in.frag_pos_light_space.xy / in.frag_pos_light_space.w) * vec2<f32>(0.5, -0.5) + 0.5;
-    let frag_pos_light_space_z = in.frag_pos_light_space.z / in.frag_pos_light_space.w;
-
     let tex_diffuse = textureSample(texture_diffuse, texture_sampler, in.tex_coord).rgb;
+    noise_angle = get_noise_angle(in.clip_pos.xy);
 
     for (var i: u32 = 0u; i < arrayLength(&light_direction_arr.arr); i = i + 1u) {
-        l += do_light_direction(light_direction_arr.arr[i], normal, view_dir, tex_diffuse, frag_pos_light_space_xy, frag_pos_light_space_z);
+        l += do_light_direction(light_direction_arr.arr[i], normal, view_dir, tex_diffuse, in.frag_pos, in.view_depth);
     }
 
     for (var i: u32 = 0u; i < arrayLength(&light_point_arr.arr); i = i + 1u) {
@@ -134,10 +196,14 @@ fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
     for (var i: u32 = 0u; i < arrayLength(&light_spot_arr.arr); i = i + 1u) {
         l += do_light_spot(light_spot_arr.arr[i], normal, tex_diffuse, in.frag_pos);
     }
+
+    if cascade_setting.is_debug != 0u {
+        l *= get_cascade_debug_color(get_cascade_idx(in.view_depth));
+    }
     return vec4<f32>(l, 1.0);
 }
 
-fn do_light_direction(light_direction: LightDirection, normal: vec3<f32>, view_dir: vec3<f32>, tex_diffuse: vec3<f32>, frag_pos_light_space_xy: vec2<f32>, frag_pos_light_space_z: f32) -> vec3<f32> {
+fn do_light_direction(light_direction: LightDirection, normal: vec3<f32>, view_dir: vec3<f32>, tex_diffuse: vec3<f32>, frag_pos: vec3<f32>, view_depth: f32) -> vec3<f32> {
     if light_direction.color.a == 0.0 {
         return vec3<f32>(0.0, 0.0, 0.0);
     }
@@ -158,39 +224,163 @@ fn do_light_direction(light_direction: LightDirection, normal: vec3<f32>, view_d
 
     let specular = light_direction.specular * light_color * spec * tex_diffuse;
 
-    let visiblity = get_direction_light_visiblity(frag_pos_light_space_xy, frag_pos_light_space_z, normal, light_dir);
+    let visiblity = get_direction_light_visiblity(frag_pos, view_depth, normal, light_dir, light_direction.shadow_sample_num);
 
     return ambient + visiblity * (diffuse + specular);
     //return vec3<f32>(visiblity);
 }
 
-fn get_direction_light_visiblity(frag_pos_light_space_xy: vec2<f32>, frag_pos_light_space_z: f32, normal: vec3<f32>, light_dir: vec3<f32>) -> f32 {
-    if frag_pos_light_space_z > 1.0 || frag_pos_light_space_xy.x > 1.0 || frag_pos_light_space_xy.y > 1.0 || frag_pos_light_space_xy.x < 0.0 || frag_pos_light_space_xy.y < 0.0 {
+fn get_cascade_idx(view_depth: f32) -> u32 {
+    for (var i: u32 = 0u; i < cascade_setting.cascade_num; i = i + 1u) {
+        if view_depth < cascade_arr.arr[i].split_far {
+            return i;
+        }
+    }
+    return cascade_setting.cascade_num;
+}
+
+fn get_cascade_debug_color(cascade_idx: u32) -> vec3<f32> {
+    var color_arr = array<vec3<f32>, 4>(
+        vec3<f32>(1.0, 0.3, 0.3),
+        vec3<f32>(0.3, 1.0, 0.3),
+        vec3<f32>(0.3, 0.3, 1.0),
+        vec3<f32>(1.0, 1.0, 0.3),
+    );
+    if cascade_idx >= cascade_setting.cascade_num {
+        return vec3<f32>(1.0, 1.0, 1.0);
+    }
+    return color_arr[cascade_idx % 4u];
+}
+
+fn get_direction_light_visiblity(frag_pos: vec3<f32>, view_depth: f32, normal: vec3<f32>, light_dir: vec3<f32>, sample_num: u32) -> f32 {
+    let cascade_idx = get_cascade_idx(view_depth);
+    if cascade_idx >= cascade_setting.cascade_num {
         return 1.0;
     }
-    //let tex_coord = vec2<u32>(u32(f32(shadow_map_size.x) * frag_pos_light_space_xy.x), u32(f32(shadow_map_size.y) * frag_pos_light_space_xy.y));
-    let bias = max(0.008 * (1.0 - dot(normal, light_dir)), 0.003);
-    var visiblity = 0.0;
-    let sample_size = 2;
-    for (var y = -sample_size; y <= sample_size; y ++) {
-        for (var x = -sample_size; x <= sample_size; x ++) {
-            let tex_coord = vec2<u32>(vec2<f32>(shadow_map_size) * frag_pos_light_space_xy);
-            let tmp_x = i32(tex_coord.x) + x;
-            let tmp_y = i32(tex_coord.y) + y;
-            if tmp_x < 0 || tmp_x >= i32(shadow_map_size.x) || tmp_y < 0 || tmp_y >= i32(shadow_map_size.y) {
-                visiblity += 1.0;
-            } else {
-                let shadow_map_value = textureLoad(texture_shadow_map, vec2<u32>(u32(tmp_x), u32(tmp_y)), 0);
-
-                visiblity += select(0.0, 1.0, frag_pos_light_space_z < shadow_map_value + bias);
-            }
+
+    var visiblity = get_cascade_visiblity(cascade_idx, frag_pos, normal, light_dir, sample_num);
+
+    // blend the end of a cascade into the next one to hide the seam, the last one fades out
+    let split_far = cascade_arr.arr[cascade_idx].split_far;
+    let blend_len = split_far * cascade_setting.blend_range;
+    let blend = (view_depth - (split_far - blend_len)) / blend_len;
+    if blend > 0.0 {
+        var visiblity_next = 1.0;
+        if cascade_idx + 1u < cascade_setting.cascade_num {
+            visiblity_next = get_cascade_visiblity(cascade_idx + 1u, frag_pos, normal, light_dir, sample_num);
         }
+        visiblity = mix(visiblity, visiblity_next, clamp(blend, 0.0, 1.0));
     }
-    visiblity /= pow(f32(sample_size * 2 + 1), 2.0);
 
     return visiblity;
 }
 
+// pcss, the average depth of the blockers around the fragment sets how wide the filter gets
+fn get_cascade_visiblity(cascade_idx: u32, frag_pos: vec3<f32>, normal: vec3<f32>, light_dir: vec3<f32>, sample_num: u32) -> f32 {
+    let cascade = cascade_arr.arr[cascade_idx];
+    let tile = shadow_tile_arr.arr[cascade_idx];
+    if tile.rect.z == 0.0 {
+        return 1.0;
+    }
+    let frag_pos_light_space = tile.view_proj * vec4<f32>(frag_pos, 1.0);
+    // 0.5 for x, -0.5 for y, because texture is y down
+    let frag_pos_light_space_xy = (frag_pos_light_space.xy / frag_pos_light_space.w) * vec2<f32>(0.5, -0.5) + 0.5;
+    let frag_pos_light_space_z = frag_pos_light_space.z / frag_pos_light_space.w;
+
+    if frag_pos_light_space_z > 1.0 || frag_pos_light_space_xy.x > 1.0 || frag_pos_light_space_xy.y > 1.0 || frag_pos_light_space_xy.x < 0.0 || frag_pos_light_space_xy.y < 0.0 {
+        return 1.0;
+    }
+    let texel = get_tile_texel(tile);
+    let cos_theta = clamp(dot(normal, light_dir), 0.05, 1.0);
+    let bias = max(0.008 * (1.0 - cos_theta), 0.003);
+    let z = frag_pos_light_space_z - bias;
+    if sample_num <= 1u {
+        return textureSampleCompareLevel(texture_shadow_map, sampler_shadow_map_compare, get_atlas_uv(tile, frag_pos_light_space_xy), z);
+    }
+
+    // shadow map uv per world unit along the depth, orthographic so it is the same everywhere
+    let uv_per_depth = cascade.depth_range / cascade.world_size;
+    let rotation = mat2x2<f32>(cos(noise_angle), sin(noise_angle), -sin(noise_angle), cos(noise_angle));
+
+    // blockers can sit anywhere between the light and the fragment
+    let search_radius = clamp(LIGHT_SIZE * frag_pos_light_space_z * uv_per_depth, texel, PENUMBRA_MAX * texel);
+    var blocker_sum = 0.0;
+    var blocker_num = 0.0;
+    for (var i = 0u; i < sample_num; i = i + 1u) {
+        let uv = frag_pos_light_space_xy + rotation * poisson_disk[i] * search_radius;
+        let depth = get_shadow_map_blocker(get_atlas_uv(tile, uv));
+        let is_blocker = select(vec4<f32>(0.0), vec4<f32>(1.0), depth < vec4<f32>(z));
+        blocker_sum += dot(is_blocker, depth);
+        blocker_num += dot(is_blocker, vec4<f32>(1.0));
+    }
+    if blocker_num == 0.0 {
+        return 1.0;
+    }
+    let blocker_depth = blocker_sum / blocker_num;
+
+    // the penumbra grows with the distance from the blocker to the receiver
+    let filter_radius = clamp(LIGHT_SIZE * (frag_pos_light_space_z - blocker_depth) * uv_per_depth, texel, PENUMBRA_MAX * texel);
+    // a wide filter reaches further along a slanted receiver
+    let slope = sqrt(1.0 - cos_theta * cos_theta) / cos_theta;
+    let z_filter = z - min(slope, 4.0) * filter_radius / uv_per_depth;
+    var visiblity = 0.0;
+    for (var i = 0u; i < sample_num; i = i + 1u) {
+        let uv = frag_pos_light_space_xy + rotation * poisson_disk[i] * filter_radius;
+        visiblity += textureSampleCompareLevel(texture_shadow_map, sampler_shadow_map_compare, get_atlas_uv(tile, uv), z_filter);
+    }
+    return visiblity / f32(sample_num);
+}
+
+// a texel of the tile in its own uv
+fn get_tile_texel(tile: ShadowTile) -> f32 {
+    return 1.0 / (tile.rect.z * f32(cascade_setting.shadow_map_size.x));
+}
+
+// from the uv of a tile to the uv of the atlas, a texel off the edges so the filters never
+// read the neighbouring tiles
+fn get_atlas_uv(tile: ShadowTile, uv: vec2<f32>) -> vec2<f32> {
+    let texel = get_tile_texel(tile);
+    return tile.rect.xy + clamp(uv, vec2<f32>(texel), vec2<f32>(1.0 - texel)) * tile.rect.zw;
+}
+
+// poisson pcf in the perspective tile of a spot light or a point light cube face
+fn get_tile_visiblity(tile_idx: i32, frag_pos: vec3<f32>, normal: vec3<f32>, light_pos: vec3<f32>) -> f32 {
+    let tile = shadow_tile_arr.arr[tile_idx];
+    if tile.rect.z == 0.0 {
+        return 1.0;
+    }
+    let texel = get_tile_texel(tile);
+    // a texel covers more of the receiver the further it is from the light, push the
+    // fragment out by about that much against acne
+    let texel_world = 2.0 * length(frag_pos - light_pos) * tile.tan_half_fov * texel;
+    let frag_pos_light_space = tile.view_proj * vec4<f32>(frag_pos + normal * texel_world * 1.5, 1.0);
+    let frag_pos_light_space_xy = (frag_pos_light_space.xy / frag_pos_light_space.w) * vec2<f32>(0.5, -0.5) + 0.5;
+    let z = frag_pos_light_space.z / frag_pos_light_space.w;
+    if z > 1.0 || any(frag_pos_light_space_xy > vec2<f32>(1.0)) || any(frag_pos_light_space_xy < vec2<f32>(0.0)) {
+        return 1.0;
+    }
+
+    let rotation = mat2x2<f32>(cos(noise_angle), sin(noise_angle), -sin(noise_angle), cos(noise_angle));
+    var visiblity = 0.0;
+    for (var i = 0u; i < LOCAL_SAMPLE_NUM; i = i + 1u) {
+        let uv = frag_pos_light_space_xy + rotation * poisson_disk[i] * LOCAL_FILTER_RADIUS * texel;
+        visiblity += textureSampleCompareLevel(texture_shadow_map, sampler_shadow_map_compare, get_atlas_uv(tile, uv), z);
+    }
+    return visiblity / f32(LOCAL_SAMPLE_NUM);
+}
+
+// the major axis picks the face, in the order of the cube map
+fn get_cube_face(dir: vec3<f32>) -> i32 {
+    let v = abs(dir);
+    if v.x >= v.y && v.x >= v.z {
+        return select(1, 0, dir.x > 0.0);
+    }
+    if v.y >= v.z {
+        return select(3, 2, dir.y > 0.0);
+    }
+    return select(5, 4, dir.z < 0.0);
+}
+
 fn do_light_point(light_point: LightPoint, normal: vec3<f32>, view_dir: vec3<f32>, tex_diffuse: vec3<f32>, frag_pos: vec3<f32>) -> vec3<f32> {
     if light_point.color.a == 0.0 {
         return vec3<f32>(0.0, 0.0, 0.0);
@@ -217,7 +407,13 @@ fn do_light_point(light_point: LightPoint, normal: vec3<f32>, view_dir: vec3<f32
     ambient *= attenuation;
     diffuse *= attenuation;
 
-    return ambient + diffuse;
+    var visiblity = 1.0;
+    if light_point.shadow_idx >= 0 {
+        let face = get_cube_face(frag_pos - light_point.pos);
+        visiblity = get_tile_visiblity(light_point.shadow_idx + face, frag_pos, normal, light_point.pos);
+    }
+
+    return ambient + visiblity * diffuse;
 }
 
 fn do_light_spot(light_spot: LightSpot, normal: vec3<f32>, tex_diffuse: vec3<f32>, frag_pos: vec3<f32>) -> vec3<f32> {
@@ -246,5 +442,10 @@ fn do_light_spot(light_spot: LightSpot, normal: vec3<f32>, tex_diffuse: vec3<f32
 
     diffuse *= intensity;
 
-    return ambient + diffuse;
+    var visiblity = 1.0;
+    if light_spot.shadow_idx >= 0 {
+        visiblity = get_tile_visiblity(light_spot.shadow_idx, frag_pos, normal, light_spot.pos);
+    }
+
+    return ambient + visiblity * diffuse;
 }
@@ -15,13 +15,21 @@ struct TransformIT {
     @location(12) t7: vec4<f32>,
 }
 
+struct ShadowTile {
+    view_proj: mat4x4<f32>,
+    rect: vec4<f32>,
+    tan_half_fov: f32,
+}
+
 @group(0)@binding(0)
-var<uniform> view_proj: mat4x4<f32>;
+var<uniform> tile_idx: u32;
+@group(0)@binding(1)
+var<storage> shadow_tile_arr: array<ShadowTile>;
 
 @vertex
 fn vs_main(in: VertexIn, transform: TransformIT) -> @builtin(position) vec4<f32> {
     let model = mat4x4<f32>(transform.t0, transform.t1, transform.t2, transform.t3);
     //let it_model = mat3x3<f32>(transform.t4.xyz, transform.t5.xyz, transform.t6.xyz); // inverse transpose model
 
-    return view_proj * model * vec4<f32>(in.pos, 1.0);
+    return shadow_tile_arr[tile_idx].view_proj * model * vec4<f32>(in.pos, 1.0);
 }
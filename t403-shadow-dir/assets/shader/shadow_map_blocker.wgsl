@@ -0,0 +1,7 @@
+
+// appended to mesh.wgsl, shadow_map_blocker_gl.wgsl replaces it on GL
+
+// the four atlas depths around uv for the blocker search
+fn get_shadow_map_blocker(uv: vec2<f32>) -> vec4<f32> {
+    return textureGather(texture_shadow_map, sampler_shadow_map, uv);
+}
@@ -0,0 +1,10 @@
+
+// appended instead of shadow_map_blocker.wgsl on GL, naga gives each GLSL texture a single sampler
+// and the atlas is already read with sampler_shadow_map_compare for pcf, so the gather goes
+// through a float view of the atlas bound at 8
+@group(0)@binding(8)
+var texture_shadow_map_blocker: texture_2d<f32>;
+
+fn get_shadow_map_blocker(uv: vec2<f32>) -> vec4<f32> {
+    return textureGather(0, texture_shadow_map_blocker, sampler_shadow_map, uv);
+}
@@ -127,4 +127,32 @@ impl Camera {
     pub fn view_proj(&self) -> Mat4 {
         self.proj().mul_mat4(&self.view())
     }
+
+    pub fn z_near(&self) -> f32 {
+        self.z_near
+    }
+
+    pub fn z_far(&self) -> f32 {
+        self.z_far
+    }
+
+    // vertical
+    pub fn fov_radian(&self) -> f32 {
+        self.fov.to_radians()
+    }
+
+    // world space corners of the view frustum between near and far
+    pub fn frustum_corner_arr(&self, near: f32, far: f32) -> [Vec3; 8] {
+        let proj = Mat4::perspective_rh(self.fov.to_radians(), self.ratio, near, far);
+        let inv_view_proj = proj.mul_mat4(&self.view()).inverse();
+
+        let mut corner_arr = [Vec3::ZERO; 8];
+        for (i, corner) in corner_arr.iter_mut().enumerate() {
+            let x = if i & 1 == 0 { -1.0 } else { 1.0 };
+            let y = if i & 2 == 0 { -1.0 } else { 1.0 };
+            let z = if i & 4 == 0 { 0.0 } else { 1.0 };
+            *corner = inv_view_proj.project_point3(Vec3::new(x, y, z));
+        }
+        corner_arr
+    }
 }
@@ -0,0 +1,113 @@
+use glam::{Mat4, Vec3, Vec4Swizzles};
+
+use crate::camera::Camera;
+
+pub const MAX_CASCADE: u32 = 8;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Cascade {
+    pub view_proj: [[f32; 4]; 4],
+
+    // view space distance where this cascade ends
+    pub split_far: f32,
+    // world units across the shadow map and along its depth, scale the penumbra to texels
+    pub world_size: f32,
+    pub depth_range: f32,
+    // 16 bytes padding
+    _padding0: u32,
+}
+
+impl Cascade {
+    pub fn new(
+        camera: &Camera,
+        light_dir: Vec3,
+        split_near: f32,
+        split_far: f32,
+        shadow_map_size: u32,
+        z_margin: f32,
+    ) -> Self {
+        // bounding sphere of the frustum slice, its size doesn't change while the camera rotates
+        let corner_arr = camera.frustum_corner_arr(split_near, split_far);
+        let center = corner_arr.iter().fold(Vec3::ZERO, |acc, c| acc + *c) / 8.0;
+        let radius = corner_arr
+            .iter()
+            .map(|c| (*c - center).length())
+            .fold(0.0_f32, f32::max);
+        let radius = (radius * 16.0).ceil() / 16.0;
+
+        let light_dir = light_dir.normalize();
+        let up = if light_dir.cross(Vec3::Y).length_squared() < 1e-6 {
+            Vec3::Z
+        } else {
+            Vec3::Y
+        };
+        // z_margin pulls the light back to catch casters in front of the slice
+        let view = Mat4::look_to_rh(center - light_dir * (radius + z_margin), light_dir, up);
+        let depth_range = radius * 2.0 + z_margin;
+        let mut proj = Mat4::orthographic_rh(-radius, radius, -radius, radius, 0.0, depth_range);
+
+        // snap the origin to a shadow map texel, so shadow edges don't shimmer when camera moves
+        let half_size = shadow_map_size as f32 / 2.0;
+        let origin = proj.mul_mat4(&view).w_axis.xy() * half_size;
+        let offset = (origin.round() - origin) / half_size;
+        proj.w_axis.x += offset.x;
+        proj.w_axis.y += offset.y;
+
+        Self {
+            view_proj: proj.mul_mat4(&view).to_cols_array_2d(),
+            split_far,
+            world_size: radius * 2.0,
+            depth_range,
+            _padding0: 0,
+        }
+    }
+
+    pub fn zero() -> Self {
+        Self {
+            view_proj: Mat4::IDENTITY.to_cols_array_2d(),
+            split_far: 0.0,
+            world_size: 1.0,
+            depth_range: 1.0,
+            _padding0: 0,
+        }
+    }
+
+    // blend between log and uniform split, lambda 1.0 is full log split
+    pub fn split_arr(near: f32, far: f32, cascade_num: u32, lambda: f32) -> Vec<f32> {
+        (1..=cascade_num)
+            .map(|i| {
+                let p = i as f32 / cascade_num as f32;
+                let split_log = near * (far / near).powf(p);
+                let split_uniform = near + (far - near) * p;
+                lambda * split_log + (1.0 - lambda) * split_uniform
+            })
+            .collect()
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CascadeSetting {
+    pub shadow_map_size: [u32; 2],
+    pub cascade_num: u32,
+    // tint fragment by cascade index
+    pub is_debug: u32,
+
+    // part of a cascade blended into the next one
+    pub blend_range: f32,
+    // 16 bytes padding
+    _padding0: [u32; 3],
+}
+
+impl CascadeSetting {
+    pub fn new(shadow_map_size: [u32; 2], cascade_num: u32, blend_range: f32) -> Self {
+        Self {
+            shadow_map_size,
+            cascade_num,
+            is_debug: 0,
+            blend_range,
+            _padding0: [0; 3],
+        }
+    }
+}
@@ -1,5 +1,8 @@
 pub mod camera;
+pub mod cascade;
 pub mod color;
+pub use common::gltf_data;
+pub use common::headless;
 pub mod input;
 pub mod light_direction;
 pub mod light_point;
@@ -12,6 +15,8 @@ pub mod pipe_hub;
 pub mod pipe_mesh;
 pub mod pipe_shadow;
 pub mod runner;
+pub mod shadow_atlas;
+pub mod shadow_quality;
 pub mod texture;
 pub mod transform;
 pub mod vertex;
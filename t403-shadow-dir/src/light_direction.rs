@@ -1,9 +1,11 @@
+use crate::shadow_quality::ShadowQuality;
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct LightDirection {
     pub dir: [f32; 3],
-    // 16 bytes padding
-    _padding0: u32,
+    // poisson taps of the shadow filter, see ShadowQuality
+    pub shadow_sample_num: u32,
 
     pub color: [f32; 4],
 
@@ -30,7 +32,7 @@ impl LightDirection {
     ) -> Self {
         Self {
             dir,
-            _padding0: 0,
+            shadow_sample_num: ShadowQuality::Medium.sample_num(),
             color,
             ambient,
             _padding2: 0,
@@ -41,6 +43,10 @@ impl LightDirection {
         }
     }
 
+    pub fn set_shadow_quality(&mut self, quality: ShadowQuality) {
+        self.shadow_sample_num = quality.sample_num();
+    }
+
     pub fn zero() -> Self {
         Self::new(
             [0.0, 0.0, 0.0],
@@ -2,8 +2,8 @@
 #[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
 pub struct LightPoint {
     pub pos: [f32; 3],
-    // 16 bytes padding
-    _padding0: u32,
+    // the first of its six tiles in the shadow atlas, -1 casts no shadow
+    pub shadow_idx: i32,
 
     pub color: [f32; 4],
 
@@ -33,7 +33,7 @@ impl LightPoint {
     ) -> Self {
         Self {
             pos,
-            _padding0: 0,
+            shadow_idx: -1,
             color,
             ambient,
             diffuse,
@@ -56,4 +56,23 @@ impl LightPoint {
             0.0,
         )
     }
+
+    // distance where the attenuated light drops below 5 / 256 of its brightest channel,
+    // nothing past it is lit visibly
+    pub fn radius(&self) -> f32 {
+        let max = |v: &[f32]| v.iter().fold(0.0_f32, |a, &b| a.max(b));
+        let brightness = max(&self.color[..3])
+            * max(&self.diffuse)
+                .max(max(&self.specular))
+                .max(max(&self.ambient));
+        let c = self.constant - brightness * 256.0 / 5.0;
+        if self.quadratic > 0.0 {
+            (-self.linear + (self.linear * self.linear - 4.0 * self.quadratic * c).sqrt())
+                / (2.0 * self.quadratic)
+        } else if self.linear > 0.0 {
+            -c / self.linear
+        } else {
+            f32::INFINITY
+        }
+    }
 }
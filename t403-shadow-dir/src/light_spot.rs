@@ -2,8 +2,8 @@
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct LightSpot {
     pub pos: [f32; 3],
-    // 16 bytes padding
-    _padding0: u32,
+    // its tile in the shadow atlas, -1 casts no shadow
+    pub shadow_idx: i32,
 
     pub front: [f32; 3],
     // 16 bytes padding
@@ -35,7 +35,7 @@ impl LightSpot {
     ) -> Self {
         Self {
             pos,
-            _padding0: 0,
+            shadow_idx: -1,
             front,
             _padding1: 0,
             color,
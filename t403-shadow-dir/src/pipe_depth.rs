@@ -1,6 +1,6 @@
 use wgpu::{
-    util::DeviceExt, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device, IndexFormat,
-    RenderPipeline, SurfaceConfiguration, TextureView,
+    util::DeviceExt, Backend, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device,
+    IndexFormat, RenderPipeline, SurfaceConfiguration, TextureView,
 };
 
 use crate::vertex::Vertex;
@@ -24,7 +24,10 @@ impl PipeDepth {
         width: u32,
         height: u32,
         is_orth: bool,
+        backend: Backend,
     ) -> Self {
+        // see depth_read_gl.wgsl
+        let is_gl = backend == Backend::Gl;
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Bind Group Layout Pipe Depth"),
             entries: &[
@@ -32,9 +35,13 @@ impl PipeDepth {
                     binding: 0,
                     visibility: wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Depth,
+                        sample_type: if is_gl {
+                            wgpu::TextureSampleType::Float { filterable: false }
+                        } else {
+                            wgpu::TextureSampleType::Depth
+                        },
                         view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: true,
+                        multisampled: false,
                     },
                     count: None,
                 },
@@ -68,7 +75,13 @@ impl PipeDepth {
                 push_constant_ranges: &[],
             });
 
-        let shader = std::fs::read_to_string("assets/shader/depth.wgsl").unwrap();
+        let mut shader = std::fs::read_to_string("assets/shader/depth.wgsl").unwrap();
+        shader += &std::fs::read_to_string(if is_gl {
+            "assets/shader/depth_read_gl.wgsl"
+        } else {
+            "assets/shader/depth_read.wgsl"
+        })
+        .unwrap();
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader Pipe Depth"),
             source: wgpu::ShaderSource::Wgsl(shader.into()),
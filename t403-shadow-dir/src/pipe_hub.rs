@@ -1,27 +1,53 @@
 use std::time::Instant;
 
+use anyhow::{anyhow, Result};
+use image::RgbaImage;
+use log::{debug, warn};
 use wgpu::{
-    Adapter, Backends, Device, DeviceDescriptor, Features, Instance, PresentMode, Queue, Surface,
-    SurfaceConfiguration, TextureUsages,
+    Adapter, Backends, CompositeAlphaMode, Device, DeviceDescriptor, Features, Instance,
+    PresentMode, Queue, Surface, SurfaceConfiguration, Texture, TextureFormat, TextureUsages,
 };
 use winit::{
-    dpi::PhysicalPosition, event::MouseScrollDelta, event_loop::EventLoop, window::Window,
+    dpi::PhysicalPosition,
+    event::{MouseScrollDelta, VirtualKeyCode},
+    event_loop::EventLoop,
+    window::Window,
 };
 
 use crate::{
-    input::Input, light_direction::LightDirection, model_light::ModelLight, pipe_depth::PipeDepth,
-    pipe_mesh::PipeMesh, pipe_shadow::PipeShadow,
+    headless::{
+        check_format_headless, gen_texture_headless, request_adapter_headless, texture_to_image,
+    },
+    input::Input,
+    light_direction::LightDirection,
+    light_point::LightPoint,
+    light_spot::LightSpot,
+    model_light::ModelLight,
+    pipe_depth::PipeDepth,
+    pipe_mesh::PipeMesh,
+    pipe_shadow::PipeShadow,
+    shadow_quality::ShadowQuality,
+    texture::{supported_sample_count, supported_sample_count_arr},
 };
 
 pub struct PipeHub {
-    pub window: Window,
+    pub window: Option<Window>,
     pub instance: Instance,
-    pub surface: Surface,
+    pub surface: Option<Surface>,
     pub adapter: Adapter,
     pub device: Device,
     pub queue: Queue,
     pub surface_config: SurfaceConfiguration,
+    // offscreen target when there is no surface
+    pub texture_headless: Option<Texture>,
     pub input: Input,
+    // C toggles the cascade debug tint, only act on the press edge
+    pub is_cascade_debug_pressed: bool,
+    // Q steps every direction light through the shadow presets, new lights start with it
+    pub shadow_quality: ShadowQuality,
+    pub is_shadow_quality_pressed: bool,
+    // M steps through the supported msaa sample counts, 1 is off
+    pub is_sample_count_pressed: bool,
 
     pub pipe_shadow: PipeShadow,
     pub pipe_mesh: PipeMesh,
@@ -53,18 +79,6 @@ impl PipeHub {
             .await
             .unwrap();
 
-        let (device, queue) = adapter
-            .request_device(
-                &DeviceDescriptor {
-                    label: Some("Device"),
-                    features: Features::empty(),
-                    limits: Default::default(),
-                },
-                None,
-            )
-            .await
-            .unwrap();
-
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_config = SurfaceConfiguration {
             usage: TextureUsages::RENDER_ATTACHMENT,
@@ -75,28 +89,98 @@ impl PipeHub {
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
         };
-        surface.configure(&device, &surface_config);
+
+        Self::init(
+            instance,
+            adapter,
+            Some(window),
+            Some(surface),
+            surface_config,
+        )
+        .await
+        .unwrap()
+    }
+
+    // render into an offscreen texture of the given size and format instead of a window
+    pub async fn new_headless(width: u32, height: u32, format: TextureFormat) -> Result<Self> {
+        check_format_headless(format)?;
+
+        let instance = Instance::new(wgpu::InstanceDescriptor {
+            backends: Backends::all(),
+            dx12_shader_compiler: Default::default(),
+        });
+
+        let adapter = request_adapter_headless(&instance)
+            .await
+            .ok_or_else(|| anyhow!("no adapter for headless rendering"))?;
+
+        let surface_config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            format,
+            width,
+            height,
+            present_mode: PresentMode::Fifo,
+            alpha_mode: CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+
+        Self::init(instance, adapter, None, None, surface_config).await
+    }
+
+    async fn init(
+        instance: Instance,
+        adapter: Adapter,
+        window: Option<Window>,
+        surface: Option<Surface>,
+        surface_config: SurfaceConfiguration,
+    ) -> Result<Self> {
+        let (device, queue) = adapter
+            .request_device(
+                &DeviceDescriptor {
+                    label: Some("Device"),
+                    // lets the msaa sample count go beyond what webgpu guarantees
+                    features: adapter.features()
+                        & Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+                    limits: Default::default(),
+                },
+                None,
+            )
+            .await?;
+
+        let texture_headless = match &surface {
+            Some(surface) => {
+                surface.configure(&device, &surface_config);
+                None
+            }
+            None => Some(gen_texture_headless(&device, &surface_config)),
+        };
 
         let input = Input::new();
 
-        let pipe_shadow = PipeShadow::new(&device, 1024 * 2, 1024 * 2);
+        let pipe_shadow = PipeShadow::new(&device, 1024 * 4, 4);
+        // 4x to start with, lowered when the adapter can't do it
+        let sample_count = supported_sample_count(&adapter, &device, surface_config.format, 4);
         let pipe_mesh = PipeMesh::new(
             &device,
             &surface_config,
-            &pipe_shadow.buffer_view_proj,
-            &pipe_shadow.texture_view_depth,
-            [pipe_shadow.width, pipe_shadow.height],
+            &pipe_shadow.buffer_cascade,
+            &pipe_shadow.buffer_cascade_setting,
+            &pipe_shadow.texture_view_depth(),
+            &pipe_shadow.buffer_tile,
+            sample_count,
+            adapter.get_info().backend,
         );
         let pipe_depth = PipeDepth::new(
             &device,
             &surface_config,
-            &pipe_shadow.texture_view_depth,
-            pipe_shadow.width,
-            pipe_shadow.height,
+            &pipe_shadow.texture_view_depth(),
+            pipe_shadow.atlas.size,
+            pipe_shadow.atlas.size,
             true,
+            adapter.get_info().backend,
         );
 
-        Self {
+        Ok(Self {
             window,
             instance,
             surface,
@@ -104,7 +188,12 @@ impl PipeHub {
             device,
             queue,
             surface_config,
+            texture_headless,
             input,
+            is_cascade_debug_pressed: false,
+            shadow_quality: ShadowQuality::Medium,
+            is_shadow_quality_pressed: false,
+            is_sample_count_pressed: false,
 
             pipe_shadow,
             pipe_mesh,
@@ -114,32 +203,46 @@ impl PipeHub {
 
             start_time: Instant::now(),
             last_time: Instant::now(),
-        }
+        })
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
         self.surface_config.width = width;
         self.surface_config.height = height;
-        self.surface.configure(&self.device, &self.surface_config);
+        match &self.surface {
+            Some(surface) => surface.configure(&self.device, &self.surface_config),
+            None => {
+                self.texture_headless =
+                    Some(gen_texture_headless(&self.device, &self.surface_config))
+            }
+        }
 
         self.pipe_mesh.resize(&self.device, &self.surface_config);
 
         self.pipe_depth.set_texture_view_depth(
             &self.device,
-            &self.pipe_shadow.texture_view_depth,
-            self.pipe_shadow.width,
-            self.pipe_shadow.height,
+            &self.pipe_shadow.texture_view_depth(),
+            self.pipe_shadow.atlas.size,
+            self.pipe_shadow.atlas.size,
         );
     }
 
     fn render(&mut self) {
-        let current_texture = self
-            .surface
-            .get_current_texture()
-            .expect("get current texture fail");
-        let texture_view = current_texture
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let current_texture = self.surface.as_ref().map(|surface| {
+            surface
+                .get_current_texture()
+                .expect("get current texture fail")
+        });
+        let texture_view = match &current_texture {
+            Some(current_texture) => current_texture
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+            None => self
+                .texture_headless
+                .as_ref()
+                .unwrap()
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+        };
 
         let mut encoder = self
             .device
@@ -152,23 +255,103 @@ impl PipeHub {
 
         self.queue.submit(std::iter::once(encoder.finish()));
 
-        current_texture.present();
+        if let Some(current_texture) = current_texture {
+            current_texture.present();
+        }
+    }
+
+    // only for a core created by new_headless
+    pub fn render_to_image(&mut self) -> RgbaImage {
+        self.render();
+        texture_to_image(
+            &self.device,
+            &self.queue,
+            self.texture_headless
+                .as_ref()
+                .expect("render to image needs headless mode"),
+            &self.surface_config,
+        )
+    }
+
+    pub fn window(&self) -> &Window {
+        self.window.as_ref().expect("no window in headless mode")
     }
 
     fn update(&mut self) {
-        self.window
-            .set_cursor_position(winit::dpi::LogicalPosition::new(
-                self.surface_config.width / 2,
-                self.surface_config.height / 2,
-            ))
-            .unwrap();
+        if let Some(window) = &self.window {
+            window
+                .set_cursor_position(winit::dpi::LogicalPosition::new(
+                    self.surface_config.width / 2,
+                    self.surface_config.height / 2,
+                ))
+                .unwrap();
+        }
 
         // let total_time = (Instant::now() - self.start_time).as_secs_f32();
         let delta_time = (Instant::now() - self.last_time).as_secs_f32();
         self.last_time = Instant::now();
 
+        self.update_delta_time(delta_time);
+    }
+
+    // a fixed delta time keeps headless frames reproducible
+    pub fn update_delta_time(&mut self, delta_time: f32) {
+        let is_cascade_debug_pressed = self.input.is_pressed(VirtualKeyCode::C);
+        if is_cascade_debug_pressed && !self.is_cascade_debug_pressed {
+            self.pipe_shadow.cascade_setting.is_debug ^= 1;
+        }
+        self.is_cascade_debug_pressed = is_cascade_debug_pressed;
+
+        let is_shadow_quality_pressed = self.input.is_pressed(VirtualKeyCode::Q);
+        if is_shadow_quality_pressed && !self.is_shadow_quality_pressed {
+            self.shadow_quality = self.shadow_quality.next();
+            for light_idx in 0..self.pipe_mesh.light_direction_arr.len() {
+                self.pipe_mesh
+                    .set_shadow_quality(&self.queue, light_idx, self.shadow_quality);
+            }
+        }
+        self.is_shadow_quality_pressed = is_shadow_quality_pressed;
+
+        let is_sample_count_pressed = self.input.is_pressed(VirtualKeyCode::M);
+        if is_sample_count_pressed && !self.is_sample_count_pressed {
+            let sample_count_arr =
+                supported_sample_count_arr(&self.adapter, &self.device, self.surface_config.format);
+            let sample_count = sample_count_arr
+                .iter()
+                .find(|&&sample_count| sample_count > self.pipe_mesh.sample_count)
+                .copied()
+                .unwrap_or(1);
+            self.set_sample_count(sample_count);
+        }
+        self.is_sample_count_pressed = is_sample_count_pressed;
+
         self.pipe_mesh
             .update(&mut self.queue, &self.input, delta_time);
+        self.pipe_shadow.update(
+            &self.queue,
+            &self.pipe_mesh.camera,
+            &self.pipe_mesh.light_spot_arr,
+            &self.pipe_mesh.light_point_arr,
+        );
+    }
+
+    // falls back to the highest supported count below sample_count
+    pub fn set_sample_count(&mut self, sample_count: u32) {
+        let sample_count_supported = supported_sample_count(
+            &self.adapter,
+            &self.device,
+            self.surface_config.format,
+            sample_count,
+        );
+        if sample_count_supported != sample_count {
+            warn!(
+                "msaa sample count {} is not supported, falling back to {}",
+                sample_count, sample_count_supported
+            );
+        }
+        self.pipe_mesh
+            .set_sample_count(&self.device, &self.surface_config, sample_count_supported);
+        debug!("msaa sample count {}", self.pipe_mesh.sample_count);
     }
 
     fn cursor_moved(&mut self, x: f32, y: f32) {
@@ -184,26 +367,47 @@ impl PipeHub {
         }
     }
 
-    pub fn add_light_direction(&mut self, light_direction: LightDirection) {
+    pub fn add_light_direction(&mut self, mut light_direction: LightDirection) {
+        light_direction.set_shadow_quality(self.shadow_quality);
         self.pipe_mesh
             .add_light_direction(&mut self.queue, light_direction);
         self.pipe_shadow
-            .set_light_direction(&self.queue, &self.pipe_mesh.light_direction_arr[0]);
+            .set_light_direction(&self.pipe_mesh.light_direction_arr[0]);
+        self.set_shadow_depth();
+    }
+
+    // the light gets its tile in the shadow atlas
+    pub fn add_light_spot(&mut self, mut light_spot: LightSpot) {
+        light_spot.shadow_idx = self.pipe_shadow.add_light_spot(&self.device);
+        self.pipe_mesh.add_light_spot(&mut self.queue, light_spot);
+        self.set_shadow_depth();
+    }
+
+    // the light gets six tiles in the shadow atlas, one per cube face
+    pub fn add_light_point(&mut self, mut light_point: LightPoint) {
+        light_point.shadow_idx = self.pipe_shadow.add_light_point(&self.device);
+        self.pipe_mesh.add_light_point(&mut self.queue, light_point);
+        self.set_shadow_depth();
+    }
+
+    fn set_shadow_depth(&mut self) {
         self.pipe_mesh.set_shadow_depth(
             &self.device,
-            &self.pipe_shadow.buffer_view_proj,
-            &self.pipe_shadow.texture_view_depth,
+            &self.pipe_shadow.buffer_cascade,
+            &self.pipe_shadow.buffer_cascade_setting,
+            &self.pipe_shadow.texture_view_depth(),
+            &self.pipe_shadow.buffer_tile,
         );
     }
 
     pub fn block_loop(event_loop: EventLoop<()>, mut hub: PipeHub) {
         event_loop.run(move |event, _, control_flow| match event {
-            winit::event::Event::RedrawRequested(window_id) if window_id == hub.window.id() => {
+            winit::event::Event::RedrawRequested(window_id) if window_id == hub.window().id() => {
                 hub.update();
                 hub.render();
             }
             winit::event::Event::WindowEvent { window_id, event }
-                if window_id == hub.window.id() =>
+                if window_id == hub.window().id() =>
             {
                 match event {
                     winit::event::WindowEvent::Resized(new_size) => {
@@ -231,7 +435,7 @@ impl PipeHub {
                     _ => {}
                 }
             }
-            winit::event::Event::MainEventsCleared => hub.window.request_redraw(),
+            winit::event::Event::MainEventsCleared => hub.window().request_redraw(),
             winit::event::Event::DeviceEvent {
                 device_id: _,
                 event,
@@ -1,10 +1,9 @@
 use wgpu::{
-    util::DeviceExt, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device, IndexFormat,
-    Queue, RenderPipeline, Sampler, SurfaceConfiguration, TextureView,
+    util::DeviceExt, Backend, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device,
+    IndexFormat, PipelineLayout, Queue, RenderPipeline, Sampler, ShaderModule,
+    SurfaceConfiguration, TextureFormat, TextureView,
 };
 
-pub const SAMPLE_COUNT: u32 = 4;
-
 use crate::{
     camera::Camera,
     input::Input,
@@ -13,21 +12,32 @@ use crate::{
     light_spot::LightSpot,
     material::Material,
     model::DrawMethod,
+    shadow_quality::ShadowQuality,
     texture::{
-        self, gen_sampler_clamp, gen_sampler_repeat, gen_texture_view_depth, gen_texture_view_msaa,
+        self, gen_sampler_clamp, gen_sampler_compare, gen_sampler_nearest, gen_sampler_repeat,
+        gen_texture_view_depth, gen_texture_view_msaa,
     },
     transform::TransformRawIT,
     vertex::Vertex,
 };
 
 pub struct PipeMesh {
+    pub shader_mesh: ShaderModule,
+    pub render_pipline_layout_mesh: PipelineLayout,
     pub render_pipline_mesh: RenderPipeline,
+    // msaa, see set_sample_count
+    pub sample_count: u32,
 
     pub sampler: Sampler,
     pub sampler_repeat: Sampler,
+    // raw depths for the blocker search, and the comparisons for the filter
+    pub sampler_shadow: Sampler,
+    pub sampler_shadow_compare: Sampler,
 
     pub texture_view_depth: TextureView,
-    pub texture_view_msaa: TextureView,
+    // none without msaa
+    pub texture_view_msaa: Option<TextureView>,
+    pub backend: Backend,
 
     pub bind_group_layout_material: BindGroupLayout,
     pub material_arr: Vec<Material>,
@@ -37,7 +47,6 @@ pub struct PipeMesh {
     pub bind_group_camera: BindGroup,
     pub buffer_view_proj: Buffer,
     pub buffer_camera_pos: Buffer,
-    pub buffer_shadow_depth_size: Buffer,
 
     pub light_direction_arr: Vec<LightDirection>,
     pub light_point_arr: Vec<LightPoint>,
@@ -52,71 +61,116 @@ impl PipeMesh {
     pub fn new(
         device: &Device,
         surface_config: &SurfaceConfiguration,
-        buffer_view_proj_light: &Buffer,
+        buffer_cascade: &Buffer,
+        buffer_cascade_setting: &Buffer,
         texture_view_shadow_depth: &TextureView,
-        shadow_depth_size: [u32; 2],
+        buffer_shadow_tile: &Buffer,
+        sample_count: u32,
+        backend: Backend,
     ) -> Self {
-        let mesh_shader = std::fs::read_to_string("assets/shader/mesh.wgsl").unwrap();
+        let mut mesh_shader = std::fs::read_to_string("assets/shader/mesh.wgsl").unwrap();
+        mesh_shader += &std::fs::read_to_string(if backend == Backend::Gl {
+            "assets/shader/shadow_map_blocker_gl.wgsl"
+        } else {
+            "assets/shader/shadow_map_blocker.wgsl"
+        })
+        .unwrap();
         let mesh_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader Mesh"),
             source: wgpu::ShaderSource::Wgsl(mesh_shader.into()),
         });
 
+        let mut entry_layout_arr = vec![
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 4,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Depth,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 5,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 6,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 7,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ];
+        // see shadow_map_blocker_gl.wgsl
+        if backend == Backend::Gl {
+            entry_layout_arr.push(wgpu::BindGroupLayoutEntry {
+                binding: 8,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            });
+        }
         let bind_group_layout_camera =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: Some("Bind Group Layout View Proj"),
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 3,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 4,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Depth,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            multisampled: true,
-                        },
-                        count: None,
-                    },
-                ],
+                entries: &entry_layout_arr,
             });
 
         let bind_group_layout_light_arr =
@@ -210,53 +264,13 @@ impl PipeMesh {
                 push_constant_ranges: &[],
             });
 
-        let render_pipline_mesh = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipline Mesh"),
-            layout: Some(&render_pipline_layout_mesh),
-            vertex: wgpu::VertexState {
-                module: &mesh_shader,
-                entry_point: "vs_main",
-                buffers: &[
-                    Vertex::vertex_buffer_layout(),
-                    TransformRawIT::vertex_buffer_layout(),
-                ],
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: texture::DEPTH_FORMAT,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState {
-                    constant: 2, // Corresponds to bilinear filtering
-                    slope_scale: 2.0,
-                    clamp: 0.0,
-                },
-            }),
-            multisample: wgpu::MultisampleState {
-                count: SAMPLE_COUNT,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &mesh_shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
-        });
+        let render_pipline_mesh = gen_render_pipline_mesh(
+            device,
+            &render_pipline_layout_mesh,
+            &mesh_shader,
+            surface_config.format,
+            sample_count,
+        );
 
         let camera = Camera::new(surface_config.width as _, surface_config.height as _);
         let buffer_view_proj = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -272,46 +286,61 @@ impl PipeMesh {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
-        let buffer_shadow_depth_size =
-            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Buffer Screen Size"),
-                contents: bytemuck::cast_slice(&shadow_depth_size),
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            });
+        let sampler_shadow = gen_sampler_nearest(device);
+        let sampler_shadow_compare = gen_sampler_compare(device);
 
+        let mut entry_arr = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(
+                    buffer_view_proj.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Buffer(
+                    buffer_camera_pos.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer(buffer_cascade.as_entire_buffer_binding()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Buffer(
+                    buffer_cascade_setting.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(&texture_view_shadow_depth),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::Sampler(&sampler_shadow),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: wgpu::BindingResource::Sampler(&sampler_shadow_compare),
+            },
+            wgpu::BindGroupEntry {
+                binding: 7,
+                resource: wgpu::BindingResource::Buffer(
+                    buffer_shadow_tile.as_entire_buffer_binding(),
+                ),
+            },
+        ];
+        if backend == Backend::Gl {
+            entry_arr.push(wgpu::BindGroupEntry {
+                binding: 8,
+                resource: wgpu::BindingResource::TextureView(texture_view_shadow_depth),
+            });
+        }
         let bind_group_camera = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Bind Group Camera"),
             layout: &bind_group_layout_camera,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer(
-                        buffer_view_proj.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Buffer(
-                        buffer_camera_pos.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Buffer(
-                        buffer_view_proj_light.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: wgpu::BindingResource::Buffer(
-                        buffer_shadow_depth_size.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 4,
-                    resource: wgpu::BindingResource::TextureView(&texture_view_shadow_depth),
-                },
-            ],
+            entries: &entry_arr,
         });
 
         let light_direction_arr = vec![];
@@ -379,21 +408,20 @@ impl PipeMesh {
         let sampler = gen_sampler_clamp(&device);
         let sampler_repeat = gen_sampler_repeat(&device);
 
-        let texture_view_depth = gen_texture_view_depth(
-            &device,
-            surface_config.width,
-            surface_config.height,
-            SAMPLE_COUNT,
-        );
-
-        let texture_view_msaa = gen_texture_view_msaa(&device, &surface_config, SAMPLE_COUNT);
+        let (texture_view_depth, texture_view_msaa) =
+            gen_texture_view_attachment(device, surface_config, sample_count, backend);
 
         Self {
+            shader_mesh: mesh_shader,
+            render_pipline_layout_mesh,
             render_pipline_mesh,
+            sample_count,
             camera,
 
             sampler,
             sampler_repeat,
+            sampler_shadow,
+            sampler_shadow_compare,
 
             bind_group_layout_camera,
             bind_group_camera,
@@ -404,9 +432,9 @@ impl PipeMesh {
             buffer_view_proj,
             buffer_camera_pos,
             texture_view_depth,
-            buffer_shadow_depth_size,
 
             texture_view_msaa,
+            backend,
 
             light_direction_arr,
             light_point_arr,
@@ -421,21 +449,38 @@ impl PipeMesh {
         self.camera
             .update_size(surface_config.width as _, surface_config.height as _);
 
-        self.texture_view_depth = gen_texture_view_depth(
+        (self.texture_view_depth, self.texture_view_msaa) =
+            gen_texture_view_attachment(device, surface_config, self.sample_count, self.backend);
+    }
+
+    // sample_count has to be supported, see texture::supported_sample_count
+    pub fn set_sample_count(
+        &mut self,
+        device: &Device,
+        surface_config: &SurfaceConfiguration,
+        sample_count: u32,
+    ) {
+        if sample_count == self.sample_count {
+            return;
+        }
+        self.sample_count = sample_count;
+        self.render_pipline_mesh = gen_render_pipline_mesh(
             device,
-            surface_config.width,
-            surface_config.height,
-            SAMPLE_COUNT,
+            &self.render_pipline_layout_mesh,
+            &self.shader_mesh,
+            surface_config.format,
+            sample_count,
         );
-        self.texture_view_msaa = gen_texture_view_msaa(device, surface_config, SAMPLE_COUNT);
+        (self.texture_view_depth, self.texture_view_msaa) =
+            gen_texture_view_attachment(device, surface_config, sample_count, self.backend);
     }
 
     pub fn render(&mut self, encoder: &mut CommandEncoder, texture_view: &TextureView) {
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Render Pass Mesh"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &self.texture_view_msaa,
-                resolve_target: Some(texture_view),
+                view: self.texture_view_msaa.as_ref().unwrap_or(texture_view),
+                resolve_target: self.texture_view_msaa.as_ref().map(|_| texture_view),
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color {
                         r: 0.1,
@@ -514,6 +559,17 @@ impl PipeMesh {
         );
     }
 
+    pub fn set_shadow_quality(&mut self, queue: &Queue, light_idx: usize, quality: ShadowQuality) {
+        if let Some(light) = self.light_direction_arr.get_mut(light_idx) {
+            light.set_shadow_quality(quality);
+            queue.write_buffer(
+                &self.buffer_light_direction,
+                0,
+                bytemuck::cast_slice(&self.light_direction_arr),
+            );
+        }
+    }
+
     pub fn add_light_point(&mut self, queue: &mut Queue, light: LightPoint) {
         self.light_point_arr.push(light);
         queue.write_buffer(
@@ -535,42 +591,138 @@ impl PipeMesh {
     pub fn set_shadow_depth(
         &mut self,
         device: &Device,
-        buffer_view_proj_light: &Buffer,
+        buffer_cascade: &Buffer,
+        buffer_cascade_setting: &Buffer,
         texture_view_shadow_depth: &TextureView,
+        buffer_shadow_tile: &Buffer,
     ) {
+        let mut entry_arr = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(
+                    self.buffer_view_proj.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Buffer(
+                    self.buffer_camera_pos.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer(buffer_cascade.as_entire_buffer_binding()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Buffer(
+                    buffer_cascade_setting.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(&texture_view_shadow_depth),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::Sampler(&self.sampler_shadow),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: wgpu::BindingResource::Sampler(&self.sampler_shadow_compare),
+            },
+            wgpu::BindGroupEntry {
+                binding: 7,
+                resource: wgpu::BindingResource::Buffer(
+                    buffer_shadow_tile.as_entire_buffer_binding(),
+                ),
+            },
+        ];
+        if self.backend == Backend::Gl {
+            entry_arr.push(wgpu::BindGroupEntry {
+                binding: 8,
+                resource: wgpu::BindingResource::TextureView(texture_view_shadow_depth),
+            });
+        }
         self.bind_group_camera = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Bind Group Camera"),
             layout: &self.bind_group_layout_camera,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer(
-                        self.buffer_view_proj.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Buffer(
-                        self.buffer_camera_pos.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Buffer(
-                        buffer_view_proj_light.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: wgpu::BindingResource::Buffer(
-                        self.buffer_shadow_depth_size.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 4,
-                    resource: wgpu::BindingResource::TextureView(&texture_view_shadow_depth),
-                },
-            ],
+            entries: &entry_arr,
         });
     }
 }
+
+fn gen_render_pipline_mesh(
+    device: &Device,
+    render_pipline_layout: &PipelineLayout,
+    shader: &ShaderModule,
+    format: TextureFormat,
+    sample_count: u32,
+) -> RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipline Mesh"),
+        layout: Some(render_pipline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[
+                Vertex::vertex_buffer_layout(),
+                TransformRawIT::vertex_buffer_layout(),
+            ],
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState {
+                constant: 2, // Corresponds to bilinear filtering
+                slope_scale: 2.0,
+                clamp: 0.0,
+            },
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    })
+}
+
+// depth and, with msaa, the multisampled color target
+fn gen_texture_view_attachment(
+    device: &Device,
+    surface_config: &SurfaceConfiguration,
+    sample_count: u32,
+    backend: Backend,
+) -> (TextureView, Option<TextureView>) {
+    let texture_view_depth = gen_texture_view_depth(
+        device,
+        surface_config.width,
+        surface_config.height,
+        sample_count,
+        backend,
+    );
+    let texture_view_msaa = (sample_count > 1)
+        .then(|| gen_texture_view_msaa(device, surface_config, sample_count, backend));
+    (texture_view_depth, texture_view_msaa)
+}
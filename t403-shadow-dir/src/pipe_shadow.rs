@@ -1,48 +1,85 @@
 use glam::{Mat4, Vec3};
 use wgpu::{
     util::DeviceExt, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device, IndexFormat,
-    Queue, RenderPipeline, TextureView,
+    Queue, RenderPipeline, Texture, TextureView,
 };
 
 use crate::{
+    camera::Camera,
+    cascade::{Cascade, CascadeSetting, MAX_CASCADE},
     light_direction::LightDirection,
+    light_point::LightPoint,
+    light_spot::LightSpot,
     material::Material,
     model::DrawMethod,
-    pipe_mesh::SAMPLE_COUNT,
-    texture::{gen_texture_view_depth, DEPTH_FORMAT},
+    shadow_atlas::{ShadowAtlas, ShadowTile},
+    texture::{gen_texture_depth_arr, DEPTH_FORMAT},
     transform::TransformRawIT,
     vertex::Vertex,
 };
 
-// const SAMPLE_COUNT: u32 = 1;
+const SAMPLE_COUNT: u32 = 1;
+const LIGHT_NEAR: f32 = 0.1;
+// texels the spot and point frusta reach past their edges, for the filter near a cube seam
+const TILE_BORDER: f32 = 3.0;
 
 pub struct PipeShadow {
     pub render_pipeline: RenderPipeline,
-    pub texture_view_depth: TextureView,
+    // every shadow of every light is a tile of this one depth texture
+    pub texture_depth: Texture,
+    pub atlas: ShadowAtlas,
     pub bind_group_layout: BindGroupLayout,
+    // the tile of every draw, a dynamic offset apart
+    pub buffer_tile_idx: Buffer,
+    pub tile_idx_stride: u32,
     pub bind_group: BindGroup,
-    pub buffer_view_proj: Buffer,
-    pub proj: Mat4,
-    pub width: u32,
-    pub height: u32,
+    // the cascades first, MAX_CASCADE of them whatever cascade_num is, then a tile per spot
+    // light and six per point light in the order they were added
+    pub tile_arr: Vec<ShadowTile>,
+    pub buffer_tile: Buffer,
+    pub cascade_arr: Vec<Cascade>,
+    pub buffer_cascade: Buffer,
+    pub cascade_setting: CascadeSetting,
+    pub buffer_cascade_setting: Buffer,
+    pub light_dir: Option<Vec3>,
+    pub cascade_num: u32,
+    pub split_lambda: f32,
+    pub shadow_distance: f32,
+    pub z_margin: f32,
+    // the far plane of the spot lights, and of the point lights that reach further
+    pub light_far: f32,
 }
 
 impl PipeShadow {
-    pub fn new(device: &Device, width: u32, height: u32) -> Self {
-        let texture_view_depth = gen_texture_view_depth(device, width, height, SAMPLE_COUNT);
+    pub fn new(device: &Device, atlas_size: u32, cascade_num: u32) -> Self {
+        let cascade_num = cascade_num.clamp(1, MAX_CASCADE);
+        let texture_depth = gen_texture_depth_arr(device, atlas_size, atlas_size, 1);
+        let atlas = ShadowAtlas::new(atlas_size, atlas_size / 32, atlas_size / 2);
 
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Bind Group Layout Shadow"),
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(4),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                count: None,
-            }],
+            ],
         });
 
         let render_pipeline_layout =
@@ -97,43 +134,57 @@ impl PipeShadow {
             multiview: None,
         });
 
-        let view = Mat4::look_to_rh(Vec3::ZERO, Vec3::ZERO, Vec3::Y);
-        let proj_size = 20.0;
-        let proj = Mat4::orthographic_rh(-proj_size, proj_size, -proj_size, proj_size, 0.1, 70.0);
-        let buffer_view_proj = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Buffer Shadow View Proj"),
-            contents: bytemuck::cast_slice(&(proj.mul_mat4(&view)).to_cols_array_2d()),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        let tile_arr = vec![ShadowTile::zero(); MAX_CASCADE as usize];
+        let buffer_tile = gen_buffer_tile(device, &tile_arr);
+        let tile_idx_stride = device.limits().min_uniform_buffer_offset_alignment;
+        let buffer_tile_idx = gen_buffer_tile_idx(device, tile_idx_stride, tile_arr.len() as u32);
+        let bind_group = gen_bind_group(device, &bind_group_layout, &buffer_tile_idx, &buffer_tile);
+
+        let cascade_arr = vec![Cascade::zero(); cascade_num as usize];
+        let buffer_cascade = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Buffer Shadow Cascade"),
+            contents: bytemuck::cast_slice(&cascade_arr),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
         });
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Bind Group Shadow"),
-            layout: &bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: wgpu::BindingResource::Buffer(
-                    buffer_view_proj.as_entire_buffer_binding(),
-                ),
-            }],
+
+        let cascade_setting = CascadeSetting::new([atlas_size, atlas_size], cascade_num, 0.1);
+        let buffer_cascade_setting = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Buffer Shadow Cascade Setting"),
+            contents: bytemuck::cast_slice(&[cascade_setting]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
         Self {
             render_pipeline,
-            texture_view_depth,
+            texture_depth,
+            atlas,
             bind_group_layout,
+            buffer_tile_idx,
+            tile_idx_stride,
             bind_group,
-            buffer_view_proj,
-            proj,
-            width,
-            height,
+            tile_arr,
+            buffer_tile,
+            cascade_arr,
+            buffer_cascade,
+            cascade_setting,
+            buffer_cascade_setting,
+            light_dir: None,
+            cascade_num,
+            split_lambda: 0.75,
+            shadow_distance: 60.0,
+            z_margin: 20.0,
+            light_far: 30.0,
         }
     }
 
+    // a single pass over the whole atlas, every tile is a viewport of its own
     pub fn render(&mut self, encoder: &mut CommandEncoder, material_arr: &Vec<Material>) {
+        let texture_view_depth = self.texture_view_depth();
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Render Pass Shadow"),
             color_attachments: &[],
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &self.texture_view_depth,
+                view: &texture_view_depth,
                 depth_ops: Some(wgpu::Operations {
                     load: wgpu::LoadOp::Clear(1.0),
                     store: true,
@@ -141,38 +192,297 @@ impl PipeShadow {
                 stencil_ops: None,
             }),
         });
-
         render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_bind_group(0, &self.bind_group, &[]);
-
-        for material in material_arr {
-            // render model
-            for model in &material.model_arr {
-                if model.draw_method == DrawMethod::Vertex {
-                    render_pass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
-                    render_pass.set_vertex_buffer(1, model.transform_buffer.slice(..));
-                    render_pass.draw(0..model.vertices_len, 0..model.instance_num);
-                } else {
-                    render_pass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
-                    render_pass.set_vertex_buffer(1, model.transform_buffer.slice(..));
-                    render_pass.set_index_buffer(model.index_buffer.slice(..), IndexFormat::Uint32);
-                    render_pass.draw_indexed(0..model.indices_len, 0, 0..model.instance_num);
+
+        let size = self.atlas.size as f32;
+        for (idx, tile) in self.tile_arr.iter().enumerate() {
+            let [x, y, width, height] = tile.rect.map(|v| v * size);
+            if width == 0.0 {
+                continue;
+            }
+            render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+            render_pass.set_scissor_rect(x as u32, y as u32, width as u32, height as u32);
+            render_pass.set_bind_group(0, &self.bind_group, &[idx as u32 * self.tile_idx_stride]);
+
+            for material in material_arr {
+                // render model
+                for model in &material.model_arr {
+                    if model.draw_method == DrawMethod::Vertex {
+                        render_pass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
+                        render_pass.set_vertex_buffer(1, model.transform_buffer.slice(..));
+                        render_pass.draw(0..model.vertices_len, 0..model.instance_num);
+                    } else {
+                        render_pass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
+                        render_pass.set_vertex_buffer(1, model.transform_buffer.slice(..));
+                        render_pass
+                            .set_index_buffer(model.index_buffer.slice(..), IndexFormat::Uint32);
+                        render_pass.draw_indexed(0..model.indices_len, 0, 0..model.instance_num);
+                    }
                 }
             }
         }
     }
 
-    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
-        self.texture_view_depth = gen_texture_view_depth(device, width, height, SAMPLE_COUNT);
-    }
+    // size every tile by how much the light matters to this frame, pack the atlas again and
+    // fit the cascades to their slices of the camera frustum
+    pub fn update(
+        &mut self,
+        queue: &Queue,
+        camera: &Camera,
+        light_spot_arr: &[LightSpot],
+        light_point_arr: &[LightPoint],
+    ) {
+        let mut importance_arr = vec![0.0; self.tile_arr.len()];
+        if self.light_dir.is_some() {
+            importance_arr[..self.cascade_num as usize].fill(1.0);
+        }
+        for light_spot in light_spot_arr.iter().filter(|l| l.shadow_idx >= 0) {
+            // the sphere around the middle of the cone
+            let front = Vec3::from(light_spot.front).normalize();
+            let center = Vec3::from(light_spot.pos) + front * self.light_far / 2.0;
+            importance_arr[light_spot.shadow_idx as usize] =
+                importance(camera, center, self.light_far / 2.0);
+        }
+        for light_point in light_point_arr.iter().filter(|l| l.shadow_idx >= 0) {
+            let radius = light_point.radius().min(self.light_far);
+            let idx = light_point.shadow_idx as usize;
+            importance_arr[idx..idx + 6].fill(importance(camera, light_point.pos.into(), radius));
+        }
+        let tile_size_arr: Vec<u32> = importance_arr
+            .iter()
+            .map(|importance| self.atlas.tile_size(*importance))
+            .collect();
+        // a point light without all six faces would sample an empty tile
+        let group_arr: Vec<_> = light_point_arr
+            .iter()
+            .filter(|l| l.shadow_idx >= 0)
+            .map(|l| l.shadow_idx as usize..l.shadow_idx as usize + 6)
+            .collect();
+        let rect_arr = self.atlas.allocate(&tile_size_arr, &group_arr);
+
+        if let Some(light_dir) = self.light_dir {
+            let near = camera.z_near();
+            let far = self.shadow_distance.min(camera.z_far());
+            let split_arr = Cascade::split_arr(near, far, self.cascade_num, self.split_lambda);
 
-    pub fn set_light_direction(&mut self, queue: &Queue, light_direction: &LightDirection) {
-        let dir: Vec3 = light_direction.dir.into();
-        let view = Mat4::look_to_rh(-dir * 15.0, dir, Vec3::Y);
+            let mut split_near = near;
+            for (idx, (cascade, split_far)) in
+                self.cascade_arr.iter_mut().zip(split_arr).enumerate()
+            {
+                let rect = rect_arr[idx];
+                *cascade = Cascade::new(
+                    camera,
+                    light_dir,
+                    split_near,
+                    split_far,
+                    rect[2].max(1),
+                    self.z_margin,
+                );
+                self.tile_arr[idx] = ShadowTile::new(
+                    Mat4::from_cols_array_2d(&cascade.view_proj),
+                    rect,
+                    self.atlas.size,
+                    0.0,
+                );
+                split_near = split_far;
+            }
+
+            queue.write_buffer(
+                &self.buffer_cascade,
+                0,
+                bytemuck::cast_slice(&self.cascade_arr),
+            );
+        }
+
+        for light_spot in light_spot_arr.iter().filter(|l| l.shadow_idx >= 0) {
+            let idx = light_spot.shadow_idx as usize;
+            // the cone just fits the frustum
+            let tan_half_fov =
+                light_spot.out_cutoff.clamp(0.1, 1.0).acos().tan() * border_scale(rect_arr[idx][2]);
+            let proj =
+                Mat4::perspective_rh(tan_half_fov.atan() * 2.0, 1.0, LIGHT_NEAR, self.light_far);
+            let view = gen_view(light_spot.pos.into(), light_spot.front.into());
+            self.tile_arr[idx] = ShadowTile::new(
+                proj.mul_mat4(&view),
+                rect_arr[idx],
+                self.atlas.size,
+                tan_half_fov,
+            );
+        }
+        for light_point in light_point_arr.iter().filter(|l| l.shadow_idx >= 0) {
+            let idx = light_point.shadow_idx as usize;
+            let far = light_point.radius().min(self.light_far);
+            for (face, view) in gen_view_cube_arr(light_point.pos.into()).iter().enumerate() {
+                let rect = rect_arr[idx + face];
+                // a little over 90 degrees, the faces overlap at the seams
+                let tan_half_fov = border_scale(rect[2]);
+                let proj = Mat4::perspective_rh(tan_half_fov.atan() * 2.0, 1.0, LIGHT_NEAR, far);
+                self.tile_arr[idx + face] =
+                    ShadowTile::new(proj.mul_mat4(view), rect, self.atlas.size, tan_half_fov);
+            }
+        }
+
+        queue.write_buffer(&self.buffer_tile, 0, bytemuck::cast_slice(&self.tile_arr));
         queue.write_buffer(
-            &self.buffer_view_proj,
+            &self.buffer_cascade_setting,
             0,
-            bytemuck::cast_slice(&(self.proj.mul_mat4(&view)).to_cols_array_2d()),
+            bytemuck::cast_slice(&[self.cascade_setting]),
+        );
+    }
+
+    // the size of the atlas, pipe_mesh needs the new texture_view_depth afterwards
+    pub fn resize(&mut self, device: &Device, atlas_size: u32) {
+        self.atlas = ShadowAtlas::new(atlas_size, atlas_size / 32, atlas_size / 2);
+        self.cascade_setting.shadow_map_size = [atlas_size, atlas_size];
+        self.texture_depth = gen_texture_depth_arr(device, atlas_size, atlas_size, 1);
+    }
+
+    pub fn set_cascade_num(&mut self, device: &Device, cascade_num: u32) {
+        self.cascade_num = cascade_num.clamp(1, MAX_CASCADE);
+        self.cascade_setting.cascade_num = self.cascade_num;
+        self.cascade_arr = vec![Cascade::zero(); self.cascade_num as usize];
+        // the tiles past cascade_num keep their slot but get no space
+        self.tile_arr[..MAX_CASCADE as usize].fill(ShadowTile::zero());
+
+        self.buffer_cascade = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Buffer Shadow Cascade"),
+            contents: bytemuck::cast_slice(&self.cascade_arr),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+    }
+
+    pub fn set_light_direction(&mut self, light_direction: &LightDirection) {
+        self.light_dir = Some(light_direction.dir.into());
+    }
+
+    // return the shadow index of the light, the tile for its cone
+    pub fn add_light_spot(&mut self, device: &Device) -> i32 {
+        self.add_tile(device, 1)
+    }
+
+    // return the shadow index of the light, the first of its six cube face tiles
+    pub fn add_light_point(&mut self, device: &Device) -> i32 {
+        self.add_tile(device, 6)
+    }
+
+    // pipe_mesh needs the new buffer_tile afterwards
+    fn add_tile(&mut self, device: &Device, tile_num: usize) -> i32 {
+        let shadow_idx = self.tile_arr.len() as i32;
+        self.tile_arr
+            .resize(self.tile_arr.len() + tile_num, ShadowTile::zero());
+
+        self.buffer_tile = gen_buffer_tile(device, &self.tile_arr);
+        self.buffer_tile_idx =
+            gen_buffer_tile_idx(device, self.tile_idx_stride, self.tile_arr.len() as u32);
+        self.bind_group = gen_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.buffer_tile_idx,
+            &self.buffer_tile,
         );
+        shadow_idx
+    }
+
+    pub fn texture_view_depth(&self) -> TextureView {
+        self.texture_depth
+            .create_view(&wgpu::TextureViewDescriptor {
+                label: Some("Texture View Shadow Atlas"),
+                format: Some(DEPTH_FORMAT),
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                aspect: wgpu::TextureAspect::DepthOnly,
+                ..Default::default()
+            })
+    }
+}
+
+// how much of the screen a light's sphere covers, 1 with the camera inside of it
+fn importance(camera: &Camera, center: Vec3, radius: f32) -> f32 {
+    let to_center = center - camera.pos;
+    let distance = to_center.length();
+    if distance <= radius {
+        return 1.0;
+    }
+    // all of it behind the camera
+    if to_center.dot(camera.front.normalize()) < -radius {
+        return 0.0;
+    }
+    (radius / (distance * (camera.fov_radian() / 2.0).tan())).min(1.0)
+}
+
+// how much wider the tangent of a frustum gets with TILE_BORDER texels on every side
+fn border_scale(tile_size: u32) -> f32 {
+    let size = tile_size as f32;
+    if size <= TILE_BORDER * 4.0 {
+        return 1.0;
+    }
+    size / (size - TILE_BORDER * 2.0)
+}
+
+fn gen_view(pos: Vec3, front: Vec3) -> Mat4 {
+    let up = if front.cross(Vec3::Y).length_squared() < 1e-6 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    };
+    Mat4::look_to_rh(pos, front, up)
+}
+
+// cube map order, right left up bottom back front
+fn gen_view_cube_arr(pos: Vec3) -> [Mat4; 6] {
+    [
+        Mat4::look_to_rh(pos, Vec3::X, Vec3::Y),
+        Mat4::look_to_rh(pos, Vec3::NEG_X, Vec3::Y),
+        Mat4::look_to_rh(pos, Vec3::Y, Vec3::Z),
+        Mat4::look_to_rh(pos, Vec3::NEG_Y, Vec3::NEG_Z),
+        Mat4::look_to_rh(pos, Vec3::NEG_Z, Vec3::Y),
+        Mat4::look_to_rh(pos, Vec3::Z, Vec3::Y),
+    ]
+}
+
+fn gen_buffer_tile(device: &Device, tile_arr: &[ShadowTile]) -> Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Buffer Shadow Tile"),
+        contents: bytemuck::cast_slice(tile_arr),
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+// the index of every tile, each at its own dynamic offset
+fn gen_buffer_tile_idx(device: &Device, stride: u32, tile_num: u32) -> Buffer {
+    let stride = (stride / 4) as usize;
+    let mut tile_idx_arr = vec![0u32; stride * tile_num as usize];
+    for tile_idx in 0..tile_num {
+        tile_idx_arr[tile_idx as usize * stride] = tile_idx;
     }
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Buffer Shadow Tile Idx"),
+        contents: bytemuck::cast_slice(&tile_idx_arr),
+        usage: wgpu::BufferUsages::UNIFORM,
+    })
+}
+
+fn gen_bind_group(
+    device: &Device,
+    bind_group_layout: &BindGroupLayout,
+    buffer_tile_idx: &Buffer,
+    buffer_tile: &Buffer,
+) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Bind Group Shadow Tile"),
+        layout: bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: buffer_tile_idx,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(4),
+                }),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Buffer(buffer_tile.as_entire_buffer_binding()),
+            },
+        ],
+    })
 }
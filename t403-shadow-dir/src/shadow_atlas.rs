@@ -0,0 +1,231 @@
+use std::ops::Range;
+
+use glam::Mat4;
+
+// one square region of the atlas, a cascade, a spot light or a point light cube face
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ShadowTile {
+    pub view_proj: [[f32; 4]; 4],
+
+    // offset and size in atlas uv, an empty tile casts no shadow
+    pub rect: [f32; 4],
+
+    // 0 for the orthographic cascades, scales the normal offset with the distance to the light
+    pub tan_half_fov: f32,
+    // 16 bytes padding
+    _padding0: [u32; 3],
+}
+
+impl ShadowTile {
+    pub fn new(view_proj: Mat4, rect: [u32; 4], atlas_size: u32, tan_half_fov: f32) -> Self {
+        let size = atlas_size as f32;
+        Self {
+            view_proj: view_proj.to_cols_array_2d(),
+            rect: rect.map(|v| v as f32 / size),
+            tan_half_fov,
+            _padding0: [0; 3],
+        }
+    }
+
+    pub fn zero() -> Self {
+        Self {
+            view_proj: Mat4::IDENTITY.to_cols_array_2d(),
+            rect: [0.0, 0.0, 0.0, 0.0],
+            tan_half_fov: 0.0,
+            _padding0: [0; 3],
+        }
+    }
+}
+
+// square tiles of power of two sizes in a single depth texture, packed again every frame
+pub struct ShadowAtlas {
+    pub size: u32,
+    pub tile_size_min: u32,
+    pub tile_size_max: u32,
+}
+
+impl ShadowAtlas {
+    // the tile bounds are rounded to powers of two inside the atlas, min up and max down
+    pub fn new(size: u32, tile_size_min: u32, tile_size_max: u32) -> Self {
+        assert!(
+            size.is_power_of_two(),
+            "shadow atlas size {} is not a power of two",
+            size
+        );
+        let tile_size_max = 1 << tile_size_max.clamp(1, size).ilog2();
+        let tile_size_min = tile_size_min.max(1).next_power_of_two().min(tile_size_max);
+        Self {
+            size,
+            tile_size_min,
+            tile_size_max,
+        }
+    }
+
+    // importance between 0 and 1, about the part of the screen the light reaches. 0 is no tile
+    pub fn tile_size(&self, importance: f32) -> u32 {
+        if importance <= 0.0 {
+            return 0;
+        }
+        let size = (importance * self.tile_size_max as f32) as u32;
+        size.next_power_of_two()
+            .clamp(self.tile_size_min, self.tile_size_max)
+    }
+
+    // texel rects x y width height in the order of tile_size_arr. the largest tiles are halved
+    // until everything fits, what still doesn't fit at the smallest size gets an empty rect.
+    // each range of group_arr, the six faces of a point light, gets space all together or none
+    pub fn allocate(&self, tile_size_arr: &[u32], group_arr: &[Range<usize>]) -> Vec<[u32; 4]> {
+        let area = |size_arr: &[u32]| size_arr.iter().map(|s| (*s as u64).pow(2)).sum::<u64>();
+        let mut size_arr: Vec<u32> = tile_size_arr
+            .iter()
+            .map(|s| match s {
+                0 => 0,
+                s => s
+                    .next_power_of_two()
+                    .clamp(self.tile_size_min, self.tile_size_max),
+            })
+            .collect();
+        // a group shares one size, halving keeps it that way
+        for group in group_arr.iter().filter(|g| !g.is_empty()) {
+            let size = size_arr[group.clone()].iter().copied().max().unwrap_or(0);
+            size_arr[group.clone()].fill(size);
+        }
+        loop {
+            let size_max = size_arr.iter().copied().max().unwrap_or(0);
+            if area(&size_arr) <= (self.size as u64).pow(2) || size_max <= self.tile_size_min {
+                break;
+            }
+            for size in size_arr.iter_mut().filter(|s| **s == size_max) {
+                *size /= 2;
+            }
+        }
+
+        // a unit is a group or a single tile outside of every group
+        let mut is_grouped = vec![false; size_arr.len()];
+        let mut unit_arr: Vec<Range<usize>> = vec![];
+        for group in group_arr.iter().filter(|g| !g.is_empty()) {
+            is_grouped[group.clone()].fill(true);
+            unit_arr.push(group.clone());
+        }
+        unit_arr.extend(
+            (0..size_arr.len())
+                .filter(|idx| !is_grouped[*idx])
+                .map(|idx| idx..idx + 1),
+        );
+
+        // largest first along the z-order curve, so every tile starts on a multiple of its size
+        unit_arr.sort_by_key(|unit| (std::cmp::Reverse(size_arr[unit.start]), unit.start));
+        let cell_num = (self.size / self.tile_size_min) as u64;
+        let mut cell_used = 0;
+        let mut rect_arr = vec![[0; 4]; size_arr.len()];
+        for unit in unit_arr {
+            let size = size_arr[unit.start];
+            let cell = (size / self.tile_size_min) as u64;
+            if size == 0 || cell_used + cell * cell * unit.len() as u64 > cell_num * cell_num {
+                continue;
+            }
+            for idx in unit {
+                let (x, y) = morton_decode(cell_used);
+                rect_arr[idx] = [x * self.tile_size_min, y * self.tile_size_min, size, size];
+                cell_used += cell * cell;
+            }
+        }
+        rect_arr
+    }
+}
+
+// the even bits are x, the odd ones y
+fn morton_decode(idx: u64) -> (u32, u32) {
+    let compact = |mut v: u64| {
+        v &= 0x5555_5555_5555_5555;
+        v = (v | (v >> 1)) & 0x3333_3333_3333_3333;
+        v = (v | (v >> 2)) & 0x0f0f_0f0f_0f0f_0f0f;
+        v = (v | (v >> 4)) & 0x00ff_00ff_00ff_00ff;
+        v = (v | (v >> 8)) & 0x0000_ffff_0000_ffff;
+        v = (v | (v >> 16)) & 0x0000_0000_ffff_ffff;
+        v as u32
+    };
+    (compact(idx), compact(idx >> 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_overlap(a: [u32; 4], b: [u32; 4]) -> bool {
+        a[0] < b[0] + b[2] && b[0] < a[0] + a[2] && a[1] < b[1] + b[3] && b[1] < a[1] + a[3]
+    }
+
+    #[test]
+    #[should_panic]
+    fn new_rejects_atlas_size_not_power_of_two() {
+        ShadowAtlas::new(1000, 32, 500);
+    }
+
+    #[test]
+    fn tile_size_power_of_two() {
+        let atlas = ShadowAtlas::new(1024, 24, 600);
+        assert_eq!(atlas.tile_size_min, 32);
+        assert_eq!(atlas.tile_size_max, 512);
+        for importance in [0.01, 0.1, 0.3, 0.7, 1.0] {
+            assert!(atlas.tile_size(importance).is_power_of_two());
+        }
+        assert_eq!(atlas.tile_size(0.0), 0);
+    }
+
+    #[test]
+    fn allocate_no_overlap_in_bounds() {
+        let atlas = ShadowAtlas::new(1024, 32, 512);
+        let tile_size_arr = [512, 64, 256, 32, 0, 128, 256, 32, 64, 128, 32, 512];
+        let rect_arr = atlas.allocate(&tile_size_arr, &[]);
+
+        for (idx, rect) in rect_arr.iter().enumerate() {
+            assert!(rect[0] + rect[2] <= atlas.size && rect[1] + rect[3] <= atlas.size);
+            assert_eq!(rect[0] % rect[2].max(1), 0);
+            assert_eq!(rect[1] % rect[3].max(1), 0);
+            for other in &rect_arr[idx + 1..] {
+                assert!(
+                    !is_overlap(*rect, *other),
+                    "{:?} overlaps {:?}",
+                    rect,
+                    other
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn allocate_halves_largest_first() {
+        let atlas = ShadowAtlas::new(64, 8, 64);
+        // 64² + 16² + 8² is over budget, only the 64 tile is halved
+        let rect_arr = atlas.allocate(&[16, 64, 8], &[]);
+        let width_arr: Vec<u32> = rect_arr.iter().map(|r| r[2]).collect();
+        assert_eq!(width_arr, vec![16, 32, 8]);
+    }
+
+    #[test]
+    fn allocate_empty_rect_once_full() {
+        let atlas = ShadowAtlas::new(64, 16, 16);
+        // room for 16 tiles of the smallest size
+        let rect_arr = atlas.allocate(&[16; 20], &[]);
+        assert!(rect_arr[..16].iter().all(|r| r[2] == 16));
+        assert!(rect_arr[16..].iter().all(|r| *r == [0; 4]));
+    }
+
+    #[test]
+    fn allocate_group_all_or_none() {
+        let atlas = ShadowAtlas::new(64, 16, 16);
+        // 12 tiles leave 4 cells, not enough for the 6 faces, the tile after them still fits
+        let face_range = 12..18;
+        let rect_arr = atlas.allocate(&[16; 19], &[face_range]);
+        assert!(rect_arr[..12].iter().all(|r| r[2] == 16));
+        assert!(rect_arr[12..18].iter().all(|r| *r == [0; 4]));
+        assert_eq!(rect_arr[18][2], 16);
+
+        // with the room for them the faces all get a tile
+        let face_range = 2..8;
+        let rect_arr = atlas.allocate(&[16; 10], &[face_range]);
+        assert!(rect_arr.iter().all(|r| r[2] == 16));
+    }
+}
@@ -40,6 +40,21 @@ pub fn gen_sampler_nearest(device: &wgpu::Device) -> wgpu::Sampler {
     })
 }
 
+// hardware pcf, linear filtering blends the four nearest comparisons
+pub fn gen_sampler_compare(device: &wgpu::Device) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("Texture Sampler Compare"),
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        compare: Some(wgpu::CompareFunction::LessEqual),
+        ..Default::default()
+    })
+}
+
 pub fn gen_texture_view(
     img_bytes: Vec<u8>,
     device: &wgpu::Device,
@@ -91,6 +106,7 @@ pub fn gen_texture_view_depth(
     width: u32,
     height: u32,
     sample_count: u32,
+    backend: wgpu::Backend,
 ) -> wgpu::TextureView {
     device
         .create_texture(&wgpu::TextureDescriptor {
@@ -104,16 +120,39 @@ pub fn gen_texture_view_depth(
             sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: DEPTH_FORMAT,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage: gen_usage_attachment(sample_count, backend),
             view_formats: &[],
         })
         .create_view(&wgpu::TextureViewDescriptor::default())
 }
 
+pub fn gen_texture_depth_arr(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    layer_num: u32,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Texture Depth Array"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: layer_num,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    })
+}
+
 pub fn gen_texture_view_msaa(
     device: &wgpu::Device,
     surface_config: &wgpu::SurfaceConfiguration,
     sample_count: u32,
+    backend: wgpu::Backend,
 ) -> wgpu::TextureView {
     device
         .create_texture(&wgpu::TextureDescriptor {
@@ -127,7 +166,7 @@ pub fn gen_texture_view_msaa(
             sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: surface_config.format,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage: gen_usage_attachment(sample_count, backend),
             view_formats: &[],
         })
         .create_view(&wgpu::TextureViewDescriptor::default())
@@ -176,3 +215,60 @@ pub fn gen_texture_view_post_processing(
         })
         .create_view(&wgpu::TextureViewDescriptor::default())
 }
+
+// msaa sample counts the color format and DEPTH_FORMAT can both be rendered and resolved with,
+// ascending and always starting with 1
+pub fn supported_sample_count_arr(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+) -> Vec<u32> {
+    // the device only trusts the adapter flags with this feature or on a downlevel backend,
+    // otherwise it validates against the counts webgpu guarantees
+    let is_adapter_flags = device
+        .features()
+        .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+        || !adapter.get_downlevel_capabilities().is_webgpu_compliant();
+    let flags = |format: wgpu::TextureFormat| {
+        if is_adapter_flags {
+            adapter.get_texture_format_features(format).flags
+        } else {
+            format.guaranteed_format_features(device.features()).flags
+        }
+    };
+    let (flags_color, flags_depth) = (flags(format), flags(DEPTH_FORMAT));
+
+    [1, 2, 4, 8, 16]
+        .into_iter()
+        .filter(|&sample_count| {
+            sample_count == 1
+                || (flags_color.sample_count_supported(sample_count)
+                    && flags_color.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE)
+                    && flags_depth.sample_count_supported(sample_count))
+        })
+        .collect()
+}
+
+// sample_count when supported, otherwise the highest supported count below it
+pub fn supported_sample_count(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> u32 {
+    supported_sample_count_arr(adapter, device, format)
+        .into_iter()
+        .rev()
+        .find(|&supported| supported <= sample_count)
+        .unwrap_or(1)
+}
+
+// wgpu's GL backend makes a render only multisampled texture a renderbuffer, a bindable one gets
+// glTexStorage2DMultisample on GL_TEXTURE_2D, GL rejects that target and the attachment stays empty
+fn gen_usage_attachment(sample_count: u32, backend: wgpu::Backend) -> wgpu::TextureUsages {
+    if sample_count > 1 && backend == wgpu::Backend::Gl {
+        wgpu::TextureUsages::RENDER_ATTACHMENT
+    } else {
+        wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT
+    }
+}
@@ -0,0 +1,182 @@
+struct LightPoint {
+    pos: vec3<f32>,
+    shadow_idx: i32,
+    color: vec4<f32>,
+    ambient: vec3<f32>,
+    constant: f32,
+    diffuse: vec3<f32>,
+    linear: f32,
+    specular: vec3<f32>,
+    quadratic: f32,
+}
+
+struct LightPointArray {
+    arr: array<LightPoint>,
+}
+
+struct LightSpot {
+    pos: vec3<f32>,
+    front: vec3<f32>,
+    color: vec4<f32>,
+    ambient: vec3<f32>,
+    diffuse: vec3<f32>,
+    in_cutoff: f32,
+    specular: vec3<f32>,
+    out_cutoff: f32,
+}
+
+struct LightSpotArray {
+    arr: array<LightSpot>,
+}
+
+struct ClusterParam {
+    view: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+    spot_ambient: vec3<f32>,
+    is_clustered: u32,
+    grid_size: vec3<u32>,
+    z_near: f32,
+    screen_size: vec2<f32>,
+    z_far: f32,
+    max_light_per_cluster: u32,
+}
+
+// view space box of a froxel
+struct Aabb {
+    min: vec3<f32>,
+    max: vec3<f32>,
+}
+
+
+@group(0)@binding(0)
+var<uniform> param: ClusterParam;
+// per cluster: point count, spot count, point indices, spot indices
+@group(0)@binding(1)
+var<storage, read_write> cluster_light_arr: array<u32>;
+@group(0)@binding(2)
+var<storage> light_point_arr: LightPointArray;
+@group(0)@binding(3)
+var<storage> light_spot_arr: LightSpotArray;
+
+
+// one invocation per cluster
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let grid = param.grid_size;
+    let cluster_idx = id.x;
+    if cluster_idx >= grid.x * grid.y * grid.z {
+        return;
+    }
+
+    let cell = vec3<u32>(cluster_idx % grid.x, (cluster_idx / grid.x) % grid.y, cluster_idx / (grid.x * grid.y));
+    let aabb = get_cluster_aabb(cell);
+    let offset = cluster_idx * (param.max_light_per_cluster + 2u);
+
+    var num = 0u;
+    for (var i: u32 = 0u; i < arrayLength(&light_point_arr.arr) && num < param.max_light_per_cluster; i = i + 1u) {
+        let light_point = light_point_arr.arr[i];
+        if light_point.color.a == 0.0 {
+            continue;
+        }
+        let pos = (param.view * vec4<f32>(light_point.pos, 1.0)).xyz;
+        if is_sphere_in_aabb(pos, get_light_point_radius(light_point), aabb) {
+            cluster_light_arr[offset + 2u + num] = i;
+            num += 1u;
+        }
+    }
+    let point_num = num;
+
+    // cone against the sphere around the froxel, spot lights don't fade with distance
+    let center = (aabb.min + aabb.max) * 0.5;
+    let radius = length(aabb.max - center);
+    for (var i: u32 = 0u; i < arrayLength(&light_spot_arr.arr) && num < param.max_light_per_cluster; i = i + 1u) {
+        let light_spot = light_spot_arr.arr[i];
+        if light_spot.color.a == 0.0 {
+            continue;
+        }
+        let pos = (param.view * vec4<f32>(light_spot.pos, 1.0)).xyz;
+        let front = normalize((param.view * vec4<f32>(light_spot.front, 0.0)).xyz);
+        if is_sphere_in_cone(center, radius, pos, front, light_spot.out_cutoff) {
+            cluster_light_arr[offset + 2u + num] = i;
+            num += 1u;
+        }
+    }
+
+    cluster_light_arr[offset] = point_num;
+    cluster_light_arr[offset + 1u] = num - point_num;
+}
+
+// depth slices grow exponentially, like the perspective divide shrinks the detail
+fn get_slice_depth(slice: u32) -> f32 {
+    let t = f32(slice) / f32(param.grid_size.z);
+    return param.z_near * pow(param.z_far / param.z_near, t);
+}
+
+// view space point on the far plane behind a ndc position
+fn get_far_point(ndc: vec2<f32>) -> vec3<f32> {
+    let p = param.inv_proj * vec4<f32>(ndc, 1.0, 1.0);
+    return p.xyz / p.w;
+}
+
+fn get_cluster_aabb(cell: vec3<u32>) -> Aabb {
+    let grid = vec2<f32>(param.grid_size.xy);
+    // screen y points down, ndc y up
+    let ndc_min = vec2<f32>(f32(cell.x) / grid.x * 2.0 - 1.0, 1.0 - f32(cell.y + 1u) / grid.y * 2.0);
+    let ndc_max = vec2<f32>(f32(cell.x + 1u) / grid.x * 2.0 - 1.0, 1.0 - f32(cell.y) / grid.y * 2.0);
+
+    let near = get_slice_depth(cell.z);
+    let far = get_slice_depth(cell.z + 1u);
+
+    var aabb: Aabb;
+    aabb.min = vec3<f32>(1e30);
+    aabb.max = vec3<f32>(-1e30);
+    for (var i = 0u; i < 4u; i = i + 1u) {
+        let ndc = select(ndc_min, ndc_max, vec2<bool>((i & 1u) != 0u, (i & 2u) != 0u));
+        let p = get_far_point(ndc);
+        // the corner ray through the eye, at the depths of the slice
+        let p_near = p * (near / -p.z);
+        let p_far = p * (far / -p.z);
+        aabb.min = min(aabb.min, min(p_near, p_far));
+        aabb.max = max(aabb.max, max(p_near, p_far));
+    }
+    return aabb;
+}
+
+// distance where the attenuated light drops below 5 / 256, same as LightPoint::radius
+fn get_light_point_radius(light_point: LightPoint) -> f32 {
+    let brightness = max_channel(light_point.color.rgb)
+        * max(max(max_channel(light_point.diffuse), max_channel(light_point.specular)), max_channel(light_point.ambient));
+    let c = light_point.constant - brightness * 256.0 / 5.0;
+    if light_point.quadratic > 0.0 {
+        let linear = light_point.linear;
+        return (-linear + sqrt(linear * linear - 4.0 * light_point.quadratic * c)) / (2.0 * light_point.quadratic);
+    }
+    if light_point.linear > 0.0 {
+        return -c / light_point.linear;
+    }
+    return 1e30;
+}
+
+fn max_channel(v: vec3<f32>) -> f32 {
+    return max(max(v.x, v.y), v.z);
+}
+
+fn is_sphere_in_aabb(center: vec3<f32>, radius: f32, aabb: Aabb) -> bool {
+    let closest = clamp(center, aabb.min, aabb.max);
+    let d = center - closest;
+    return dot(d, d) <= radius * radius;
+}
+
+// cos_angle is the outer cutoff
+fn is_sphere_in_cone(center: vec3<f32>, radius: f32, pos: vec3<f32>, front: vec3<f32>, cos_angle: f32) -> bool {
+    let v = center - pos;
+    let len_sq = dot(v, v);
+    let len_front = dot(v, front);
+    // behind the light
+    if len_front < -radius {
+        return false;
+    }
+    let sin_angle = sqrt(max(1.0 - cos_angle * cos_angle, 0.0));
+    let dist = cos_angle * sqrt(max(len_sq - len_front * len_front, 0.0)) - len_front * sin_angle;
+    return dist <= radius;
+}
@@ -0,0 +1,109 @@
+// geometry pass of the deferred path, the surface of every pixel is written out once so the
+// lighting only runs for what is visible
+
+struct VertexIn {
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tex_coord: vec2<f32>,
+    @location(3) tangent: vec4<f32>,
+}
+
+struct VertexOut {
+    @builtin(position) clip_pos: vec4<f32>,
+    @location(0) frag_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tex_coord: vec2<f32>,
+    @location(3) tangent: vec4<f32>,
+}
+
+struct TransformIT {
+    @location(5) t0: vec4<f32>,
+    @location(6) t1: vec4<f32>,
+    @location(7) t2: vec4<f32>,
+    @location(8) t3: vec4<f32>,
+    @location(9) t4: vec4<f32>,
+    @location(10) t5: vec4<f32>,
+    @location(11) t6: vec4<f32>,
+    @location(12) t7: vec4<f32>,
+}
+
+struct MaterialFactor {
+    base_color: vec4<f32>,
+    emissive: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    shininess: f32,
+    // 0 blinn phong, 1 cook torrance
+    is_pbr: u32,
+}
+
+struct GBufferOut {
+    // w is 1 where something was drawn
+    @location(0) position: vec4<f32>,
+    // w is the shininess
+    @location(1) normal: vec4<f32>,
+    // a is the specular strength
+    @location(2) albedo_specular: vec4<f32>,
+}
+
+@group(0)@binding(0)
+var<uniform> view_proj: mat4x4<f32>;
+
+@vertex
+fn vs_main(in: VertexIn, transform: TransformIT) -> VertexOut {
+    let model = mat4x4<f32>(transform.t0, transform.t1, transform.t2, transform.t3);
+    let it_model = mat3x3<f32>(transform.t4.xyz, transform.t5.xyz, transform.t6.xyz); // inverse transpose model
+
+    var out: VertexOut;
+    out.clip_pos = view_proj * model * vec4<f32>(in.pos, 1.0);
+    out.frag_pos = (model * vec4<f32>(in.pos, 1.0)).xyz;
+    out.normal = it_model * in.normal;
+    out.tex_coord = in.tex_coord;
+    out.tangent = vec4<f32>((model * vec4<f32>(in.tangent.xyz, 0.0)).xyz, in.tangent.w);
+    return out;
+}
+
+
+@group(2)@binding(0)
+var texture_sampler: sampler;
+@group(2)@binding(1)
+var texture_base_color: texture_2d<f32>;
+@group(2)@binding(2)
+var<uniform> material: MaterialFactor;
+@group(2)@binding(3)
+var texture_metallic_roughness: texture_2d<f32>;
+@group(2)@binding(4)
+var texture_normal: texture_2d<f32>;
+
+
+@fragment
+fn fs_main(in: VertexOut) -> GBufferOut {
+    let base_color = textureSample(texture_base_color, texture_sampler, in.tex_coord) * material.base_color;
+    let metallic_roughness = textureSample(texture_metallic_roughness, texture_sampler, in.tex_coord);
+    let tex_normal = textureSample(texture_normal, texture_sampler, in.tex_coord).xyz;
+
+    // pbr materials are lit as blinn phong here, rough surfaces get a weaker highlight
+    let roughness = clamp(material.roughness * metallic_roughness.g, 0.0, 1.0);
+    let specular = select(1.0, 1.0 - roughness, material.is_pbr == 1u);
+
+    var out: GBufferOut;
+    out.position = vec4<f32>(in.frag_pos, 1.0);
+    out.normal = vec4<f32>(get_normal(normalize(in.normal), in.tangent, tex_normal), material.shininess);
+    out.albedo_specular = vec4<f32>(base_color.rgb, specular);
+    return out;
+}
+
+// normal map in the tangent frame of the vertex, tbn
+fn get_normal(normal: vec3<f32>, tangent: vec4<f32>, tex_normal: vec3<f32>) -> vec3<f32> {
+    if material.normal_scale == 0.0 || dot(tangent.xyz, tangent.xyz) == 0.0 {
+        return normal;
+    }
+
+    let t = normalize(tangent.xyz - normal * dot(normal, tangent.xyz));
+    let b = cross(normal, t) * tangent.w;
+
+    let n = (tex_normal * 2.0 - 1.0) * vec3<f32>(material.normal_scale, material.normal_scale, 1.0);
+    return normalize(mat3x3<f32>(t, b, normal) * n);
+}
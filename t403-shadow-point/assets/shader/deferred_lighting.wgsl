@@ -0,0 +1,397 @@
+// lighting pass of the deferred path, a fullscreen triangle adds the direction and spot lights
+// and every point light is drawn as a volume around it, only the pixels it covers are shaded
+
+struct VertexOut {
+    @builtin(position) clip_pos: vec4<f32>,
+}
+
+struct VertexLightIn {
+    @location(0) pos: vec3<f32>,
+    @location(1) color: vec3<f32>,
+}
+
+struct VertexVolumeOut {
+    @builtin(position) clip_pos: vec4<f32>,
+    @location(0) @interpolate(flat) light_idx: u32,
+    @location(1) @interpolate(flat) radius: f32,
+}
+
+struct Transform {
+    @location(5) t0: vec4<f32>,
+    @location(6) t1: vec4<f32>,
+    @location(7) t2: vec4<f32>,
+    @location(8) t3: vec4<f32>,
+}
+
+struct LightDirection {
+    dir: vec3<f32>,
+    color: vec4<f32>,
+    ambient: vec3<f32>,
+    diffuse: vec3<f32>,
+    specular: vec3<f32>,
+}
+
+struct LightDirectionArray {
+    arr: array<LightDirection>,
+}
+
+struct LightPoint {
+    pos: vec3<f32>,
+    shadow_idx: i32,
+    color: vec4<f32>,
+    ambient: vec3<f32>,
+    constant: f32,
+    diffuse: vec3<f32>,
+    linear: f32,
+    specular: vec3<f32>,
+    quadratic: f32,
+}
+
+struct LightPointArray {
+    arr: array<LightPoint>,
+}
+
+struct LightPointShadow {
+    view_proj_arr: array<mat4x4<f32>, 6>,
+    pos: vec3<f32>,
+    near: f32,
+    far: f32,
+    sample_num: u32,
+    filter_radius: f32,
+    technique: u32,
+    light_bleeding: f32,
+}
+
+struct LightSpot {
+    pos: vec3<f32>,
+    front: vec3<f32>,
+    color: vec4<f32>,
+    ambient: vec3<f32>,
+    diffuse: vec3<f32>,
+    in_cutoff: f32,
+    specular: vec3<f32>,
+    out_cutoff: f32,
+}
+
+struct LightSpotArray {
+    arr: array<LightSpot>,
+}
+
+// one pixel of the g-buffer
+struct Surface {
+    pos: vec3<f32>,
+    normal: vec3<f32>,
+    albedo: vec3<f32>,
+    specular: f32,
+    shininess: f32,
+    ao: f32,
+}
+
+
+@group(0)@binding(0)
+var<uniform> view_proj: mat4x4<f32>;
+@group(0)@binding(1)
+var<uniform> camera_pos: vec3<f32>;
+@group(0)@binding(2)
+var<storage> light_point_shadow_arr: array<LightPointShadow>;
+// bindings 3 and 4, the shadow cubes, are in shadow_map_compare.wgsl
+@group(0)@binding(5)
+var texture_shadow_moment: texture_cube_array<f32>;
+@group(0)@binding(6)
+var sampler_shadow_moment: sampler;
+
+// same as EVSM_EXPONENT in pipe_shadow.rs
+const EVSM_EXPONENT: f32 = 5.0;
+// the smallest variance, relative to the slope of the warp, flat receivers would have none
+const MOMENT_BIAS: f32 = 0.0001;
+// world size of a screen pixel at the fragment, picks the mip level of the moments
+var<private> pixel_footprint: f32 = 0.0;
+
+// the first 8 and 16 points are spread as evenly as all 32
+var<private> poisson_disk: array<vec2<f32>, 32> = array<vec2<f32>, 32>(
+    vec2<f32>(-0.3523, -0.6983), vec2<f32>(0.5679, 0.7941), vec2<f32>(-0.6625, 0.5697), vec2<f32>(0.7603, -0.3429),
+    vec2<f32>(0.0266, 0.0610), vec2<f32>(-0.8812, -0.2843), vec2<f32>(0.3789, -0.9243), vec2<f32>(-0.1118, 0.9157),
+    vec2<f32>(0.9286, 0.2866), vec2<f32>(0.2081, -0.4226), vec2<f32>(0.4607, 0.2975), vec2<f32>(-0.4603, -0.0256),
+    vec2<f32>(-0.2136, 0.5106), vec2<f32>(0.1900, 0.6294), vec2<f32>(-0.9132, 0.2248), vec2<f32>(-0.0117, -0.9878),
+    vec2<f32>(0.4534, -0.1024), vec2<f32>(-0.1689, -0.2995), vec2<f32>(-0.7197, -0.6678), vec2<f32>(0.5193, -0.6059),
+    vec2<f32>(-0.4316, 0.8239), vec2<f32>(0.2324, 0.9711), vec2<f32>(-0.5459, -0.3946), vec2<f32>(0.0033, -0.6559),
+    vec2<f32>(0.9230, -0.0443), vec2<f32>(0.1172, 0.3327), vec2<f32>(-0.4973, 0.3008), vec2<f32>(0.7432, 0.4915),
+    vec2<f32>(-0.7507, -0.0154), vec2<f32>(0.1728, -0.1671), vec2<f32>(0.6924, 0.1873), vec2<f32>(0.6913, -0.1062),
+);
+// rotates the disk per pixel, set by the fragment entry point, noise instead of banding
+var<private> noise_angle: f32 = 0.0;
+
+// interleaved gradient noise
+fn get_noise_angle(pixel: vec2<f32>) -> f32 {
+    return 6.2831853 * fract(52.9829189 * fract(dot(pixel, vec2<f32>(0.06711056, 0.00583715))));
+}
+
+@group(1)@binding(0)
+var<storage> light_direction_arr: LightDirectionArray;
+@group(1)@binding(1)
+var<storage> light_point_arr: LightPointArray;
+@group(1)@binding(2)
+var<storage> light_spot_arr: LightSpotArray;
+
+@group(2)@binding(0)
+var texture_position: texture_2d<f32>;
+@group(2)@binding(1)
+var texture_normal: texture_2d<f32>;
+@group(2)@binding(2)
+var texture_albedo_specular: texture_2d<f32>;
+// g-buffer channel shown by fs_debug, see GBufferView
+@group(2)@binding(3)
+var<uniform> view: u32;
+
+@group(3)@binding(0)
+var texture_ao: texture_2d<f32>;
+
+
+// one triangle covering the screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOut {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOut;
+    out.clip_pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return out;
+}
+
+// the direction and spot lights reach every pixel
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(in.clip_pos.xy);
+    if textureLoad(texture_position, pixel, 0).w == 0.0 {
+        discard;
+    }
+    let surface = load_surface(pixel);
+    let view_dir = normalize(camera_pos - surface.pos);
+
+    var l = vec3<f32>(0.0, 0.0, 0.0);
+    for (var i: u32 = 0u; i < arrayLength(&light_direction_arr.arr); i = i + 1u) {
+        l += do_light_direction(light_direction_arr.arr[i], surface, view_dir);
+    }
+
+    for (var i: u32 = 0u; i < arrayLength(&light_spot_arr.arr); i = i + 1u) {
+        l += do_light_spot(light_spot_arr.arr[i], surface);
+    }
+    return vec4<f32>(l, 1.0);
+}
+
+// the cube of the light volume is scaled to the radius of the light
+@vertex
+fn vs_volume(in: VertexLightIn, transform: Transform, @builtin(instance_index) light_idx: u32) -> VertexVolumeOut {
+    let model = mat4x4<f32>(transform.t0, transform.t1, transform.t2, transform.t3);
+
+    var out: VertexVolumeOut;
+    out.clip_pos = view_proj * model * vec4<f32>(in.pos, 1.0);
+    out.light_idx = light_idx;
+    out.radius = length(transform.t0.xyz);
+    return out;
+}
+
+@fragment
+fn fs_volume(in: VertexVolumeOut) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(in.clip_pos.xy);
+    if textureLoad(texture_position, pixel, 0).w == 0.0 {
+        discard;
+    }
+    let surface = load_surface(pixel);
+    let light_point = light_point_arr.arr[in.light_idx];
+    // the corners of the cube reach past the radius
+    if length(light_point.pos - surface.pos) > in.radius {
+        discard;
+    }
+    noise_angle = get_noise_angle(in.clip_pos.xy);
+    pixel_footprint = get_pixel_footprint(pixel, surface.pos);
+
+    return vec4<f32>(do_light_point(light_point, surface, normalize(camera_pos - surface.pos)), 1.0);
+}
+
+// a single g-buffer channel, scaled to 0..1
+@fragment
+fn fs_debug(in: VertexOut) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(in.clip_pos.xy);
+    let position = textureLoad(texture_position, pixel, 0);
+    let normal = textureLoad(texture_normal, pixel, 0);
+    let albedo_specular = textureLoad(texture_albedo_specular, pixel, 0);
+
+    var color = vec3<f32>(0.0);
+    switch view {
+        case 1u: {
+            color = fract(position.xyz);
+        }
+        case 2u: {
+            color = normal.xyz * 0.5 + 0.5;
+        }
+        case 3u: {
+            color = albedo_specular.rgb;
+        }
+        case 4u: {
+            color = vec3<f32>(albedo_specular.a);
+        }
+        case 5u: {
+            color = vec3<f32>(normal.w / 256.0);
+        }
+        default: {}
+    }
+    return vec4<f32>(color * position.w, 1.0);
+}
+
+fn load_surface(pixel: vec2<i32>) -> Surface {
+    let normal = textureLoad(texture_normal, pixel, 0);
+    let albedo_specular = textureLoad(texture_albedo_specular, pixel, 0);
+
+    var surface: Surface;
+    surface.pos = textureLoad(texture_position, pixel, 0).xyz;
+    surface.normal = normal.xyz;
+    surface.albedo = albedo_specular.rgb;
+    surface.specular = albedo_specular.a;
+    surface.shininess = normal.w;
+    surface.ao = textureLoad(texture_ao, pixel, 0).r;
+    return surface;
+}
+
+fn do_light_direction(light_direction: LightDirection, surface: Surface, view_dir: vec3<f32>) -> vec3<f32> {
+    if light_direction.color.a == 0.0 {
+        return vec3<f32>(0.0, 0.0, 0.0);
+    }
+
+    let light_color = light_direction.color.rgb;
+
+    let ambient = light_color * light_direction.ambient * surface.albedo * surface.ao;
+
+    let light_dir = normalize(-light_direction.dir);
+    let diff = max(dot(surface.normal, light_dir), 0.0);
+    let diffuse = light_direction.diffuse * light_color * (diff * surface.albedo);
+
+    let halfway_dir = normalize(light_dir + view_dir);
+    let spec = pow(max(dot(surface.normal, halfway_dir), 0.0), surface.shininess * 3.0);
+    let specular = light_direction.specular * light_color * spec * surface.albedo * surface.specular;
+
+    return ambient + (diffuse + specular);
+}
+
+fn get_point_light_visiblity(shadow_idx: i32, frag_pos_world: vec3<f32>, normal: vec3<f32>) -> f32 {
+    if shadow_idx < 0 {
+        return 1.0;
+    }
+
+    let light_shadow = light_point_shadow_arr[shadow_idx];
+    let light_to_frag_world = frag_pos_world - light_shadow.pos;
+    let len = length(light_to_frag_world);
+    let dir = light_to_frag_world / len;
+    if light_shadow.technique == 1u {
+        return get_point_light_visiblity_moment(shadow_idx, light_shadow, dir, len);
+    }
+    // in world units, the texels grow with the distance to the light
+    let bias = len * max(0.01 * (1.0 - dot(normal, -dir)), 0.002);
+
+    // the disk lies on the plane facing the light
+    let up = select(vec3<f32>(0.0, 1.0, 0.0), vec3<f32>(1.0, 0.0, 0.0), abs(dir.y) > 0.99);
+    let tangent = normalize(cross(up, dir));
+    let bitangent = cross(dir, tangent);
+    let rotation = mat2x2<f32>(cos(noise_angle), sin(noise_angle), -sin(noise_angle), cos(noise_angle));
+
+    let sample_num = clamp(light_shadow.sample_num, 1u, 32u);
+    // a single comparison stays in the center
+    let radius = select(light_shadow.filter_radius, 0.0, sample_num == 1u);
+    // the cubes store the distance to the light over far
+    let depth = (len - bias) / light_shadow.far;
+    var visiblity = 0.0;
+    for (var i = 0u; i < sample_num; i = i + 1u) {
+        let offset = rotation * poisson_disk[i] * radius;
+        let sample_dir = dir + tangent * offset.x + bitangent * offset.y;
+        visiblity += get_shadow_map_compare(sample_dir, shadow_idx, depth);
+    }
+    return visiblity / f32(sample_num);
+}
+
+// exponential variance shadow maps, chebyshev's inequality bounds how much of the filtered
+// area is further from the light than the fragment
+fn get_point_light_visiblity_moment(shadow_idx: i32, light_shadow: LightPointShadow, dir: vec3<f32>, len: f32) -> f32 {
+    // a texel of a 90 degree cube face covers about this much at the fragment
+    let texel_world = 2.0 * len / f32(textureDimensions(texture_shadow_moment).x);
+    let lod = max(log2(pixel_footprint / texel_world), 0.0);
+    let moment = textureSampleLevel(texture_shadow_moment, sampler_shadow_moment, dir, shadow_idx, lod);
+
+    let x = clamp(len / light_shadow.far, 0.0, 1.0) * 2.0 - 1.0;
+    let pos = exp(EVSM_EXPONENT * x);
+    let neg = -exp(-EVSM_EXPONENT * x);
+    let visiblity_pos = get_chebyshev_upper_bound(moment.xy, pos, MOMENT_BIAS * EVSM_EXPONENT * pos);
+    let visiblity_neg = get_chebyshev_upper_bound(moment.zw, neg, MOMENT_BIAS * EVSM_EXPONENT * neg);
+
+    // the faint tail of the bound is where stacked casters let light bleed through
+    let visiblity = min(visiblity_pos, visiblity_neg);
+    return clamp((visiblity - light_shadow.light_bleeding) / (1.0 - light_shadow.light_bleeding), 0.0, 1.0);
+}
+
+fn get_chebyshev_upper_bound(moment: vec2<f32>, depth: f32, min_deviation: f32) -> f32 {
+    if depth <= moment.x {
+        return 1.0;
+    }
+    let variance = max(moment.y - moment.x * moment.x, min_deviation * min_deviation);
+    let d = depth - moment.x;
+    return variance / (variance + d * d);
+}
+
+// distance to the neighbouring g-buffer texels, derivatives are not allowed after the discards.
+// the closer side of each axis, the other one may be across an edge
+fn get_pixel_footprint(pixel: vec2<i32>, pos: vec3<f32>) -> f32 {
+    let size = vec2<i32>(textureDimensions(texture_position));
+    var footprint = vec2<f32>(0.0);
+    for (var i = 0; i < 2; i++) {
+        let axis = select(vec2<i32>(1, 0), vec2<i32>(0, 1), i == 1);
+        let pos_next = textureLoad(texture_position, clamp(pixel + axis, vec2<i32>(0), size - 1), 0).xyz;
+        let pos_prev = textureLoad(texture_position, clamp(pixel - axis, vec2<i32>(0), size - 1), 0).xyz;
+        footprint[i] = min(length(pos_next - pos), length(pos_prev - pos));
+    }
+    return max(footprint.x, footprint.y);
+}
+
+fn do_light_point(light_point: LightPoint, surface: Surface, view_dir: vec3<f32>) -> vec3<f32> {
+    if light_point.color.a == 0.0 {
+        return vec3<f32>(0.0, 0.0, 0.0);
+    }
+
+    let light_color = light_point.color.rgb;
+
+    var ambient = light_color * light_point.ambient * surface.albedo * surface.ao;
+
+    let light_dir = normalize(light_point.pos - surface.pos);
+    let diff = max(dot(surface.normal, light_dir), 0.0);
+    var diffuse = light_color * light_point.diffuse * (diff * surface.albedo);
+
+    let halfway_dir = normalize(light_dir + view_dir);
+    let spec = pow(max(dot(surface.normal, halfway_dir), 0.0), surface.shininess * 3.0);
+    let specular = light_point.specular * light_color * spec * surface.albedo * surface.specular;
+
+    let len = length(light_point.pos - surface.pos);
+    let attenuation = 1.0 / (light_point.constant + light_point.linear * len + light_point.quadratic * (len * len));
+
+    ambient *= attenuation;
+    diffuse *= attenuation;
+
+    let visiblity = get_point_light_visiblity(light_point.shadow_idx, surface.pos, surface.normal);
+
+    return ambient + visiblity * (diffuse + specular);
+}
+
+fn do_light_spot(light_spot: LightSpot, surface: Surface) -> vec3<f32> {
+    if light_spot.color.a == 0.0 {
+        return vec3<f32>(0.0, 0.0, 0.0);
+    }
+
+    let light_dir = normalize(light_spot.pos - surface.pos);
+    let light_color = light_spot.color.rgb;
+
+    let theta = dot(light_dir, normalize(-light_spot.front));
+    let epsilon = light_spot.in_cutoff - light_spot.out_cutoff;
+    let intensity = clamp((theta - light_spot.out_cutoff) / epsilon, 0.0, 1.0);
+
+    let ambient = light_color * light_spot.ambient * surface.albedo * surface.ao;
+
+    let diff = max(dot(surface.normal, light_dir), 0.0);
+    let diffuse = light_color * light_spot.diffuse * (diff * surface.albedo) * intensity;
+
+    return ambient + diffuse;
+}
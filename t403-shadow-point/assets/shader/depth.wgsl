@@ -18,10 +18,7 @@ fn vs_main(in: VertexIn) -> VertexOut {
 }
 
 
-@group(0)@binding(0)
-var texture_depth: texture_depth_2d;
-@group(0)@binding(1)
-var texture_sampler: sampler;
+// bindings 0 and 1, the depth texture and its sampler, are in depth_read.wgsl
 @group(0)@binding(2)
 var<uniform> orth: u32;
 
@@ -29,7 +26,7 @@ var<uniform> orth: u32;
 fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
     let near = 0.1;
     let far = 100.0;
-    let depth = textureSample(texture_depth, texture_sampler, in.tex_coord);
+    let depth = get_depth(in.tex_coord);
     if orth != u32(0) {
         return vec4<f32>(vec3<f32>(depth), 1.0);
     } else {
@@ -0,0 +1,10 @@
+
+// appended to depth.wgsl, depth_read_gl.wgsl replaces it on GL
+@group(0)@binding(0)
+var texture_depth: texture_depth_2d;
+@group(0)@binding(1)
+var texture_sampler: sampler;
+
+fn get_depth(tex_coord: vec2<f32>) -> f32 {
+    return textureSample(texture_depth, texture_sampler, tex_coord);
+}
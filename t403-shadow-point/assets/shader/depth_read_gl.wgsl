@@ -0,0 +1,11 @@
+
+// appended instead of depth_read.wgsl on GL, GLSL only samples a depth texture with a comparison,
+// as plain floats it can't be filtered
+@group(0)@binding(0)
+var texture_depth: texture_2d<f32>;
+@group(0)@binding(1)
+var texture_sampler: sampler;
+
+fn get_depth(tex_coord: vec2<f32>) -> f32 {
+    return textureSample(texture_depth, texture_sampler, tex_coord).r;
+}
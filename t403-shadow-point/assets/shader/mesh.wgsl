@@ -2,6 +2,7 @@ struct VertexIn {
     @location(0) pos: vec3<f32>,
     @location(1) normal: vec3<f32>,
     @location(2) tex_coord: vec2<f32>,
+    @location(3) tangent: vec4<f32>,
 }
 
 struct VertexOut {
@@ -10,6 +11,7 @@ struct VertexOut {
     @location(1) normal: vec3<f32>,
     @location(2) tex_coord: vec2<f32>,
     @location(3) frag_pos_world: vec3<f32>,
+    @location(4) tangent: vec4<f32>,
 }
 
 struct TransformIT {
@@ -37,6 +39,7 @@ struct LightDirectionArray {
 
 struct LightPoint {
     pos: vec3<f32>,
+    shadow_idx: i32,
     color: vec4<f32>,
     ambient: vec3<f32>,
     constant: f32,
@@ -50,6 +53,17 @@ struct LightPointArray {
     arr: array<LightPoint>,
 }
 
+struct LightPointShadow {
+    view_proj_arr: array<mat4x4<f32>, 6>,
+    pos: vec3<f32>,
+    near: f32,
+    far: f32,
+    sample_num: u32,
+    filter_radius: f32,
+    technique: u32,
+    light_bleeding: f32,
+}
+
 struct LightSpot {
     pos: vec3<f32>,
     front: vec3<f32>,
@@ -65,21 +79,88 @@ struct LightSpotArray {
     arr: array<LightSpot>,
 }
 
+struct ClusterParam {
+    view: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+    // ambient of every spot light, they are culled by their cone
+    spot_ambient: vec3<f32>,
+    is_clustered: u32,
+    grid_size: vec3<u32>,
+    z_near: f32,
+    screen_size: vec2<f32>,
+    z_far: f32,
+    max_light_per_cluster: u32,
+}
+
+// the lights of one froxel, or all of them when not clustered
+struct Cluster {
+    offset: u32,
+    point_num: u32,
+    spot_num: u32,
+}
+
+struct MaterialFactor {
+    base_color: vec4<f32>,
+    emissive: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
+    shininess: f32,
+    // 0 blinn phong, 1 cook torrance
+    is_pbr: u32,
+}
+
+struct Surface {
+    albedo: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+    // reflectance at normal incidence
+    f0: vec3<f32>,
+    normal: vec3<f32>,
+    view_dir: vec3<f32>,
+}
+
+const PI: f32 = 3.14159265359;
+
 
 @group(0)@binding(0)
 var<uniform> view_proj: mat4x4<f32>;
 @group(0)@binding(1)
 var<uniform> camera_pos: vec3<f32>;
 @group(0)@binding(2)
-var<storage> view_proj_shadow_map_arr: array<mat4x4<f32>, 6>;
-@group(0)@binding(3)
-var<uniform> light_point_pos: vec3<f32>;
-@group(0)@binding(4)
-var texture_shadow_map: texture_cube<f32>;
+var<storage> light_point_shadow_arr: array<LightPointShadow>;
+// bindings 3 and 4, the shadow cubes, are in shadow_map_compare.wgsl
 @group(0)@binding(5)
-var sampler_shadow_map: sampler;
+var texture_shadow_moment: texture_cube_array<f32>;
 @group(0)@binding(6)
-var<uniform> near_far: vec2<f32>;
+var sampler_shadow_moment: sampler;
+
+// same as EVSM_EXPONENT in pipe_shadow.rs
+const EVSM_EXPONENT: f32 = 5.0;
+// the smallest variance, relative to the slope of the warp, flat receivers would have none
+const MOMENT_BIAS: f32 = 0.0001;
+// world size of a screen pixel at the fragment, picks the mip level of the moments
+var<private> pixel_footprint: f32 = 0.0;
+
+// the first 8 and 16 points are spread as evenly as all 32
+var<private> poisson_disk: array<vec2<f32>, 32> = array<vec2<f32>, 32>(
+    vec2<f32>(-0.3523, -0.6983), vec2<f32>(0.5679, 0.7941), vec2<f32>(-0.6625, 0.5697), vec2<f32>(0.7603, -0.3429),
+    vec2<f32>(0.0266, 0.0610), vec2<f32>(-0.8812, -0.2843), vec2<f32>(0.3789, -0.9243), vec2<f32>(-0.1118, 0.9157),
+    vec2<f32>(0.9286, 0.2866), vec2<f32>(0.2081, -0.4226), vec2<f32>(0.4607, 0.2975), vec2<f32>(-0.4603, -0.0256),
+    vec2<f32>(-0.2136, 0.5106), vec2<f32>(0.1900, 0.6294), vec2<f32>(-0.9132, 0.2248), vec2<f32>(-0.0117, -0.9878),
+    vec2<f32>(0.4534, -0.1024), vec2<f32>(-0.1689, -0.2995), vec2<f32>(-0.7197, -0.6678), vec2<f32>(0.5193, -0.6059),
+    vec2<f32>(-0.4316, 0.8239), vec2<f32>(0.2324, 0.9711), vec2<f32>(-0.5459, -0.3946), vec2<f32>(0.0033, -0.6559),
+    vec2<f32>(0.9230, -0.0443), vec2<f32>(0.1172, 0.3327), vec2<f32>(-0.4973, 0.3008), vec2<f32>(0.7432, 0.4915),
+    vec2<f32>(-0.7507, -0.0154), vec2<f32>(0.1728, -0.1671), vec2<f32>(0.6924, 0.1873), vec2<f32>(0.6913, -0.1062),
+);
+// rotates the disk per pixel, set by the fragment entry point, noise instead of banding
+var<private> noise_angle: f32 = 0.0;
+
+// interleaved gradient noise
+fn get_noise_angle(pixel: vec2<f32>) -> f32 {
+    return 6.2831853 * fract(52.9829189 * fract(dot(pixel, vec2<f32>(0.06711056, 0.00583715))));
+}
 
 @vertex
 fn vs_main(in: VertexIn, transform: TransformIT) -> VertexOut {
@@ -92,6 +173,8 @@ fn vs_main(in: VertexIn, transform: TransformIT) -> VertexOut {
     out.frag_pos = (model * vec4<f32>(in.pos, 1.0)).xyz;
     out.normal = it_model * in.normal;
     out.tex_coord = in.tex_coord;
+    // tangents lie in the surface, the model matrix keeps them there
+    out.tangent = vec4<f32>((model * vec4<f32>(in.tangent.xyz, 0.0)).xyz, in.tangent.w);
 
     out.frag_pos_world = (model * vec4<f32>(in.pos, 1.0)).xyz;
 
@@ -105,48 +188,140 @@ var<storage> light_direction_arr: LightDirectionArray;
 var<storage> light_point_arr: LightPointArray;
 @group(1)@binding(2)
 var<storage> light_spot_arr: LightSpotArray;
+@group(1)@binding(3)
+var<uniform> cluster_param: ClusterParam;
+// written by cluster.wgsl
+@group(1)@binding(4)
+var<storage> cluster_light_arr: array<u32>;
 
 
 @group(2)@binding(0)
 var texture_sampler: sampler;
 @group(2)@binding(1)
-var texture_diffuse: texture_2d<f32>;
+var texture_base_color: texture_2d<f32>;
 @group(2)@binding(2)
-var<uniform> color: vec3<f32>;
+var<uniform> material: MaterialFactor;
 @group(2)@binding(3)
-var<uniform> shininess: f32;
+var texture_metallic_roughness: texture_2d<f32>;
+@group(2)@binding(4)
+var texture_normal: texture_2d<f32>;
+@group(2)@binding(5)
+var texture_occlusion: texture_2d<f32>;
+@group(2)@binding(6)
+var texture_emissive: texture_2d<f32>;
+
+
+// screen space ambient occlusion, same size as the screen
+@group(3)@binding(0)
+var texture_ao: texture_2d<f32>;
 
 
 @fragment
 fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
-    var l = vec3<f32>(0.0, 0.0, 0.0);
-    let normal = normalize(in.normal);
-    let view_dir = normalize(camera_pos - in.frag_pos);
-
-    let tex_diffuse = textureSample(texture_diffuse, texture_sampler, in.tex_coord).rgb;
+    // sample before branching, it needs uniform control flow
+    let base_color = textureSample(texture_base_color, texture_sampler, in.tex_coord) * material.base_color;
+    let metallic_roughness = textureSample(texture_metallic_roughness, texture_sampler, in.tex_coord);
+    let tex_normal = textureSample(texture_normal, texture_sampler, in.tex_coord).xyz;
+    let occlusion = textureSample(texture_occlusion, texture_sampler, in.tex_coord).r;
+    let emissive = textureSample(texture_emissive, texture_sampler, in.tex_coord).rgb * material.emissive;
+    let normal = get_normal(normalize(in.normal), in.tangent, tex_normal);
+    let ao_screen = textureLoad(texture_ao, vec2<i32>(in.clip_pos.xy), 0).r;
+    let cluster = get_cluster(in.clip_pos.xy, in.frag_pos);
+    noise_angle = get_noise_angle(in.clip_pos.xy);
+    pixel_footprint = max(length(dpdx(in.frag_pos_world)), length(dpdy(in.frag_pos_world)));
+
+    if material.is_pbr == 0u {
+        return vec4<f32>(do_blinn_phong(in, cluster, base_color.rgb, normal, ao_screen), 1.0);
+    }
 
+    var surface: Surface;
+    surface.albedo = base_color.rgb;
+    surface.metallic = clamp(material.metallic * metallic_roughness.b, 0.0, 1.0);
+    surface.roughness = clamp(material.roughness * metallic_roughness.g, 0.04, 1.0);
+    // dielectrics reflect about 4%, metals tint the reflection with their albedo
+    surface.f0 = mix(vec3<f32>(0.04), surface.albedo, surface.metallic);
+    surface.normal = normal;
+    surface.view_dir = normalize(camera_pos - in.frag_pos);
+    let ao = (1.0 + material.occlusion_strength * (occlusion - 1.0)) * ao_screen;
+
+    var l = emissive + cluster_param.spot_ambient * surface.albedo * ao;
     for (var i: u32 = 0u; i < arrayLength(&light_direction_arr.arr); i = i + 1u) {
-        l += do_light_direction(light_direction_arr.arr[i], normal, view_dir, tex_diffuse);
+        l += do_light_direction_pbr(light_direction_arr.arr[i], surface, ao);
     }
 
-    for (var i: u32 = 0u; i < arrayLength(&light_point_arr.arr); i = i + 1u) {
-        l += do_light_point(light_point_arr.arr[i], normal, view_dir, tex_diffuse, in.frag_pos, in.frag_pos_world);
+    for (var i: u32 = 0u; i < cluster.point_num; i = i + 1u) {
+        l += do_light_point_pbr(light_point_arr.arr[get_light_point_idx(cluster, i)], surface, ao, in.frag_pos, in.frag_pos_world);
     }
 
-    for (var i: u32 = 0u; i < arrayLength(&light_spot_arr.arr); i = i + 1u) {
-        l += do_light_spot(light_spot_arr.arr[i], normal, tex_diffuse, in.frag_pos);
+    for (var i: u32 = 0u; i < cluster.spot_num; i = i + 1u) {
+        l += do_light_spot_pbr(light_spot_arr.arr[get_light_spot_idx(cluster, i)], surface, in.frag_pos);
     }
     return vec4<f32>(l, 1.0);
 }
 
-fn do_light_direction(light_direction: LightDirection, normal: vec3<f32>, view_dir: vec3<f32>, tex_diffuse: vec3<f32>) -> vec3<f32> {
+fn get_cluster(pixel: vec2<f32>, frag_pos: vec3<f32>) -> Cluster {
+    var cluster: Cluster;
+    if cluster_param.is_clustered == 0u {
+        cluster.offset = 0u;
+        cluster.point_num = arrayLength(&light_point_arr.arr);
+        cluster.spot_num = arrayLength(&light_spot_arr.arr);
+        return cluster;
+    }
+
+    let grid = cluster_param.grid_size;
+    let tile = min(vec2<u32>(pixel / cluster_param.screen_size * vec2<f32>(grid.xy)), grid.xy - 1u);
+    // inverse of the exponential slices in cluster.wgsl
+    let depth = -(cluster_param.view * vec4<f32>(frag_pos, 1.0)).z;
+    let t = log(max(depth, cluster_param.z_near) / cluster_param.z_near) / log(cluster_param.z_far / cluster_param.z_near);
+    let slice = min(u32(t * f32(grid.z)), grid.z - 1u);
+
+    let cluster_idx = tile.x + tile.y * grid.x + slice * grid.x * grid.y;
+    cluster.offset = cluster_idx * (cluster_param.max_light_per_cluster + 2u);
+    cluster.point_num = cluster_light_arr[cluster.offset];
+    cluster.spot_num = cluster_light_arr[cluster.offset + 1u];
+    return cluster;
+}
+
+fn get_light_point_idx(cluster: Cluster, i: u32) -> u32 {
+    if cluster_param.is_clustered == 0u {
+        return i;
+    }
+    return cluster_light_arr[cluster.offset + 2u + i];
+}
+
+fn get_light_spot_idx(cluster: Cluster, i: u32) -> u32 {
+    if cluster_param.is_clustered == 0u {
+        return i;
+    }
+    return cluster_light_arr[cluster.offset + 2u + cluster.point_num + i];
+}
+
+fn do_blinn_phong(in: VertexOut, cluster: Cluster, tex_diffuse: vec3<f32>, normal: vec3<f32>, ao: f32) -> vec3<f32> {
+    var l = cluster_param.spot_ambient * tex_diffuse * ao;
+    let view_dir = normalize(camera_pos - in.frag_pos);
+
+    for (var i: u32 = 0u; i < arrayLength(&light_direction_arr.arr); i = i + 1u) {
+        l += do_light_direction(light_direction_arr.arr[i], normal, view_dir, tex_diffuse, ao);
+    }
+
+    for (var i: u32 = 0u; i < cluster.point_num; i = i + 1u) {
+        l += do_light_point(light_point_arr.arr[get_light_point_idx(cluster, i)], normal, view_dir, tex_diffuse, ao, in.frag_pos, in.frag_pos_world);
+    }
+
+    for (var i: u32 = 0u; i < cluster.spot_num; i = i + 1u) {
+        l += do_light_spot(light_spot_arr.arr[get_light_spot_idx(cluster, i)], normal, tex_diffuse, in.frag_pos);
+    }
+    return l;
+}
+
+fn do_light_direction(light_direction: LightDirection, normal: vec3<f32>, view_dir: vec3<f32>, tex_diffuse: vec3<f32>, ao: f32) -> vec3<f32> {
     if light_direction.color.a == 0.0 {
         return vec3<f32>(0.0, 0.0, 0.0);
     }
 
     let light_color = light_direction.color.rgb;
 
-    let ambient = light_color * light_direction.ambient * tex_diffuse;
+    let ambient = light_color * light_direction.ambient * tex_diffuse * ao;
 
     let light_dir = normalize(-light_direction.dir);
     let diff = max(dot(normal, light_dir), 0.0);
@@ -156,45 +331,85 @@ fn do_light_direction(light_direction: LightDirection, normal: vec3<f32>, view_d
     //let spec = pow(max(dot(view_dir, reflect_dir), 0.0), shininess) * light_direction.specular;
 
     let halfway_dir = normalize(light_dir + view_dir);
-    let spec = pow(max(dot(normal, halfway_dir), 0.0), shininess * 3.0);
+    let spec = pow(max(dot(normal, halfway_dir), 0.0), material.shininess * 3.0);
 
     let specular = light_direction.specular * light_color * spec * tex_diffuse;
 
     return ambient + (diffuse + specular);
 }
 
-fn get_point_light_visiblity(frag_pos_world: vec3<f32>, normal: vec3<f32>) -> f32 {
-    let light_to_frag_world = frag_pos_world - light_point_pos;
-    let dir_light_to_frag_world = normalize(light_to_frag_world);
-    let len = length(light_to_frag_world);
-    let v = (len - near_far.x) / (near_far.y - near_far.x);
-
-    let bias = max(0.005 * (1.0 - dot(normal, -light_to_frag_world)), 0.002);
+fn get_point_light_visiblity(shadow_idx: i32, frag_pos_world: vec3<f32>, normal: vec3<f32>) -> f32 {
+    if shadow_idx < 0 {
+        return 1.0;
+    }
 
+    let light_shadow = light_point_shadow_arr[shadow_idx];
+    let light_to_frag_world = frag_pos_world - light_shadow.pos;
+    let len = length(light_to_frag_world);
+    let dir = light_to_frag_world / len;
+    if light_shadow.technique == 1u {
+        return get_point_light_visiblity_moment(shadow_idx, light_shadow, dir, len);
+    }
+    // in world units, the texels grow with the distance to the light
+    let bias = len * max(0.01 * (1.0 - dot(normal, -dir)), 0.002);
+
+    // the disk lies on the plane facing the light
+    let up = select(vec3<f32>(0.0, 1.0, 0.0), vec3<f32>(1.0, 0.0, 0.0), abs(dir.y) > 0.99);
+    let tangent = normalize(cross(up, dir));
+    let bitangent = cross(dir, tangent);
+    let rotation = mat2x2<f32>(cos(noise_angle), sin(noise_angle), -sin(noise_angle), cos(noise_angle));
+
+    let sample_num = clamp(light_shadow.sample_num, 1u, 32u);
+    // a single comparison stays in the center
+    let radius = select(light_shadow.filter_radius, 0.0, sample_num == 1u);
+    // the cubes store the distance to the light over far
+    let depth = (len - bias) / light_shadow.far;
     var visiblity = 0.0;
-    let sample_size = 2;
-    //for (var y = -sample_size; y <= sample_size; y ++) {
-     //   for (var x = -sample_size; x <= sample_size; x ++) {
-    //let offset = vec2<i32>(x, y);
-    let v_sample = textureSample(texture_shadow_map, sampler_shadow_map, dir_light_to_frag_world).r;
-    visiblity = select(0.0, 1.0, v < v_sample + bias);
-      //  }
-    //}
+    for (var i = 0u; i < sample_num; i = i + 1u) {
+        let offset = rotation * poisson_disk[i] * radius;
+        let sample_dir = dir + tangent * offset.x + bitangent * offset.y;
+        visiblity += get_shadow_map_compare(sample_dir, shadow_idx, depth);
+    }
+    return visiblity / f32(sample_num);
+}
 
-    //visiblity /= pow(f32(sample_size * 2 + 1), 2.0);
+// exponential variance shadow maps, chebyshev's inequality bounds how much of the filtered
+// area is further from the light than the fragment
+fn get_point_light_visiblity_moment(shadow_idx: i32, light_shadow: LightPointShadow, dir: vec3<f32>, len: f32) -> f32 {
+    // a texel of a 90 degree cube face covers about this much at the fragment
+    let texel_world = 2.0 * len / f32(textureDimensions(texture_shadow_moment).x);
+    let lod = max(log2(pixel_footprint / texel_world), 0.0);
+    let moment = textureSampleLevel(texture_shadow_moment, sampler_shadow_moment, dir, shadow_idx, lod);
+
+    let x = clamp(len / light_shadow.far, 0.0, 1.0) * 2.0 - 1.0;
+    let pos = exp(EVSM_EXPONENT * x);
+    let neg = -exp(-EVSM_EXPONENT * x);
+    let visiblity_pos = get_chebyshev_upper_bound(moment.xy, pos, MOMENT_BIAS * EVSM_EXPONENT * pos);
+    let visiblity_neg = get_chebyshev_upper_bound(moment.zw, neg, MOMENT_BIAS * EVSM_EXPONENT * neg);
+
+    // the faint tail of the bound is where stacked casters let light bleed through
+    let visiblity = min(visiblity_pos, visiblity_neg);
+    return clamp((visiblity - light_shadow.light_bleeding) / (1.0 - light_shadow.light_bleeding), 0.0, 1.0);
+}
 
-    return visiblity;
-    //return depth / 2.0;
+fn get_chebyshev_upper_bound(moment: vec2<f32>, depth: f32, min_deviation: f32) -> f32 {
+    if depth <= moment.x {
+        return 1.0;
+    }
+    let variance = max(moment.y - moment.x * moment.x, min_deviation * min_deviation);
+    let d = depth - moment.x;
+    return variance / (variance + d * d);
 }
 
-fn do_light_point(light_point: LightPoint, normal: vec3<f32>, view_dir: vec3<f32>, tex_diffuse: vec3<f32>, frag_pos: vec3<f32>, frag_pos_world: vec3<f32>) -> vec3<f32> {
-    if light_point.color.a == 0.0 {
+fn do_light_point(light_point: LightPoint, normal: vec3<f32>, view_dir: vec3<f32>, tex_diffuse: vec3<f32>, ao: f32, frag_pos: vec3<f32>, frag_pos_world: vec3<f32>) -> vec3<f32> {
+    // past the radius it is cut off, like in the clusters and the deferred light volumes
+    if light_point.color.a == 0.0 || length(light_point.pos - frag_pos) > get_light_point_radius(light_point) {
         return vec3<f32>(0.0, 0.0, 0.0);
     }
 
     let light_color = light_point.color.rgb;
 
-    var ambient = light_color * light_point.ambient * tex_diffuse;
+    var ambient = light_color * light_point.ambient * tex_diffuse * ao;
 
     let light_dir = normalize(light_point.pos - frag_pos);
     let diff = max(dot(normal, light_dir), 0.0);
@@ -205,7 +420,7 @@ fn do_light_point(light_point: LightPoint, normal: vec3<f32>, view_dir: vec3<f32
     //let spec = pow(max(dot(view_dir, reflect_dir), 0.0), shininess);
 
     let halfway_dir = normalize(light_dir + view_dir);
-    let spec = pow(max(dot(normal, halfway_dir), 0.0), shininess * 3.0);
+    let spec = pow(max(dot(normal, halfway_dir), 0.0), material.shininess * 3.0);
     let specular = light_point.specular * light_color * spec * tex_diffuse;
 
     let len = length(light_point.pos - frag_pos);
@@ -214,12 +429,32 @@ fn do_light_point(light_point: LightPoint, normal: vec3<f32>, view_dir: vec3<f32
     ambient *= attenuation;
     diffuse *= attenuation;
 
-    var visiblity = get_point_light_visiblity(frag_pos_world, normal);
+    var visiblity = get_point_light_visiblity(light_point.shadow_idx, frag_pos_world, normal);
 
     return ambient + visiblity * (diffuse + specular);
     //return vec3<f32>(visiblity);
 }
 
+// distance where the attenuated light drops below 5 / 256, same as LightPoint::radius
+fn get_light_point_radius(light_point: LightPoint) -> f32 {
+    let brightness = max_channel(light_point.color.rgb)
+        * max(max(max_channel(light_point.diffuse), max_channel(light_point.specular)), max_channel(light_point.ambient));
+    let c = light_point.constant - brightness * 256.0 / 5.0;
+    if light_point.quadratic > 0.0 {
+        let linear = light_point.linear;
+        return (-linear + sqrt(linear * linear - 4.0 * light_point.quadratic * c)) / (2.0 * light_point.quadratic);
+    }
+    if light_point.linear > 0.0 {
+        return -c / light_point.linear;
+    }
+    return 1e30;
+}
+
+fn max_channel(v: vec3<f32>) -> f32 {
+    return max(max(v.x, v.y), v.z);
+}
+
+// without the ambient term, that is cluster_param.spot_ambient
 fn do_light_spot(light_spot: LightSpot, normal: vec3<f32>, tex_diffuse: vec3<f32>, frag_pos: vec3<f32>) -> vec3<f32> {
     if light_spot.color.a == 0.0 {
         return vec3<f32>(0.0, 0.0, 0.0);
@@ -232,8 +467,6 @@ fn do_light_spot(light_spot: LightSpot, normal: vec3<f32>, tex_diffuse: vec3<f32
     let epsilon = light_spot.in_cutoff - light_spot.out_cutoff;
     let intensity = clamp((theta - light_spot.out_cutoff) / epsilon, 0.0, 1.0);
 
-    let ambient = light_color * light_spot.ambient * tex_diffuse;
-
     let diff = max(dot(normal, light_dir), 0.0);
     var diffuse = light_color * light_spot.diffuse * (diff * tex_diffuse);
 
@@ -242,9 +475,116 @@ fn do_light_spot(light_spot: LightSpot, normal: vec3<f32>, tex_diffuse: vec3<f32
     //let spec = pow(max(dot(view_dir, reflect_dir), 0.0), shininess);
 
     let halfway_dir = normalize(light_dir + view_dir);
-    let spec = pow(max(dot(view_dir, halfway_dir), 0.0), shininess);
+    let spec = pow(max(dot(view_dir, halfway_dir), 0.0), material.shininess);
 
     diffuse *= intensity;
 
-    return ambient + diffuse;
+    return diffuse;
+}
+
+// normal map in the tangent frame of the vertex, tbn
+fn get_normal(normal: vec3<f32>, tangent: vec4<f32>, tex_normal: vec3<f32>) -> vec3<f32> {
+    if material.normal_scale == 0.0 || dot(tangent.xyz, tangent.xyz) == 0.0 {
+        return normal;
+    }
+
+    // interpolated tangent drifts from the normal, gram schmidt
+    let t = normalize(tangent.xyz - normal * dot(normal, tangent.xyz));
+    let b = cross(normal, t) * tangent.w;
+
+    let n = (tex_normal * 2.0 - 1.0) * vec3<f32>(material.normal_scale, material.normal_scale, 1.0);
+    return normalize(mat3x3<f32>(t, b, normal) * n);
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+fn geometry_schlick_ggx(n_dot_x: f32, roughness: f32) -> f32 {
+    let r = roughness + 1.0;
+    let k = r * r / 8.0;
+    return n_dot_x / (n_dot_x * (1.0 - k) + k);
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// radiance is the light reaching the fragment, before the cosine term
+fn cook_torrance(surface: Surface, light_dir: vec3<f32>, radiance: vec3<f32>) -> vec3<f32> {
+    let halfway_dir = normalize(light_dir + surface.view_dir);
+    let n_dot_l = max(dot(surface.normal, light_dir), 0.0);
+    let n_dot_v = max(dot(surface.normal, surface.view_dir), 0.0);
+    let n_dot_h = max(dot(surface.normal, halfway_dir), 0.0);
+
+    let d = distribution_ggx(n_dot_h, surface.roughness);
+    let g = geometry_schlick_ggx(n_dot_v, surface.roughness) * geometry_schlick_ggx(n_dot_l, surface.roughness);
+    let f = fresnel_schlick(max(dot(halfway_dir, surface.view_dir), 0.0), surface.f0);
+
+    let specular = d * g * f / (4.0 * n_dot_v * n_dot_l + 0.0001);
+    // refracted light, metals absorb it
+    let k_d = (vec3<f32>(1.0) - f) * (1.0 - surface.metallic);
+
+    return (k_d * surface.albedo / PI + specular) * radiance * n_dot_l;
+}
+
+// PI keeps a white light of diffuse 1 on a white surface as bright as in blinn phong
+fn get_light_radiance(color: vec3<f32>, diffuse: vec3<f32>) -> vec3<f32> {
+    return color * diffuse * PI;
+}
+
+fn do_light_direction_pbr(light_direction: LightDirection, surface: Surface, ao: f32) -> vec3<f32> {
+    if light_direction.color.a == 0.0 {
+        return vec3<f32>(0.0, 0.0, 0.0);
+    }
+
+    let light_color = light_direction.color.rgb;
+    let ambient = light_color * light_direction.ambient * surface.albedo * ao;
+
+    let light_dir = normalize(-light_direction.dir);
+    let radiance = get_light_radiance(light_color, light_direction.diffuse);
+
+    return ambient + cook_torrance(surface, light_dir, radiance);
+}
+
+fn do_light_point_pbr(light_point: LightPoint, surface: Surface, ao: f32, frag_pos: vec3<f32>, frag_pos_world: vec3<f32>) -> vec3<f32> {
+    // past the radius it is cut off, like in the clusters and the deferred light volumes
+    if light_point.color.a == 0.0 || length(light_point.pos - frag_pos) > get_light_point_radius(light_point) {
+        return vec3<f32>(0.0, 0.0, 0.0);
+    }
+
+    let light_color = light_point.color.rgb;
+
+    let len = length(light_point.pos - frag_pos);
+    let attenuation = 1.0 / (light_point.constant + light_point.linear * len + light_point.quadratic * (len * len));
+
+    let ambient = light_color * light_point.ambient * surface.albedo * ao * attenuation;
+
+    let light_dir = normalize(light_point.pos - frag_pos);
+    let radiance = get_light_radiance(light_color, light_point.diffuse) * attenuation;
+
+    let visiblity = get_point_light_visiblity(light_point.shadow_idx, frag_pos_world, surface.normal);
+
+    return ambient + visiblity * cook_torrance(surface, light_dir, radiance);
+}
+
+// without the ambient term, that is cluster_param.spot_ambient
+fn do_light_spot_pbr(light_spot: LightSpot, surface: Surface, frag_pos: vec3<f32>) -> vec3<f32> {
+    if light_spot.color.a == 0.0 {
+        return vec3<f32>(0.0, 0.0, 0.0);
+    }
+
+    let light_dir = normalize(light_spot.pos - frag_pos);
+    let light_color = light_spot.color.rgb;
+
+    let theta = dot(light_dir, normalize(-light_spot.front));
+    let epsilon = light_spot.in_cutoff - light_spot.out_cutoff;
+    let intensity = clamp((theta - light_spot.out_cutoff) / epsilon, 0.0, 1.0);
+
+    let radiance = get_light_radiance(light_color, light_spot.diffuse) * intensity;
+
+    return cook_torrance(surface, light_dir, radiance);
 }
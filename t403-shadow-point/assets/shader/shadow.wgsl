@@ -7,6 +7,7 @@ struct VertexIn {
 struct VertexOut {
     @builtin(position) clip_pos: vec4<f32>,
     @location(0) pos_world: vec3<f32>,
+    @location(1) @interpolate(flat) light_idx: u32,
 }
 
 struct TransformIT {
@@ -20,27 +21,65 @@ struct TransformIT {
     @location(12) t7: vec4<f32>,
 }
 
+struct LightPointShadow {
+    view_proj_arr: array<mat4x4<f32>, 6>,
+    pos: vec3<f32>,
+    near: f32,
+    far: f32,
+    sample_num: u32,
+    filter_radius: f32,
+    technique: u32,
+    light_bleeding: f32,
+}
+
+// the first face of the pass, the multiview pass adds the view index
 @group(0)@binding(0)
-var<uniform> view_proj: mat4x4<f32>;
+var<uniform> face_idx: u32;
 @group(0)@binding(1)
-var<uniform> light_point_pos: vec3<f32>;
-@group(0)@binding(2)
-var<uniform> near_far: vec2<f32>;
+var<storage> light_point_shadow_arr: array<LightPointShadow>;
+
+// same as EVSM_EXPONENT in pipe_shadow.rs, exp(2 * 5) still fits in a f16
+const EVSM_EXPONENT: f32 = 5.0;
 
 @vertex
 fn vs_main(in: VertexIn, transform: TransformIT) -> VertexOut {
+    return vs_face(in, transform, face_idx);
+}
+
+fn vs_face(in: VertexIn, transform: TransformIT, face_idx: u32) -> VertexOut {
     let model = mat4x4<f32>(transform.t0, transform.t1, transform.t2, transform.t3);
     //let it_model = mat3x3<f32>(transform.t4.xyz, transform.t5.xyz, transform.t6.xyz); // inverse transpose model
+    let view_proj = light_point_shadow_arr[face_idx / 6u].view_proj_arr[face_idx % 6u];
     var out: VertexOut;
     out.clip_pos = view_proj * model * vec4<f32>(in.pos, 1.0);
     out.pos_world = (model * vec4<f32>(in.pos, 1.0)).xyz;
+    out.light_idx = face_idx / 6u;
 
     return out;
 }
 
+// the distance to the light over far, linear instead of the projected depth
+fn get_depth(in: VertexOut) -> f32 {
+    let light_shadow = light_point_shadow_arr[in.light_idx];
+    return clamp(length(in.pos_world - light_shadow.pos) / light_shadow.far, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @builtin(frag_depth) f32 {
+    return get_depth(in);
+}
+
+struct MomentOut {
+    @location(0) moment: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
+}
+
+// the moments of the positive and negative exponential warp of the distance to the light
 @fragment
-fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
-    let len = length(in.pos_world - light_point_pos);
-    let v = (len - near_far.x) / (near_far.y - near_far.x);
-    return vec4<f32>(v);
+fn fs_moment(in: VertexOut) -> MomentOut {
+    let depth = get_depth(in);
+    let x = depth * 2.0 - 1.0;
+    let pos = exp(EVSM_EXPONENT * x);
+    let neg = -exp(-EVSM_EXPONENT * x);
+    return MomentOut(vec4<f32>(pos, pos * pos, neg, neg * neg), depth);
 }
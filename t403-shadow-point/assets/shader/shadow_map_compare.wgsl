@@ -0,0 +1,12 @@
+
+// appended to mesh.wgsl and deferred_lighting.wgsl, shadow_map_compare_gl.wgsl replaces it on GL
+@group(0)@binding(3)
+var texture_shadow_map: texture_depth_cube_array;
+@group(0)@binding(4)
+var sampler_shadow_map: sampler_comparison;
+
+// 1.0 where depth is not behind the cube around dir, linear filtering blends the four nearest
+// comparisons
+fn get_shadow_map_compare(dir: vec3<f32>, shadow_idx: i32, depth: f32) -> f32 {
+    return textureSampleCompareLevel(texture_shadow_map, sampler_shadow_map, dir, shadow_idx, depth);
+}
@@ -0,0 +1,41 @@
+
+// appended instead of shadow_map_compare.wgsl on GL, naga's GLSL output has no compare at an
+// explicit level on a cube array, so the four nearest depths are gathered and compared here
+@group(0)@binding(3)
+var texture_shadow_map: texture_cube_array<f32>;
+@group(0)@binding(4)
+var sampler_shadow_map: sampler;
+
+fn get_shadow_map_compare(dir: vec3<f32>, shadow_idx: i32, depth: f32) -> f32 {
+    let depth_arr = textureGather(0, texture_shadow_map, sampler_shadow_map, dir, shadow_idx);
+    let is_lit = select(vec4<f32>(0.0), vec4<f32>(1.0), vec4<f32>(depth) <= depth_arr);
+
+    // the bilinear weights a comparison sampler would use, from where dir hits the texel grid
+    let size = f32(textureDimensions(texture_shadow_map).x);
+    let f = fract(get_cube_face_uv(dir) * size - 0.5);
+    let weight = vec4<f32>(
+        (1.0 - f.x) * f.y,
+        f.x * f.y,
+        f.x * (1.0 - f.y),
+        (1.0 - f.x) * (1.0 - f.y),
+    );
+    return dot(is_lit, weight);
+}
+
+// uv on the cube face dir points at, same face selection and orientation as the texture units
+fn get_cube_face_uv(dir: vec3<f32>) -> vec2<f32> {
+    let dir_abs = abs(dir);
+    var major = 0.0;
+    var st = vec2<f32>(0.0);
+    if dir_abs.x >= dir_abs.y && dir_abs.x >= dir_abs.z {
+        major = dir_abs.x;
+        st = vec2<f32>(-sign(dir.x) * dir.z, -dir.y);
+    } else if dir_abs.y >= dir_abs.z {
+        major = dir_abs.y;
+        st = vec2<f32>(dir.x, sign(dir.y) * dir.z);
+    } else {
+        major = dir_abs.z;
+        st = vec2<f32>(sign(dir.z) * dir.x, -dir.y);
+    }
+    return (st / major + 1.0) * 0.5;
+}
@@ -0,0 +1,57 @@
+// separable gaussian blur and the mip chain of the moment cubes. every cube face is a layer
+// of its own and is blurred alone, the seams between the faces are not blended
+
+@group(0)@binding(0)
+var texture_src: texture_2d_array<f32>;
+@group(0)@binding(1)
+var texture_dst: texture_storage_2d_array<rgba16float, write>;
+
+// 9 taps, sigma 2, from the center outwards
+const BLUR_SIZE: i32 = 4;
+var<private> blur_weight_arr: array<f32, 5> = array<f32, 5>(0.2042, 0.1802, 0.1238, 0.0663, 0.0276);
+
+
+fn blur(id: vec3<u32>, dir: vec2<i32>) {
+    let size = vec2<i32>(textureDimensions(texture_src));
+    let pixel = vec2<i32>(id.xy);
+    if any(pixel >= size) {
+        return;
+    }
+    let layer = i32(id.z);
+
+    var sum = vec4<f32>(0.0);
+    for (var i = -BLUR_SIZE; i <= BLUR_SIZE; i++) {
+        let neighbour = clamp(pixel + dir * i, vec2<i32>(0), size - 1);
+        sum += blur_weight_arr[abs(i)] * textureLoad(texture_src, neighbour, layer, 0);
+    }
+    textureStore(texture_dst, pixel, layer, sum);
+}
+
+// one invocation per texel, one z per layer
+@compute @workgroup_size(8, 8, 1)
+fn cs_blur_x(@builtin(global_invocation_id) id: vec3<u32>) {
+    blur(id, vec2<i32>(1, 0));
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn cs_blur_y(@builtin(global_invocation_id) id: vec3<u32>) {
+    blur(id, vec2<i32>(0, 1));
+}
+
+// a mip level from the one above it, moments average linearly
+@compute @workgroup_size(8, 8, 1)
+fn cs_downsample(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = vec2<i32>(textureDimensions(texture_dst));
+    let pixel = vec2<i32>(id.xy);
+    if any(pixel >= size) {
+        return;
+    }
+    let layer = i32(id.z);
+
+    let src = pixel * 2;
+    let sum = textureLoad(texture_src, src, layer, 0)
+        + textureLoad(texture_src, src + vec2<i32>(1, 0), layer, 0)
+        + textureLoad(texture_src, src + vec2<i32>(0, 1), layer, 0)
+        + textureLoad(texture_src, src + vec2<i32>(1, 1), layer, 0);
+    textureStore(texture_dst, pixel, layer, sum * 0.25);
+}
@@ -0,0 +1,6 @@
+// appended to shadow.wgsl when the device has Features::MULTIVIEW, a single pass then renders
+// all six faces of a light, one view per layer
+@vertex
+fn vs_multiview(in: VertexIn, transform: TransformIT, @builtin(view_index) view_index: i32) -> VertexOut {
+    return vs_face(in, transform, face_idx + u32(view_index));
+}
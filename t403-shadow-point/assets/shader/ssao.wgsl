@@ -0,0 +1,77 @@
+// ambient occlusion, a hemisphere kernel around the normal is rotated by a tiled noise and
+// every sample behind the stored depth occludes the pixel
+
+struct VertexOut {
+    @builtin(position) clip_pos: vec4<f32>,
+    @location(0) tex_coord: vec2<f32>,
+}
+
+struct SsaoParam {
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    kernel_arr: array<vec4<f32>, 64>,
+    radius: f32,
+    bias: f32,
+    sample_count: u32,
+    blur_size: u32,
+}
+
+@group(0)@binding(0)
+var<uniform> param: SsaoParam;
+
+@group(1)@binding(0)
+var texture_normal_depth: texture_2d<f32>;
+@group(1)@binding(1)
+var texture_noise: texture_2d<f32>;
+
+// one triangle covering the screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOut {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOut;
+    out.clip_pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.tex_coord = uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(texture_normal_depth));
+    let pixel = vec2<i32>(in.clip_pos.xy);
+    let normal_depth = textureLoad(texture_normal_depth, pixel, 0);
+    let depth = normal_depth.w;
+    if depth == 0.0 {
+        return vec4<f32>(1.0);
+    }
+
+    // back from the screen position and the distance to the view space position
+    let ndc = vec2<f32>(in.tex_coord.x * 2.0 - 1.0, 1.0 - in.tex_coord.y * 2.0);
+    let view_pos = vec3<f32>(ndc.x / param.proj[0][0], ndc.y / param.proj[1][1], -1.0) * depth;
+    let normal = normal_depth.xyz;
+
+    // gram schmidt on the noise gives a tangent frame with a random turn around the normal
+    let noise = textureLoad(texture_noise, pixel % vec2<i32>(4), 0).xy * 2.0 - 1.0;
+    let random = vec3<f32>(noise, 0.0);
+    let tangent = normalize(random - normal * dot(random, normal));
+    let bitangent = cross(normal, tangent);
+    let tbn = mat3x3<f32>(tangent, bitangent, normal);
+
+    let sample_count = min(param.sample_count, 64u);
+    var occlusion = 0.0;
+    for (var i = 0u; i < sample_count; i++) {
+        let sample_pos = view_pos + tbn * param.kernel_arr[i].xyz * param.radius;
+
+        let clip_pos = param.proj * vec4<f32>(sample_pos, 1.0);
+        let uv = clip_pos.xy / clip_pos.w * vec2<f32>(0.5, -0.5) + 0.5;
+        let sample_pixel = clamp(vec2<i32>(uv * vec2<f32>(size)), vec2<i32>(0), size - 1);
+        let scene_depth = textureLoad(texture_normal_depth, sample_pixel, 0).w;
+
+        // geometry far in front of the pixel is not touching it
+        let range = smoothstep(0.0, 1.0, param.radius / abs(depth - scene_depth));
+        if scene_depth > 0.0 && scene_depth <= -sample_pos.z - param.bias {
+            occlusion += range;
+        }
+    }
+
+    return vec4<f32>(1.0 - occlusion / f32(max(sample_count, 1u)));
+}
@@ -0,0 +1,59 @@
+// box blur over the noise pattern of the ssao pass, texels of other surfaces are skipped so
+// the occlusion does not bleed over edges
+
+struct VertexOut {
+    @builtin(position) clip_pos: vec4<f32>,
+    @location(0) tex_coord: vec2<f32>,
+}
+
+struct SsaoParam {
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    kernel_arr: array<vec4<f32>, 64>,
+    radius: f32,
+    bias: f32,
+    sample_count: u32,
+    blur_size: u32,
+}
+
+@group(0)@binding(0)
+var<uniform> param: SsaoParam;
+
+@group(1)@binding(0)
+var texture_ao: texture_2d<f32>;
+@group(1)@binding(1)
+var texture_normal_depth: texture_2d<f32>;
+
+// one triangle covering the screen
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOut {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOut;
+    out.clip_pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.tex_coord = uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(texture_ao));
+    let pixel = vec2<i32>(in.clip_pos.xy);
+    let depth = textureLoad(texture_normal_depth, pixel, 0).w;
+
+    let blur_size = i32(param.blur_size);
+    var sum = 0.0;
+    var weight = 0.0;
+    for (var y = -blur_size; y <= blur_size; y++) {
+        for (var x = -blur_size; x <= blur_size; x++) {
+            let neighbour = clamp(pixel + vec2<i32>(x, y), vec2<i32>(0), size - 1);
+            let neighbour_depth = textureLoad(texture_normal_depth, neighbour, 0).w;
+            if abs(neighbour_depth - depth) < param.radius {
+                sum += textureLoad(texture_ao, neighbour, 0).r;
+                weight += 1.0;
+            }
+        }
+    }
+
+    // the pixel itself always passes, weight is at least 1
+    return vec4<f32>(sum / weight);
+}
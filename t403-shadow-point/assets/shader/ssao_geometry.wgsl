@@ -0,0 +1,56 @@
+// view space normal and linear depth of the scene, the input of the ssao pass
+
+struct VertexIn {
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+}
+
+struct VertexOut {
+    @builtin(position) clip_pos: vec4<f32>,
+    @location(0) view_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+}
+
+struct TransformIT {
+    @location(5) t0: vec4<f32>,
+    @location(6) t1: vec4<f32>,
+    @location(7) t2: vec4<f32>,
+    @location(8) t3: vec4<f32>,
+    @location(9) t4: vec4<f32>,
+    @location(10) t5: vec4<f32>,
+    @location(11) t6: vec4<f32>,
+    @location(12) t7: vec4<f32>,
+}
+
+struct SsaoParam {
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    kernel_arr: array<vec4<f32>, 64>,
+    radius: f32,
+    bias: f32,
+    sample_count: u32,
+    blur_size: u32,
+}
+
+@group(0)@binding(0)
+var<uniform> param: SsaoParam;
+
+@vertex
+fn vs_main(in: VertexIn, transform: TransformIT) -> VertexOut {
+    let model = mat4x4<f32>(transform.t0, transform.t1, transform.t2, transform.t3);
+    let it_model = mat3x3<f32>(transform.t4.xyz, transform.t5.xyz, transform.t6.xyz);
+
+    let view_pos = param.view * model * vec4<f32>(in.pos, 1.0);
+    var out: VertexOut;
+    out.clip_pos = param.proj * view_pos;
+    out.view_pos = view_pos.xyz;
+    // the view matrix only rotates and moves, normals can go through it as they are
+    out.normal = (param.view * vec4<f32>(it_model * in.normal, 0.0)).xyz;
+    return out;
+}
+
+// w is the distance in front of the camera, 0 is left where nothing is drawn
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    return vec4<f32>(normalize(in.normal), -in.view_pos.z);
+}
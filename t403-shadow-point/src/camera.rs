@@ -116,6 +116,14 @@ impl Camera {
         self.ratio = width / height;
     }
 
+    pub fn z_near(&self) -> f32 {
+        self.z_near
+    }
+
+    pub fn z_far(&self) -> f32 {
+        self.z_far
+    }
+
     pub fn view(&self) -> Mat4 {
         Mat4::look_to_rh(self.pos, self.front, self.up)
     }
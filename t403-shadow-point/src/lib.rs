@@ -1,17 +1,26 @@
 pub mod camera;
 pub mod color;
+pub use common::gltf_data;
+pub use common::headless;
 pub mod input;
 pub mod light_direction;
 pub mod light_point;
+pub mod light_point_shadow;
 pub mod light_spot;
 pub mod material;
 pub mod model;
 pub mod model_light;
+pub mod pipe_cluster;
+pub mod pipe_deferred;
 pub mod pipe_depth;
 pub mod pipe_hub;
 pub mod pipe_mesh;
 pub mod pipe_shadow;
+pub mod pipe_ssao;
 pub mod runner;
+pub mod scene;
+pub mod shadow_quality;
+pub mod shadow_technique;
 pub mod texture;
 pub mod transform;
 pub mod vertex;
@@ -0,0 +1,94 @@
+use glam::{Mat4, Vec3};
+
+use crate::{
+    light_point::LightPoint, shadow_quality::ShadowQuality, shadow_technique::ShadowTechnique,
+};
+
+pub const MAX_LIGHT_POINT_SHADOW: usize = 16;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightPointShadow {
+    // cube map order, right left up bottom back front
+    pub view_proj_arr: [[[f32; 4]; 4]; 6],
+
+    pub pos: [f32; 3],
+    pub near: f32,
+
+    pub far: f32,
+    // poisson taps, see ShadowQuality
+    pub sample_num: u32,
+    // radius of the poisson disk on the unit sphere around the light
+    pub filter_radius: f32,
+    // ShadowTechnique as u32, 0 compares depths, 1 reads moments
+    pub technique: u32,
+
+    // the moments cut off this much of the shadow's soft end, hides light bleeding through
+    // stacked casters but hardens the edges
+    pub light_bleeding: f32,
+    // 16 bytes padding
+    _padding0: [u32; 3],
+}
+
+impl LightPointShadow {
+    pub fn new(light_point: &LightPoint, near: f32, far: f32) -> Self {
+        let proj = Mat4::perspective_rh(90.0_f32.to_radians(), 1.0, near, far);
+        let view_proj_arr = Self::view_arr(light_point.pos.into())
+            .map(|view| proj.mul_mat4(&view).to_cols_array_2d());
+
+        Self {
+            view_proj_arr,
+            pos: light_point.pos,
+            near,
+            far,
+            sample_num: ShadowQuality::Medium.sample_num(),
+            filter_radius: 0.01,
+            technique: ShadowTechnique::DepthCompare as u32,
+            light_bleeding: 0.2,
+            _padding0: [0; 3],
+        }
+    }
+
+    pub fn zero() -> Self {
+        Self {
+            view_proj_arr: [Mat4::IDENTITY.to_cols_array_2d(); 6],
+            pos: [0.0, 0.0, 0.0],
+            near: 0.0,
+            far: 1.0,
+            sample_num: 1,
+            filter_radius: 0.0,
+            technique: ShadowTechnique::DepthCompare as u32,
+            light_bleeding: 0.0,
+            _padding0: [0; 3],
+        }
+    }
+
+    pub fn set_quality(&mut self, quality: ShadowQuality) {
+        self.sample_num = quality.sample_num();
+    }
+
+    pub fn set_technique(&mut self, technique: ShadowTechnique) {
+        self.technique = technique as u32;
+    }
+
+    pub fn set_light_bleeding(&mut self, light_bleeding: f32) {
+        self.light_bleeding = light_bleeding.clamp(0.0, 0.99);
+    }
+
+    pub fn view_arr(pos: Vec3) -> [Mat4; 6] {
+        [
+            // Right
+            Mat4::look_to_rh(pos, Vec3::new(1.0, 0.0, 0.0), Vec3::Y),
+            // Left
+            Mat4::look_to_rh(pos, Vec3::new(-1.0, 0.0, 0.0), Vec3::Y),
+            // Up
+            Mat4::look_to_rh(pos, Vec3::new(0.0, 1.0, 0.0), Vec3::Z),
+            // Bottom
+            Mat4::look_to_rh(pos, Vec3::new(0.0, -1.0, 0.0), Vec3::NEG_Z),
+            // Back
+            Mat4::look_to_rh(pos, Vec3::new(0.0, 0.0, -1.0), Vec3::Y),
+            // Front
+            Mat4::look_to_rh(pos, Vec3::new(0.0, 0.0, 1.0), Vec3::Y),
+        ]
+    }
+}
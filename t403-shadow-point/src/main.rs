@@ -1,7 +1,12 @@
-use t207_gltf::runner::run;
+use t207_gltf::runner::{run, run_stress};
 
 fn main() {
     env_logger::init();
 
-    run();
+    // `cargo run -- stress` for the clustered lighting stress scene
+    if std::env::args().any(|arg| arg == "stress") {
+        run_stress();
+    } else {
+        run();
+    }
 }
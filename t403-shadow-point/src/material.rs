@@ -1,35 +1,66 @@
-use wgpu::{util::DeviceExt, BindGroupLayout, Sampler};
+use wgpu::{util::DeviceExt, BindGroupLayout, Buffer, Sampler, TextureView};
 
 use crate::{model::Model, pipe_hub::PipeHub};
 
 pub struct Material {
-    pub diffuse: wgpu::TextureView,
-    pub shininess: f32,
+    pub texture: MaterialTexture,
+    pub factor: MaterialFactor,
+    pub buffer_factor: Buffer,
 
     pub bind_group: wgpu::BindGroup,
     pub model_arr: Vec<Model>,
 }
 
 impl Material {
+    // blinn phong material of the earlier chapters, normal map in tangent space
     pub fn new(
         diffuse: wgpu::TextureView,
+        normal: Option<wgpu::TextureView>,
         shininess: f32,
         core: &PipeHub,
         material_bind_group_layout: &BindGroupLayout,
         texture_sampler: &Sampler,
+    ) -> Self {
+        let mut factor = MaterialFactor::blinn_phong(shininess);
+        if normal.is_some() {
+            factor.normal_scale = 1.0;
+        }
+
+        Self::new_pbr(
+            MaterialTexture {
+                base_color: Some(diffuse),
+                normal,
+                ..Default::default()
+            },
+            factor,
+            core,
+            material_bind_group_layout,
+            texture_sampler,
+        )
+    }
+
+    pub fn new_pbr(
+        texture: MaterialTexture,
+        factor: MaterialFactor,
+        core: &PipeHub,
+        material_bind_group_layout: &BindGroupLayout,
+        texture_sampler: &Sampler,
     ) -> Self {
         let device = &core.device;
 
-        let color_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Color Buffer"),
-            contents: bytemuck::cast_slice(&[1.0, 0.5, 0.31]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-        let shininess_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Material Buffer"),
-            contents: bytemuck::bytes_of(&shininess),
+        let buffer_factor = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Buffer Material Factor"),
+            contents: bytemuck::cast_slice(&[factor]),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
+
+        // missing textures sample as white, or as a flat normal
+        let texture_view_white = &core.pipe_mesh.texture_view_white;
+        let texture_view_normal = texture
+            .normal
+            .as_ref()
+            .unwrap_or(&core.pipe_mesh.texture_view_normal_flat);
+
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Material Bind Group"),
             layout: material_bind_group_layout,
@@ -40,26 +71,48 @@ impl Material {
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
-                    resource: wgpu::BindingResource::TextureView(&diffuse),
+                    resource: wgpu::BindingResource::TextureView(
+                        texture.base_color.as_ref().unwrap_or(texture_view_white),
+                    ),
                 },
                 wgpu::BindGroupEntry {
                     binding: 2,
                     resource: wgpu::BindingResource::Buffer(
-                        color_buffer.as_entire_buffer_binding(),
+                        buffer_factor.as_entire_buffer_binding(),
                     ),
                 },
                 wgpu::BindGroupEntry {
                     binding: 3,
-                    resource: wgpu::BindingResource::Buffer(
-                        shininess_buffer.as_entire_buffer_binding(),
+                    resource: wgpu::BindingResource::TextureView(
+                        texture
+                            .metallic_roughness
+                            .as_ref()
+                            .unwrap_or(texture_view_white),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(texture_view_normal),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(
+                        texture.occlusion.as_ref().unwrap_or(texture_view_white),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::TextureView(
+                        texture.emissive.as_ref().unwrap_or(texture_view_white),
                     ),
                 },
             ],
         });
 
         Self {
-            diffuse,
-            shininess,
+            texture,
+            factor,
+            buffer_factor,
             bind_group,
             model_arr: vec![],
         }
@@ -69,3 +122,60 @@ impl Material {
         self.model_arr.push(model);
     }
 }
+
+// texture inputs of a gltf metallic roughness material
+#[derive(Default)]
+pub struct MaterialTexture {
+    // srgb
+    pub base_color: Option<TextureView>,
+    // linear, roughness in g, metallic in b
+    pub metallic_roughness: Option<TextureView>,
+    // linear, tangent space
+    pub normal: Option<TextureView>,
+    // linear, occlusion in r
+    pub occlusion: Option<TextureView>,
+    // srgb
+    pub emissive: Option<TextureView>,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MaterialFactor {
+    // linear, multiplied with the textures
+    pub base_color: [f32; 4],
+    pub emissive: [f32; 3],
+    pub metallic: f32,
+    pub roughness: f32,
+    pub normal_scale: f32,
+    pub occlusion_strength: f32,
+    pub shininess: f32,
+    // 0 blinn phong, 1 cook torrance
+    pub is_pbr: u32,
+    // 16 bytes padding
+    _padding0: [u32; 3],
+}
+
+impl MaterialFactor {
+    pub fn blinn_phong(shininess: f32) -> Self {
+        Self {
+            shininess,
+            is_pbr: 0,
+            ..Self::metallic_roughness([1.0; 4], 0.0, 1.0)
+        }
+    }
+
+    pub fn metallic_roughness(base_color: [f32; 4], metallic: f32, roughness: f32) -> Self {
+        Self {
+            base_color,
+            emissive: [0.0; 3],
+            metallic,
+            roughness,
+            // no normal map
+            normal_scale: 0.0,
+            occlusion_strength: 1.0,
+            shininess: 32.0,
+            is_pbr: 1,
+            _padding0: [0; 3],
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use wgpu::{util::DeviceExt, Buffer, Device};
 
-use crate::{transform::Transform, vertex::Vertex};
+use crate::{
+    transform::{Transform, TransformRawIT},
+    vertex::Vertex,
+};
 
 #[derive(Debug)]
 pub struct Model {
@@ -20,6 +23,21 @@ impl Model {
         vertices: Vec<Vertex>,
         indices: Vec<u32>,
         transform_arr: Vec<Transform>,
+    ) -> Self {
+        let transform_raw_arr = transform_arr
+            .iter()
+            .map(|t| t.to_raw_it())
+            .collect::<Vec<_>>();
+        Self::new_raw(device, draw_method, vertices, indices, &transform_raw_arr)
+    }
+
+    // instances given as their world matrices already packed for the vertex buffer
+    pub fn new_raw(
+        device: &Device,
+        draw_method: DrawMethod,
+        vertices: Vec<Vertex>,
+        indices: Vec<u32>,
+        transform_raw_arr: &[TransformRawIT],
     ) -> Self {
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
@@ -32,14 +50,9 @@ impl Model {
             usage: wgpu::BufferUsages::INDEX,
         });
 
-        let transform_mat_arr = transform_arr
-            .iter()
-            .map(|t| t.to_raw_it())
-            .collect::<Vec<_>>();
-
         let transform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Transform Buffer"),
-            contents: bytemuck::cast_slice(&transform_mat_arr),
+            contents: bytemuck::cast_slice(transform_raw_arr),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
@@ -50,7 +63,7 @@ impl Model {
             transform_buffer,
             vertices_len: vertices.len() as u32,
             indices_len: indices.len() as u32,
-            instance_num: transform_arr.len() as u32,
+            instance_num: transform_raw_arr.len() as u32,
         }
     }
 }
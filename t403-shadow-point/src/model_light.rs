@@ -1,4 +1,4 @@
-use wgpu::{util::DeviceExt, Buffer, BufferUsages, Device};
+use wgpu::{util::DeviceExt, Buffer, BufferUsages, Device, Queue};
 
 use crate::{transform::Transform, vertex_light::VertexLight};
 
@@ -26,7 +26,7 @@ impl ModelLight {
         let transform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Transform Buffer"),
             contents: bytemuck::cast_slice(&transform_mat_arr),
-            usage: wgpu::BufferUsages::VERTEX,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         });
 
         Self {
@@ -36,4 +36,14 @@ impl ModelLight {
             instance_len: transform_arr.len() as _,
         }
     }
+
+    // same instance count, only moves them
+    pub fn set_transform_arr(&self, queue: &Queue, transform_arr: &[Transform]) {
+        let transform_mat_arr = transform_arr.iter().map(|t| t.to_raw()).collect::<Vec<_>>();
+        queue.write_buffer(
+            &self.transform_buffer,
+            0,
+            bytemuck::cast_slice(&transform_mat_arr),
+        );
+    }
 }
@@ -0,0 +1,195 @@
+use wgpu::{
+    BindGroup, BindGroupLayout, CommandEncoder, ComputePipeline, Device, Queue,
+    SurfaceConfiguration,
+};
+
+use crate::{camera::Camera, light_spot::LightSpot, pipe_mesh::PipeMesh};
+
+// froxel grid, screen tiles in x and y, exponential depth slices between near and far
+pub const CLUSTER_X: u32 = 16;
+pub const CLUSTER_Y: u32 = 9;
+pub const CLUSTER_Z: u32 = 24;
+pub const CLUSTER_NUM: u32 = CLUSTER_X * CLUSTER_Y * CLUSTER_Z;
+// point and spot lights together, the rest of a crowded cluster is dropped
+pub const MAX_LIGHT_PER_CLUSTER: u32 = 256;
+// point count, spot count, then the point indices followed by the spot indices
+pub const CLUSTER_LIGHT_SIZE: u64 = (CLUSTER_NUM * (2 + MAX_LIGHT_PER_CLUSTER)) as u64 * 4;
+const WORKGROUP_SIZE: u32 = 64;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ClusterParam {
+    view: [[f32; 4]; 4],
+    inv_proj: [[f32; 4]; 4],
+    // spot lights are culled by their cone, their ambient term lights everything so it
+    // is added up here instead
+    spot_ambient: [f32; 3],
+    // 0 shades every light in every fragment
+    is_clustered: u32,
+    grid_size: [u32; 3],
+    z_near: f32,
+    screen_size: [f32; 2],
+    z_far: f32,
+    max_light_per_cluster: u32,
+}
+
+impl ClusterParam {
+    pub fn new(
+        camera: &Camera,
+        surface_config: &SurfaceConfiguration,
+        light_spot_arr: &[LightSpot],
+        is_clustered: bool,
+    ) -> Self {
+        let spot_ambient = light_spot_arr
+            .iter()
+            .filter(|light_spot| light_spot.color[3] != 0.0)
+            .fold([0.0; 3], |sum, light_spot| {
+                [0, 1, 2].map(|i| sum[i] + light_spot.color[i] * light_spot.ambient[i])
+            });
+        Self {
+            view: camera.view().to_cols_array_2d(),
+            inv_proj: camera.proj().inverse().to_cols_array_2d(),
+            spot_ambient,
+            is_clustered: is_clustered as u32,
+            grid_size: [CLUSTER_X, CLUSTER_Y, CLUSTER_Z],
+            z_near: camera.z_near(),
+            screen_size: [surface_config.width as f32, surface_config.height as f32],
+            z_far: camera.z_far(),
+            max_light_per_cluster: MAX_LIGHT_PER_CLUSTER,
+        }
+    }
+}
+
+// clustered forward shading, a compute pass bins the point and spot lights into the
+// froxels they reach, pipe_mesh then only shades the lights of the fragment's cluster.
+// the buffers live in pipe_mesh, this pass only fills them
+pub struct PipeCluster {
+    // off makes pipe_mesh loop over every light again
+    pub is_enabled: bool,
+
+    pub bind_group_layout: BindGroupLayout,
+    pub bind_group: BindGroup,
+    pub compute_pipline: ComputePipeline,
+}
+
+impl PipeCluster {
+    pub fn new(device: &Device, pipe_mesh: &PipeMesh) -> Self {
+        let shader = std::fs::read_to_string("assets/shader/cluster.wgsl").unwrap();
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader Cluster"),
+            source: wgpu::ShaderSource::Wgsl(shader.into()),
+        });
+
+        let buffer_entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Bind Group Layout Cluster"),
+            entries: &[
+                buffer_entry(0, wgpu::BufferBindingType::Uniform),
+                buffer_entry(1, wgpu::BufferBindingType::Storage { read_only: false }),
+                buffer_entry(2, wgpu::BufferBindingType::Storage { read_only: true }),
+                buffer_entry(3, wgpu::BufferBindingType::Storage { read_only: true }),
+            ],
+        });
+        let bind_group = gen_bind_group(device, &bind_group_layout, pipe_mesh);
+
+        let compute_pipline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Compute Pipline Layout Cluster"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let compute_pipline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Compute Pipline Cluster"),
+            layout: Some(&compute_pipline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        Self {
+            is_enabled: true,
+            bind_group_layout,
+            bind_group,
+            compute_pipline,
+        }
+    }
+
+    // after pipe_mesh made new light buffers for more lights
+    pub fn set_light_buffer(&mut self, device: &Device, pipe_mesh: &PipeMesh) {
+        self.bind_group = gen_bind_group(device, &self.bind_group_layout, pipe_mesh);
+    }
+
+    // after the camera and the lights moved
+    pub fn update(
+        &self,
+        queue: &Queue,
+        surface_config: &SurfaceConfiguration,
+        pipe_mesh: &PipeMesh,
+    ) {
+        let param = ClusterParam::new(
+            &pipe_mesh.camera,
+            surface_config,
+            &pipe_mesh.light_spot_arr,
+            self.is_enabled,
+        );
+        queue.write_buffer(
+            &pipe_mesh.buffer_cluster_param,
+            0,
+            bytemuck::bytes_of(&param),
+        );
+    }
+
+    pub fn render(&self, encoder: &mut CommandEncoder) {
+        if !self.is_enabled {
+            return;
+        }
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Compute Pass Cluster"),
+        });
+        compute_pass.set_pipeline(&self.compute_pipline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups(CLUSTER_NUM.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+}
+
+fn gen_bind_group(device: &Device, layout: &BindGroupLayout, pipe_mesh: &PipeMesh) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Bind Group Cluster"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(
+                    pipe_mesh.buffer_cluster_param.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Buffer(
+                    pipe_mesh.buffer_cluster_light.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer(
+                    pipe_mesh.buffer_light_point.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Buffer(
+                    pipe_mesh.buffer_light_spot.as_entire_buffer_binding(),
+                ),
+            },
+        ],
+    })
+}
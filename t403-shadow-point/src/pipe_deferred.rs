@@ -0,0 +1,520 @@
+use glam::{Quat, Vec3};
+use wgpu::{
+    util::DeviceExt, Backend, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device,
+    IndexFormat, PipelineLayout, RenderPipeline, ShaderModule, SurfaceConfiguration, TextureFormat,
+    TextureView,
+};
+
+use crate::{
+    light_point::LightPoint,
+    model::DrawMethod,
+    model_light::ModelLight,
+    pipe_mesh::{read_shader_shadow_map_compare, PipeMesh},
+    texture::{gen_texture_depth, gen_texture_view_target, DEPTH_FORMAT},
+    transform::{Transform, TransformRaw, TransformRawIT},
+    vertex::Vertex,
+    vertex_light::VertexLight,
+};
+
+// world space, f32 so the lighting doesn't band far from the origin
+const POSITION_FORMAT: TextureFormat = TextureFormat::Rgba32Float;
+const NORMAL_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+const ALBEDO_SPECULAR_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+// lights without attenuation still get a volume the camera can see all of
+const MAX_LIGHT_RADIUS: f32 = 100.0;
+
+// what the deferred path shows, the lit scene or one channel of the g-buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GBufferView {
+    Lighting,
+    Position,
+    Normal,
+    Albedo,
+    Specular,
+    Shininess,
+}
+
+impl GBufferView {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Lighting => Self::Position,
+            Self::Position => Self::Normal,
+            Self::Normal => Self::Albedo,
+            Self::Albedo => Self::Specular,
+            Self::Specular => Self::Shininess,
+            Self::Shininess => Self::Lighting,
+        }
+    }
+}
+
+// deferred shading, an alternative to PipeMesh that shares its camera, lights and materials
+pub struct PipeDeferred {
+    pub view: GBufferView,
+    pub buffer_view: Buffer,
+
+    pub render_pipline_gbuffer: RenderPipeline,
+    // direction and spot lights over the whole screen
+    pub render_pipline_lighting: RenderPipeline,
+    // point lights, one volume each
+    pub render_pipline_volume: RenderPipeline,
+    pub render_pipline_debug: RenderPipeline,
+
+    pub texture_view_depth: TextureView,
+    pub texture_view_position: TextureView,
+    pub texture_view_normal: TextureView,
+    pub texture_view_albedo_specular: TextureView,
+    pub bind_group_layout_gbuffer: BindGroupLayout,
+    pub bind_group_gbuffer: BindGroup,
+
+    // a cube instance per point light, scaled to its radius
+    pub model_light_volume: Option<ModelLight>,
+}
+
+impl PipeDeferred {
+    pub fn new(
+        device: &Device,
+        surface_config: &SurfaceConfiguration,
+        pipe_mesh: &PipeMesh,
+    ) -> Self {
+        let view = GBufferView::Lighting;
+        let buffer_view = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Buffer GBuffer View"),
+            contents: bytemuck::cast_slice(&[view as u32]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout_gbuffer =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Bind Group Layout GBuffer"),
+                entries: &[
+                    texture_entry(0),
+                    texture_entry(1),
+                    texture_entry(2),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        // the material sits at group 2 like in the mesh pass, group 1 is unused
+        let render_pipline_layout_gbuffer =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipline Layout GBuffer"),
+                bind_group_layouts: &[
+                    &pipe_mesh.bind_group_layout_camera,
+                    &pipe_mesh.bind_group_layout_light_arr,
+                    &pipe_mesh.bind_group_layout_material,
+                ],
+                push_constant_ranges: &[],
+            });
+        let render_pipline_layout_lighting =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipline Layout Deferred Lighting"),
+                bind_group_layouts: &[
+                    &pipe_mesh.bind_group_layout_camera,
+                    &pipe_mesh.bind_group_layout_light_arr,
+                    &bind_group_layout_gbuffer,
+                    &pipe_mesh.bind_group_layout_ao,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let shader_gbuffer = gen_shader(device, "assets/shader/deferred_gbuffer.wgsl");
+        let render_pipline_gbuffer =
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Render Pipline GBuffer"),
+                layout: Some(&render_pipline_layout_gbuffer),
+                vertex: wgpu::VertexState {
+                    module: &shader_gbuffer,
+                    entry_point: "vs_main",
+                    buffers: &[
+                        Vertex::vertex_buffer_layout(),
+                        TransformRawIT::vertex_buffer_layout(),
+                    ],
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader_gbuffer,
+                    entry_point: "fs_main",
+                    targets: &[
+                        Some(POSITION_FORMAT.into()),
+                        Some(NORMAL_FORMAT.into()),
+                        Some(ALBEDO_SPECULAR_FORMAT.into()),
+                    ],
+                }),
+                multiview: None,
+            });
+
+        let mut shader_lighting =
+            std::fs::read_to_string("assets/shader/deferred_lighting.wgsl").unwrap();
+        shader_lighting += &read_shader_shadow_map_compare(pipe_mesh.backend);
+        let shader_lighting = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("assets/shader/deferred_lighting.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(shader_lighting.into()),
+        });
+        let render_pipline_lighting = gen_render_pipline_screen(
+            device,
+            &render_pipline_layout_lighting,
+            &shader_lighting,
+            "fs_main",
+            surface_config.format,
+        );
+        let render_pipline_debug = gen_render_pipline_screen(
+            device,
+            &render_pipline_layout_lighting,
+            &shader_lighting,
+            "fs_debug",
+            surface_config.format,
+        );
+        let render_pipline_volume =
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Render Pipline Deferred Volume"),
+                layout: Some(&render_pipline_layout_lighting),
+                vertex: wgpu::VertexState {
+                    module: &shader_lighting,
+                    entry_point: "vs_volume",
+                    buffers: &[
+                        VertexLight::vertex_buffer_layout(),
+                        TransformRaw::vertex_buffer_layout(),
+                    ],
+                },
+                // the back faces still cover the pixels when the camera is inside the volume
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Front),
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader_lighting,
+                    entry_point: "fs_volume",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: surface_config.format,
+                        blend: Some(wgpu::BlendState {
+                            color: wgpu::BlendComponent {
+                                src_factor: wgpu::BlendFactor::One,
+                                dst_factor: wgpu::BlendFactor::One,
+                                operation: wgpu::BlendOperation::Add,
+                            },
+                            alpha: wgpu::BlendComponent::REPLACE,
+                        }),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            });
+
+        let (
+            texture_view_depth,
+            texture_view_position,
+            texture_view_normal,
+            texture_view_albedo_specular,
+        ) = gen_texture_view_gbuffer(device, surface_config, pipe_mesh.backend);
+        let bind_group_gbuffer = gen_bind_group_gbuffer(
+            device,
+            &bind_group_layout_gbuffer,
+            [
+                &texture_view_position,
+                &texture_view_normal,
+                &texture_view_albedo_specular,
+            ],
+            &buffer_view,
+        );
+
+        Self {
+            view,
+            buffer_view,
+
+            render_pipline_gbuffer,
+            render_pipline_lighting,
+            render_pipline_volume,
+            render_pipline_debug,
+
+            texture_view_depth,
+            texture_view_position,
+            texture_view_normal,
+            texture_view_albedo_specular,
+            bind_group_layout_gbuffer,
+            bind_group_gbuffer,
+
+            model_light_volume: None,
+        }
+    }
+
+    pub fn resize(
+        &mut self,
+        device: &Device,
+        surface_config: &SurfaceConfiguration,
+        backend: Backend,
+    ) {
+        (
+            self.texture_view_depth,
+            self.texture_view_position,
+            self.texture_view_normal,
+            self.texture_view_albedo_specular,
+        ) = gen_texture_view_gbuffer(device, surface_config, backend);
+        self.bind_group_gbuffer = gen_bind_group_gbuffer(
+            device,
+            &self.bind_group_layout_gbuffer,
+            [
+                &self.texture_view_position,
+                &self.texture_view_normal,
+                &self.texture_view_albedo_specular,
+            ],
+            &self.buffer_view,
+        );
+    }
+
+    pub fn set_view(&mut self, queue: &wgpu::Queue, view: GBufferView) {
+        self.view = view;
+        queue.write_buffer(&self.buffer_view, 0, bytemuck::cast_slice(&[view as u32]));
+    }
+
+    // the volumes follow the point lights, call after one is added
+    pub fn set_light_point_arr(&mut self, device: &Device, light_point_arr: &[LightPoint]) {
+        let transform_arr = gen_transform_volume_arr(light_point_arr);
+        self.model_light_volume = (!transform_arr.is_empty())
+            .then(|| ModelLight::new(device, VertexLight::cube([1.0; 3]).into(), transform_arr));
+    }
+
+    // lights moved, their number is the same as in the last set_light_point_arr
+    pub fn update_light_point_arr(&self, queue: &wgpu::Queue, light_point_arr: &[LightPoint]) {
+        if let Some(model_light_volume) = &self.model_light_volume {
+            model_light_volume.set_transform_arr(queue, &gen_transform_volume_arr(light_point_arr));
+        }
+    }
+
+    pub fn render(
+        &self,
+        encoder: &mut CommandEncoder,
+        texture_view: &TextureView,
+        pipe_mesh: &PipeMesh,
+    ) {
+        {
+            let color_attachment = |view| {
+                Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })
+            };
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass GBuffer"),
+                color_attachments: &[
+                    color_attachment(&self.texture_view_position),
+                    color_attachment(&self.texture_view_normal),
+                    color_attachment(&self.texture_view_albedo_specular),
+                ],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.texture_view_depth,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            render_pass.set_pipeline(&self.render_pipline_gbuffer);
+            render_pass.set_bind_group(0, &pipe_mesh.bind_group_camera, &[]);
+            render_pass.set_bind_group(1, &pipe_mesh.bind_group_light_arr, &[]);
+            for material in &pipe_mesh.material_arr {
+                render_pass.set_bind_group(2, &material.bind_group, &[]);
+                for model in &material.model_arr {
+                    render_pass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
+                    render_pass.set_vertex_buffer(1, model.transform_buffer.slice(..));
+                    if model.draw_method == DrawMethod::Vertex {
+                        render_pass.draw(0..model.vertices_len, 0..model.instance_num);
+                    } else {
+                        render_pass
+                            .set_index_buffer(model.index_buffer.slice(..), IndexFormat::Uint32);
+                        render_pass.draw_indexed(0..model.indices_len, 0, 0..model.instance_num);
+                    }
+                }
+            }
+        }
+
+        // same clear color as the mesh pass, the lighting skips pixels without geometry
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass Deferred Lighting"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: texture_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: 0.1,
+                        g: 0.2,
+                        b: 0.3,
+                        a: 1.0,
+                    }),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_bind_group(0, &pipe_mesh.bind_group_camera, &[]);
+        render_pass.set_bind_group(1, &pipe_mesh.bind_group_light_arr, &[]);
+        render_pass.set_bind_group(2, &self.bind_group_gbuffer, &[]);
+        render_pass.set_bind_group(3, &pipe_mesh.bind_group_ao, &[]);
+
+        if self.view != GBufferView::Lighting {
+            render_pass.set_pipeline(&self.render_pipline_debug);
+            render_pass.draw(0..3, 0..1);
+            return;
+        }
+
+        render_pass.set_pipeline(&self.render_pipline_lighting);
+        render_pass.draw(0..3, 0..1);
+
+        if let Some(model_light_volume) = &self.model_light_volume {
+            render_pass.set_pipeline(&self.render_pipline_volume);
+            render_pass.set_vertex_buffer(0, model_light_volume.vertex_buffer.slice(..));
+            render_pass.set_vertex_buffer(1, model_light_volume.transform_buffer.slice(..));
+            render_pass.draw(
+                0..model_light_volume.vertices_len,
+                0..model_light_volume.instance_len,
+            );
+        }
+    }
+}
+
+fn gen_shader(device: &Device, path: &str) -> ShaderModule {
+    let shader = std::fs::read_to_string(path).unwrap();
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(path),
+        source: wgpu::ShaderSource::Wgsl(shader.into()),
+    })
+}
+
+// fullscreen triangle, the lighting of direction and spot lights or the debug view
+fn gen_render_pipline_screen(
+    device: &Device,
+    render_pipline_layout: &PipelineLayout,
+    shader: &ShaderModule,
+    entry_point: &str,
+    format: TextureFormat,
+) -> RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipline Deferred Screen"),
+        layout: Some(render_pipline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point,
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    })
+}
+
+// depth, position, normal and albedo specular
+fn gen_texture_view_gbuffer(
+    device: &Device,
+    surface_config: &SurfaceConfiguration,
+    backend: Backend,
+) -> (TextureView, TextureView, TextureView, TextureView) {
+    (
+        gen_texture_depth(
+            device,
+            surface_config.width,
+            surface_config.height,
+            1,
+            backend,
+        )
+        .create_view(&wgpu::TextureViewDescriptor::default()),
+        gen_texture_view_target(device, surface_config, POSITION_FORMAT),
+        gen_texture_view_target(device, surface_config, NORMAL_FORMAT),
+        gen_texture_view_target(device, surface_config, ALBEDO_SPECULAR_FORMAT),
+    )
+}
+
+fn gen_bind_group_gbuffer(
+    device: &Device,
+    layout: &BindGroupLayout,
+    texture_view_arr: [&TextureView; 3],
+    buffer_view: &Buffer,
+) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Bind Group GBuffer"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(texture_view_arr[0]),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(texture_view_arr[1]),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(texture_view_arr[2]),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Buffer(buffer_view.as_entire_buffer_binding()),
+            },
+        ],
+    })
+}
+
+// unit cubes scaled to cover each light's radius
+fn gen_transform_volume_arr(light_point_arr: &[LightPoint]) -> Vec<Transform> {
+    light_point_arr
+        .iter()
+        .map(|light_point| {
+            Transform::new(
+                light_point.pos.into(),
+                Quat::IDENTITY,
+                Vec3::splat(light_point.radius().min(MAX_LIGHT_RADIUS)),
+            )
+        })
+        .collect()
+}
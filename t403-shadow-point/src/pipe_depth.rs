@@ -1,9 +1,12 @@
 use wgpu::{
-    util::DeviceExt, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device, IndexFormat,
-    RenderPipeline, SurfaceConfiguration, TextureView,
+    util::DeviceExt, Backend, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device,
+    IndexFormat, RenderPipeline, SurfaceConfiguration, TextureView,
 };
 
-use crate::{texture::gen_sampler_clamp, vertex::Vertex};
+use crate::{
+    texture::{gen_sampler_clamp, gen_sampler_nearest},
+    vertex::Vertex,
+};
 
 pub struct PipeDepth {
     pub render_pipline: RenderPipeline,
@@ -23,7 +26,10 @@ impl PipeDepth {
         surface_config: &SurfaceConfiguration,
         texture_view_depth: &TextureView,
         is_orth: bool,
+        backend: Backend,
     ) -> Self {
+        // see depth_read_gl.wgsl
+        let is_gl = backend == Backend::Gl;
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Bind Group Layout Pipe Depth"),
             entries: &[
@@ -31,7 +37,11 @@ impl PipeDepth {
                     binding: 0,
                     visibility: wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Depth,
+                        sample_type: if is_gl {
+                            wgpu::TextureSampleType::Float { filterable: false }
+                        } else {
+                            wgpu::TextureSampleType::Depth
+                        },
                         view_dimension: wgpu::TextureViewDimension::D2,
                         multisampled: false,
                     },
@@ -40,7 +50,11 @@ impl PipeDepth {
                 wgpu::BindGroupLayoutEntry {
                     binding: 1,
                     visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    ty: wgpu::BindingType::Sampler(if is_gl {
+                        wgpu::SamplerBindingType::NonFiltering
+                    } else {
+                        wgpu::SamplerBindingType::Filtering
+                    }),
                     count: None,
                 },
                 wgpu::BindGroupLayoutEntry {
@@ -63,7 +77,13 @@ impl PipeDepth {
                 push_constant_ranges: &[],
             });
 
-        let shader = std::fs::read_to_string("assets/shader/depth.wgsl").unwrap();
+        let mut shader = std::fs::read_to_string("assets/shader/depth.wgsl").unwrap();
+        shader += &std::fs::read_to_string(if is_gl {
+            "assets/shader/depth_read_gl.wgsl"
+        } else {
+            "assets/shader/depth_read.wgsl"
+        })
+        .unwrap();
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader Pipe Depth"),
             source: wgpu::ShaderSource::Wgsl(shader.into()),
@@ -110,7 +130,11 @@ impl PipeDepth {
             usage: wgpu::BufferUsages::UNIFORM,
         });
 
-        let texture_sampler = gen_sampler_clamp(device);
+        let texture_sampler = if is_gl {
+            gen_sampler_nearest(device)
+        } else {
+            gen_sampler_clamp(device)
+        };
 
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Bind Group Pipe Depth"),
@@ -1,36 +1,76 @@
 use std::time::Instant;
 
+use anyhow::{anyhow, Result};
+use image::RgbaImage;
+use log::{debug, warn};
 use wgpu::{
-    util::DeviceExt, Adapter, Backends, Device, DeviceDescriptor, Features, Instance, PresentMode,
-    Queue, Surface, SurfaceConfiguration, TextureUsages,
+    Adapter, Backends, CompositeAlphaMode, Device, DeviceDescriptor, Features, Instance,
+    PresentMode, Queue, Surface, SurfaceConfiguration, Texture, TextureFormat, TextureUsages,
 };
 use winit::{
-    dpi::PhysicalPosition, event::MouseScrollDelta, event_loop::EventLoop, window::Window,
+    dpi::PhysicalPosition,
+    event::{MouseScrollDelta, VirtualKeyCode},
+    event_loop::EventLoop,
+    window::Window,
 };
 
 use crate::{
-    input::Input, light_point::LightPoint, model_light::ModelLight, pipe_depth::PipeDepth,
-    pipe_mesh::PipeMesh, pipe_shadow::PipeShadow, texture::DEPTH_FORMAT,
+    headless::{
+        check_format_headless, gen_texture_headless, request_adapter_headless, texture_to_image,
+    },
+    input::Input,
+    light_point::LightPoint,
+    light_point_shadow::MAX_LIGHT_POINT_SHADOW,
+    light_spot::LightSpot,
+    model_light::ModelLight,
+    pipe_cluster::PipeCluster,
+    pipe_deferred::PipeDeferred,
+    pipe_depth::PipeDepth,
+    pipe_mesh::PipeMesh,
+    pipe_shadow::PipeShadow,
+    pipe_ssao::PipeSsao,
+    shadow_quality::ShadowQuality,
+    shadow_technique::ShadowTechnique,
+    texture::{supported_sample_count, supported_sample_count_arr},
 };
 
+// msaa sample count to start with, lowered when the adapter can't do it
+const SAMPLE_COUNT: u32 = 4;
+
 pub struct PipeHub {
-    pub window: Window,
+    pub window: Option<Window>,
     pub instance: Instance,
-    pub surface: Surface,
+    pub surface: Option<Surface>,
     pub adapter: Adapter,
     pub device: Device,
     pub queue: Queue,
     pub surface_config: SurfaceConfiguration,
+    // offscreen target when there is no surface
+    pub texture_headless: Option<Texture>,
     pub input: Input,
 
     pub pipe_shadow: PipeShadow,
+    // given to every new shadow casting light, q steps all of them through the presets
+    pub shadow_quality: ShadowQuality,
+    // the same for the light bleeding reduction of the moment shadows, b steps it
+    pub light_bleeding: f32,
+    pub pipe_ssao: PipeSsao,
     pub pipe_mesh: PipeMesh,
+    pub pipe_cluster: PipeCluster,
+    // renders instead of pipe_mesh when is_deferred
+    pub pipe_deferred: PipeDeferred,
+    pub is_deferred: bool,
     pub pipe_depth: PipeDepth,
 
     pub model_light_arr: Vec<ModelLight>,
+    // point lights without a shadow circle the scene
+    pub is_light_point_orbiting: bool,
 
     pub start_time: Instant,
     pub last_time: Instant,
+    // frames and seconds since the frame time was last logged
+    frame_num: u32,
+    frame_time_sum: f32,
 }
 
 impl PipeHub {
@@ -53,18 +93,6 @@ impl PipeHub {
             .await
             .unwrap();
 
-        let (device, queue) = adapter
-            .request_device(
-                &DeviceDescriptor {
-                    label: Some("Device"),
-                    features: Features::empty(),
-                    limits: Default::default(),
-                },
-                None,
-            )
-            .await
-            .unwrap();
-
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_config = SurfaceConfiguration {
             usage: TextureUsages::RENDER_ATTACHMENT,
@@ -75,42 +103,101 @@ impl PipeHub {
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
         };
-        surface.configure(&device, &surface_config);
+
+        Self::init(
+            instance,
+            adapter,
+            Some(window),
+            Some(surface),
+            surface_config,
+        )
+        .await
+        .unwrap()
+    }
+
+    // render into an offscreen texture of the given size and format instead of a window
+    pub async fn new_headless(width: u32, height: u32, format: TextureFormat) -> Result<Self> {
+        check_format_headless(format)?;
+
+        let instance = Instance::new(wgpu::InstanceDescriptor {
+            backends: Backends::all(),
+            dx12_shader_compiler: Default::default(),
+        });
+
+        let adapter = request_adapter_headless(&instance)
+            .await
+            .ok_or_else(|| anyhow!("no adapter for headless rendering"))?;
+
+        let surface_config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            format,
+            width,
+            height,
+            present_mode: PresentMode::Fifo,
+            alpha_mode: CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+
+        Self::init(instance, adapter, None, None, surface_config).await
+    }
+
+    async fn init(
+        instance: Instance,
+        adapter: Adapter,
+        window: Option<Window>,
+        surface: Option<Surface>,
+        surface_config: SurfaceConfiguration,
+    ) -> Result<Self> {
+        let (device, queue) = adapter
+            .request_device(
+                &DeviceDescriptor {
+                    label: Some("Device"),
+                    // lets the msaa sample count go beyond what webgpu guarantees, and the point
+                    // shadows render all cube faces in one pass where multiview is there
+                    features: adapter.features()
+                        & (Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+                            | Features::MULTIVIEW),
+                    limits: Default::default(),
+                },
+                None,
+            )
+            .await?;
+
+        let texture_headless = match &surface {
+            Some(surface) => {
+                surface.configure(&device, &surface_config);
+                None
+            }
+            None => Some(gen_texture_headless(&device, &surface_config)),
+        };
 
         let input = Input::new();
 
-        let pipe_shadow = PipeShadow::new(&device, &surface_config, 1024 * 2, 1024 * 2);
+        let pipe_shadow = PipeShadow::new(&device, 1024, 1024);
+        let backend = adapter.get_info().backend;
+        let pipe_ssao = PipeSsao::new(&device, &queue, &surface_config, backend);
+        let sample_count =
+            supported_sample_count(&adapter, &device, surface_config.format, SAMPLE_COUNT);
         let pipe_mesh = PipeMesh::new(
             &device,
+            &queue,
             &surface_config,
-            &pipe_shadow
-                .texture_cube
-                .create_view(&wgpu::TextureViewDescriptor {
-                    label: Some("Texture View Shadow Map"),
-                    dimension: Some(wgpu::TextureViewDimension::Cube),
-                    ..Default::default()
-                }),
-            &pipe_shadow.buffer_near_far,
+            &pipe_shadow,
+            sample_count,
+            &pipe_ssao.texture_view_ao,
+            backend,
         );
+        let pipe_cluster = PipeCluster::new(&device, &pipe_mesh);
+        let pipe_deferred = PipeDeferred::new(&device, &surface_config, &pipe_mesh);
         let pipe_depth = PipeDepth::new(
             &device,
             &surface_config,
-            &pipe_shadow
-                .texture_depth
-                .create_view(&wgpu::TextureViewDescriptor {
-                    label: Some("Pipe Depth"),
-                    format: Some(DEPTH_FORMAT),
-                    dimension: Some(wgpu::TextureViewDimension::D2),
-                    aspect: wgpu::TextureAspect::DepthOnly,
-                    base_mip_level: 0,
-                    mip_level_count: None,
-                    base_array_layer: 0,
-                    array_layer_count: Some(1),
-                }),
+            &pipe_shadow.texture_view_depth_debug(),
             false,
+            backend,
         );
 
-        Self {
+        Ok(Self {
             window,
             instance,
             surface,
@@ -118,84 +205,224 @@ impl PipeHub {
             device,
             queue,
             surface_config,
+            texture_headless,
             input,
 
             pipe_shadow,
+            shadow_quality: ShadowQuality::Medium,
+            light_bleeding: 0.2,
+            pipe_ssao,
             pipe_mesh,
+            pipe_cluster,
+            pipe_deferred,
+            is_deferred: false,
             pipe_depth,
 
             model_light_arr: vec![],
+            is_light_point_orbiting: false,
 
             start_time: Instant::now(),
             last_time: Instant::now(),
-        }
+            frame_num: 0,
+            frame_time_sum: 0.0,
+        })
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
         self.surface_config.width = width;
         self.surface_config.height = height;
-        self.surface.configure(&self.device, &self.surface_config);
+        match &self.surface {
+            Some(surface) => surface.configure(&self.device, &self.surface_config),
+            None => {
+                self.texture_headless =
+                    Some(gen_texture_headless(&self.device, &self.surface_config))
+            }
+        }
 
+        let backend = self.adapter.get_info().backend;
+        self.pipe_ssao
+            .resize(&self.device, &self.surface_config, backend);
         self.pipe_mesh.resize(&self.device, &self.surface_config);
+        self.pipe_mesh
+            .set_texture_view_ao(&self.device, &self.pipe_ssao.texture_view_ao);
+        self.pipe_deferred
+            .resize(&self.device, &self.surface_config, backend);
 
-        self.pipe_depth.set_texture_view_depth(
-            &self.device,
-            &self
-                .pipe_shadow
-                .texture_depth
-                .create_view(&wgpu::TextureViewDescriptor {
-                    label: Some("Texture View Shadow Map Depth"),
-                    dimension: Some(wgpu::TextureViewDimension::D2),
-                    format: Some(DEPTH_FORMAT),
-                    aspect: wgpu::TextureAspect::DepthOnly,
-                    base_mip_level: 0,
-                    mip_level_count: None,
-                    base_array_layer: 0,
-                    array_layer_count: Some(1),
-                }),
-        );
+        self.set_texture_view_depth_debug();
     }
 
     fn render(&mut self) {
-        let current_texture = self
-            .surface
-            .get_current_texture()
-            .expect("get current texture fail");
-        let texture_view = current_texture
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let current_texture = self.surface.as_ref().map(|surface| {
+            surface
+                .get_current_texture()
+                .expect("get current texture fail")
+        });
+        let texture_view = match &current_texture {
+            Some(current_texture) => current_texture
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+            None => self
+                .texture_headless
+                .as_ref()
+                .unwrap()
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+        };
 
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
 
-        self.pipe_shadow.render(
-            &mut encoder,
-            &self.surface_config,
-            &self.pipe_mesh.material_arr,
-        );
-        self.pipe_mesh.render(&mut encoder, &texture_view);
+        self.pipe_shadow
+            .render(&mut encoder, &self.pipe_mesh.material_arr);
+        self.pipe_ssao
+            .render(&mut encoder, &self.pipe_mesh.material_arr);
+        if self.is_deferred {
+            self.pipe_deferred
+                .render(&mut encoder, &texture_view, &self.pipe_mesh);
+        } else {
+            self.pipe_cluster.render(&mut encoder);
+            self.pipe_mesh.render(&mut encoder, &texture_view);
+        }
         self.pipe_depth.render(&mut encoder, &texture_view);
 
         self.queue.submit(std::iter::once(encoder.finish()));
 
-        current_texture.present();
+        if let Some(current_texture) = current_texture {
+            current_texture.present();
+        }
+    }
+
+    // only for a core created by new_headless
+    pub fn render_to_image(&mut self) -> RgbaImage {
+        self.render();
+        texture_to_image(
+            &self.device,
+            &self.queue,
+            self.texture_headless
+                .as_ref()
+                .expect("render to image needs headless mode"),
+            &self.surface_config,
+        )
+    }
+
+    // falls back to the highest supported count below sample_count
+    pub fn set_sample_count(&mut self, sample_count: u32) {
+        let sample_count_supported = supported_sample_count(
+            &self.adapter,
+            &self.device,
+            self.surface_config.format,
+            sample_count,
+        );
+        if sample_count_supported != sample_count {
+            warn!(
+                "msaa sample count {} is not supported, falling back to {}",
+                sample_count, sample_count_supported
+            );
+        }
+        self.pipe_mesh
+            .set_sample_count(&self.device, &self.surface_config, sample_count_supported);
+        debug!("msaa sample count {}", self.pipe_mesh.sample_count);
+    }
+
+    // m steps through the supported msaa sample counts, 1 is off, o switches ssao,
+    // g switches to deferred shading and v steps through its g-buffer views,
+    // c switches between clustered and shading every light, q steps through the shadow
+    // filter presets, t switches between depth and moment shadows and b steps the light
+    // bleeding reduction of the moments
+    fn on_key_released(&mut self, key: Option<VirtualKeyCode>) {
+        if key == Some(VirtualKeyCode::Q) {
+            self.shadow_quality = self.shadow_quality.next();
+            for shadow_idx in 0..self.pipe_shadow.light_point_shadow_arr.len() {
+                self.set_shadow_quality(shadow_idx as i32, self.shadow_quality);
+            }
+            debug!("shadow quality {:?}", self.shadow_quality);
+        }
+        if key == Some(VirtualKeyCode::T) {
+            self.set_shadow_technique(self.pipe_shadow.technique.next());
+            debug!("shadow technique {:?}", self.pipe_shadow.technique);
+        }
+        if key == Some(VirtualKeyCode::B) {
+            // 0 to 0.8 and around again
+            self.light_bleeding = ((self.light_bleeding * 10.0).round() + 2.0) % 10.0 / 10.0;
+            for shadow_idx in 0..self.pipe_shadow.light_point_shadow_arr.len() {
+                self.set_light_bleeding(shadow_idx as i32, self.light_bleeding);
+            }
+            debug!("light bleeding reduction {}", self.light_bleeding);
+        }
+        if key == Some(VirtualKeyCode::C) {
+            self.pipe_cluster.is_enabled = !self.pipe_cluster.is_enabled;
+            debug!("clustered {}", self.pipe_cluster.is_enabled);
+        }
+        if key == Some(VirtualKeyCode::G) {
+            self.is_deferred = !self.is_deferred;
+            debug!("deferred {}", self.is_deferred);
+        }
+        if key == Some(VirtualKeyCode::V) && self.is_deferred {
+            let view = self.pipe_deferred.view.next();
+            self.pipe_deferred.set_view(&self.queue, view);
+            debug!("g-buffer view {:?}", view);
+        }
+        if key == Some(VirtualKeyCode::O) {
+            self.pipe_ssao.is_enabled = !self.pipe_ssao.is_enabled;
+            debug!("ssao {}", self.pipe_ssao.is_enabled);
+        }
+        if key == Some(VirtualKeyCode::M) {
+            let sample_count_arr =
+                supported_sample_count_arr(&self.adapter, &self.device, self.surface_config.format);
+            let sample_count = sample_count_arr
+                .iter()
+                .find(|&&sample_count| sample_count > self.pipe_mesh.sample_count)
+                .copied()
+                .unwrap_or(1);
+            self.set_sample_count(sample_count);
+        }
+    }
+
+    pub fn window(&self) -> &Window {
+        self.window.as_ref().expect("no window in headless mode")
     }
 
     fn update(&mut self) {
-        self.window
-            .set_cursor_position(winit::dpi::LogicalPosition::new(
-                self.surface_config.width / 2,
-                self.surface_config.height / 2,
-            ))
-            .unwrap();
+        if let Some(window) = &self.window {
+            window
+                .set_cursor_position(winit::dpi::LogicalPosition::new(
+                    self.surface_config.width / 2,
+                    self.surface_config.height / 2,
+                ))
+                .unwrap();
+        }
 
         // let total_time = (Instant::now() - self.start_time).as_secs_f32();
         let delta_time = (Instant::now() - self.last_time).as_secs_f32();
         self.last_time = Instant::now();
 
+        self.frame_num += 1;
+        self.frame_time_sum += delta_time;
+        if self.frame_time_sum >= 1.0 {
+            debug!(
+                "{:.2} ms per frame, clustered {}",
+                self.frame_time_sum * 1000.0 / self.frame_num as f32,
+                self.pipe_cluster.is_enabled
+            );
+            self.frame_num = 0;
+            self.frame_time_sum = 0.0;
+        }
+
+        self.update_delta_time(delta_time);
+    }
+
+    // a fixed delta time keeps headless frames reproducible
+    pub fn update_delta_time(&mut self, delta_time: f32) {
         self.pipe_mesh
             .update(&mut self.queue, &self.input, delta_time);
+        if self.is_light_point_orbiting {
+            self.pipe_mesh.orbit_light_point(&self.queue, delta_time);
+            self.pipe_deferred
+                .update_light_point_arr(&self.queue, &self.pipe_mesh.light_point_arr);
+        }
+        self.pipe_cluster
+            .update(&self.queue, &self.surface_config, &self.pipe_mesh);
+        self.pipe_ssao.update(&self.queue, &self.pipe_mesh.camera);
     }
 
     fn cursor_moved(&mut self, x: f32, y: f32) {
@@ -211,54 +438,77 @@ impl PipeHub {
         }
     }
 
-    pub fn add_light_point(&mut self, light_point: LightPoint) {
-        self.pipe_mesh.add_light_point(&mut self.queue, light_point);
-        self.pipe_shadow
-            .set_light_point(&self.device, &self.pipe_mesh.light_point_arr[0]);
-        if let Some((_, view_mat4_arr)) = &self.pipe_shadow.bind_group_view_arr {
-            let view_proj_mat4_arr = view_mat4_arr
-                .iter()
-                .map(|view| self.pipe_shadow.proj.mul_mat4(&view).to_cols_array_2d())
-                .collect::<Vec<[[f32; 4]; 4]>>();
-            let buffer_view_proj_mat4_arr =
-                self.device
-                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: Some("Buffer View Proj Mat4 Array"),
-                        contents: bytemuck::cast_slice(&view_proj_mat4_arr),
-                        usage: wgpu::BufferUsages::STORAGE,
-                    });
-            let buffer_light_point_pos =
-                self.device
-                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: Some("Buffer Light Point Pos"),
-                        contents: bytemuck::cast_slice(&self.pipe_mesh.light_point_arr[0].pos),
-                        usage: wgpu::BufferUsages::UNIFORM,
-                    });
-            self.pipe_mesh.set_shadow_map(
-                &self.device,
-                buffer_view_proj_mat4_arr,
-                buffer_light_point_pos,
-                &self
-                    .pipe_shadow
-                    .texture_cube
-                    .create_view(&wgpu::TextureViewDescriptor {
-                        label: Some("Texture View Shadow Map Depth"),
-                        dimension: Some(wgpu::TextureViewDimension::Cube),
-                        ..Default::default()
-                    }),
-                &self.pipe_shadow.buffer_near_far,
-            );
+    pub fn add_light_point(&mut self, mut light_point: LightPoint) {
+        if self.pipe_shadow.light_point_shadow_arr.len() < MAX_LIGHT_POINT_SHADOW {
+            light_point.shadow_idx = self
+                .pipe_shadow
+                .add_light_point(&self.device, &light_point);
+            self.set_shadow_quality(light_point.shadow_idx, self.shadow_quality);
+            self.set_light_bleeding(light_point.shadow_idx, self.light_bleeding);
+            self.pipe_mesh
+                .set_shadow_map(&self.device, &self.pipe_shadow);
+            self.set_texture_view_depth_debug();
         }
+        self.pipe_mesh
+            .add_light_point(&self.device, &mut self.queue, light_point);
+        self.pipe_cluster
+            .set_light_buffer(&self.device, &self.pipe_mesh);
+        self.pipe_deferred
+            .set_light_point_arr(&self.device, &self.pipe_mesh.light_point_arr);
+    }
+
+    // shadow_idx as in LightPoint, the lights without a shadow have none to filter
+    pub fn set_shadow_quality(&mut self, shadow_idx: i32, quality: ShadowQuality) {
+        self.pipe_shadow
+            .set_quality(&self.queue, shadow_idx, quality);
+    }
+
+    pub fn set_light_bleeding(&mut self, shadow_idx: i32, light_bleeding: f32) {
+        self.pipe_shadow
+            .set_light_bleeding(&self.queue, shadow_idx, light_bleeding);
+    }
+
+    // for every shadow casting light
+    pub fn set_shadow_technique(&mut self, technique: ShadowTechnique) {
+        self.pipe_shadow
+            .set_technique(&self.device, &self.queue, technique);
+        self.pipe_mesh
+            .set_shadow_map(&self.device, &self.pipe_shadow);
+        self.set_texture_view_depth_debug();
+    }
+
+    // PipeDepth holds a view of pipe_shadow.texture_depth, a new shadowed light or technique
+    // replaces that texture
+    fn set_texture_view_depth_debug(&mut self) {
+        self.pipe_depth
+            .set_texture_view_depth(&self.device, &self.pipe_shadow.texture_view_depth_debug());
+    }
+
+    // many lights at once and without shadows
+    pub fn add_light_point_arr(&mut self, light_point_arr: &[LightPoint]) {
+        self.pipe_mesh
+            .add_light_point_arr(&self.device, &mut self.queue, light_point_arr);
+        self.pipe_cluster
+            .set_light_buffer(&self.device, &self.pipe_mesh);
+        self.pipe_deferred
+            .set_light_point_arr(&self.device, &self.pipe_mesh.light_point_arr);
+    }
+
+    pub fn add_light_spot(&mut self, light_spot: LightSpot) {
+        self.pipe_mesh
+            .add_light_spot(&self.device, &mut self.queue, light_spot);
+        self.pipe_cluster
+            .set_light_buffer(&self.device, &self.pipe_mesh);
     }
 
     pub fn block_loop(event_loop: EventLoop<()>, mut hub: PipeHub) {
         event_loop.run(move |event, _, control_flow| match event {
-            winit::event::Event::RedrawRequested(window_id) if window_id == hub.window.id() => {
+            winit::event::Event::RedrawRequested(window_id) if window_id == hub.window().id() => {
                 hub.update();
                 hub.render();
             }
             winit::event::Event::WindowEvent { window_id, event }
-                if window_id == hub.window.id() =>
+                if window_id == hub.window().id() =>
             {
                 match event {
                     winit::event::WindowEvent::Resized(new_size) => {
@@ -281,12 +531,15 @@ impl PipeHub {
                         input: keyboard_input,
                         ..
                     } => {
+                        if keyboard_input.state == winit::event::ElementState::Released {
+                            hub.on_key_released(keyboard_input.virtual_keycode);
+                        }
                         hub.input.on_input(keyboard_input);
                     }
                     _ => {}
                 }
             }
-            winit::event::Event::MainEventsCleared => hub.window.request_redraw(),
+            winit::event::Event::MainEventsCleared => hub.window().request_redraw(),
             winit::event::Event::DeviceEvent {
                 device_id: _,
                 event,
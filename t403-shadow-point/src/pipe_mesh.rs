@@ -1,11 +1,11 @@
-use glam::Mat4;
+use glam::{Quat, Vec3};
+use image::{Rgba, RgbaImage};
 use wgpu::{
-    util::DeviceExt, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device, IndexFormat,
-    Queue, RenderPipeline, Sampler, SurfaceConfiguration, TextureView,
+    util::DeviceExt, Backend, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device,
+    IndexFormat, PipelineLayout, Queue, RenderPipeline, Sampler, ShaderModule,
+    SurfaceConfiguration, TextureFormat, TextureView,
 };
 
-pub const SAMPLE_COUNT: u32 = 4;
-
 use crate::{
     camera::Camera,
     input::Input,
@@ -14,24 +14,37 @@ use crate::{
     light_spot::LightSpot,
     material::Material,
     model::DrawMethod,
+    pipe_cluster::{ClusterParam, CLUSTER_LIGHT_SIZE},
+    pipe_shadow::PipeShadow,
     texture::{
         self, gen_sampler_clamp, gen_sampler_repeat, gen_texture_depth, gen_texture_view_msaa,
+        gen_texture_view_rgba,
     },
     transform::TransformRawIT,
     vertex::Vertex,
 };
 
 pub struct PipeMesh {
+    pub shader_mesh: ShaderModule,
+    pub render_pipline_layout_mesh: PipelineLayout,
+    // rebuilt when the sample count changes
     pub render_pipline_mesh: RenderPipeline,
+    // msaa samples per pixel, 1 renders straight into the output
+    pub sample_count: u32,
+    pub backend: Backend,
 
     pub sampler: Sampler,
     pub sampler_repeat: Sampler,
 
     pub texture_view_depth: TextureView,
-    pub texture_view_msaa: TextureView,
+    // only with msaa, resolved into the output
+    pub texture_view_msaa: Option<TextureView>,
 
     pub bind_group_layout_material: BindGroupLayout,
     pub material_arr: Vec<Material>,
+    // stand in for textures a material doesn't have
+    pub texture_view_white: TextureView,
+    pub texture_view_normal_flat: TextureView,
 
     pub camera: Camera,
     pub bind_group_layout_camera: BindGroupLayout,
@@ -39,29 +52,45 @@ pub struct PipeMesh {
     pub buffer_view_proj: Buffer,
     pub buffer_camera_pos: Buffer,
     pub sampler_view_shadow_map: Sampler,
+    // trilinear, the moments of the filterable shadows
+    pub sampler_shadow_moment: Sampler,
 
     pub light_direction_arr: Vec<LightDirection>,
     pub light_point_arr: Vec<LightPoint>,
     pub light_spot_arr: Vec<LightSpot>,
+    pub bind_group_layout_light_arr: BindGroupLayout,
     pub bind_group_light_arr: BindGroup,
     pub buffer_light_direction: Buffer,
     pub buffer_light_point: Buffer,
     pub buffer_light_spot: Buffer,
+    // filled by pipe_cluster, with them a fragment only shades the lights of its cluster
+    pub buffer_cluster_param: Buffer,
+    pub buffer_cluster_light: Buffer,
+
+    // screen space ambient occlusion, multiplies the ambient term
+    pub bind_group_layout_ao: BindGroupLayout,
+    pub bind_group_ao: BindGroup,
 }
 
 impl PipeMesh {
     pub fn new(
         device: &Device,
+        queue: &Queue,
         surface_config: &SurfaceConfiguration,
-        texture_view_shadow_map: &TextureView,
-        buffer_near_far: &Buffer,
+        pipe_shadow: &PipeShadow,
+        sample_count: u32,
+        texture_view_ao: &TextureView,
+        backend: Backend,
     ) -> Self {
-        let mesh_shader = std::fs::read_to_string("assets/shader/mesh.wgsl").unwrap();
-        let mesh_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        let mut shader_mesh = std::fs::read_to_string("assets/shader/mesh.wgsl").unwrap();
+        shader_mesh += &read_shader_shadow_map_compare(backend);
+        let shader_mesh = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader Mesh"),
-            source: wgpu::ShaderSource::Wgsl(mesh_shader.into()),
+            source: wgpu::ShaderSource::Wgsl(shader_mesh.into()),
         });
 
+        // see shadow_map_compare_gl.wgsl
+        let is_shadow_map_gather = backend == Backend::Gl;
         let bind_group_layout_camera =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: Some("Bind Group Layout View Proj"),
@@ -88,7 +117,7 @@ impl PipeMesh {
                     },
                     wgpu::BindGroupLayoutEntry {
                         binding: 2,
-                        visibility: wgpu::ShaderStages::VERTEX,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::Buffer {
                             ty: wgpu::BufferBindingType::Storage { read_only: true },
                             has_dynamic_offset: false,
@@ -99,37 +128,41 @@ impl PipeMesh {
                     wgpu::BindGroupLayoutEntry {
                         binding: 3,
                         visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: if is_shadow_map_gather {
+                                wgpu::TextureSampleType::Float { filterable: false }
+                            } else {
+                                wgpu::TextureSampleType::Depth
+                            },
+                            view_dimension: wgpu::TextureViewDimension::CubeArray,
+                            multisampled: false,
                         },
                         count: None,
                     },
                     wgpu::BindGroupLayoutEntry {
                         binding: 4,
                         visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            view_dimension: wgpu::TextureViewDimension::Cube,
-                            multisampled: false,
-                        },
+                        ty: wgpu::BindingType::Sampler(if is_shadow_map_gather {
+                            wgpu::SamplerBindingType::NonFiltering
+                        } else {
+                            wgpu::SamplerBindingType::Comparison
+                        }),
                         count: None,
                     },
                     wgpu::BindGroupLayoutEntry {
                         binding: 5,
                         visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::CubeArray,
+                            multisampled: false,
+                        },
                         count: None,
                     },
                     wgpu::BindGroupLayoutEntry {
                         binding: 6,
                         visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                         count: None,
                     },
                 ],
@@ -169,6 +202,26 @@ impl PipeMesh {
                         },
                         count: None,
                     },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
                 ],
             });
 
@@ -205,16 +258,62 @@ impl PipeMesh {
                     wgpu::BindGroupLayoutEntry {
                         binding: 3,
                         visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 5,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 6,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
                         },
                         count: None,
                     },
                 ],
             });
 
+        let bind_group_layout_ao =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Bind Group Layout Ao"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                }],
+            });
+        let bind_group_ao = gen_bind_group_ao(device, &bind_group_layout_ao, texture_view_ao);
+
         let render_pipline_layout_mesh =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipline Layout Mesh"),
@@ -222,57 +321,18 @@ impl PipeMesh {
                     &bind_group_layout_camera,
                     &bind_group_layout_light_arr,
                     &bind_group_layout_material,
+                    &bind_group_layout_ao,
                 ],
                 push_constant_ranges: &[],
             });
 
-        let render_pipline_mesh = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipline Mesh"),
-            layout: Some(&render_pipline_layout_mesh),
-            vertex: wgpu::VertexState {
-                module: &mesh_shader,
-                entry_point: "vs_main",
-                buffers: &[
-                    Vertex::vertex_buffer_layout(),
-                    TransformRawIT::vertex_buffer_layout(),
-                ],
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: texture::DEPTH_FORMAT,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState {
-                    constant: 2, // Corresponds to bilinear filtering
-                    slope_scale: 2.0,
-                    clamp: 0.0,
-                },
-            }),
-            multisample: wgpu::MultisampleState {
-                count: SAMPLE_COUNT,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &mesh_shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
-        });
+        let render_pipline_mesh = gen_render_pipline_mesh(
+            device,
+            &render_pipline_layout_mesh,
+            &shader_mesh,
+            surface_config.format,
+            sample_count,
+        );
 
         let camera = Camera::new(surface_config.width as _, surface_config.height as _);
         let buffer_view_proj = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -288,32 +348,36 @@ impl PipeMesh {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
-        let buffer_shadow_map_view_proj_arr =
-            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Buffer Shadow Map View Proj Array"),
-                contents: bytemuck::cast_slice(
-                    &(0..6)
-                        .map(|_| Mat4::IDENTITY.to_cols_array_2d())
-                        .collect::<Vec<[[f32; 4]; 4]>>(),
-                ),
-                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-            });
-
-        let buffer_light_point_pos = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("buffer_light_point_pos"),
-            contents: bytemuck::cast_slice(&[0.0, 0.0, 0.0]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let sampler_view_shadow_map = device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some("sampler_view_shadow_map"),
+        // linear filtering blends the four nearest comparisons, the gather on GL weighs them itself
+        let sampler_view_shadow_map = if is_shadow_map_gather {
+            device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("sampler_view_shadow_map"),
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                ..Default::default()
+            })
+        } else {
+            device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("sampler_view_shadow_map"),
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                compare: Some(wgpu::CompareFunction::LessEqual),
+                ..Default::default()
+            })
+        };
+        let sampler_shadow_moment = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("sampler_shadow_moment"),
             address_mode_u: wgpu::AddressMode::ClampToEdge,
             address_mode_v: wgpu::AddressMode::ClampToEdge,
             address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            compare: None,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
             ..Default::default()
         });
 
@@ -336,28 +400,30 @@ impl PipeMesh {
                 wgpu::BindGroupEntry {
                     binding: 2,
                     resource: wgpu::BindingResource::Buffer(
-                        buffer_shadow_map_view_proj_arr.as_entire_buffer_binding(),
+                        pipe_shadow
+                            .buffer_light_point_shadow
+                            .as_entire_buffer_binding(),
                     ),
                 },
                 wgpu::BindGroupEntry {
                     binding: 3,
-                    resource: wgpu::BindingResource::Buffer(
-                        buffer_light_point_pos.as_entire_buffer_binding(),
+                    resource: wgpu::BindingResource::TextureView(
+                        &pipe_shadow.texture_view_cube_arr(),
                     ),
                 },
                 wgpu::BindGroupEntry {
                     binding: 4,
-                    resource: wgpu::BindingResource::TextureView(&texture_view_shadow_map),
+                    resource: wgpu::BindingResource::Sampler(&sampler_view_shadow_map),
                 },
                 wgpu::BindGroupEntry {
                     binding: 5,
-                    resource: wgpu::BindingResource::Sampler(&sampler_view_shadow_map),
+                    resource: wgpu::BindingResource::TextureView(
+                        &pipe_shadow.texture_view_moment_cube_arr(),
+                    ),
                 },
                 wgpu::BindGroupEntry {
                     binding: 6,
-                    resource: wgpu::BindingResource::Buffer(
-                        buffer_near_far.as_entire_buffer_binding(),
-                    ),
+                    resource: wgpu::BindingResource::Sampler(&sampler_shadow_moment),
                 },
             ],
         });
@@ -399,6 +465,24 @@ impl PipeMesh {
             usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
         });
 
+        // every light is shaded until pipe_cluster has binned them
+        let buffer_cluster_param = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Buffer Cluster Param"),
+            contents: bytemuck::bytes_of(&ClusterParam::new(
+                &camera,
+                surface_config,
+                &light_spot_arr,
+                false,
+            )),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let buffer_cluster_light = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Buffer Cluster Light"),
+            size: CLUSTER_LIGHT_SIZE,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
         let bind_group_light_arr = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Bind Group Light Array"),
             layout: &bind_group_layout_light_arr,
@@ -421,24 +505,46 @@ impl PipeMesh {
                         buffer_light_spot.as_entire_buffer_binding(),
                     ),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Buffer(
+                        buffer_cluster_param.as_entire_buffer_binding(),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Buffer(
+                        buffer_cluster_light.as_entire_buffer_binding(),
+                    ),
+                },
             ],
         });
 
         let sampler = gen_sampler_clamp(&device);
         let sampler_repeat = gen_sampler_repeat(&device);
 
-        let texture_view_depth = gen_texture_depth(
-            &device,
-            surface_config.width,
-            surface_config.height,
-            SAMPLE_COUNT,
-        )
-        .create_view(&wgpu::TextureViewDescriptor::default());
+        let (texture_view_depth, texture_view_msaa) =
+            gen_texture_view_attachment(device, surface_config, sample_count, backend);
 
-        let texture_view_msaa = gen_texture_view_msaa(&device, &surface_config, SAMPLE_COUNT);
+        let texture_view_white = gen_texture_view_rgba(
+            &RgbaImage::from_pixel(1, 1, Rgba([255, 255, 255, 255])),
+            wgpu::TextureFormat::Rgba8Unorm,
+            device,
+            queue,
+        );
+        let texture_view_normal_flat = gen_texture_view_rgba(
+            &RgbaImage::from_pixel(1, 1, Rgba([128, 128, 255, 255])),
+            wgpu::TextureFormat::Rgba8Unorm,
+            device,
+            queue,
+        );
 
         Self {
+            shader_mesh,
+            render_pipline_layout_mesh,
             render_pipline_mesh,
+            sample_count,
+            backend,
             camera,
 
             sampler,
@@ -446,15 +552,19 @@ impl PipeMesh {
 
             bind_group_layout_camera,
             bind_group_camera,
+            bind_group_layout_light_arr,
             bind_group_light_arr,
             bind_group_layout_material,
             material_arr: vec![],
+            texture_view_white,
+            texture_view_normal_flat,
 
             buffer_view_proj,
             buffer_camera_pos,
             texture_view_depth,
             texture_view_msaa,
             sampler_view_shadow_map,
+            sampler_shadow_moment,
 
             light_direction_arr,
             light_point_arr,
@@ -462,6 +572,11 @@ impl PipeMesh {
             buffer_light_direction,
             buffer_light_point,
             buffer_light_spot,
+            buffer_cluster_param,
+            buffer_cluster_light,
+
+            bind_group_layout_ao,
+            bind_group_ao,
         }
     }
 
@@ -469,22 +584,43 @@ impl PipeMesh {
         self.camera
             .update_size(surface_config.width as _, surface_config.height as _);
 
-        self.texture_view_depth = gen_texture_depth(
+        (self.texture_view_depth, self.texture_view_msaa) =
+            gen_texture_view_attachment(device, surface_config, self.sample_count, self.backend);
+    }
+
+    // after the ao texture was created again, on resize
+    pub fn set_texture_view_ao(&mut self, device: &Device, texture_view_ao: &TextureView) {
+        self.bind_group_ao = gen_bind_group_ao(device, &self.bind_group_layout_ao, texture_view_ao);
+    }
+
+    // sample_count has to be supported, see texture::supported_sample_count
+    pub fn set_sample_count(
+        &mut self,
+        device: &Device,
+        surface_config: &SurfaceConfiguration,
+        sample_count: u32,
+    ) {
+        if sample_count == self.sample_count {
+            return;
+        }
+        self.sample_count = sample_count;
+        self.render_pipline_mesh = gen_render_pipline_mesh(
             device,
-            surface_config.width,
-            surface_config.height,
-            SAMPLE_COUNT,
-        )
-        .create_view(&wgpu::TextureViewDescriptor::default());
-        self.texture_view_msaa = gen_texture_view_msaa(device, surface_config, SAMPLE_COUNT);
+            &self.render_pipline_layout_mesh,
+            &self.shader_mesh,
+            surface_config.format,
+            sample_count,
+        );
+        (self.texture_view_depth, self.texture_view_msaa) =
+            gen_texture_view_attachment(device, surface_config, sample_count, self.backend);
     }
 
     pub fn render(&mut self, encoder: &mut CommandEncoder, texture_view: &TextureView) {
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Render Pass Mesh"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &self.texture_view_msaa,
-                resolve_target: Some(texture_view),
+                view: self.texture_view_msaa.as_ref().unwrap_or(texture_view),
+                resolve_target: self.texture_view_msaa.as_ref().map(|_| texture_view),
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color {
                         r: 0.1,
@@ -508,6 +644,7 @@ impl PipeMesh {
         render_pass.set_pipeline(&self.render_pipline_mesh);
         render_pass.set_bind_group(0, &self.bind_group_camera, &[]);
         render_pass.set_bind_group(1, &self.bind_group_light_arr, &[]);
+        render_pass.set_bind_group(3, &self.bind_group_ao, &[]);
 
         for material in &self.material_arr {
             render_pass.set_bind_group(2, &material.bind_group, &[]);
@@ -554,17 +691,69 @@ impl PipeMesh {
         }
     }
 
-    pub fn add_light_direction(&mut self, queue: &mut Queue, light: LightDirection) {
+    pub fn add_light_direction(
+        &mut self,
+        device: &Device,
+        queue: &mut Queue,
+        light: LightDirection,
+    ) {
         self.light_direction_arr.push(light);
-        queue.write_buffer(
-            &self.buffer_light_direction,
-            0,
-            bytemuck::cast_slice(&self.light_direction_arr),
-        );
+        let size = (self.light_direction_arr.len() * std::mem::size_of::<LightDirection>()) as u64;
+        if size > self.buffer_light_direction.size() {
+            self.buffer_light_direction = gen_buffer_light(
+                device,
+                "Buffer Light Directioin",
+                bytemuck::cast_slice(&self.light_direction_arr),
+            );
+            self.update_bind_group_light_arr(device);
+        } else {
+            queue.write_buffer(
+                &self.buffer_light_direction,
+                0,
+                bytemuck::cast_slice(&self.light_direction_arr),
+            );
+        }
+    }
+
+    pub fn add_light_point(&mut self, device: &Device, queue: &mut Queue, light: LightPoint) {
+        self.add_light_point_arr(device, queue, &[light]);
+    }
+
+    // grows the buffer once for all of them
+    pub fn add_light_point_arr(
+        &mut self,
+        device: &Device,
+        queue: &mut Queue,
+        light_arr: &[LightPoint],
+    ) {
+        self.light_point_arr.extend_from_slice(light_arr);
+        let size = (self.light_point_arr.len() * std::mem::size_of::<LightPoint>()) as u64;
+        if size > self.buffer_light_point.size() {
+            self.buffer_light_point = gen_buffer_light(
+                device,
+                "Buffer Light Point",
+                bytemuck::cast_slice(&self.light_point_arr),
+            );
+            self.update_bind_group_light_arr(device);
+        } else {
+            queue.write_buffer(
+                &self.buffer_light_point,
+                0,
+                bytemuck::cast_slice(&self.light_point_arr),
+            );
+        }
     }
 
-    pub fn add_light_point(&mut self, queue: &mut Queue, light: LightPoint) {
-        self.light_point_arr.push(light);
+    // lights without a shadow circle the y axis, each ring at its own speed
+    pub fn orbit_light_point(&mut self, queue: &Queue, delta_time: f32) {
+        for (i, light_point) in self.light_point_arr.iter_mut().enumerate() {
+            if light_point.shadow_idx >= 0 {
+                continue;
+            }
+            let speed = 0.2 + 0.1 * (i % 4) as f32;
+            let rotation = Quat::from_rotation_y(speed * delta_time);
+            light_point.pos = (rotation * Vec3::from(light_point.pos)).into();
+        }
         queue.write_buffer(
             &self.buffer_light_point,
             0,
@@ -572,23 +761,67 @@ impl PipeMesh {
         );
     }
 
-    pub fn add_light_spot(&mut self, queue: &mut Queue, light: LightSpot) {
+    pub fn add_light_spot(&mut self, device: &Device, queue: &mut Queue, light: LightSpot) {
         self.light_spot_arr.push(light);
-        queue.write_buffer(
-            &self.buffer_light_spot,
-            0,
-            bytemuck::cast_slice(&self.light_spot_arr),
-        );
+        let size = (self.light_spot_arr.len() * std::mem::size_of::<LightSpot>()) as u64;
+        if size > self.buffer_light_spot.size() {
+            self.buffer_light_spot = gen_buffer_light(
+                device,
+                "Buffer Light Spot",
+                bytemuck::cast_slice(&self.light_spot_arr),
+            );
+            self.update_bind_group_light_arr(device);
+        } else {
+            queue.write_buffer(
+                &self.buffer_light_spot,
+                0,
+                bytemuck::cast_slice(&self.light_spot_arr),
+            );
+        }
     }
 
-    pub fn set_shadow_map(
-        &mut self,
-        device: &Device,
-        buffer_view_proj_arr_shadow_map: Buffer,
-        buffer_light_point_pos: Buffer,
-        texture_view_shadow_map: &TextureView,
-        buffer_near_far: &Buffer,
-    ) {
+    // light buffers grow with the light array, the bind group has to follow them
+    fn update_bind_group_light_arr(&mut self, device: &Device) {
+        self.bind_group_light_arr = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Bind Group Light Array"),
+            layout: &self.bind_group_layout_light_arr,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(
+                        self.buffer_light_direction.as_entire_buffer_binding(),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(
+                        self.buffer_light_point.as_entire_buffer_binding(),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(
+                        self.buffer_light_spot.as_entire_buffer_binding(),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Buffer(
+                        self.buffer_cluster_param.as_entire_buffer_binding(),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Buffer(
+                        self.buffer_cluster_light.as_entire_buffer_binding(),
+                    ),
+                },
+            ],
+        });
+    }
+
+    // after pipe_shadow made new textures
+    pub fn set_shadow_map(&mut self, device: &Device, pipe_shadow: &PipeShadow) {
         self.bind_group_camera = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Bind Group Camera"),
             layout: &self.bind_group_layout_camera,
@@ -608,30 +841,141 @@ impl PipeMesh {
                 wgpu::BindGroupEntry {
                     binding: 2,
                     resource: wgpu::BindingResource::Buffer(
-                        buffer_view_proj_arr_shadow_map.as_entire_buffer_binding(),
+                        pipe_shadow
+                            .buffer_light_point_shadow
+                            .as_entire_buffer_binding(),
                     ),
                 },
                 wgpu::BindGroupEntry {
                     binding: 3,
-                    resource: wgpu::BindingResource::Buffer(
-                        buffer_light_point_pos.as_entire_buffer_binding(),
+                    resource: wgpu::BindingResource::TextureView(
+                        &pipe_shadow.texture_view_cube_arr(),
                     ),
                 },
                 wgpu::BindGroupEntry {
                     binding: 4,
-                    resource: wgpu::BindingResource::TextureView(&texture_view_shadow_map),
+                    resource: wgpu::BindingResource::Sampler(&self.sampler_view_shadow_map),
                 },
                 wgpu::BindGroupEntry {
                     binding: 5,
-                    resource: wgpu::BindingResource::Sampler(&self.sampler_view_shadow_map),
+                    resource: wgpu::BindingResource::TextureView(
+                        &pipe_shadow.texture_view_moment_cube_arr(),
+                    ),
                 },
                 wgpu::BindGroupEntry {
                     binding: 6,
-                    resource: wgpu::BindingResource::Buffer(
-                        buffer_near_far.as_entire_buffer_binding(),
-                    ),
+                    resource: wgpu::BindingResource::Sampler(&self.sampler_shadow_moment),
                 },
             ],
         });
     }
 }
+
+// the point shadow lookup of mesh.wgsl and deferred_lighting.wgsl
+pub fn read_shader_shadow_map_compare(backend: Backend) -> String {
+    let path = if backend == Backend::Gl {
+        "assets/shader/shadow_map_compare_gl.wgsl"
+    } else {
+        "assets/shader/shadow_map_compare.wgsl"
+    };
+    std::fs::read_to_string(path).unwrap()
+}
+
+fn gen_buffer_light(device: &Device, label: &str, data: &[u8]) -> Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(label),
+        contents: data,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+fn gen_bind_group_ao(
+    device: &Device,
+    layout: &BindGroupLayout,
+    texture_view_ao: &TextureView,
+) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Bind Group Ao"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(texture_view_ao),
+        }],
+    })
+}
+
+fn gen_render_pipline_mesh(
+    device: &Device,
+    render_pipline_layout: &PipelineLayout,
+    shader: &ShaderModule,
+    format: TextureFormat,
+    sample_count: u32,
+) -> RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipline Mesh"),
+        layout: Some(render_pipline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[
+                Vertex::vertex_buffer_layout(),
+                TransformRawIT::vertex_buffer_layout(),
+            ],
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState {
+                constant: 2, // Corresponds to bilinear filtering
+                slope_scale: 2.0,
+                clamp: 0.0,
+            },
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    })
+}
+
+// depth and, with msaa, the multisampled color target
+fn gen_texture_view_attachment(
+    device: &Device,
+    surface_config: &SurfaceConfiguration,
+    sample_count: u32,
+    backend: Backend,
+) -> (TextureView, Option<TextureView>) {
+    let texture_view_depth = gen_texture_depth(
+        device,
+        surface_config.width,
+        surface_config.height,
+        sample_count,
+        backend,
+    )
+    .create_view(&wgpu::TextureViewDescriptor::default());
+    let texture_view_msaa = (sample_count > 1)
+        .then(|| gen_texture_view_msaa(device, surface_config, sample_count, backend));
+    (texture_view_depth, texture_view_msaa)
+}
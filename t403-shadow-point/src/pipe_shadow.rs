@@ -1,70 +1,88 @@
-use glam::{Mat4, Vec3};
+use std::num::NonZeroU32;
+
 use wgpu::{
-    util::DeviceExt, BindGroup, BindGroupLayout, CommandEncoder, Device, IndexFormat,
-    RenderPipeline, Texture,
+    util::DeviceExt, BindGroup, BindGroupLayout, Buffer, CommandEncoder, ComputePipeline, Device,
+    IndexFormat, PipelineLayout, Queue, RenderPipeline, ShaderModule, Texture, TextureView,
 };
 
 use crate::{
     light_point::LightPoint,
+    light_point_shadow::LightPointShadow,
     material::Material,
     model::DrawMethod,
-    texture::{gen_texture_cube, gen_texture_depth, DEPTH_FORMAT},
+    shadow_quality::ShadowQuality,
+    shadow_technique::ShadowTechnique,
+    texture::{
+        gen_texture_depth_cube_arr, gen_texture_moment_cube_arr, DEPTH_FORMAT, MOMENT_FORMAT,
+    },
     transform::TransformRawIT,
     vertex::Vertex,
 };
 
 const SAMPLE_COUNT: u32 = 1;
+// same as EVSM_EXPONENT in the shaders, the moments clear to the warp of the far plane
+pub const EVSM_EXPONENT: f32 = 5.0;
+const WORKGROUP_SIZE: u32 = 8;
 
 pub struct PipeShadow {
+    pub technique: ShadowTechnique,
+    // all six faces of a light in one pass, otherwise a pass per face. wgsl can not pick the
+    // layer from the vertex shader, so there is no layered instancing to fall back to
+    pub is_multiview: bool,
     pub render_pipeline: RenderPipeline,
-    pub texture_cube: Texture,
+    // depth tested like render_pipeline, but writes the moments of the distance too
+    pub render_pipeline_moment: RenderPipeline,
+    // one layer per cube face of every shadow casting light, the distance to the light over far
     pub texture_depth: Texture,
+    // same layers as texture_depth with ShadowTechnique::Moment, a texel per face otherwise so
+    // the mesh shaders always have something to bind
+    pub texture_moment: Texture,
+    // the moments between the horizontal and the vertical blur
+    pub texture_moment_blur: Texture,
     pub bind_group_layout: BindGroupLayout,
-    pub bind_group_view_arr: Option<([BindGroup; 6], [Mat4; 6])>,
-    pub buffer_near_far: wgpu::Buffer,
-    pub proj: Mat4,
+    // the first face of every pass, a dynamic offset apart
+    pub buffer_face_idx: Buffer,
+    pub face_idx_stride: u32,
+    pub bind_group: BindGroup,
+
+    pub bind_group_layout_moment: BindGroupLayout,
+    pub compute_pipline_blur_x: ComputePipeline,
+    pub compute_pipline_blur_y: ComputePipeline,
+    pub compute_pipline_downsample: ComputePipeline,
+    pub bind_group_blur_x: BindGroup,
+    pub bind_group_blur_y: BindGroup,
+    // from every mip level into the next smaller one
+    pub bind_group_mip_arr: Vec<BindGroup>,
+
+    pub light_point_shadow_arr: Vec<LightPointShadow>,
+    pub buffer_light_point_shadow: Buffer,
+    pub near_far: [f32; 2],
     pub width: u32,
     pub height: u32,
 }
 
 impl PipeShadow {
-    pub fn new(
-        device: &Device,
-        surface_config: &wgpu::SurfaceConfiguration,
-        width: u32,
-        height: u32,
-    ) -> Self {
-        let texture_cube = gen_texture_cube(device, &surface_config, width, height);
-        let texture_depth = gen_texture_depth(device, width, height, 1);
+    pub fn new(device: &Device, width: u32, height: u32) -> Self {
+        let texture_depth = gen_texture_depth_cube_arr(device, width, height, 1);
 
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Bind Group Layout Shadow"),
             entries: &[
                 wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
+                        has_dynamic_offset: true,
+                        min_binding_size: wgpu::BufferSize::new(4),
                     },
                     count: None,
                 },
                 wgpu::BindGroupLayoutEntry {
                     binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 2,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
                         has_dynamic_offset: false,
                         min_binding_size: None,
                     },
@@ -80,243 +98,566 @@ impl PipeShadow {
                 push_constant_ranges: &[],
             });
 
-        let shader = std::fs::read_to_string("assets/shader/shadow.wgsl").unwrap();
+        let is_multiview = device.features().contains(wgpu::Features::MULTIVIEW);
+        let mut shader = std::fs::read_to_string("assets/shader/shadow.wgsl").unwrap();
+        // view_index does not validate without the feature
+        if is_multiview {
+            shader += &std::fs::read_to_string("assets/shader/shadow_multiview.wgsl").unwrap();
+        }
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader Shadow"),
             source: wgpu::ShaderSource::Wgsl(shader.into()),
         });
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline Pipe Shadow"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[
-                    Vertex::vertex_buffer_layout(),
-                    TransformRawIT::vertex_buffer_layout(),
+        let render_pipeline = gen_render_pipeline(
+            device,
+            &render_pipeline_layout,
+            &shader,
+            false,
+            is_multiview,
+        );
+        let render_pipeline_moment =
+            gen_render_pipeline(device, &render_pipeline_layout, &shader, true, is_multiview);
+
+        let bind_group_layout_moment =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Bind Group Layout Shadow Moment"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2Array,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: MOMENT_FORMAT,
+                            view_dimension: wgpu::TextureViewDimension::D2Array,
+                        },
+                        count: None,
+                    },
                 ],
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: DEPTH_FORMAT,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState {
-                    constant: 2, // Corresponds to bilinear filtering
-                    slope_scale: 2.0,
-                    clamp: 0.0,
-                },
-            }),
-            multisample: wgpu::MultisampleState {
-                count: SAMPLE_COUNT,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
+            });
+
+        let compute_pipline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Compute Pipline Layout Shadow Moment"),
+                bind_group_layouts: &[&bind_group_layout_moment],
+                push_constant_ranges: &[],
+            });
+        let shader_moment = std::fs::read_to_string("assets/shader/shadow_moment.wgsl").unwrap();
+        let shader_moment = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader Shadow Moment"),
+            source: wgpu::ShaderSource::Wgsl(shader_moment.into()),
         });
+        let compute_pipline_blur_x =
+            gen_compute_pipline(device, &compute_pipline_layout, &shader_moment, "cs_blur_x");
+        let compute_pipline_blur_y =
+            gen_compute_pipline(device, &compute_pipline_layout, &shader_moment, "cs_blur_y");
+        let compute_pipline_downsample = gen_compute_pipline(
+            device,
+            &compute_pipline_layout,
+            &shader_moment,
+            "cs_downsample",
+        );
 
-        // let proj_size = 20.0;
-        // let proj = Mat4::orthographic_rh(-proj_size, proj_size, -proj_size, proj_size, 0.1, 70.0);
-        let near_far = [0.1, 100.0];
-        let proj = Mat4::perspective_rh(
-            90.0_f32.to_radians(),
-            (width / height) as f32,
-            near_far[0],
-            near_far[1],
+        let texture_moment = gen_texture_moment_cube_arr(device, 1, 1, 1, 1);
+        let texture_moment_blur = gen_texture_moment_cube_arr(device, 1, 1, 1, 1);
+        let bind_group_blur_x = gen_bind_group_moment(
+            device,
+            &bind_group_layout_moment,
+            &gen_texture_view_mip(&texture_moment, 0),
+            &gen_texture_view_mip(&texture_moment_blur, 0),
         );
-        let bind_group_view_arr: Option<([BindGroup; 6], [Mat4; 6])> = None;
-        let buffer_near_far = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Buffer Near Far"),
-            contents: bytemuck::cast_slice(&near_far),
-            usage: wgpu::BufferUsages::UNIFORM,
-        });
+        let bind_group_blur_y = gen_bind_group_moment(
+            device,
+            &bind_group_layout_moment,
+            &gen_texture_view_mip(&texture_moment_blur, 0),
+            &gen_texture_view_mip(&texture_moment, 0),
+        );
+
+        let buffer_light_point_shadow =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Buffer Light Point Shadow"),
+                contents: bytemuck::cast_slice(&[LightPointShadow::zero()]),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            });
+        let face_idx_stride = device.limits().min_uniform_buffer_offset_alignment;
+        let buffer_face_idx = gen_buffer_face_idx(device, face_idx_stride, 1);
+        let bind_group = gen_bind_group(
+            device,
+            &bind_group_layout,
+            &buffer_face_idx,
+            &buffer_light_point_shadow,
+        );
+
         Self {
+            technique: ShadowTechnique::DepthCompare,
+            is_multiview,
             render_pipeline,
-            texture_cube,
+            render_pipeline_moment,
             texture_depth,
+            texture_moment,
+            texture_moment_blur,
             bind_group_layout,
-            bind_group_view_arr,
-            proj,
+            buffer_face_idx,
+            face_idx_stride,
+            bind_group,
+            bind_group_layout_moment,
+            compute_pipline_blur_x,
+            compute_pipline_blur_y,
+            compute_pipline_downsample,
+            bind_group_blur_x,
+            bind_group_blur_y,
+            bind_group_mip_arr: vec![],
+            light_point_shadow_arr: vec![],
+            buffer_light_point_shadow,
+            near_far: [0.1, 100.0],
             width,
             height,
-            buffer_near_far,
         }
     }
 
-    pub fn render(
-        &mut self,
-        encoder: &mut CommandEncoder,
-        surface_config: &wgpu::SurfaceConfiguration,
-        material_arr: &Vec<Material>,
-    ) {
-        if let Some((bind_group_arr, _)) = &self.bind_group_view_arr {
-            for (idx, bind_group) in bind_group_arr.iter().enumerate() {
-                let texture_view = self.texture_cube.create_view(&wgpu::TextureViewDescriptor {
-                    label: Some("Texture View Shadow Map"),
-                    format: Some(surface_config.format),
-                    dimension: Some(wgpu::TextureViewDimension::D2),
-                    aspect: wgpu::TextureAspect::All,
-                    base_mip_level: 0,
-                    mip_level_count: None,
-                    base_array_layer: idx as u32,
-                    array_layer_count: Some(1),
-                });
-                let texture_view_depth =
-                    &self
-                        .texture_depth
-                        .create_view(&wgpu::TextureViewDescriptor {
-                            label: Some("Texture View Shadow Map Depth"),
-                            format: Some(DEPTH_FORMAT),
-                            dimension: Some(wgpu::TextureViewDimension::D2),
-                            aspect: wgpu::TextureAspect::DepthOnly,
-                            base_mip_level: 0,
-                            mip_level_count: None,
-                            base_array_layer: 0,
-                            array_layer_count: None,
-                        });
-                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("Render Pass Shadow"),
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: &texture_view,
+    pub fn render(&mut self, encoder: &mut CommandEncoder, material_arr: &Vec<Material>) {
+        let is_moment = self.technique == ShadowTechnique::Moment;
+        let light_num = self.light_point_shadow_arr.len() as u32;
+        // a pass per light with every face a view, or a pass per face
+        let (pass_num, layer_num, dimension) = if self.is_multiview {
+            (light_num, 6, wgpu::TextureViewDimension::D2Array)
+        } else {
+            (light_num * 6, 1, wgpu::TextureViewDimension::D2)
+        };
+        for idx in 0..pass_num {
+            let face_idx = idx * layer_num;
+            let texture_view_depth =
+                &self
+                    .texture_depth
+                    .create_view(&wgpu::TextureViewDescriptor {
+                        label: Some("Texture View Shadow Map Depth"),
+                        format: Some(DEPTH_FORMAT),
+                        dimension: Some(dimension),
+                        aspect: wgpu::TextureAspect::DepthOnly,
+                        base_mip_level: 0,
+                        mip_level_count: None,
+                        base_array_layer: face_idx,
+                        array_layer_count: Some(layer_num),
+                    });
+            let texture_view_moment = is_moment.then(|| {
+                self.texture_moment
+                    .create_view(&wgpu::TextureViewDescriptor {
+                        label: Some("Texture View Shadow Map Moment"),
+                        dimension: Some(dimension),
+                        base_mip_level: 0,
+                        mip_level_count: Some(1),
+                        base_array_layer: face_idx,
+                        array_layer_count: Some(layer_num),
+                        ..Default::default()
+                    })
+            });
+            let color_attachment_arr: Vec<_> = texture_view_moment
+                .iter()
+                .map(|view| {
+                    Some(wgpu::RenderPassColorAttachment {
+                        view,
                         resolve_target: None,
                         ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color {
-                                r: f64::MAX,
-                                g: f64::MAX,
-                                b: f64::MAX,
-                                a: f64::MAX,
-                            }),
+                            load: wgpu::LoadOp::Clear(moment_far()),
                             store: true,
                         },
-                    })],
-                    depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                        view: texture_view_depth,
-                        depth_ops: Some(wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(1.0),
-                            store: true,
-                        }),
-                        stencil_ops: None,
+                    })
+                })
+                .collect();
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass Shadow"),
+                color_attachments: &color_attachment_arr,
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: texture_view_depth,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
                     }),
-                });
+                    stencil_ops: None,
+                }),
+            });
 
+            if is_moment {
+                render_pass.set_pipeline(&self.render_pipeline_moment);
+            } else {
                 render_pass.set_pipeline(&self.render_pipeline);
-                render_pass.set_bind_group(0, &bind_group, &[]);
-
-                for material in material_arr {
-                    // render model
-                    for model in &material.model_arr {
-                        if model.draw_method == DrawMethod::Vertex {
-                            render_pass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
-                            render_pass.set_vertex_buffer(1, model.transform_buffer.slice(..));
-                            render_pass.draw(0..model.vertices_len, 0..model.instance_num);
-                        } else {
-                            render_pass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
-                            render_pass.set_vertex_buffer(1, model.transform_buffer.slice(..));
-                            render_pass.set_index_buffer(
-                                model.index_buffer.slice(..),
-                                IndexFormat::Uint32,
-                            );
-                            render_pass.draw_indexed(
-                                0..model.indices_len,
-                                0,
-                                0..model.instance_num,
-                            );
-                        }
+            }
+            render_pass.set_bind_group(0, &self.bind_group, &[face_idx * self.face_idx_stride]);
+
+            for material in material_arr {
+                // render model
+                for model in &material.model_arr {
+                    if model.draw_method == DrawMethod::Vertex {
+                        render_pass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
+                        render_pass.set_vertex_buffer(1, model.transform_buffer.slice(..));
+                        render_pass.draw(0..model.vertices_len, 0..model.instance_num);
+                    } else {
+                        render_pass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
+                        render_pass.set_vertex_buffer(1, model.transform_buffer.slice(..));
+                        render_pass
+                            .set_index_buffer(model.index_buffer.slice(..), IndexFormat::Uint32);
+                        render_pass.draw_indexed(0..model.indices_len, 0, 0..model.instance_num);
                     }
                 }
             }
         }
+
+        if is_moment && pass_num > 0 {
+            self.render_moment_blur(encoder);
+        }
+    }
+
+    // blurs the moments of the largest level, then averages them down the mip chain
+    fn render_moment_blur(&self, encoder: &mut CommandEncoder) {
+        let layer_num = self.texture_moment.depth_or_array_layers();
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Compute Pass Shadow Moment"),
+        });
+        let group_x = self.width.div_ceil(WORKGROUP_SIZE);
+        let group_y = self.height.div_ceil(WORKGROUP_SIZE);
+        compute_pass.set_pipeline(&self.compute_pipline_blur_x);
+        compute_pass.set_bind_group(0, &self.bind_group_blur_x, &[]);
+        compute_pass.dispatch_workgroups(group_x, group_y, layer_num);
+        compute_pass.set_pipeline(&self.compute_pipline_blur_y);
+        compute_pass.set_bind_group(0, &self.bind_group_blur_y, &[]);
+        compute_pass.dispatch_workgroups(group_x, group_y, layer_num);
+
+        compute_pass.set_pipeline(&self.compute_pipline_downsample);
+        for (idx, bind_group) in self.bind_group_mip_arr.iter().enumerate() {
+            let mip_level = idx as u32 + 1;
+            let width = (self.width >> mip_level).max(1);
+            let height = (self.height >> mip_level).max(1);
+            compute_pass.set_bind_group(0, bind_group, &[]);
+            compute_pass.dispatch_workgroups(
+                width.div_ceil(WORKGROUP_SIZE),
+                height.div_ceil(WORKGROUP_SIZE),
+                layer_num,
+            );
+        }
     }
 
+    // the shadow map resolution, pipe_mesh needs the new texture_view_cube_arr afterwards
     pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
-        self.texture_depth = gen_texture_depth(device, width, height, SAMPLE_COUNT);
+        self.width = width;
+        self.height = height;
+        self.gen_texture(device);
     }
 
-    pub fn set_light_point(&mut self, device: &Device, light_point: &LightPoint) {
-        // cube map order
-        let view_mat4_arr: [Mat4; 6] = [
-            // Right
-            Mat4::look_to_rh(light_point.pos.into(), Vec3::new(1.0, 0.0, 0.0), Vec3::Y),
-            // Left
-            Mat4::look_to_rh(light_point.pos.into(), Vec3::new(-1.0, 0.0, 0.0), Vec3::Y),
-            // Up
-            Mat4::look_to_rh(light_point.pos.into(), Vec3::new(0.0, 1.0, 0.0), Vec3::Z),
-            // Bottom
-            Mat4::look_to_rh(
-                light_point.pos.into(),
-                Vec3::new(0.0, -1.0, 0.0),
-                Vec3::NEG_Z,
-            ),
-            // Back
-            Mat4::look_to_rh(light_point.pos.into(), Vec3::new(0.0, 0.0, -1.0), Vec3::Y),
-            // Front
-            Mat4::look_to_rh(light_point.pos.into(), Vec3::new(0.0, 0.0, 1.0), Vec3::Y),
-        ];
-        let buffer_light_point_pos = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Buffer Light Point Pos"),
-            contents: bytemuck::cast_slice(&light_point.pos),
-            usage: wgpu::BufferUsages::UNIFORM,
-        });
+    // the textures for the lights and the technique, pipe_mesh needs the new views afterwards
+    fn gen_texture(&mut self, device: &Device) {
+        let cube_num = self.light_point_shadow_arr.len() as u32;
+        self.texture_depth = gen_texture_depth_cube_arr(device, self.width, self.height, cube_num);
 
-        let bind_group_arr = view_mat4_arr
-            .iter()
-            .map(|view| {
-                let buffer_view_proj =
-                    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: Some("Shadow Map View Proj Buffer"),
-                        contents: bytemuck::cast_slice(
-                            &self.proj.mul_mat4(view).to_cols_array_2d(),
-                        ),
-                        usage: wgpu::BufferUsages::UNIFORM,
-                    });
+        let (width, height) = match self.technique {
+            ShadowTechnique::DepthCompare => (1, 1),
+            ShadowTechnique::Moment => (self.width, self.height),
+        };
+        // down to a single texel
+        let mip_level_count = 32 - width.max(height).leading_zeros();
+        self.texture_moment =
+            gen_texture_moment_cube_arr(device, width, height, cube_num, mip_level_count);
+        self.texture_moment_blur = gen_texture_moment_cube_arr(device, width, height, cube_num, 1);
 
-                device.create_bind_group(&wgpu::BindGroupDescriptor {
-                    label: Some("Bind Group Shadow Map"),
-                    layout: &self.bind_group_layout,
-                    entries: &[
-                        wgpu::BindGroupEntry {
-                            binding: 0,
-                            resource: wgpu::BindingResource::Buffer(
-                                buffer_view_proj.as_entire_buffer_binding(),
-                            ),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding: 1,
-                            resource: wgpu::BindingResource::Buffer(
-                                buffer_light_point_pos.as_entire_buffer_binding(),
-                            ),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding: 2,
-                            resource: wgpu::BindingResource::Buffer(
-                                self.buffer_near_far.as_entire_buffer_binding(),
-                            ),
-                        },
-                    ],
-                })
+        self.bind_group_blur_x = gen_bind_group_moment(
+            device,
+            &self.bind_group_layout_moment,
+            &gen_texture_view_mip(&self.texture_moment, 0),
+            &gen_texture_view_mip(&self.texture_moment_blur, 0),
+        );
+        self.bind_group_blur_y = gen_bind_group_moment(
+            device,
+            &self.bind_group_layout_moment,
+            &gen_texture_view_mip(&self.texture_moment_blur, 0),
+            &gen_texture_view_mip(&self.texture_moment, 0),
+        );
+        self.bind_group_mip_arr = (1..mip_level_count)
+            .map(|mip_level| {
+                gen_bind_group_moment(
+                    device,
+                    &self.bind_group_layout_moment,
+                    &gen_texture_view_mip(&self.texture_moment, mip_level - 1),
+                    &gen_texture_view_mip(&self.texture_moment, mip_level),
+                )
+            })
+            .collect();
+    }
+
+    pub fn texture_view_cube_arr(&self) -> TextureView {
+        self.texture_depth
+            .create_view(&wgpu::TextureViewDescriptor {
+                label: Some("Texture View Shadow Map Cube Array"),
+                dimension: Some(wgpu::TextureViewDimension::CubeArray),
+                aspect: wgpu::TextureAspect::DepthOnly,
+                ..Default::default()
             })
-            .collect::<Vec<BindGroup>>()
-            .try_into()
-            .unwrap();
+    }
+
+    // the first face of the first cube as a plain 2d view, what PipeDepth shows. texture_depth
+    // is replaced when lights are added or the technique changes, so take a new one after those
+    pub fn texture_view_depth_debug(&self) -> TextureView {
+        self.texture_depth
+            .create_view(&wgpu::TextureViewDescriptor {
+                label: Some("Texture View Shadow Map Depth"),
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                format: Some(DEPTH_FORMAT),
+                aspect: wgpu::TextureAspect::DepthOnly,
+                base_mip_level: 0,
+                mip_level_count: None,
+                base_array_layer: 0,
+                array_layer_count: Some(1),
+            })
+    }
+
+    // with all mips, filtered by the hardware
+    pub fn texture_view_moment_cube_arr(&self) -> TextureView {
+        self.texture_moment
+            .create_view(&wgpu::TextureViewDescriptor {
+                label: Some("Texture View Shadow Map Moment Cube Array"),
+                dimension: Some(wgpu::TextureViewDimension::CubeArray),
+                ..Default::default()
+            })
+    }
+
+    pub fn set_quality(&mut self, queue: &Queue, shadow_idx: i32, quality: ShadowQuality) {
+        if let Some(light_point_shadow) = self.light_point_shadow_arr.get_mut(shadow_idx as usize) {
+            light_point_shadow.set_quality(quality);
+            self.update_light_point_shadow(queue, shadow_idx as usize);
+        }
+    }
+
+    pub fn set_light_bleeding(&mut self, queue: &Queue, shadow_idx: i32, light_bleeding: f32) {
+        if let Some(light_point_shadow) = self.light_point_shadow_arr.get_mut(shadow_idx as usize) {
+            light_point_shadow.set_light_bleeding(light_bleeding);
+            self.update_light_point_shadow(queue, shadow_idx as usize);
+        }
+    }
+
+    // for every light, pipe_mesh needs the new views afterwards
+    pub fn set_technique(&mut self, device: &Device, queue: &Queue, technique: ShadowTechnique) {
+        self.technique = technique;
+        for light_point_shadow in &mut self.light_point_shadow_arr {
+            light_point_shadow.set_technique(technique);
+        }
+        if !self.light_point_shadow_arr.is_empty() {
+            queue.write_buffer(
+                &self.buffer_light_point_shadow,
+                0,
+                bytemuck::cast_slice(&self.light_point_shadow_arr),
+            );
+        }
+        self.gen_texture(device);
+    }
+
+    fn update_light_point_shadow(&self, queue: &Queue, shadow_idx: usize) {
+        queue.write_buffer(
+            &self.buffer_light_point_shadow,
+            (shadow_idx * std::mem::size_of::<LightPointShadow>()) as u64,
+            bytemuck::bytes_of(&self.light_point_shadow_arr[shadow_idx]),
+        );
+    }
+
+    // return the shadow index of the light, every light gets its own cube in the cube array
+    pub fn add_light_point(&mut self, device: &Device, light_point: &LightPoint) -> i32 {
+        let mut light_point_shadow =
+            LightPointShadow::new(light_point, self.near_far[0], self.near_far[1]);
+        light_point_shadow.set_technique(self.technique);
+        self.light_point_shadow_arr.push(light_point_shadow);
+        let cube_num = self.light_point_shadow_arr.len() as u32;
+
+        self.gen_texture(device);
+
+        self.buffer_light_point_shadow =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Buffer Light Point Shadow"),
+                contents: bytemuck::cast_slice(&self.light_point_shadow_arr),
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            });
+
+        self.buffer_face_idx = gen_buffer_face_idx(device, self.face_idx_stride, cube_num * 6);
+        self.bind_group = gen_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.buffer_face_idx,
+            &self.buffer_light_point_shadow,
+        );
+
+        cube_num as i32 - 1
+    }
+}
+
+fn gen_render_pipeline(
+    device: &Device,
+    render_pipeline_layout: &PipelineLayout,
+    shader: &ShaderModule,
+    is_moment: bool,
+    is_multiview: bool,
+) -> RenderPipeline {
+    let targets = [Some(wgpu::ColorTargetState {
+        format: MOMENT_FORMAT,
+        blend: None,
+        write_mask: wgpu::ColorWrites::ALL,
+    })];
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline Pipe Shadow"),
+        layout: Some(render_pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: if is_multiview {
+                "vs_multiview"
+            } else {
+                "vs_main"
+            },
+            buffers: &[
+                Vertex::vertex_buffer_layout(),
+                TransformRawIT::vertex_buffer_layout(),
+            ],
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            // frag_depth skips the hardware bias, the mesh shaders offset the distance instead
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: SAMPLE_COUNT,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        // the comparisons only write the distance, the moments go into a color target as well
+        fragment: Some(if is_moment {
+            wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_moment",
+                targets: &targets,
+            }
+        } else {
+            wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[],
+            }
+        }),
+        multiview: is_multiview.then(|| NonZeroU32::new(6).unwrap()),
+    })
+}
+
+// the first face of every face or light, each at its own dynamic offset
+fn gen_buffer_face_idx(device: &Device, stride: u32, face_num: u32) -> Buffer {
+    let stride = (stride / 4) as usize;
+    let mut face_idx_arr = vec![0u32; stride * face_num.max(1) as usize];
+    for face_idx in 0..face_num {
+        face_idx_arr[face_idx as usize * stride] = face_idx;
+    }
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Buffer Shadow Face Idx"),
+        contents: bytemuck::cast_slice(&face_idx_arr),
+        usage: wgpu::BufferUsages::UNIFORM,
+    })
+}
+
+fn gen_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    buffer_face_idx: &Buffer,
+    buffer_light_point_shadow: &Buffer,
+) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Bind Group Shadow Map"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: buffer_face_idx,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(4),
+                }),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Buffer(
+                    buffer_light_point_shadow.as_entire_buffer_binding(),
+                ),
+            },
+        ],
+    })
+}
+
+fn gen_compute_pipline(
+    device: &Device,
+    compute_pipline_layout: &PipelineLayout,
+    shader: &ShaderModule,
+    entry_point: &str,
+) -> ComputePipeline {
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some(entry_point),
+        layout: Some(compute_pipline_layout),
+        module: shader,
+        entry_point,
+    })
+}
+
+// every layer of a single mip level
+fn gen_texture_view_mip(texture: &Texture, mip_level: u32) -> TextureView {
+    texture.create_view(&wgpu::TextureViewDescriptor {
+        label: Some("Texture View Shadow Map Moment Mip"),
+        dimension: Some(wgpu::TextureViewDimension::D2Array),
+        base_mip_level: mip_level,
+        mip_level_count: Some(1),
+        ..Default::default()
+    })
+}
+
+fn gen_bind_group_moment(
+    device: &Device,
+    layout: &BindGroupLayout,
+    texture_view_src: &TextureView,
+    texture_view_dst: &TextureView,
+) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Bind Group Shadow Moment"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(texture_view_src),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(texture_view_dst),
+            },
+        ],
+    })
+}
 
-        self.bind_group_view_arr = Some((bind_group_arr, view_mat4_arr));
+// what fs_moment writes for a fragment on the far plane
+fn moment_far() -> wgpu::Color {
+    let pos = (EVSM_EXPONENT as f64).exp();
+    let neg = -(-EVSM_EXPONENT as f64).exp();
+    wgpu::Color {
+        r: pos,
+        g: pos * pos,
+        b: neg,
+        a: neg * neg,
     }
 }
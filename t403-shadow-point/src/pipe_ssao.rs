@@ -0,0 +1,522 @@
+use glam::{Mat4, Vec3};
+use image::{Rgba, RgbaImage};
+use wgpu::{
+    util::DeviceExt, Backend, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device,
+    IndexFormat, PipelineLayout, Queue, RenderPipeline, ShaderModule, SurfaceConfiguration,
+    TextureFormat, TextureView,
+};
+
+use crate::{
+    camera::Camera,
+    material::Material,
+    model::DrawMethod,
+    texture::{gen_texture_depth, gen_texture_view_rgba, gen_texture_view_target, DEPTH_FORMAT},
+    transform::TransformRawIT,
+    vertex::Vertex,
+};
+
+// size of the kernel array in the shader
+pub const MAX_SAMPLE_COUNT: usize = 64;
+// side of the tiled rotation noise, the blur should cover it
+const NOISE_SIZE: u32 = 4;
+// view space normal in xyz and the linear depth in w, f32 keeps the depth exact far away
+const NORMAL_DEPTH_FORMAT: TextureFormat = TextureFormat::Rgba32Float;
+pub const AO_FORMAT: TextureFormat = TextureFormat::R8Unorm;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SsaoParam {
+    view: [[f32; 4]; 4],
+    proj: [[f32; 4]; 4],
+    // xyz in the hemisphere around +z, w unused
+    kernel_arr: [[f32; 4]; MAX_SAMPLE_COUNT],
+    radius: f32,
+    bias: f32,
+    sample_count: u32,
+    blur_size: u32,
+}
+
+// screen space ambient occlusion, the ao texture is read by the mesh pass to darken the
+// ambient term where the geometry is close together
+pub struct PipeSsao {
+    // off leaves the ao texture white
+    pub is_enabled: bool,
+    // view space size of the hemisphere
+    pub radius: f32,
+    // depth difference below which a sample doesn't occlude, against acne on flat surfaces
+    pub bias: f32,
+    // kernel samples per pixel, see set_sample_count
+    pub sample_count: u32,
+    // texels on each side of the blurred one, 0 turns the blur off
+    pub blur_size: u32,
+    pub kernel_arr: [[f32; 4]; MAX_SAMPLE_COUNT],
+
+    pub buffer_param: Buffer,
+    pub bind_group_layout_param: BindGroupLayout,
+    pub bind_group_param: BindGroup,
+    pub bind_group_layout_texture: BindGroupLayout,
+
+    pub render_pipline_geometry: RenderPipeline,
+    pub render_pipline_ssao: RenderPipeline,
+    pub render_pipline_blur: RenderPipeline,
+
+    pub texture_view_noise: TextureView,
+    pub texture_view_depth: TextureView,
+    pub texture_view_normal_depth: TextureView,
+    // before the blur
+    pub texture_view_ao_raw: TextureView,
+    pub texture_view_ao: TextureView,
+    pub bind_group_ssao: BindGroup,
+    pub bind_group_blur: BindGroup,
+}
+
+impl PipeSsao {
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        surface_config: &SurfaceConfiguration,
+        backend: Backend,
+    ) -> Self {
+        let sample_count = 32;
+        let kernel_arr = gen_kernel_arr(sample_count);
+
+        let param = SsaoParam {
+            view: Mat4::IDENTITY.to_cols_array_2d(),
+            proj: Mat4::IDENTITY.to_cols_array_2d(),
+            kernel_arr,
+            radius: 0.5,
+            bias: 0.025,
+            sample_count,
+            blur_size: 2,
+        };
+        let buffer_param = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Buffer Ssao Param"),
+            contents: bytemuck::bytes_of(&param),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bind_group_layout_param =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Bind Group Layout Ssao Param"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+        let bind_group_param = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Bind Group Ssao Param"),
+            layout: &bind_group_layout_param,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(buffer_param.as_entire_buffer_binding()),
+            }],
+        });
+
+        // every texel is loaded, nothing is filtered
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout_texture =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Bind Group Layout Ssao Texture"),
+                entries: &[texture_entry(0), texture_entry(1)],
+            });
+
+        let render_pipline_layout_geometry =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipline Layout Ssao Geometry"),
+                bind_group_layouts: &[&bind_group_layout_param],
+                push_constant_ranges: &[],
+            });
+        let render_pipline_layout_screen =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipline Layout Ssao"),
+                bind_group_layouts: &[&bind_group_layout_param, &bind_group_layout_texture],
+                push_constant_ranges: &[],
+            });
+
+        let shader_geometry = gen_shader(device, "assets/shader/ssao_geometry.wgsl");
+        let render_pipline_geometry =
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Render Pipline Ssao Geometry"),
+                layout: Some(&render_pipline_layout_geometry),
+                vertex: wgpu::VertexState {
+                    module: &shader_geometry,
+                    entry_point: "vs_main",
+                    buffers: &[
+                        Vertex::vertex_buffer_layout(),
+                        TransformRawIT::vertex_buffer_layout(),
+                    ],
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader_geometry,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: NORMAL_DEPTH_FORMAT,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            });
+
+        let render_pipline_ssao = gen_render_pipline_screen(
+            device,
+            &render_pipline_layout_screen,
+            &gen_shader(device, "assets/shader/ssao.wgsl"),
+            "Render Pipline Ssao",
+        );
+        let render_pipline_blur = gen_render_pipline_screen(
+            device,
+            &render_pipline_layout_screen,
+            &gen_shader(device, "assets/shader/ssao_blur.wgsl"),
+            "Render Pipline Ssao Blur",
+        );
+
+        let texture_view_noise = gen_texture_view_rgba(
+            &gen_noise_img(),
+            wgpu::TextureFormat::Rgba8Unorm,
+            device,
+            queue,
+        );
+
+        let (texture_view_depth, texture_view_normal_depth, texture_view_ao_raw, texture_view_ao) =
+            gen_texture_view_target_arr(device, surface_config, backend);
+        let (bind_group_ssao, bind_group_blur) = gen_bind_group_texture(
+            device,
+            &bind_group_layout_texture,
+            &texture_view_normal_depth,
+            &texture_view_noise,
+            &texture_view_ao_raw,
+        );
+
+        Self {
+            is_enabled: true,
+            radius: param.radius,
+            bias: param.bias,
+            sample_count,
+            blur_size: param.blur_size,
+            kernel_arr,
+
+            buffer_param,
+            bind_group_layout_param,
+            bind_group_param,
+            bind_group_layout_texture,
+
+            render_pipline_geometry,
+            render_pipline_ssao,
+            render_pipline_blur,
+
+            texture_view_noise,
+            texture_view_depth,
+            texture_view_normal_depth,
+            texture_view_ao_raw,
+            texture_view_ao,
+            bind_group_ssao,
+            bind_group_blur,
+        }
+    }
+
+    // the ao texture is new, the mesh pass has to bind it again
+    pub fn resize(
+        &mut self,
+        device: &Device,
+        surface_config: &SurfaceConfiguration,
+        backend: Backend,
+    ) {
+        (
+            self.texture_view_depth,
+            self.texture_view_normal_depth,
+            self.texture_view_ao_raw,
+            self.texture_view_ao,
+        ) = gen_texture_view_target_arr(device, surface_config, backend);
+        (self.bind_group_ssao, self.bind_group_blur) = gen_bind_group_texture(
+            device,
+            &self.bind_group_layout_texture,
+            &self.texture_view_normal_depth,
+            &self.texture_view_noise,
+            &self.texture_view_ao_raw,
+        );
+    }
+
+    // the kernel is spread over the samples that are used, clamped to 1..=MAX_SAMPLE_COUNT
+    pub fn set_sample_count(&mut self, sample_count: u32) {
+        self.sample_count = sample_count.clamp(1, MAX_SAMPLE_COUNT as u32);
+        self.kernel_arr = gen_kernel_arr(self.sample_count);
+    }
+
+    pub fn update(&self, queue: &Queue, camera: &Camera) {
+        let param = SsaoParam {
+            view: camera.view().to_cols_array_2d(),
+            proj: camera.proj().to_cols_array_2d(),
+            kernel_arr: self.kernel_arr,
+            radius: self.radius,
+            bias: self.bias,
+            sample_count: self.sample_count,
+            blur_size: self.blur_size,
+        };
+        queue.write_buffer(&self.buffer_param, 0, bytemuck::bytes_of(&param));
+    }
+
+    pub fn render(&self, encoder: &mut CommandEncoder, material_arr: &[Material]) {
+        if !self.is_enabled {
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass Ssao Off"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.texture_view_ao,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            return;
+        }
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass Ssao Geometry"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.texture_view_normal_depth,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.texture_view_depth,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            render_pass.set_pipeline(&self.render_pipline_geometry);
+            render_pass.set_bind_group(0, &self.bind_group_param, &[]);
+            for model in material_arr.iter().flat_map(|material| &material.model_arr) {
+                render_pass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
+                render_pass.set_vertex_buffer(1, model.transform_buffer.slice(..));
+                if model.draw_method == DrawMethod::Vertex {
+                    render_pass.draw(0..model.vertices_len, 0..model.instance_num);
+                } else {
+                    render_pass.set_index_buffer(model.index_buffer.slice(..), IndexFormat::Uint32);
+                    render_pass.draw_indexed(0..model.indices_len, 0, 0..model.instance_num);
+                }
+            }
+        }
+
+        self.render_screen(
+            encoder,
+            "Render Pass Ssao",
+            &self.render_pipline_ssao,
+            &self.bind_group_ssao,
+            &self.texture_view_ao_raw,
+        );
+        self.render_screen(
+            encoder,
+            "Render Pass Ssao Blur",
+            &self.render_pipline_blur,
+            &self.bind_group_blur,
+            &self.texture_view_ao,
+        );
+    }
+
+    fn render_screen(
+        &self,
+        encoder: &mut CommandEncoder,
+        label: &str,
+        render_pipline: &RenderPipeline,
+        bind_group: &BindGroup,
+        texture_view: &TextureView,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: texture_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(render_pipline);
+        render_pass.set_bind_group(0, &self.bind_group_param, &[]);
+        render_pass.set_bind_group(1, bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn gen_shader(device: &Device, path: &str) -> ShaderModule {
+    let shader = std::fs::read_to_string(path).unwrap();
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(path),
+        source: wgpu::ShaderSource::Wgsl(shader.into()),
+    })
+}
+
+// fullscreen triangle into an ao texture
+fn gen_render_pipline_screen(
+    device: &Device,
+    render_pipline_layout: &PipelineLayout,
+    shader: &ShaderModule,
+    label: &str,
+) -> RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(render_pipline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: AO_FORMAT,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    })
+}
+
+// depth, normal depth, ao before and after the blur
+fn gen_texture_view_target_arr(
+    device: &Device,
+    surface_config: &SurfaceConfiguration,
+    backend: Backend,
+) -> (TextureView, TextureView, TextureView, TextureView) {
+    (
+        gen_texture_depth(
+            device,
+            surface_config.width,
+            surface_config.height,
+            1,
+            backend,
+        )
+        .create_view(&wgpu::TextureViewDescriptor::default()),
+        gen_texture_view_target(device, surface_config, NORMAL_DEPTH_FORMAT),
+        gen_texture_view_target(device, surface_config, AO_FORMAT),
+        gen_texture_view_target(device, surface_config, AO_FORMAT),
+    )
+}
+
+// the ssao pass reads normal depth and noise, the blur reads the raw ao and normal depth
+fn gen_bind_group_texture(
+    device: &Device,
+    layout: &BindGroupLayout,
+    texture_view_normal_depth: &TextureView,
+    texture_view_noise: &TextureView,
+    texture_view_ao_raw: &TextureView,
+) -> (BindGroup, BindGroup) {
+    let gen_bind_group = |label, texture_view_0, texture_view_1| {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(label),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(texture_view_0),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(texture_view_1),
+                },
+            ],
+        })
+    };
+    (
+        gen_bind_group(
+            "Bind Group Ssao",
+            texture_view_normal_depth,
+            texture_view_noise,
+        ),
+        gen_bind_group(
+            "Bind Group Ssao Blur",
+            texture_view_ao_raw,
+            texture_view_normal_depth,
+        ),
+    )
+}
+
+// xorshift, the same kernel and noise every run
+fn random(state: &mut u32) -> f32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state as f32 / u32::MAX as f32
+}
+
+// points in the hemisphere around +z, more of them close to the center
+fn gen_kernel_arr(sample_count: u32) -> [[f32; 4]; MAX_SAMPLE_COUNT] {
+    let mut state = 0x9e37_79b9;
+    let mut kernel_arr = [[0.0; 4]; MAX_SAMPLE_COUNT];
+    for (i, kernel) in kernel_arr
+        .iter_mut()
+        .take(sample_count as usize)
+        .enumerate()
+    {
+        let dir = Vec3::new(
+            random(&mut state) * 2.0 - 1.0,
+            random(&mut state) * 2.0 - 1.0,
+            random(&mut state),
+        )
+        .normalize_or_zero();
+        let t = i as f32 / sample_count as f32;
+        let scale = 0.1 + 0.9 * t * t;
+        *kernel = (dir * random(&mut state) * scale).extend(0.0).to_array();
+    }
+    kernel_arr
+}
+
+// random directions in the xy plane, mapped from -1..1 to 0..255
+fn gen_noise_img() -> RgbaImage {
+    let mut state = 0x2545_f491;
+    RgbaImage::from_fn(NOISE_SIZE, NOISE_SIZE, |_, _| {
+        let x = random(&mut state);
+        let y = random(&mut state);
+        Rgba([(x * 255.0) as u8, (y * 255.0) as u8, 128, 255])
+    })
+}
@@ -1,4 +1,6 @@
+use anyhow::Result;
 use glam::{Quat, Vec3};
+use wgpu::TextureFormat;
 use winit::{event_loop::EventLoop, window::WindowBuilder};
 
 use crate::{
@@ -6,7 +8,8 @@ use crate::{
     material::Material,
     model::{DrawMethod, Model},
     pipe_hub::PipeHub,
-    texture::gen_texture_view,
+    scene::{load_gltf_scene, Scene},
+    texture::{gen_texture_view, gen_texture_view_linear},
     transform::Transform,
     vertex::Vertex,
 };
@@ -14,6 +17,15 @@ use crate::{
 const BASE_GLTF_PATH: &str = "assets/gltf/";
 
 pub fn run() {
+    run_scene(load_scene);
+}
+
+// a thousand small point lights, c switches the clustered shading to compare
+pub fn run_stress() {
+    run_scene(load_scene_stress);
+}
+
+fn run_scene(load_scene: fn(&mut PipeHub) -> Result<()>) {
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
 
@@ -26,134 +38,100 @@ pub fn run() {
         .unwrap();
     pollster::block_on(async {
         let mut core = PipeHub::new(window).await;
-        // load_box_model(&mut core);
-        // load_rect_model(&mut core);
-        // load_triangle_model(&mut core);
-        load_gltf_model(&mut core);
-        load_plane_model(&mut core);
-        core.add_light_point(LightPoint::new(
-            [-2.5, 0.5, 0.0],
-            [1.0, 0.5, 0.0, 1.0],
-            [0.1, 0.1, 0.1],
-            [0.5, 0.5, 0.5],
-            [1.0, 1.0, 1.0],
-            1.0,
-            0.09,
-            0.032,
-        ));
+        load_scene(&mut core).unwrap();
 
         PipeHub::block_loop(event_loop, core);
     });
 }
 
-pub fn load_gltf_model(core: &mut PipeHub) {
-    let base_path = std::path::Path::new(BASE_GLTF_PATH);
-
-    let gltf_path = base_path.join("box.gltf");
-    let gltf_info = gltf::Gltf::open(gltf_path).unwrap();
-
-    let mut buffer_data = Vec::new();
-    for buffer in gltf_info.buffers() {
-        match buffer.source() {
-            gltf::buffer::Source::Bin => {}
-            gltf::buffer::Source::Uri(path) => {
-                let path = base_path.join(path);
-                let data = std::fs::read(path).unwrap();
-                buffer_data.push(data);
-            }
-        }
-    }
-
-    // let material_arr = vec![];
-    for material in gltf_info.materials() {
-        let info = material
-            .pbr_metallic_roughness()
-            .base_color_texture()
-            .unwrap();
-        let texture = info.texture();
-        let source = texture.source().source();
-        let texture_view;
-        match source {
-            gltf::image::Source::View { view, .. } => {
-                texture_view =
-                    gen_texture_view(buffer_data[view.index()].clone(), &core.device, &core.queue)
-                        .unwrap();
-            }
-            gltf::image::Source::Uri { uri, .. } => {
-                texture_view = gen_texture_view(
-                    std::fs::read(base_path.join(url_escape::decode(uri).to_string())).unwrap(),
-                    &core.device,
-                    &core.queue,
-                )
-                .unwrap();
-            }
-        }
-
-        let material = Material::new(
-            texture_view,
-            32.0,
-            core,
-            &core.pipe_mesh.bind_group_layout_material,
-            &core.pipe_mesh.sampler,
-        );
-        core.pipe_mesh.material_arr.push(material);
-    }
-
-    for mesh in gltf_info.meshes() {
-        let primitives = mesh.primitives();
-        primitives.for_each(|primitive| {
-            let reader = primitive.reader(|buffer| Some(&buffer_data[buffer.index()]));
-
-            let mut vertices = vec![];
-            if let Some(positions) = reader.read_positions() {
-                positions.for_each(|position| {
-                    vertices.push(Vertex::new(
-                        position,
-                        Default::default(),
-                        Default::default(),
-                    ));
-                });
-            }
-
-            if let Some(normals) = reader.read_normals() {
-                let mut normal_idx = 0;
-                normals.for_each(|normal| {
-                    vertices[normal_idx].normal = normal;
-                    normal_idx += 1;
-                });
-            }
+pub fn run_headless(width: u32, height: u32, path: &str) -> Result<()> {
+    pollster::block_on(async {
+        let mut core = PipeHub::new_headless(width, height, TextureFormat::Rgba8UnormSrgb).await?;
+        load_scene(&mut core)?;
+        core.update_delta_time(1.0 / 60.0);
+        core.render_to_image().save(path)?;
+        Ok(())
+    })
+}
 
-            if let Some(tex_coords) = reader.read_tex_coords(0).map(|v| v.into_f32()) {
-                let mut tex_coord_idx = 0;
-                tex_coords.for_each(|tex_coord| {
-                    vertices[tex_coord_idx].tex_coord = tex_coord;
-                    tex_coord_idx += 1;
-                });
-            }
+pub fn load_scene(core: &mut PipeHub) -> Result<()> {
+    // load_box_model(core);
+    // load_rect_model(core);
+    // load_triangle_model(core);
+    load_gltf_model(core)?;
+    load_plane_model(core);
+    core.add_light_point(LightPoint::new(
+        [-2.5, 0.5, 0.0],
+        [1.0, 0.5, 0.0, 1.0],
+        [0.1, 0.1, 0.1],
+        [0.5, 0.5, 0.5],
+        [1.0, 1.0, 1.0],
+        1.0,
+        0.09,
+        0.032,
+    ));
+    core.add_light_point(LightPoint::new(
+        [2.5, 4.0, 2.0],
+        [0.0, 0.5, 1.0, 1.0],
+        [0.05, 0.05, 0.05],
+        [0.5, 0.5, 0.5],
+        [1.0, 1.0, 1.0],
+        1.0,
+        0.09,
+        0.032,
+    ));
+    core.add_light_point(LightPoint::new(
+        [0.0, 8.0, -3.0],
+        [1.0, 1.0, 1.0, 1.0],
+        [0.05, 0.05, 0.05],
+        [0.5, 0.5, 0.5],
+        [1.0, 1.0, 1.0],
+        1.0,
+        0.09,
+        0.032,
+    ));
+
+    Ok(())
+}
 
-            let mut indices = vec![];
-            if let Some(indices_raw) = reader.read_indices() {
-                indices.append(&mut indices_raw.into_u32().collect::<Vec<u32>>());
-            }
+pub fn load_scene_stress(core: &mut PipeHub) -> Result<()> {
+    load_scene(core)?;
+    core.add_light_point_arr(&gen_light_point_stress_arr(1000));
+    core.is_light_point_orbiting = true;
+    Ok(())
+}
 
-            let model = Model::new(
-                &core.device,
-                DrawMethod::Index,
-                vertices,
-                indices,
-                vec![
-                    Transform::new(Vec3::new(0.0, 0.0, 0.0), Quat::IDENTITY, Vec3::ONE),
-                    Transform::new(Vec3::new(-1.0, 6.0, 0.0), Quat::IDENTITY, Vec3::ONE),
-                    Transform::new(Vec3::new(-0.0, 3.0, 0.0), Quat::IDENTITY, Vec3::ONE),
-                    Transform::new(Vec3::new(3.0, 1.5, 0.0), Quat::IDENTITY, Vec3::ONE),
+// spread over the plane on a sunflower spiral, just above it, in all hues
+fn gen_light_point_stress_arr(num: usize) -> Vec<LightPoint> {
+    (0..num)
+        .map(|i| {
+            let t = i as f32 / num as f32;
+            let radius = 9.5 * t.sqrt();
+            let angle = i as f32 * 2.399_963;
+            let hue = (i as f32 * 0.618_034).fract();
+            let color = [0.0, 2.0 / 3.0, 1.0 / 3.0]
+                .map(|offset| ((((hue + offset) % 1.0) * 6.0 - 3.0).abs() - 1.0).clamp(0.0, 1.0));
+            LightPoint::new(
+                [
+                    radius * angle.cos(),
+                    -0.7 + (i % 3) as f32 * 0.3,
+                    radius * angle.sin(),
                 ],
-            );
+                [color[0], color[1], color[2], 1.0],
+                [0.0, 0.0, 0.0],
+                [0.4, 0.4, 0.4],
+                [0.4, 0.4, 0.4],
+                1.0,
+                1.0,
+                8.0,
+            )
+        })
+        .collect()
+}
 
-            core.pipe_mesh.material_arr[primitive.material().index().unwrap() as usize]
-                .model_arr
-                .push(model);
-        });
-    }
+pub fn load_gltf_model(core: &mut PipeHub) -> Result<Scene> {
+    let gltf_path = std::path::Path::new(BASE_GLTF_PATH).join("boxes.gltf");
+    load_gltf_scene(core, &gltf_path)
 }
 
 pub fn load_plane_model(core: &mut PipeHub) {
@@ -229,9 +207,16 @@ fn plane_material(core: &PipeHub) -> Material {
         &queue,
     )
     .unwrap();
+    let texture_normal_view = gen_texture_view_linear(
+        std::fs::read("assets/texture/wood_normal.png").unwrap(),
+        device,
+        queue,
+    )
+    .unwrap();
 
     Material::new(
         texture_diffuse_view,
+        Some(texture_normal_view),
         32.0,
         core,
         &core.pipe_mesh.bind_group_layout_material,
@@ -250,6 +235,7 @@ fn box_material(core: &PipeHub) -> Material {
 
     Material::new(
         texture_diffuse_view,
+        None,
         32.0,
         core,
         &core.pipe_mesh.bind_group_layout_material,
@@ -0,0 +1,233 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use glam::Mat4;
+
+use crate::{
+    gltf_data::{load_gltf, load_gltf_image},
+    material::{Material, MaterialFactor, MaterialTexture},
+    model::{DrawMethod, Model},
+    pipe_hub::PipeHub,
+    texture::{gen_texture_view, gen_texture_view_linear},
+    transform::TransformRawIT,
+    vertex::Vertex,
+};
+
+pub struct Scene {
+    pub name: Option<String>,
+    pub node_arr: Vec<SceneNode>,
+}
+
+pub struct SceneNode {
+    pub name: Option<String>,
+    pub local: Mat4,
+    // parent transforms applied
+    pub world: Mat4,
+    // index of the gltf mesh, every node of a mesh is one instance of its models
+    pub mesh_idx: Option<usize>,
+    pub children: Vec<SceneNode>,
+}
+
+impl Scene {
+    // depth first search
+    pub fn find_node(&self, name: &str) -> Option<&SceneNode> {
+        self.node_arr.iter().find_map(|node| node.find_node(name))
+    }
+}
+
+impl SceneNode {
+    pub fn find_node(&self, name: &str) -> Option<&SceneNode> {
+        if self.name.as_deref() == Some(name) {
+            return Some(self);
+        }
+        self.children.iter().find_map(|node| node.find_node(name))
+    }
+}
+
+// load the default scene of a gltf file, its models are pushed to core.pipe_mesh.material_arr
+pub fn load_gltf_scene(core: &mut PipeHub, path: &Path) -> Result<Scene> {
+    let base_path = path.parent().unwrap_or(Path::new(""));
+    let (gltf_info, buffer_data) = load_gltf(path)?;
+
+    let gltf_scene = gltf_info
+        .default_scene()
+        .or_else(|| gltf_info.scenes().next())
+        .ok_or_else(|| anyhow!("{:?} has no scene", path))?;
+
+    // world transform of every node using a mesh
+    let mut instance_arr: Vec<Vec<Mat4>> = gltf_info.meshes().map(|_| vec![]).collect();
+    let node_arr = gltf_scene
+        .nodes()
+        .map(|node| load_node(&node, Mat4::IDENTITY, &mut instance_arr))
+        .collect();
+
+    // gltf material index is offset by the materials loaded before
+    let material_offset = core.pipe_mesh.material_arr.len();
+    for material in gltf_info.materials() {
+        let material = load_material(core, &material, base_path, &buffer_data)?;
+        core.pipe_mesh.material_arr.push(material);
+    }
+    let mut material_default_idx = None;
+
+    for mesh in gltf_info.meshes() {
+        let transform_arr = &instance_arr[mesh.index()];
+        if transform_arr.is_empty() {
+            continue;
+        }
+
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                continue;
+            }
+            let reader = primitive.reader(|buffer| Some(&*buffer_data[buffer.index()]));
+
+            let mut vertices = vec![];
+            if let Some(positions) = reader.read_positions() {
+                positions.for_each(|position| {
+                    vertices.push(Vertex::new(
+                        position,
+                        Default::default(),
+                        Default::default(),
+                    ));
+                });
+            }
+
+            if let Some(normals) = reader.read_normals() {
+                for (vertex, normal) in vertices.iter_mut().zip(normals) {
+                    vertex.normal = normal;
+                }
+            }
+
+            if let Some(tex_coords) = reader.read_tex_coords(0).map(|v| v.into_f32()) {
+                for (vertex, tex_coord) in vertices.iter_mut().zip(tex_coords) {
+                    vertex.tex_coord = tex_coord;
+                }
+            }
+
+            let (draw_method, indices) = match reader.read_indices() {
+                Some(indices_raw) => (DrawMethod::Index, indices_raw.into_u32().collect()),
+                None => (DrawMethod::Vertex, vec![]),
+            };
+
+            // generated from the uvs when the gltf has no tangents
+            match reader.read_tangents() {
+                Some(tangents) => {
+                    for (vertex, tangent) in vertices.iter_mut().zip(tangents) {
+                        vertex.tangent = tangent;
+                    }
+                }
+                None => Vertex::gen_tangents(&mut vertices, &indices),
+            }
+
+            // the world matrices go to the gpu as they are, shear from a non uniform parent included
+            let model = Model::new_raw(
+                &core.device,
+                draw_method,
+                vertices,
+                indices,
+                &transform_arr
+                    .iter()
+                    .map(|m| TransformRawIT::from_mat4(*m))
+                    .collect::<Vec<_>>(),
+            );
+
+            let material_idx = match primitive.material().index() {
+                Some(idx) => material_offset + idx,
+                // gltf default material, white and fully rough metal
+                None => *material_default_idx.get_or_insert_with(|| {
+                    let material = Material::new_pbr(
+                        Default::default(),
+                        MaterialFactor::metallic_roughness([1.0; 4], 1.0, 1.0),
+                        core,
+                        &core.pipe_mesh.bind_group_layout_material,
+                        &core.pipe_mesh.sampler_repeat,
+                    );
+                    core.pipe_mesh.material_arr.push(material);
+                    core.pipe_mesh.material_arr.len() - 1
+                }),
+            };
+            core.pipe_mesh.material_arr[material_idx].add_model(model);
+        }
+    }
+
+    Ok(Scene {
+        name: gltf_scene.name().map(str::to_string),
+        node_arr,
+    })
+}
+
+fn load_node(node: &gltf::Node, parent: Mat4, instance_arr: &mut [Vec<Mat4>]) -> SceneNode {
+    let local = Mat4::from_cols_array_2d(&node.transform().matrix());
+    let world = parent * local;
+
+    let mesh_idx = node.mesh().map(|mesh| mesh.index());
+    if let Some(mesh_idx) = mesh_idx {
+        instance_arr[mesh_idx].push(world);
+    }
+
+    SceneNode {
+        name: node.name().map(str::to_string),
+        local,
+        world,
+        mesh_idx,
+        children: node
+            .children()
+            .map(|child| load_node(&child, world, instance_arr))
+            .collect(),
+    }
+}
+
+fn load_material(
+    core: &PipeHub,
+    material: &gltf::Material,
+    base_path: &Path,
+    buffer_data: &[gltf::buffer::Data],
+) -> Result<Material> {
+    let pbr = material.pbr_metallic_roughness();
+    let load_texture = |texture: Option<gltf::Texture>, is_srgb: bool| -> Result<_> {
+        let texture = match texture {
+            Some(texture) => texture,
+            None => return Ok(None),
+        };
+        let img_bytes = load_gltf_image(&texture.source(), base_path, buffer_data)?;
+        let texture_view = if is_srgb {
+            gen_texture_view(img_bytes, &core.device, &core.queue)?
+        } else {
+            gen_texture_view_linear(img_bytes, &core.device, &core.queue)?
+        };
+        Ok(Some(texture_view))
+    };
+
+    let texture = MaterialTexture {
+        base_color: load_texture(pbr.base_color_texture().map(|t| t.texture()), true)?,
+        metallic_roughness: load_texture(
+            pbr.metallic_roughness_texture().map(|t| t.texture()),
+            false,
+        )?,
+        normal: load_texture(material.normal_texture().map(|t| t.texture()), false)?,
+        occlusion: load_texture(material.occlusion_texture().map(|t| t.texture()), false)?,
+        emissive: load_texture(material.emissive_texture().map(|t| t.texture()), true)?,
+    };
+
+    let mut factor = MaterialFactor::metallic_roughness(
+        pbr.base_color_factor(),
+        pbr.metallic_factor(),
+        pbr.roughness_factor(),
+    );
+    factor.emissive = material.emissive_factor();
+    if let Some(normal) = material.normal_texture() {
+        factor.normal_scale = normal.scale();
+    }
+    if let Some(occlusion) = material.occlusion_texture() {
+        factor.occlusion_strength = occlusion.strength();
+    }
+
+    // gltf textures repeat unless the sampler says otherwise
+    Ok(Material::new_pbr(
+        texture,
+        factor,
+        core,
+        &core.pipe_mesh.bind_group_layout_material,
+        &core.pipe_mesh.sampler_repeat,
+    ))
+}
@@ -0,0 +1,18 @@
+// what the point light shadows store and how the mesh shaders read them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShadowTechnique {
+    // depth cubes, filtered with poisson comparisons, see ShadowQuality
+    DepthCompare,
+    // exponential variance shadow maps, blurred and mipmapped moments of the distance to the
+    // light, filtered by the hardware
+    Moment,
+}
+
+impl ShadowTechnique {
+    pub fn next(self) -> Self {
+        match self {
+            Self::DepthCompare => Self::Moment,
+            Self::Moment => Self::DepthCompare,
+        }
+    }
+}
@@ -1,5 +1,4 @@
 use anyhow::Result;
-use image::GenericImageView;
 
 pub fn gen_sampler_clamp(device: &wgpu::Device) -> wgpu::Sampler {
     device.create_sampler(&wgpu::SamplerDescriptor {
@@ -46,8 +45,36 @@ pub fn gen_texture_view(
     queue: &wgpu::Queue,
 ) -> Result<wgpu::TextureView> {
     let img = image::load_from_memory(&img_bytes)?;
-    let img_rgba = img.to_rgba8();
-    let img_dim = img.dimensions();
+    Ok(gen_texture_view_rgba(
+        &img.to_rgba8(),
+        wgpu::TextureFormat::Rgba8UnormSrgb,
+        device,
+        queue,
+    ))
+}
+
+// data textures like normal or metallic roughness maps, no srgb decode
+pub fn gen_texture_view_linear(
+    img_bytes: Vec<u8>,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> Result<wgpu::TextureView> {
+    let img = image::load_from_memory(&img_bytes)?;
+    Ok(gen_texture_view_rgba(
+        &img.to_rgba8(),
+        wgpu::TextureFormat::Rgba8Unorm,
+        device,
+        queue,
+    ))
+}
+
+pub fn gen_texture_view_rgba(
+    img_rgba: &image::RgbaImage,
+    format: wgpu::TextureFormat,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> wgpu::TextureView {
+    let img_dim = img_rgba.dimensions();
 
     let texture_size = wgpu::Extent3d {
         width: img_dim.0,
@@ -61,7 +88,7 @@ pub fn gen_texture_view(
         mip_level_count: 1,
         sample_count: 1,
         dimension: wgpu::TextureDimension::D2,
-        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        format,
         usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
         view_formats: &[],
     });
@@ -73,7 +100,7 @@ pub fn gen_texture_view(
             origin: wgpu::Origin3d::ZERO,
             aspect: wgpu::TextureAspect::All,
         },
-        &img_rgba,
+        img_rgba,
         wgpu::ImageDataLayout {
             offset: 0,
             bytes_per_row: Some(4 * img_dim.0),
@@ -82,15 +109,18 @@ pub fn gen_texture_view(
         texture_size,
     );
 
-    Ok(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
 }
 
 pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+// filterable without extra features, and storable for the compute blur
+pub const MOMENT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
 pub fn gen_texture_depth(
     device: &wgpu::Device,
     width: u32,
     height: u32,
     sample_count: u32,
+    backend: wgpu::Backend,
 ) -> wgpu::Texture {
     device.create_texture(&wgpu::TextureDescriptor {
         label: Some("Texture"),
@@ -103,51 +133,56 @@ pub fn gen_texture_depth(
         sample_count,
         dimension: wgpu::TextureDimension::D2,
         format: DEPTH_FORMAT,
-        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        usage: gen_usage_attachment(sample_count, backend),
         view_formats: &[],
     })
 }
 
-pub fn gen_texture_cube(
+// six layers per cube, sampled through a cube array view with a comparison sampler
+pub fn gen_texture_depth_cube_arr(
     device: &wgpu::Device,
-    surface_config: &wgpu::SurfaceConfiguration,
     width: u32,
     height: u32,
+    cube_num: u32,
 ) -> wgpu::Texture {
     device.create_texture(&wgpu::TextureDescriptor {
-        label: Some("Texture Cube"),
+        label: Some("Texture Depth Cube Array"),
         size: wgpu::Extent3d {
             width,
             height,
-            depth_or_array_layers: 6,
+            depth_or_array_layers: 6 * cube_num.max(1),
         },
         mip_level_count: 1,
         sample_count: 1,
         dimension: wgpu::TextureDimension::D2,
-        format: surface_config.format,
+        format: DEPTH_FORMAT,
         usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
         view_formats: &[],
     })
 }
 
-pub fn gen_texture_depth_cube(
+// moments of the light distance for the filterable shadows, mipmapped and blurred in place
+pub fn gen_texture_moment_cube_arr(
     device: &wgpu::Device,
     width: u32,
     height: u32,
-    sample_count: u32,
+    cube_num: u32,
+    mip_level_count: u32,
 ) -> wgpu::Texture {
     device.create_texture(&wgpu::TextureDescriptor {
-        label: Some("Texture"),
+        label: Some("Texture Moment Cube Array"),
         size: wgpu::Extent3d {
             width,
             height,
-            depth_or_array_layers: 6,
+            depth_or_array_layers: 6 * cube_num.max(1),
         },
-        mip_level_count: 1,
-        sample_count,
+        mip_level_count,
+        sample_count: 1,
         dimension: wgpu::TextureDimension::D2,
-        format: DEPTH_FORMAT,
-        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: MOMENT_FORMAT,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::STORAGE_BINDING,
         view_formats: &[],
     })
 }
@@ -156,6 +191,7 @@ pub fn gen_texture_view_msaa(
     device: &wgpu::Device,
     surface_config: &wgpu::SurfaceConfiguration,
     sample_count: u32,
+    backend: wgpu::Backend,
 ) -> wgpu::TextureView {
     device
         .create_texture(&wgpu::TextureDescriptor {
@@ -169,7 +205,7 @@ pub fn gen_texture_view_msaa(
             sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: surface_config.format,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage: gen_usage_attachment(sample_count, backend),
             view_formats: &[],
         })
         .create_view(&wgpu::TextureViewDescriptor::default())
@@ -219,26 +255,83 @@ pub fn gen_texture_view_post_processing(
         .create_view(&wgpu::TextureViewDescriptor::default())
 }
 
-pub fn gen_texture_view_cube_map_depth(
+// render target of a screen space pass, sized like the surface
+pub fn gen_texture_view_target(
     device: &wgpu::Device,
-    width: u32,
-    height: u32,
-    sample_count: u32,
+    surface_config: &wgpu::SurfaceConfiguration,
+    format: wgpu::TextureFormat,
 ) -> wgpu::TextureView {
     device
         .create_texture(&wgpu::TextureDescriptor {
-            label: Some("Texture View Cube Map"),
+            label: Some("Texture View Target"),
             size: wgpu::Extent3d {
-                width,
-                height,
-                depth_or_array_layers: 6,
+                width: surface_config.width,
+                height: surface_config.height,
+                depth_or_array_layers: 1,
             },
             mip_level_count: 1,
-            sample_count,
+            sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: DEPTH_FORMAT,
+            format,
             usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
             view_formats: &[],
         })
         .create_view(&wgpu::TextureViewDescriptor::default())
 }
+
+// msaa sample counts the color format and DEPTH_FORMAT can both be rendered and resolved with,
+// ascending and always starting with 1
+pub fn supported_sample_count_arr(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+) -> Vec<u32> {
+    // the device only trusts the adapter flags with this feature or on a downlevel backend,
+    // otherwise it validates against the counts webgpu guarantees
+    let is_adapter_flags = device
+        .features()
+        .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+        || !adapter.get_downlevel_capabilities().is_webgpu_compliant();
+    let flags = |format: wgpu::TextureFormat| {
+        if is_adapter_flags {
+            adapter.get_texture_format_features(format).flags
+        } else {
+            format.guaranteed_format_features(device.features()).flags
+        }
+    };
+    let (flags_color, flags_depth) = (flags(format), flags(DEPTH_FORMAT));
+
+    [1, 2, 4, 8, 16]
+        .into_iter()
+        .filter(|&sample_count| {
+            sample_count == 1
+                || (flags_color.sample_count_supported(sample_count)
+                    && flags_color.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE)
+                    && flags_depth.sample_count_supported(sample_count))
+        })
+        .collect()
+}
+
+// sample_count when supported, otherwise the highest supported count below it
+pub fn supported_sample_count(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> u32 {
+    supported_sample_count_arr(adapter, device, format)
+        .into_iter()
+        .rev()
+        .find(|&supported| supported <= sample_count)
+        .unwrap_or(1)
+}
+
+// wgpu's GL backend makes a render only multisampled texture a renderbuffer, a bindable one gets
+// glTexStorage2DMultisample on GL_TEXTURE_2D, GL rejects that target and the attachment stays empty
+fn gen_usage_attachment(sample_count: u32, backend: wgpu::Backend) -> wgpu::TextureUsages {
+    if sample_count > 1 && backend == wgpu::Backend::Gl {
+        wgpu::TextureUsages::RENDER_ATTACHMENT
+    } else {
+        wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT
+    }
+}
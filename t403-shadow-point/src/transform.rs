@@ -29,12 +29,7 @@ impl Transform {
     pub fn to_raw_it(&self) -> TransformRawIT {
         let mat =
             Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation);
-        let mut combine: [[f32; 4]; MAT4_NUM_IT * 4] = [[0.0; 4]; MAT4_NUM_IT * 4];
-        combine[..4].copy_from_slice(&mat.to_cols_array_2d());
-        let it_mat = mat.inverse().transpose();
-        combine[4..8].copy_from_slice(&it_mat.to_cols_array_2d());
-
-        TransformRawIT { model: combine }
+        TransformRawIT::from_mat4(mat)
     }
 }
 
@@ -63,6 +58,16 @@ pub struct TransformRawIT {
 }
 
 impl TransformRawIT {
+    // any affine matrix, a gltf world matrix can carry shear that Transform can't hold
+    pub fn from_mat4(mat: Mat4) -> Self {
+        let mut combine: [[f32; 4]; MAT4_NUM_IT * 4] = [[0.0; 4]; MAT4_NUM_IT * 4];
+        combine[..4].copy_from_slice(&mat.to_cols_array_2d());
+        let it_mat = mat.inverse().transpose();
+        combine[4..8].copy_from_slice(&it_mat.to_cols_array_2d());
+
+        TransformRawIT { model: combine }
+    }
+
     const ATTRS: [wgpu::VertexAttribute; MAT4_NUM_IT * 4] = wgpu::vertex_attr_array![5 => Float32x4, 6 => Float32x4, 7 => Float32x4, 8 => Float32x4, 9 => Float32x4, 10 => Float32x4, 11 => Float32x4, 12 => Float32x4];
     pub fn vertex_buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
         wgpu::VertexBufferLayout {
@@ -72,3 +77,29 @@ impl TransformRawIT {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_it_keeps_sheared_world_matrix() {
+        let parent = Mat4::from_scale(Vec3::new(3.0, 1.0, 0.5));
+        let child = Mat4::from_rotation_translation(
+            Quat::from_rotation_z(std::f32::consts::FRAC_PI_4),
+            Vec3::new(1.0, 2.0, 3.0),
+        );
+        let world = parent * child;
+
+        // the product is sheared, scale rotation translation can't rebuild it
+        let (scale, rotation, translation) = world.to_scale_rotation_translation();
+        let world_srt = Mat4::from_scale_rotation_translation(scale, rotation, translation);
+        assert!(!world_srt.abs_diff_eq(world, 1e-3));
+
+        let raw = TransformRawIT::from_mat4(world);
+        let model = Mat4::from_cols_array_2d(&raw.model[..4].try_into().unwrap());
+        let model_it = Mat4::from_cols_array_2d(&raw.model[4..].try_into().unwrap());
+        assert!(model.abs_diff_eq(world, 1e-6));
+        assert!(model_it.abs_diff_eq(world.inverse().transpose(), 1e-6));
+    }
+}
@@ -1,22 +1,38 @@
+const INDICES_RECT: [u32; 6] = [0, 1, 3, 1, 2, 3];
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
     pub pos: [f32; 3],
     pub normal: [f32; 3],
     pub tex_coord: [f32; 2],
+    // w is the handedness, bitangent = cross(normal, tangent.xyz) * w
+    pub tangent: [f32; 4],
 }
 
 impl Vertex {
+    // tangent is left zero, see gen_tangents
     pub fn new(pos: [f32; 3], normal: [f32; 3], tex_coord: [f32; 2]) -> Self {
         Self {
             pos,
             normal,
             tex_coord,
+            tangent: [0.0; 4],
         }
     }
 
-    const ATTRS: [wgpu::VertexAttribute; 3] =
-        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x2];
+    // mikktspace tangents, an empty indices means every 3 vertices are a triangle
+    pub fn gen_tangents(vertices: &mut [Self], indices: &[u32]) {
+        bevy_mikktspace::generate_tangents(&mut TangentGeometry { vertices, indices });
+    }
+
+    fn with_tangents<const N: usize>(mut vertices: [Self; N], indices: &[u32]) -> [Self; N] {
+        Self::gen_tangents(&mut vertices, indices);
+        vertices
+    }
+
+    const ATTRS: [wgpu::VertexAttribute; 4] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x2, 3 => Float32x4];
     pub fn vertex_buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
         wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<Self>() as u64,
@@ -26,11 +42,14 @@ impl Vertex {
     }
 
     pub fn triangle() -> [Self; 3] {
-        [
-            Self::new([-0.5, -0.5, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0]),
-            Self::new([0.5, -0.5, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0]),
-            Self::new([0.0, 0.5, 0.0], [0.0, 0.0, 1.0], [0.5, 0.0]),
-        ]
+        Self::with_tangents(
+            [
+                Self::new([-0.5, -0.5, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0]),
+                Self::new([0.5, -0.5, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0]),
+                Self::new([0.0, 0.5, 0.0], [0.0, 0.0, 1.0], [0.5, 0.0]),
+            ],
+            &[],
+        )
     }
 
     pub fn plane() -> ([Self; 4], [u32; 6]) {
@@ -39,25 +58,31 @@ impl Vertex {
         let max = size;
         let size_2 = size * 2.0;
         (
-            [
-                Self::new([min, 0.0, max], [0.0, 1.0, 0.0], [0.0, size_2]),
-                Self::new([max, 0.0, max], [0.0, 1.0, 0.0], [size_2, size_2]),
-                Self::new([max, 0.0, min], [0.0, 1.0, 0.0], [size_2, 0.0]),
-                Self::new([min, 0.0, min], [0.0, 1.0, 0.0], [0.0, 0.0]),
-            ],
-            [0, 1, 3, 1, 2, 3],
+            Self::with_tangents(
+                [
+                    Self::new([min, 0.0, max], [0.0, 1.0, 0.0], [0.0, size_2]),
+                    Self::new([max, 0.0, max], [0.0, 1.0, 0.0], [size_2, size_2]),
+                    Self::new([max, 0.0, min], [0.0, 1.0, 0.0], [size_2, 0.0]),
+                    Self::new([min, 0.0, min], [0.0, 1.0, 0.0], [0.0, 0.0]),
+                ],
+                &INDICES_RECT,
+            ),
+            INDICES_RECT,
         )
     }
 
     pub fn rect() -> ([Self; 4], [u32; 6]) {
         (
-            [
-                Self::new([-0.5, -0.5, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0]),
-                Self::new([0.5, -0.5, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0]),
-                Self::new([0.5, 0.5, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0]),
-                Self::new([-0.5, 0.5, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0]),
-            ],
-            [0, 1, 3, 1, 2, 3],
+            Self::with_tangents(
+                [
+                    Self::new([-0.5, -0.5, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0]),
+                    Self::new([0.5, -0.5, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0]),
+                    Self::new([0.5, 0.5, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0]),
+                    Self::new([-0.5, 0.5, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0]),
+                ],
+                &INDICES_RECT,
+            ),
+            INDICES_RECT,
         )
     }
 
@@ -65,13 +90,16 @@ impl Vertex {
         let min = min.min(max);
         let max = min.max(max);
         (
-            [
-                Self::new([min, min, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0]),
-                Self::new([max, min, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0]),
-                Self::new([max, max, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0]),
-                Self::new([min, max, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0]),
-            ],
-            [0, 1, 3, 1, 2, 3],
+            Self::with_tangents(
+                [
+                    Self::new([min, min, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0]),
+                    Self::new([max, min, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0]),
+                    Self::new([max, max, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0]),
+                    Self::new([min, max, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0]),
+                ],
+                &INDICES_RECT,
+            ),
+            INDICES_RECT,
         )
     }
 
@@ -79,60 +107,112 @@ impl Vertex {
         let min = -1.0;
         let max = 1.0;
         (
+            Self::with_tangents(
+                [
+                    Self::new([min, min, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0]),
+                    Self::new([max, min, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0]),
+                    Self::new([max, max, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0]),
+                    Self::new([min, max, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0]),
+                ],
+                &INDICES_RECT,
+            ),
+            INDICES_RECT,
+        )
+    }
+
+    pub fn cube() -> [Self; 36] {
+        Self::with_tangents(
             [
-                Self::new([min, min, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0]),
-                Self::new([max, min, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0]),
-                Self::new([max, max, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0]),
-                Self::new([min, max, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0]),
+                // front
+                Self::new([-0.5, -0.5, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0]),
+                Self::new([0.5, -0.5, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0]),
+                Self::new([-0.5, 0.5, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0]),
+                Self::new([0.5, -0.5, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0]),
+                Self::new([0.5, 0.5, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0]),
+                Self::new([-0.5, 0.5, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0]),
+                // right
+                Self::new([0.5, -0.5, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0]),
+                Self::new([0.5, -0.5, -1.0], [1.0, 0.0, 0.0], [1.0, 1.0]),
+                Self::new([0.5, 0.5, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0]),
+                Self::new([0.5, -0.5, -1.0], [1.0, 0.0, 0.0], [1.0, 1.0]),
+                Self::new([0.5, 0.5, -1.0], [1.0, 0.0, 0.0], [1.0, 0.0]),
+                Self::new([0.5, 0.5, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0]),
+                // back
+                Self::new([0.5, -0.5, -1.0], [0.0, 0.0, -1.0], [0.0, 1.0]),
+                Self::new([-0.5, -0.5, -1.0], [0.0, 0.0, -1.0], [1.0, 1.0]),
+                Self::new([0.5, 0.5, -1.0], [0.0, 0.0, -1.0], [0.0, 0.0]),
+                Self::new([-0.5, -0.5, -1.0], [0.0, 0.0, -1.0], [1.0, 1.0]),
+                Self::new([-0.5, 0.5, -1.0], [0.0, 0.0, -1.0], [1.0, 0.0]),
+                Self::new([0.5, 0.5, -1.0], [0.0, 0.0, -1.0], [0.0, 0.0]),
+                // left
+                Self::new([-0.5, -0.5, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0]),
+                Self::new([-0.5, -0.5, 0.0], [-1.0, 0.0, 0.0], [1.0, 1.0]),
+                Self::new([-0.5, 0.5, -1.0], [-1.0, 0.0, 0.0], [0.0, 0.0]),
+                Self::new([-0.5, -0.5, 0.0], [-1.0, 0.0, 0.0], [1.0, 1.0]),
+                Self::new([-0.5, 0.5, 0.0], [-1.0, 0.0, 0.0], [1.0, 0.0]),
+                Self::new([-0.5, 0.5, -1.0], [-1.0, 0.0, 0.0], [0.0, 0.0]),
+                // bottom
+                Self::new([-0.5, -0.5, -1.0], [0.0, -1.0, 0.0], [0.0, 1.0]),
+                Self::new([0.5, -0.5, -1.0], [0.0, -1.0, 0.0], [1.0, 1.0]),
+                Self::new([-0.5, -0.5, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0]),
+                Self::new([0.5, -0.5, -1.0], [0.0, -1.0, 0.0], [1.0, 1.0]),
+                Self::new([0.5, -0.5, 0.0], [0.0, -1.0, 0.0], [1.0, 0.0]),
+                Self::new([-0.5, -0.5, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0]),
+                // top
+                Self::new([-0.5, 0.5, 0.0], [0.0, 1.0, 0.0], [0.0, 1.0]),
+                Self::new([0.5, 0.5, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0]),
+                Self::new([-0.5, 0.5, -1.0], [0.0, 1.0, 0.0], [0.0, 0.0]),
+                Self::new([0.5, 0.5, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0]),
+                Self::new([0.5, 0.5, -1.0], [0.0, 1.0, 0.0], [1.0, 0.0]),
+                Self::new([-0.5, 0.5, -1.0], [0.0, 1.0, 0.0], [0.0, 0.0]),
             ],
-            [0, 1, 3, 1, 2, 3],
+            &[],
         )
     }
+}
 
-    pub fn cube() -> [Self; 36] {
-        [
-            // front
-            Self::new([-0.5, -0.5, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0]),
-            Self::new([0.5, -0.5, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0]),
-            Self::new([-0.5, 0.5, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0]),
-            Self::new([0.5, -0.5, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0]),
-            Self::new([0.5, 0.5, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0]),
-            Self::new([-0.5, 0.5, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0]),
-            // right
-            Self::new([0.5, -0.5, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0]),
-            Self::new([0.5, -0.5, -1.0], [1.0, 0.0, 0.0], [1.0, 1.0]),
-            Self::new([0.5, 0.5, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0]),
-            Self::new([0.5, -0.5, -1.0], [1.0, 0.0, 0.0], [1.0, 1.0]),
-            Self::new([0.5, 0.5, -1.0], [1.0, 0.0, 0.0], [1.0, 0.0]),
-            Self::new([0.5, 0.5, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0]),
-            // back
-            Self::new([0.5, -0.5, -1.0], [0.0, 0.0, -1.0], [0.0, 1.0]),
-            Self::new([-0.5, -0.5, -1.0], [0.0, 0.0, -1.0], [1.0, 1.0]),
-            Self::new([0.5, 0.5, -1.0], [0.0, 0.0, -1.0], [0.0, 0.0]),
-            Self::new([-0.5, -0.5, -1.0], [0.0, 0.0, -1.0], [1.0, 1.0]),
-            Self::new([-0.5, 0.5, -1.0], [0.0, 0.0, -1.0], [1.0, 0.0]),
-            Self::new([0.5, 0.5, -1.0], [0.0, 0.0, -1.0], [0.0, 0.0]),
-            // left
-            Self::new([-0.5, -0.5, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0]),
-            Self::new([-0.5, -0.5, 0.0], [-1.0, 0.0, 0.0], [1.0, 1.0]),
-            Self::new([-0.5, 0.5, -1.0], [-1.0, 0.0, 0.0], [0.0, 0.0]),
-            Self::new([-0.5, -0.5, 0.0], [-1.0, 0.0, 0.0], [1.0, 1.0]),
-            Self::new([-0.5, 0.5, 0.0], [-1.0, 0.0, 0.0], [1.0, 0.0]),
-            Self::new([-0.5, 0.5, -1.0], [-1.0, 0.0, 0.0], [0.0, 0.0]),
-            // bottom
-            Self::new([-0.5, -0.5, -1.0], [0.0, -1.0, 0.0], [0.0, 1.0]),
-            Self::new([0.5, -0.5, -1.0], [0.0, -1.0, 0.0], [1.0, 1.0]),
-            Self::new([-0.5, -0.5, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0]),
-            Self::new([0.5, -0.5, -1.0], [0.0, -1.0, 0.0], [1.0, 1.0]),
-            Self::new([0.5, -0.5, 0.0], [0.0, -1.0, 0.0], [1.0, 0.0]),
-            Self::new([-0.5, -0.5, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0]),
-            // top
-            Self::new([-0.5, 0.5, 0.0], [0.0, 1.0, 0.0], [0.0, 1.0]),
-            Self::new([0.5, 0.5, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0]),
-            Self::new([-0.5, 0.5, -1.0], [0.0, 1.0, 0.0], [0.0, 0.0]),
-            Self::new([0.5, 0.5, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0]),
-            Self::new([0.5, 0.5, -1.0], [0.0, 1.0, 0.0], [1.0, 0.0]),
-            Self::new([-0.5, 0.5, -1.0], [0.0, 1.0, 0.0], [0.0, 0.0]),
-        ]
+struct TangentGeometry<'a> {
+    vertices: &'a mut [Vertex],
+    indices: &'a [u32],
+}
+
+impl TangentGeometry<'_> {
+    fn index(&self, face: usize, vert: usize) -> usize {
+        match self.indices.is_empty() {
+            true => face * 3 + vert,
+            false => self.indices[face * 3 + vert] as usize,
+        }
+    }
+}
+
+impl bevy_mikktspace::Geometry for TangentGeometry<'_> {
+    fn num_faces(&self) -> usize {
+        match self.indices.is_empty() {
+            true => self.vertices.len() / 3,
+            false => self.indices.len() / 3,
+        }
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertices[self.index(face, vert)].pos
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertices[self.index(face, vert)].normal
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.vertices[self.index(face, vert)].tex_coord
+    }
+
+    // shared vertices keep the tangent of their last face
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        let idx = self.index(face, vert);
+        // mikktspace expects v up, gltf and wgpu uv have v down, so the handedness flips
+        self.vertices[idx].tangent = [tangent[0], tangent[1], tangent[2], -tangent[3]];
     }
 }
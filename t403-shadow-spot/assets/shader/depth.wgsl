@@ -18,8 +18,7 @@ fn vs_main(in: VertexIn) -> VertexOut {
 }
 
 
-@group(0)@binding(0)
-var texture_depth: texture_depth_multisampled_2d;
+// binding 0, the depth texture, is in depth_read.wgsl
 @group(0)@binding(1)
 var<uniform> texture_size: vec2<u32>;
 @group(0)@binding(2)
@@ -31,7 +30,7 @@ fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
     let far = 100.0;
     let x = u32(in.tex_coord.x * f32(texture_size.x));
     let y = u32(in.tex_coord.y * f32(texture_size.y));
-    let depth = textureLoad(texture_depth, vec2<u32>(x, y), 0);
+    let depth = get_depth(vec2<u32>(x, y));
     if orth != u32(0) {
         return vec4<f32>(vec3<f32>(depth), 1.0);
     } else {
@@ -25,6 +25,7 @@ struct TransformIT {
 
 struct LightDirection {
     dir: vec3<f32>,
+    shadow_sample_num: u32,
     color: vec4<f32>,
     ambient: vec3<f32>,
     diffuse: vec3<f32>,
@@ -52,7 +53,9 @@ struct LightPointArray {
 
 struct LightSpot {
     pos: vec3<f32>,
+    shadow_sample_num: u32,
     front: vec3<f32>,
+    cookie: u32,
     color: vec4<f32>,
     ambient: vec3<f32>,
     diffuse: vec3<f32>,
@@ -74,7 +77,40 @@ var<uniform> view_proj_light: mat4x4<f32>;
 @group(0)@binding(3)
 var<uniform> shadow_map_size: vec2<u32>;
 @group(0)@binding(4)
-var texture_shadow_map: texture_depth_multisampled_2d;
+var texture_shadow_map: texture_depth_2d;
+@group(0)@binding(5)
+var sampler_shadow_map: sampler;
+@group(0)@binding(6)
+var sampler_shadow_map_compare: sampler_comparison;
+
+// same perspective as PipeShadowMap, the depths are linearized with it
+const SHADOW_NEAR: f32 = 0.1;
+const SHADOW_FAR: f32 = 100.0;
+// tan(22.5), half the fov of the shadow map
+const SHADOW_TAN_HALF_FOV: f32 = 0.4142136;
+// width of the light in world units, a bigger light casts softer shadows
+const LIGHT_SIZE: f32 = 0.1;
+// in shadow map texels, caps the blocker search and the filter
+const PENUMBRA_MAX: f32 = 24.0;
+
+// the first 8 and 16 points are spread as evenly as all 32
+var<private> poisson_disk: array<vec2<f32>, 32> = array<vec2<f32>, 32>(
+    vec2<f32>(-0.3523, -0.6983), vec2<f32>(0.5679, 0.7941), vec2<f32>(-0.6625, 0.5697), vec2<f32>(0.7603, -0.3429),
+    vec2<f32>(0.0266, 0.0610), vec2<f32>(-0.8812, -0.2843), vec2<f32>(0.3789, -0.9243), vec2<f32>(-0.1118, 0.9157),
+    vec2<f32>(0.9286, 0.2866), vec2<f32>(0.2081, -0.4226), vec2<f32>(0.4607, 0.2975), vec2<f32>(-0.4603, -0.0256),
+    vec2<f32>(-0.2136, 0.5106), vec2<f32>(0.1900, 0.6294), vec2<f32>(-0.9132, 0.2248), vec2<f32>(-0.0117, -0.9878),
+    vec2<f32>(0.4534, -0.1024), vec2<f32>(-0.1689, -0.2995), vec2<f32>(-0.7197, -0.6678), vec2<f32>(0.5193, -0.6059),
+    vec2<f32>(-0.4316, 0.8239), vec2<f32>(0.2324, 0.9711), vec2<f32>(-0.5459, -0.3946), vec2<f32>(0.0033, -0.6559),
+    vec2<f32>(0.9230, -0.0443), vec2<f32>(0.1172, 0.3327), vec2<f32>(-0.4973, 0.3008), vec2<f32>(0.7432, 0.4915),
+    vec2<f32>(-0.7507, -0.0154), vec2<f32>(0.1728, -0.1671), vec2<f32>(0.6924, 0.1873), vec2<f32>(0.6913, -0.1062),
+);
+// rotates the disk per pixel, set by the fragment entry point, noise instead of banding
+var<private> noise_angle: f32 = 0.0;
+
+// interleaved gradient noise
+fn get_noise_angle(pixel: vec2<f32>) -> f32 {
+    return 6.2831853 * fract(52.9829189 * fract(dot(pixel, vec2<f32>(0.06711056, 0.00583715))));
+}
 
 @vertex
 fn vs_main(in: VertexIn, transform: TransformIT) -> VertexOut {
@@ -99,6 +135,10 @@ var<storage> light_direction_arr: LightDirectionArray;
 var<storage> light_point_arr: LightPointArray;
 @group(1)@binding(2)
 var<storage> light_spot_arr: LightSpotArray;
+@group(1)@binding(3)
+var texture_cookie: texture_2d<f32>;
+@group(1)@binding(4)
+var sampler_cookie: sampler;
 
 
 @group(2)@binding(0)
@@ -116,6 +156,7 @@ fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
     var l = vec3<f32>(0.0, 0.0, 0.0);
     let normal = normalize(in.normal);
     let view_dir = normalize(camera_pos - in.frag_pos);
+    noise_angle = get_noise_angle(in.clip_pos.xy);
 
     // 0.5 for x, -0.5 for y, because texture is y down
     let frag_pos_light_space_xy = (in.frag_pos_light_space.xy / in.frag_pos_light_space.w) * vec2<f32>(0.5, -0.5) + 0.5;
@@ -159,37 +200,73 @@ fn do_light_direction(light_direction: LightDirection, normal: vec3<f32>, view_d
 
     let specular = light_direction.specular * light_color * spec * tex_diffuse;
 
-    let visiblity = get_direction_light_visiblity(frag_pos_light_space_xy, frag_pos_light_space_z, normal, light_dir);
+    let visiblity = get_direction_light_visiblity(frag_pos_light_space_xy, frag_pos_light_space_z, normal, light_dir, light_direction.shadow_sample_num);
 
     return ambient + visiblity * (diffuse + specular);
 }
 
-fn get_direction_light_visiblity(frag_pos_light_space_xy: vec2<f32>, frag_pos_light_space_z: f32, normal: vec3<f32>, light_dir: vec3<f32>) -> f32 {
+// pcss, the blocker search finds how far the occluders are, the filter then grows with
+// the gap between them and the receiver
+fn get_direction_light_visiblity(frag_pos_light_space_xy: vec2<f32>, frag_pos_light_space_z: f32, normal: vec3<f32>, light_dir: vec3<f32>, sample_num: u32) -> f32 {
     if frag_pos_light_space_z > 1.0 || frag_pos_light_space_xy.x > 1.0 || frag_pos_light_space_xy.y > 1.0 || frag_pos_light_space_xy.x < 0.0 || frag_pos_light_space_xy.y < 0.0 {
         return 0.5;
     }
-    //let tex_coord = vec2<u32>(u32(f32(shadow_map_size.x) * frag_pos_light_space_xy.x), u32(f32(shadow_map_size.y) * frag_pos_light_space_xy.y));
-    let bias = max(0.008 * (1.0 - dot(normal, light_dir)), 0.003);
+    let z_receiver = get_linear_depth(frag_pos_light_space_z);
+    // shadow map uv per world unit at the receiver
+    let uv_per_world = 1.0 / (2.0 * SHADOW_TAN_HALF_FOV * z_receiver);
+    let texel = 1.0 / f32(shadow_map_size.x);
+
+    // bias by the world size of a texel, slanted receivers cross more depth per texel
+    let cos_theta = clamp(dot(normal, light_dir), 0.05, 1.0);
+    let slope = min(sqrt(1.0 - cos_theta * cos_theta) / cos_theta, 4.0);
+    let bias = (1.0 + slope) * texel / uv_per_world;
+    let z = get_ndc_depth(z_receiver - bias);
+    if sample_num <= 1u {
+        return textureSampleCompareLevel(texture_shadow_map, sampler_shadow_map_compare, frag_pos_light_space_xy, z);
+    }
+
+    let rotation = mat2x2<f32>(cos(noise_angle), sin(noise_angle), -sin(noise_angle), cos(noise_angle));
+
+    // sized for blockers half way to the light, the closer ones hit the cap anyway
+    let search_radius = clamp(LIGHT_SIZE * uv_per_world, texel, PENUMBRA_MAX * texel);
+    var blocker_sum = 0.0;
+    var blocker_num = 0.0;
+    for (var i = 0u; i < sample_num; i = i + 1u) {
+        let uv = frag_pos_light_space_xy + rotation * poisson_disk[i] * search_radius;
+        let depth = get_shadow_map_blocker(uv);
+        let is_blocker = select(vec4<f32>(0.0), vec4<f32>(1.0), depth < vec4<f32>(z));
+        blocker_sum += dot(is_blocker, get_linear_depth_vec4(depth));
+        blocker_num += dot(is_blocker, vec4<f32>(1.0));
+    }
+    if blocker_num == 0.0 {
+        return 1.0;
+    }
+    let z_blocker = blocker_sum / blocker_num;
+
+    // similar triangles between the light, the blocker and the receiver
+    let penumbra = LIGHT_SIZE * (z_receiver - z_blocker) / z_blocker;
+    let filter_radius = clamp(0.5 * penumbra * uv_per_world, texel, PENUMBRA_MAX * texel);
+    // a wide filter reaches further along a slanted receiver
+    let z_filter = get_ndc_depth(z_receiver - bias - slope * filter_radius / uv_per_world);
     var visiblity = 0.0;
-    let sample_size = 2;
-    let tex_coord = vec2<u32>(vec2<f32>(shadow_map_size) * frag_pos_light_space_xy);
-    for (var y = -sample_size; y <= sample_size; y ++) {
-        for (var x = -sample_size; x <= sample_size; x ++) {
-            let tmp_x = i32(tex_coord.x) + x;
-            let tmp_y = i32(tex_coord.y) + y;
-            if tmp_x < 0 || tmp_x >= i32(shadow_map_size.x) || tmp_y < 0 || tmp_y >= i32(shadow_map_size.y) {
-                visiblity += 1.0;
-            } else {
-                let shadow_map_value = textureLoad(texture_shadow_map, vec2<u32>(u32(tmp_x), u32(tmp_y)), 0);
-
-                visiblity += select(0.0, 1.0, frag_pos_light_space_z < shadow_map_value + bias);
-            }
-        }
+    for (var i = 0u; i < sample_num; i = i + 1u) {
+        let uv = frag_pos_light_space_xy + rotation * poisson_disk[i] * filter_radius;
+        visiblity += textureSampleCompareLevel(texture_shadow_map, sampler_shadow_map_compare, uv, z_filter);
     }
-    visiblity /= pow(f32(sample_size * 2 + 1), 2.0);
+    return visiblity / f32(sample_num);
+}
 
-    let shadow_map_value = textureLoad(texture_shadow_map, tex_coord, 0);
-    return visiblity;
+// view space distance along the light's front for a shadow map depth
+fn get_linear_depth(depth: f32) -> f32 {
+    return SHADOW_NEAR * SHADOW_FAR / (SHADOW_FAR - depth * (SHADOW_FAR - SHADOW_NEAR));
+}
+
+fn get_linear_depth_vec4(depth: vec4<f32>) -> vec4<f32> {
+    return SHADOW_NEAR * SHADOW_FAR / (SHADOW_FAR - depth * (SHADOW_FAR - SHADOW_NEAR));
+}
+
+fn get_ndc_depth(z: f32) -> f32 {
+    return SHADOW_FAR * (z - SHADOW_NEAR) / ((SHADOW_FAR - SHADOW_NEAR) * z);
 }
 
 fn do_light_point(light_point: LightPoint, normal: vec3<f32>, view_dir: vec3<f32>, tex_diffuse: vec3<f32>, frag_pos: vec3<f32>) -> vec3<f32> {
@@ -246,10 +323,33 @@ fn do_light_spot(light_spot: LightSpot, normal: vec3<f32>, tex_diffuse: vec3<f32
     let spec = pow(max(dot(normal, halfway_dir), 0.0), shininess * 3.0);
     let specular = light_color * spec * tex_diffuse;
 
-    let tex_coord = vec2<u32>(vec2<f32>(shadow_map_size) * frag_pos_light_space_xy);
-    let shadow_map_value = textureLoad(texture_shadow_map, tex_coord, 0);
-    let visiblity = get_direction_light_visiblity(frag_pos_light_space_xy, frag_pos_light_space_z, normal, light_dir);
+    let visiblity = get_direction_light_visiblity(frag_pos_light_space_xy, frag_pos_light_space_z, normal, light_dir, light_spot.shadow_sample_num);
+    let cookie = get_cookie(light_spot, frag_pos);
 
-    return ambient + (diffuse + specular) * intensity * visiblity;
+    return ambient + (diffuse + specular) * intensity * visiblity * cookie;
     //return vec3<f32>(visiblity);
 }
+
+// the cookie fills the square frustum around the outer cone, its axes as in the shadow map's
+// gen_view, y up or z up for a light that looks straight up or down
+fn get_cookie(light_spot: LightSpot, frag_pos: vec3<f32>) -> vec3<f32> {
+    if light_spot.cookie == 0u {
+        return vec3<f32>(1.0, 1.0, 1.0);
+    }
+
+    let front = normalize(light_spot.front);
+    let right_y = cross(front, vec3<f32>(0.0, 1.0, 0.0));
+    let right = normalize(select(right_y, cross(front, vec3<f32>(0.0, 0.0, 1.0)), dot(right_y, right_y) < 1e-6));
+    let up = cross(right, front);
+    let local = frag_pos - light_spot.pos;
+    let depth = dot(local, front);
+    if depth <= 0.0 {
+        return vec3<f32>(0.0, 0.0, 0.0);
+    }
+
+    let tan_out_cutoff = sqrt(1.0 - light_spot.out_cutoff * light_spot.out_cutoff) / light_spot.out_cutoff;
+    // 0.5 for x, -0.5 for y, because texture is y down
+    let uv = vec2<f32>(dot(local, right), dot(local, up)) / (depth * tan_out_cutoff) * vec2<f32>(0.5, -0.5) + 0.5;
+    // the light loop isn't uniform control flow, no implicit lod
+    return textureSampleLevel(texture_cookie, sampler_cookie, uv, 0.0).rgb;
+}
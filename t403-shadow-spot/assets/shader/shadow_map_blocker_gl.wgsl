@@ -0,0 +1,10 @@
+
+// appended instead of shadow_map_blocker.wgsl on GL, GLSL pairs every texture with one sampler and
+// texture_shadow_map already goes with the comparison sampler, the blocker search reads the same
+// view again through a binding of its own
+@group(0)@binding(7)
+var texture_shadow_map_blocker: texture_2d<f32>;
+
+fn get_shadow_map_blocker(uv: vec2<f32>) -> vec4<f32> {
+    return textureGather(0, texture_shadow_map_blocker, sampler_shadow_map, uv);
+}
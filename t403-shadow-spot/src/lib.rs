@@ -1,5 +1,7 @@
 pub mod camera;
 pub mod color;
+pub use common::gltf_data;
+pub use common::headless;
 pub mod input;
 pub mod light_direction;
 pub mod light_point;
@@ -12,6 +14,7 @@ pub mod pipe_hub;
 pub mod pipe_mesh;
 pub mod pipe_shadow_map;
 pub mod runner;
+pub mod shadow_quality;
 pub mod texture;
 pub mod transform;
 pub mod vertex;
@@ -1,13 +1,17 @@
+use glam::Vec3;
+
+use crate::{camera::Camera, material::Material, shadow_quality::ShadowQuality};
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct LightSpot {
     pub pos: [f32; 3],
-    // 16 bytes padding
-    _padding0: u32,
+    // poisson taps of the shadow filter, see ShadowQuality
+    pub shadow_sample_num: u32,
 
     pub front: [f32; 3],
-    // 16 bytes padding
-    _padding1: u32,
+    // 1 projects the cookie texture of PipeMesh through the cone
+    pub cookie: u32,
 
     pub color: [f32; 4],
 
@@ -35,9 +39,9 @@ impl LightSpot {
     ) -> Self {
         Self {
             pos,
-            _padding0: 0,
+            shadow_sample_num: ShadowQuality::Medium.sample_num(),
             front,
-            _padding1: 0,
+            cookie: 0,
             color,
             ambient,
             _padding3: 0,
@@ -48,6 +52,10 @@ impl LightSpot {
         }
     }
 
+    pub fn set_shadow_quality(&mut self, quality: ShadowQuality) {
+        self.shadow_sample_num = quality.sample_num();
+    }
+
     pub fn zero() -> Self {
         Self::new(
             [0.0, 0.0, 0.0],
@@ -61,3 +69,43 @@ impl LightSpot {
         )
     }
 }
+
+// what an attached spot light follows, the index is into material_arr, model_arr and its
+// transform_arr
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachTarget {
+    Camera,
+    Node(usize, usize, usize),
+}
+
+// pos and front of the light in the space of the target, for the camera -z is ahead
+#[derive(Debug, Clone, Copy)]
+pub struct LightSpotAttach {
+    pub target: AttachTarget,
+    pub pos: Vec3,
+    pub front: Vec3,
+}
+
+impl LightSpotAttach {
+    pub fn new(target: AttachTarget, pos: Vec3, front: Vec3) -> Self {
+        Self { target, pos, front }
+    }
+
+    // world pos and front, none when the node is gone
+    pub fn world(&self, camera: &Camera, material_arr: &[Material]) -> Option<(Vec3, Vec3)> {
+        let mat = match self.target {
+            AttachTarget::Camera => camera.view().inverse(),
+            AttachTarget::Node(material_idx, model_idx, instance_idx) => material_arr
+                .get(material_idx)?
+                .model_arr
+                .get(model_idx)?
+                .transform_arr
+                .get(instance_idx)?
+                .mat(),
+        };
+        Some((
+            mat.transform_point3(self.pos),
+            mat.transform_vector3(self.front).normalize(),
+        ))
+    }
+}
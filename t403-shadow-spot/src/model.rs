@@ -1,6 +1,9 @@
-use wgpu::{util::DeviceExt, Buffer, Device};
+use wgpu::{util::DeviceExt, Buffer, Device, Queue};
 
-use crate::{transform::Transform, vertex::Vertex};
+use crate::{
+    transform::{Transform, TransformRawIT},
+    vertex::Vertex,
+};
 
 #[derive(Debug)]
 pub struct Model {
@@ -8,6 +11,8 @@ pub struct Model {
     pub vertex_buffer: Buffer,
     pub index_buffer: Buffer,
     pub transform_buffer: Buffer,
+    // lights attached to an instance read it back
+    pub transform_arr: Vec<Transform>,
     pub vertices_len: u32,
     pub indices_len: u32,
     pub instance_num: u32,
@@ -40,7 +45,7 @@ impl Model {
         let transform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Transform Buffer"),
             contents: bytemuck::cast_slice(&transform_mat_arr),
-            usage: wgpu::BufferUsages::VERTEX,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
         });
 
         Self {
@@ -51,8 +56,19 @@ impl Model {
             vertices_len: vertices.len() as u32,
             indices_len: indices.len() as u32,
             instance_num: transform_arr.len() as u32,
+            transform_arr,
         }
     }
+
+    // the lights attached to the instance follow it in the next update
+    pub fn set_transform(&mut self, queue: &Queue, instance_idx: usize, transform: Transform) {
+        self.transform_arr[instance_idx] = transform;
+        queue.write_buffer(
+            &self.transform_buffer,
+            (instance_idx * std::mem::size_of::<TransformRawIT>()) as u64,
+            bytemuck::bytes_of(&transform.to_raw_it()),
+        );
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, PartialOrd, Ord)]
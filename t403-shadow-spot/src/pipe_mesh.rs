@@ -1,33 +1,43 @@
 use wgpu::{
-    util::DeviceExt, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device, IndexFormat,
-    Queue, RenderPipeline, Sampler, SurfaceConfiguration, TextureView,
+    util::DeviceExt, Backend, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device,
+    IndexFormat, PipelineLayout, Queue, RenderPipeline, Sampler, ShaderModule,
+    SurfaceConfiguration, TextureFormat, TextureView,
 };
 
-pub const SAMPLE_COUNT: u32 = 4;
-
 use crate::{
     camera::Camera,
     input::Input,
     light_direction::LightDirection,
     light_point::LightPoint,
-    light_spot::LightSpot,
+    light_spot::{LightSpot, LightSpotAttach},
     material::Material,
     model::DrawMethod,
+    shadow_quality::ShadowQuality,
     texture::{
-        self, gen_sampler_clamp, gen_sampler_repeat, gen_texture_view_depth, gen_texture_view_msaa,
+        self, gen_sampler_clamp, gen_sampler_compare, gen_sampler_nearest, gen_sampler_repeat,
+        gen_texture_view_depth, gen_texture_view_msaa,
     },
     transform::TransformRawIT,
     vertex::Vertex,
 };
 
 pub struct PipeMesh {
+    pub shader_mesh: ShaderModule,
+    pub render_pipline_layout_mesh: PipelineLayout,
     pub render_pipline_mesh: RenderPipeline,
+    // msaa, see set_sample_count
+    pub sample_count: u32,
 
     pub sampler: Sampler,
     pub sampler_repeat: Sampler,
+    // raw depths for the blocker search, and the comparisons for the filter
+    pub sampler_shadow: Sampler,
+    pub sampler_shadow_compare: Sampler,
 
     pub texture_view_depth: TextureView,
-    pub texture_view_msaa: TextureView,
+    // none without msaa
+    pub texture_view_msaa: Option<TextureView>,
+    pub backend: Backend,
 
     pub bind_group_layout_material: BindGroupLayout,
     pub material_arr: Vec<Material>,
@@ -42,6 +52,9 @@ pub struct PipeMesh {
     pub light_direction_arr: Vec<LightDirection>,
     pub light_point_arr: Vec<LightPoint>,
     pub light_spot_arr: Vec<LightSpot>,
+    // by the index of light_spot_arr, none stays where it was put
+    pub light_spot_attach_arr: Vec<Option<LightSpotAttach>>,
+    pub bind_group_layout_light_arr: BindGroupLayout,
     pub bind_group_light_arr: BindGroup,
     pub buffer_light_direction: Buffer,
     pub buffer_light_point: Buffer,
@@ -55,22 +68,114 @@ impl PipeMesh {
         buffer_view_proj_light: &Buffer,
         texture_view_shadow_map: &TextureView,
         shadow_map_size: [u32; 2],
+        texture_view_cookie: &TextureView,
+        sample_count: u32,
+        backend: Backend,
     ) -> Self {
-        let mesh_shader = std::fs::read_to_string("assets/shader/mesh.wgsl").unwrap();
+        let mut mesh_shader = std::fs::read_to_string("assets/shader/mesh.wgsl").unwrap();
+        mesh_shader += &std::fs::read_to_string(if backend == Backend::Gl {
+            "assets/shader/shadow_map_blocker_gl.wgsl"
+        } else {
+            "assets/shader/shadow_map_blocker.wgsl"
+        })
+        .unwrap();
         let mesh_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Shader Mesh"),
             source: wgpu::ShaderSource::Wgsl(mesh_shader.into()),
         });
 
+        let mut entry_layout_arr = vec![
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 4,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Depth,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 5,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 6,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                count: None,
+            },
+        ];
+        // see shadow_map_blocker_gl.wgsl
+        if backend == Backend::Gl {
+            entry_layout_arr.push(wgpu::BindGroupLayoutEntry {
+                binding: 7,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            });
+        }
         let bind_group_layout_camera =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: Some("Bind Group Layout View Proj"),
+                entries: &entry_layout_arr,
+            });
+
+        let bind_group_layout_light_arr =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Bind Group Layout Light Arr"),
                 entries: &[
                     wgpu::BindGroupLayoutEntry {
                         binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
                             has_dynamic_offset: false,
                             min_binding_size: None,
                         },
@@ -80,7 +185,7 @@ impl PipeMesh {
                         binding: 1,
                         visibility: wgpu::ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
                             has_dynamic_offset: false,
                             min_binding_size: None,
                         },
@@ -88,69 +193,28 @@ impl PipeMesh {
                     },
                     wgpu::BindGroupLayoutEntry {
                         binding: 2,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 3,
                         visibility: wgpu::ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
                             has_dynamic_offset: false,
                             min_binding_size: None,
                         },
                         count: None,
                     },
                     wgpu::BindGroupLayoutEntry {
-                        binding: 4,
+                        binding: 3,
                         visibility: wgpu::ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Depth,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
                             view_dimension: wgpu::TextureViewDimension::D2,
-                            multisampled: true,
-                        },
-                        count: None,
-                    },
-                ],
-            });
-
-        let bind_group_layout_light_arr =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Bind Group Layout Light Arr"),
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
+                            multisampled: false,
                         },
                         count: None,
                     },
                     wgpu::BindGroupLayoutEntry {
-                        binding: 2,
+                        binding: 4,
                         visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Storage { read_only: true },
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                         count: None,
                     },
                 ],
@@ -210,53 +274,13 @@ impl PipeMesh {
                 push_constant_ranges: &[],
             });
 
-        let render_pipline_mesh = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipline Mesh"),
-            layout: Some(&render_pipline_layout_mesh),
-            vertex: wgpu::VertexState {
-                module: &mesh_shader,
-                entry_point: "vs_main",
-                buffers: &[
-                    Vertex::vertex_buffer_layout(),
-                    TransformRawIT::vertex_buffer_layout(),
-                ],
-            },
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: texture::DEPTH_FORMAT,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState {
-                    constant: 2, // Corresponds to bilinear filtering
-                    slope_scale: 2.0,
-                    clamp: 0.0,
-                },
-            }),
-            multisample: wgpu::MultisampleState {
-                count: SAMPLE_COUNT,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &mesh_shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            multiview: None,
-        });
+        let render_pipline_mesh = gen_render_pipline_mesh(
+            device,
+            &render_pipline_layout_mesh,
+            &mesh_shader,
+            surface_config.format,
+            sample_count,
+        );
 
         let camera = Camera::new(surface_config.width as _, surface_config.height as _);
         let buffer_view_proj = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -278,39 +302,57 @@ impl PipeMesh {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
+        let sampler_shadow = gen_sampler_nearest(device);
+        let sampler_shadow_compare = gen_sampler_compare(device);
+
+        let mut entry_arr = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(
+                    buffer_view_proj.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Buffer(
+                    buffer_camera_pos.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer(
+                    buffer_view_proj_light.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Buffer(
+                    buffer_shadow_map_size.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(&texture_view_shadow_map),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::Sampler(&sampler_shadow),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: wgpu::BindingResource::Sampler(&sampler_shadow_compare),
+            },
+        ];
+        if backend == Backend::Gl {
+            entry_arr.push(wgpu::BindGroupEntry {
+                binding: 7,
+                resource: wgpu::BindingResource::TextureView(texture_view_shadow_map),
+            });
+        }
         let bind_group_camera = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Bind Group Camera"),
             layout: &bind_group_layout_camera,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer(
-                        buffer_view_proj.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Buffer(
-                        buffer_camera_pos.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Buffer(
-                        buffer_view_proj_light.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: wgpu::BindingResource::Buffer(
-                        buffer_shadow_map_size.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 4,
-                    resource: wgpu::BindingResource::TextureView(&texture_view_shadow_map),
-                },
-            ],
+            entries: &entry_arr,
         });
 
         let light_direction_arr = vec![];
@@ -350,49 +392,35 @@ impl PipeMesh {
             usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
         });
 
-        let bind_group_light_arr = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Bind Group Light Array"),
-            layout: &bind_group_layout_light_arr,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer(
-                        buffer_light_direction.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Buffer(
-                        buffer_light_point.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Buffer(
-                        buffer_light_spot.as_entire_buffer_binding(),
-                    ),
-                },
+        let sampler = gen_sampler_clamp(device);
+        let bind_group_light_arr = gen_bind_group_light_arr(
+            device,
+            &bind_group_layout_light_arr,
+            [
+                &buffer_light_direction,
+                &buffer_light_point,
+                &buffer_light_spot,
             ],
-        });
+            texture_view_cookie,
+            &sampler,
+        );
 
-        let sampler = gen_sampler_clamp(&device);
         let sampler_repeat = gen_sampler_repeat(&device);
 
-        let texture_view_depth = gen_texture_view_depth(
-            &device,
-            surface_config.width,
-            surface_config.height,
-            SAMPLE_COUNT,
-        );
-
-        let texture_view_msaa = gen_texture_view_msaa(&device, &surface_config, SAMPLE_COUNT);
+        let (texture_view_depth, texture_view_msaa) =
+            gen_texture_view_attachment(device, surface_config, sample_count, backend);
 
         Self {
+            shader_mesh: mesh_shader,
+            render_pipline_layout_mesh,
             render_pipline_mesh,
+            sample_count,
             camera,
 
             sampler,
             sampler_repeat,
+            sampler_shadow,
+            sampler_shadow_compare,
 
             bind_group_layout_camera,
             bind_group_camera,
@@ -406,10 +434,13 @@ impl PipeMesh {
             buffer_shadow_map_size,
 
             texture_view_msaa,
+            backend,
 
             light_direction_arr,
             light_point_arr,
             light_spot_arr,
+            light_spot_attach_arr: vec![],
+            bind_group_layout_light_arr,
             buffer_light_direction,
             buffer_light_point,
             buffer_light_spot,
@@ -420,21 +451,38 @@ impl PipeMesh {
         self.camera
             .update_size(surface_config.width as _, surface_config.height as _);
 
-        self.texture_view_depth = gen_texture_view_depth(
+        (self.texture_view_depth, self.texture_view_msaa) =
+            gen_texture_view_attachment(device, surface_config, self.sample_count, self.backend);
+    }
+
+    // sample_count has to be supported, see texture::supported_sample_count
+    pub fn set_sample_count(
+        &mut self,
+        device: &Device,
+        surface_config: &SurfaceConfiguration,
+        sample_count: u32,
+    ) {
+        if sample_count == self.sample_count {
+            return;
+        }
+        self.sample_count = sample_count;
+        self.render_pipline_mesh = gen_render_pipline_mesh(
             device,
-            surface_config.width,
-            surface_config.height,
-            SAMPLE_COUNT,
+            &self.render_pipline_layout_mesh,
+            &self.shader_mesh,
+            surface_config.format,
+            sample_count,
         );
-        self.texture_view_msaa = gen_texture_view_msaa(device, surface_config, SAMPLE_COUNT);
+        (self.texture_view_depth, self.texture_view_msaa) =
+            gen_texture_view_attachment(device, surface_config, sample_count, self.backend);
     }
 
     pub fn render(&mut self, encoder: &mut CommandEncoder, texture_view: &TextureView) {
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Render Pass Mesh"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &self.texture_view_msaa,
-                resolve_target: Some(texture_view),
+                view: self.texture_view_msaa.as_ref().unwrap_or(texture_view),
+                resolve_target: self.texture_view_msaa.as_ref().map(|_| texture_view),
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color {
                         r: 0.1,
@@ -493,15 +541,54 @@ impl PipeMesh {
             bytemuck::cast_slice(&self.camera.pos.to_array()),
         );
 
-        // if self.light_spot_arr.len() > 0 {
-        //     self.light_spot_arr[0].pos = self.camera.pos.into();
-        //     self.light_spot_arr[0].front = self.camera.front.into();
-        //     queue.write_buffer(
-        //         &self.buffer_light_spot,
-        //         0,
-        //         bytemuck::cast_slice(&self.light_spot_arr),
-        //     );
-        // }
+        self.update_light_spot_attach(queue);
+    }
+
+    // after the camera moved, the attached lights take their targets' place
+    fn update_light_spot_attach(&mut self, queue: &Queue) {
+        let mut is_moved = false;
+        for (light, attach) in self
+            .light_spot_arr
+            .iter_mut()
+            .zip(&self.light_spot_attach_arr)
+        {
+            let world = attach.and_then(|attach| attach.world(&self.camera, &self.material_arr));
+            if let Some((pos, front)) = world {
+                light.pos = pos.into();
+                light.front = front.into();
+                is_moved = true;
+            }
+        }
+        if is_moved {
+            queue.write_buffer(
+                &self.buffer_light_spot,
+                0,
+                bytemuck::cast_slice(&self.light_spot_arr),
+            );
+        }
+    }
+
+    // the light follows the target from the next update on, the shadow map only moves with it
+    // when it is PipeHub's shadow_light_spot_idx
+    pub fn attach_light_spot(&mut self, light_idx: usize, attach: LightSpotAttach) {
+        if let Some(light_attach) = self.light_spot_attach_arr.get_mut(light_idx) {
+            *light_attach = Some(attach);
+        }
+    }
+
+    // shared by every light with cookie set
+    pub fn set_texture_cookie(&mut self, device: &Device, texture_view_cookie: &TextureView) {
+        self.bind_group_light_arr = gen_bind_group_light_arr(
+            device,
+            &self.bind_group_layout_light_arr,
+            [
+                &self.buffer_light_direction,
+                &self.buffer_light_point,
+                &self.buffer_light_spot,
+            ],
+            texture_view_cookie,
+            &self.sampler,
+        );
     }
 
     pub fn add_light_direction(&mut self, queue: &mut Queue, light: LightDirection) {
@@ -513,6 +600,38 @@ impl PipeMesh {
         );
     }
 
+    pub fn set_light_direction_shadow_quality(
+        &mut self,
+        queue: &Queue,
+        light_idx: usize,
+        quality: ShadowQuality,
+    ) {
+        if let Some(light) = self.light_direction_arr.get_mut(light_idx) {
+            light.set_shadow_quality(quality);
+            queue.write_buffer(
+                &self.buffer_light_direction,
+                0,
+                bytemuck::cast_slice(&self.light_direction_arr),
+            );
+        }
+    }
+
+    pub fn set_light_spot_shadow_quality(
+        &mut self,
+        queue: &Queue,
+        light_idx: usize,
+        quality: ShadowQuality,
+    ) {
+        if let Some(light) = self.light_spot_arr.get_mut(light_idx) {
+            light.set_shadow_quality(quality);
+            queue.write_buffer(
+                &self.buffer_light_spot,
+                0,
+                bytemuck::cast_slice(&self.light_spot_arr),
+            );
+        }
+    }
+
     pub fn add_light_point(&mut self, queue: &mut Queue, light: LightPoint) {
         self.light_point_arr.push(light);
         queue.write_buffer(
@@ -524,6 +643,7 @@ impl PipeMesh {
 
     pub fn add_light_spot(&mut self, queue: &mut Queue, light: LightSpot) {
         self.light_spot_arr.push(light);
+        self.light_spot_attach_arr.push(None);
         queue.write_buffer(
             &self.buffer_light_spot,
             0,
@@ -537,39 +657,171 @@ impl PipeMesh {
         buffer_view_proj_light: &Buffer,
         texture_view_shadow_map: &TextureView,
     ) {
+        let mut entry_arr = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(
+                    self.buffer_view_proj.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Buffer(
+                    self.buffer_camera_pos.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer(
+                    buffer_view_proj_light.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Buffer(
+                    self.buffer_shadow_map_size.as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(&texture_view_shadow_map),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::Sampler(&self.sampler_shadow),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: wgpu::BindingResource::Sampler(&self.sampler_shadow_compare),
+            },
+        ];
+        if self.backend == Backend::Gl {
+            entry_arr.push(wgpu::BindGroupEntry {
+                binding: 7,
+                resource: wgpu::BindingResource::TextureView(texture_view_shadow_map),
+            });
+        }
         self.bind_group_camera = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Bind Group Camera"),
             layout: &self.bind_group_layout_camera,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::Buffer(
-                        self.buffer_view_proj.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Buffer(
-                        self.buffer_camera_pos.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Buffer(
-                        buffer_view_proj_light.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: wgpu::BindingResource::Buffer(
-                        self.buffer_shadow_map_size.as_entire_buffer_binding(),
-                    ),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 4,
-                    resource: wgpu::BindingResource::TextureView(&texture_view_shadow_map),
-                },
-            ],
+            entries: &entry_arr,
         });
     }
 }
+
+// direction, point and spot lights, then the cookie of the spot lights
+fn gen_bind_group_light_arr(
+    device: &Device,
+    bind_group_layout: &BindGroupLayout,
+    buffer_light_arr: [&Buffer; 3],
+    texture_view_cookie: &TextureView,
+    sampler_cookie: &Sampler,
+) -> BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Bind Group Light Array"),
+        layout: bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(
+                    buffer_light_arr[0].as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Buffer(
+                    buffer_light_arr[1].as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Buffer(
+                    buffer_light_arr[2].as_entire_buffer_binding(),
+                ),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(texture_view_cookie),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::Sampler(sampler_cookie),
+            },
+        ],
+    })
+}
+
+fn gen_render_pipline_mesh(
+    device: &Device,
+    render_pipline_layout: &PipelineLayout,
+    shader: &ShaderModule,
+    format: TextureFormat,
+    sample_count: u32,
+) -> RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipline Mesh"),
+        layout: Some(render_pipline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[
+                Vertex::vertex_buffer_layout(),
+                TransformRawIT::vertex_buffer_layout(),
+            ],
+        },
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState {
+                constant: 2, // Corresponds to bilinear filtering
+                slope_scale: 2.0,
+                clamp: 0.0,
+            },
+        }),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        multiview: None,
+    })
+}
+
+// depth and, with msaa, the multisampled color target
+fn gen_texture_view_attachment(
+    device: &Device,
+    surface_config: &SurfaceConfiguration,
+    sample_count: u32,
+    backend: Backend,
+) -> (TextureView, Option<TextureView>) {
+    let texture_view_depth = gen_texture_view_depth(
+        device,
+        surface_config.width,
+        surface_config.height,
+        sample_count,
+        backend,
+    );
+    let texture_view_msaa = (sample_count > 1)
+        .then(|| gen_texture_view_msaa(device, surface_config, sample_count, backend));
+    (texture_view_depth, texture_view_msaa)
+}
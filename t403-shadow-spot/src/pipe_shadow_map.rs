@@ -1,7 +1,7 @@
 use glam::{Mat4, Vec3};
 use wgpu::{
-    util::DeviceExt, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device, IndexFormat,
-    Queue, RenderPipeline, TextureView,
+    util::DeviceExt, Backend, BindGroup, BindGroupLayout, Buffer, CommandEncoder, Device,
+    IndexFormat, Queue, RenderPipeline, TextureView,
 };
 
 use crate::{
@@ -9,13 +9,13 @@ use crate::{
     light_spot::LightSpot,
     material::Material,
     model::DrawMethod,
-    pipe_mesh::SAMPLE_COUNT,
     texture::{gen_texture_view_depth, DEPTH_FORMAT},
     transform::TransformRawIT,
     vertex::Vertex,
 };
 
-// const SAMPLE_COUNT: u32 = 1;
+// the mesh pass filters it with comparison samplers, they don't take multisampled textures
+const SAMPLE_COUNT: u32 = 1;
 
 pub struct PipeShadowMap {
     pub render_pipeline: RenderPipeline,
@@ -29,8 +29,9 @@ pub struct PipeShadowMap {
 }
 
 impl PipeShadowMap {
-    pub fn new(device: &Device, width: u32, height: u32) -> Self {
-        let texture_view_depth = gen_texture_view_depth(device, width, height, SAMPLE_COUNT);
+    pub fn new(device: &Device, width: u32, height: u32, backend: Backend) -> Self {
+        let texture_view_depth =
+            gen_texture_view_depth(device, width, height, SAMPLE_COUNT, backend);
 
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Bind Group Layout Shadow"),
@@ -164,13 +165,14 @@ impl PipeShadowMap {
         }
     }
 
-    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
-        self.texture_view_depth = gen_texture_view_depth(device, width, height, SAMPLE_COUNT);
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32, backend: Backend) {
+        self.texture_view_depth =
+            gen_texture_view_depth(device, width, height, SAMPLE_COUNT, backend);
     }
 
     pub fn set_light_direction(&mut self, queue: &Queue, light_direction: &LightDirection) {
         let dir: Vec3 = light_direction.dir.into();
-        let view = Mat4::look_to_rh(-dir * 15.0, dir, Vec3::Y);
+        let view = gen_view(-dir * 15.0, dir);
         queue.write_buffer(
             &self.buffer_view_proj,
             0,
@@ -179,7 +181,7 @@ impl PipeShadowMap {
     }
 
     pub fn set_light_spot(&mut self, queue: &Queue, light_spot: &LightSpot) {
-        let view = Mat4::look_to_rh(light_spot.pos.into(), light_spot.front.into(), Vec3::Y);
+        let view = gen_view(light_spot.pos.into(), light_spot.front.into());
         queue.write_buffer(
             &self.buffer_view_proj,
             0,
@@ -187,3 +189,13 @@ impl PipeShadowMap {
         );
     }
 }
+
+// y up unless the light looks straight up or down, get_cookie in mesh.wgsl picks the same axes
+fn gen_view(pos: Vec3, front: Vec3) -> Mat4 {
+    let up = if front.normalize().cross(Vec3::Y).length_squared() < 1e-6 {
+        Vec3::Z
+    } else {
+        Vec3::Y
+    };
+    Mat4::look_to_rh(pos, front, up)
+}
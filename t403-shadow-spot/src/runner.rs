@@ -1,8 +1,11 @@
+use anyhow::{anyhow, Result};
 use glam::{Quat, Vec3};
+use wgpu::TextureFormat;
 use winit::{event_loop::EventLoop, window::WindowBuilder};
 
 use crate::{
-    light_spot::LightSpot,
+    gltf_data::{load_gltf, load_gltf_image},
+    light_spot::{AttachTarget, LightSpot, LightSpotAttach},
     material::Material,
     model::{DrawMethod, Model},
     pipe_hub::PipeHub,
@@ -26,69 +29,71 @@ pub fn run() {
         .unwrap();
     pollster::block_on(async {
         let mut hub = PipeHub::new(window).await;
-        // load_box_model(&mut core);
-        // load_rect_model(&mut core);
-        // load_triangle_model(&mut core);
-        load_gltf_model(&mut hub);
-        load_plane_model(&mut hub);
-        let light_spot_pos = Vec3::new(2.0, 4.0, 2.0);
-        hub.add_light_spot(LightSpot::new(
-            light_spot_pos.into(),
-            (Vec3::ZERO - light_spot_pos).into(),
-            [1.0, 1.0, 1.0, 1.0],
-            [0.1, 0.1, 0.1],
-            [0.5, 0.5, 0.5],
-            [0.2, 0.2, 0.2],
-            12.5_f32.to_radians().cos(),
-            17.5_f32.to_radians().cos(),
-        ));
+        load_scene(&mut hub).unwrap();
 
         PipeHub::block_loop(event_loop, hub);
     });
 }
 
-pub fn load_gltf_model(core: &mut PipeHub) {
+pub fn run_headless(width: u32, height: u32, path: &str) -> Result<()> {
+    pollster::block_on(async {
+        let mut hub = PipeHub::new_headless(width, height, TextureFormat::Rgba8UnormSrgb).await?;
+        load_scene(&mut hub)?;
+        hub.update_delta_time(1.0 / 60.0);
+        hub.render_to_image().save(path)?;
+        Ok(())
+    })
+}
+
+pub fn load_scene(hub: &mut PipeHub) -> Result<()> {
+    // load_box_model(&mut core);
+    // load_rect_model(&mut core);
+    // load_triangle_model(&mut core);
+    load_gltf_model(hub)?;
+    load_plane_model(hub);
+
+    // a flashlight held a little right of and below the eye, so its shadows show
+    let mut flashlight = LightSpot::new(
+        [0.0, 0.0, 0.0],
+        [0.0, 0.0, -1.0],
+        [1.0, 1.0, 1.0, 1.0],
+        [0.05, 0.05, 0.05],
+        [1.0, 1.0, 1.0],
+        [0.3, 0.3, 0.3],
+        10.0_f32.to_radians().cos(),
+        20.0_f32.to_radians().cos(),
+    );
+    flashlight.cookie = 1;
+    hub.add_light_spot(flashlight);
+    hub.pipe_mesh.attach_light_spot(
+        hub.pipe_mesh.light_spot_arr.len() - 1,
+        LightSpotAttach::new(AttachTarget::Camera, Vec3::new(0.3, -0.3, 0.0), Vec3::NEG_Z),
+    );
+    let texture_view_cookie = gen_texture_view(
+        std::fs::read("assets/texture/flashlight.png")?,
+        &hub.device,
+        &hub.queue,
+    )?;
+    hub.pipe_mesh
+        .set_texture_cookie(&hub.device, &texture_view_cookie);
+
+    Ok(())
+}
+
+pub fn load_gltf_model(core: &mut PipeHub) -> Result<()> {
     let base_path = std::path::Path::new(BASE_GLTF_PATH);
 
     let gltf_path = base_path.join("box.gltf");
-    let gltf_info = gltf::Gltf::open(gltf_path).unwrap();
-
-    let mut buffer_data = Vec::new();
-    for buffer in gltf_info.buffers() {
-        match buffer.source() {
-            gltf::buffer::Source::Bin => {}
-            gltf::buffer::Source::Uri(path) => {
-                let path = base_path.join(path);
-                let data = std::fs::read(path).unwrap();
-                buffer_data.push(data);
-            }
-        }
-    }
+    let (gltf_info, buffer_data) = load_gltf(&gltf_path)?;
 
     // let material_arr = vec![];
     for material in gltf_info.materials() {
         let info = material
             .pbr_metallic_roughness()
             .base_color_texture()
-            .unwrap();
-        let texture = info.texture();
-        let source = texture.source().source();
-        let texture_view;
-        match source {
-            gltf::image::Source::View { view, .. } => {
-                texture_view =
-                    gen_texture_view(buffer_data[view.index()].clone(), &core.device, &core.queue)
-                        .unwrap();
-            }
-            gltf::image::Source::Uri { uri, .. } => {
-                texture_view = gen_texture_view(
-                    std::fs::read(base_path.join(url_escape::decode(uri).to_string())).unwrap(),
-                    &core.device,
-                    &core.queue,
-                )
-                .unwrap();
-            }
-        }
+            .ok_or_else(|| anyhow!("material {:?} has no base color texture", material.name()))?;
+        let img_bytes = load_gltf_image(&info.texture().source(), base_path, &buffer_data)?;
+        let texture_view = gen_texture_view(img_bytes, &core.device, &core.queue)?;
 
         let material = Material::new(
             texture_view,
@@ -101,9 +106,8 @@ pub fn load_gltf_model(core: &mut PipeHub) {
     }
 
     for mesh in gltf_info.meshes() {
-        let primitives = mesh.primitives();
-        primitives.for_each(|primitive| {
-            let reader = primitive.reader(|buffer| Some(&buffer_data[buffer.index()]));
+        for primitive in mesh.primitives() {
+            let reader = primitive.reader(|buffer| Some(&*buffer_data[buffer.index()]));
 
             let mut vertices = vec![];
             if let Some(positions) = reader.read_positions() {
@@ -149,11 +153,16 @@ pub fn load_gltf_model(core: &mut PipeHub) {
                 )],
             );
 
-            core.pipe_mesh.material_arr[primitive.material().index().unwrap() as usize]
+            let material_idx = primitive.material().index().ok_or_else(|| {
+                anyhow!("mesh {:?} has a primitive without material", mesh.name())
+            })?;
+            core.pipe_mesh.material_arr[material_idx]
                 .model_arr
                 .push(model);
-        });
+        }
     }
+
+    Ok(())
 }
 
 pub fn load_plane_model(core: &mut PipeHub) {
@@ -0,0 +1,30 @@
+// how soft a light's shadow edge is, the blocker search and the filter both take this many
+// taps from the poisson disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShadowQuality {
+    // a single comparison, no blocker search
+    Hard,
+    Low,
+    Medium,
+    High,
+}
+
+impl ShadowQuality {
+    pub fn sample_num(self) -> u32 {
+        match self {
+            Self::Hard => 1,
+            Self::Low => 8,
+            Self::Medium => 16,
+            Self::High => 32,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Hard => Self::Low,
+            Self::Low => Self::Medium,
+            Self::Medium => Self::High,
+            Self::High => Self::Hard,
+        }
+    }
+}
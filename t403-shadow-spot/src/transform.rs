@@ -3,6 +3,7 @@ use glam::{Mat4, Quat, Vec3};
 const MAT4_NUM: usize = 1;
 const MAT4_NUM_IT: usize = 2;
 
+#[derive(Debug, Clone, Copy)]
 pub struct Transform {
     pub translation: Vec3,
     pub rotation: Quat,
@@ -18,17 +19,17 @@ impl Transform {
         }
     }
 
-    pub fn to_raw(&self) -> TransformRaw {
-        let mat =
-            Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation);
+    pub fn mat(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
 
+    pub fn to_raw(&self) -> TransformRaw {
         TransformRaw {
-            model: mat.to_cols_array_2d(),
+            model: self.mat().to_cols_array_2d(),
         }
     }
     pub fn to_raw_it(&self) -> TransformRawIT {
-        let mat =
-            Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation);
+        let mat = self.mat();
         let mut combine: [[f32; 4]; MAT4_NUM_IT * 4] = [[0.0; 4]; MAT4_NUM_IT * 4];
         combine[..4].copy_from_slice(&mat.to_cols_array_2d());
         let it_mat = mat.inverse().transpose();
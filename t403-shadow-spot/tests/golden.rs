@@ -0,0 +1,26 @@
+use glam::Vec3;
+use t207_gltf::{pipe_hub::PipeHub, runner::load_scene};
+use wgpu::TextureFormat;
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+const FRAME_TIME: f32 = 1.0 / 60.0;
+
+#[test]
+fn golden_scene() {
+    let mut hub = pollster::block_on(PipeHub::new_headless(
+        WIDTH,
+        HEIGHT,
+        TextureFormat::Rgba8UnormSrgb,
+    ))
+    .expect("golden tests need an adapter");
+
+    load_scene(&mut hub).unwrap();
+
+    let camera = &mut hub.pipe_mesh.camera;
+    camera.pos = Vec3::new(4.0, 5.0, 8.0);
+    camera.front = (Vec3::new(0.0, 0.0, 0.0) - camera.pos).normalize();
+    hub.update_delta_time(FRAME_TIME);
+
+    common::assert_golden!("scene", &hub.render_to_image());
+}